@@ -0,0 +1,437 @@
+//! Command-line arguments of the `cekunit` binary.
+
+use crate::output::{OrderBy, OutputMode, Printer};
+use crate::profile;
+use crate::script::OnError;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use schemars::JsonSchema;
+use serde::Deserialize;
+#[cfg(feature = "serve")]
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// Command-line client for the CekUnit admin panel.
+///
+/// Configuration is read like the library does: an optional config file, a `.env`
+/// file and the process environment (see `BASE_URL`, `USER_EMAIL`, `USER_PASSWORD`).
+#[derive(Debug, Parser)]
+#[command(name = "cekunit", version, about, propagate_version = true)]
+pub struct Cli {
+    /// Profile whose config file and session cache to use.
+    #[arg(
+        long,
+        short = 'P',
+        global = true,
+        value_name = "NAME",
+        env = "CEKUNIT_PROFILE",
+        value_parser = profile::parse_name
+    )]
+    pub profile: Option<String>,
+
+    /// Config file (TOML or JSON) to use instead of searching for one.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// `.env` file to use instead of searching for one.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub env_file: Option<PathBuf>,
+
+    /// Directory for the session cache, instead of the platform cache directory.
+    #[arg(long, global = true, value_name = "DIR", env = "CEKUNIT_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Output format; only `table` prints anything besides data on standard output.
+    #[arg(long, short, global = true, value_enum, default_value_t = OutputMode::Table)]
+    pub output: OutputMode,
+
+    /// Columns to show in listings, in this order, e.g. `no,nopol,status`.
+    #[arg(long, global = true, value_delimiter = ',', value_name = "COLUMNS")]
+    pub columns: Option<Vec<String>>,
+
+    /// Sort listings locally by a column, e.g. `nama` or `no:desc`.
+    #[arg(long, global = true, value_name = "COLUMN[:asc|desc]")]
+    pub order_by: Option<OrderBy>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    /// Returns the printer configured by the output options.
+    pub fn printer(&self) -> Printer {
+        Printer {
+            mode: self.output,
+            columns: self.columns.clone(),
+            order_by: self.order_by.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Log in and cache the session, replacing any cached session.
+    Login,
+    /// Log out and clear the cached session.
+    Logout,
+    /// Show the cached session.
+    Status {
+        /// Also ask the server whether the session is still accepted.
+        #[arg(long)]
+        verify: bool,
+    },
+    /// CekUnit records on the dashboard.
+    #[command(subcommand)]
+    Dashboard(DashboardCommand),
+    /// Data entered by users.
+    #[command(subcommand)]
+    InputUser(InputUserCommand),
+    /// Customer (nasabah) data entry.
+    #[command(subcommand)]
+    InputData(InputDataCommand),
+    /// Persons in charge.
+    #[command(subcommand)]
+    Pic(PicCommand),
+    /// Application users.
+    #[command(subcommand)]
+    Users(UsersCommand),
+    /// Start an interactive shell that keeps one session open.
+    Shell,
+    /// Browse and edit the listings in a full-screen terminal UI.
+    Tui,
+    /// Create a configuration interactively, test it and write it to a file.
+    Init(InitArgs),
+    /// Apply a YAML, TOML or JSON script of operations in one session.
+    Run(RunArgs),
+    /// Serve records, PICs, users and exports as a local JSON API for other tools.
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+    /// Answer line-delimited JSON-RPC 2.0 requests on standard input, for editor
+    /// plugins and agents.
+    Rpc,
+    /// Check the configuration, the connection, the login, the endpoints and the
+    /// session cache, and suggest fixes.
+    Doctor,
+    /// Print a shell completion script, e.g. `cekunit completions bash > /etc/bash_completion.d/cekunit`.
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print the manual page, or write pages for every subcommand with `--out`.
+    Man {
+        /// Directory to write `cekunit.1`, `cekunit-login.1`, ... to.
+        #[arg(long, value_name = "DIR")]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DashboardCommand {
+    /// List records.
+    List {
+        /// Only show records matching this term.
+        #[arg(long, short)]
+        search: Option<String>,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// Search records; the same as `list --search`.
+    Search {
+        /// Search term.
+        term: String,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// Export all records to a file.
+    Export {
+        #[command(flatten)]
+        export: ExportArgs,
+    },
+    /// List the distinct values of a column.
+    Unique {
+        /// Column name, e.g. `merk`.
+        column: String,
+    },
+    /// Update fields of a record.
+    Update {
+        /// Record number.
+        no: String,
+        /// Fields to set, as `name=value`.
+        #[arg(required = true, value_parser = parse_field)]
+        fields: Vec<(String, String)>,
+    },
+    /// Delete a record.
+    Delete {
+        /// Record number.
+        no: String,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+    /// Delete every record whose column has the given value.
+    DeleteBy {
+        /// Column name.
+        column: String,
+        /// Value to match.
+        value: String,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+    /// Delete all records.
+    DeleteAll {
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum InputUserCommand {
+    /// List rows.
+    List {
+        #[command(flatten)]
+        filter: InputUserFilter,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// Export rows to a file.
+    Export {
+        #[command(flatten)]
+        filter: InputUserFilter,
+        #[command(flatten)]
+        export: ExportArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum InputDataCommand {
+    /// Submit a new customer record.
+    Create {
+        /// Fields to submit, as `name=value`.
+        #[arg(required = true, value_parser = parse_field)]
+        fields: Vec<(String, String)>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PicCommand {
+    /// List persons in charge.
+    List {
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// Create a person in charge.
+    Create {
+        /// Fields to submit, as `name=value`.
+        #[arg(required = true, value_parser = parse_field)]
+        fields: Vec<(String, String)>,
+    },
+    /// Update fields of a person in charge.
+    Update {
+        /// PIC id.
+        id: String,
+        /// Fields to set, as `name=value`.
+        #[arg(required = true, value_parser = parse_field)]
+        fields: Vec<(String, String)>,
+    },
+    /// Delete a person in charge.
+    Delete {
+        /// PIC id.
+        id: String,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum UsersCommand {
+    /// List users.
+    List {
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// Update fields of a user.
+    Update {
+        /// User id.
+        id: String,
+        /// Fields to set, as `name=value`.
+        #[arg(required = true, value_parser = parse_field)]
+        fields: Vec<(String, String)>,
+    },
+}
+
+/// Pagination and server-side sorting of a listing.
+#[derive(Debug, Clone, Args)]
+pub struct PageArgs {
+    /// Page to fetch (1-based).
+    #[arg(long, short, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub page: u32,
+    /// Fetch every page from `--page` on, at most 20.
+    #[arg(long, short)]
+    pub all: bool,
+    /// Column the server sorts by.
+    #[arg(long)]
+    pub sort: Option<String>,
+    /// Sort direction.
+    #[arg(long, value_enum)]
+    pub direction: Option<Direction>,
+}
+
+/// Date and text filters of the input-user listing.
+#[derive(Debug, Clone, Args)]
+pub struct InputUserFilter {
+    /// Only show rows matching this term.
+    #[arg(long, short)]
+    pub search: Option<String>,
+    /// First date to include, e.g. `2025-01-01`.
+    #[arg(long, value_name = "DATE")]
+    pub from: Option<String>,
+    /// Last date to include.
+    #[arg(long, value_name = "DATE")]
+    pub to: Option<String>,
+}
+
+/// Options of the export commands.
+#[derive(Debug, Clone, Args)]
+pub struct ExportArgs {
+    /// Export format; supported values depend on the server.
+    #[arg(long, short, default_value = "excel")]
+    pub format: String,
+    /// Column to sort by.
+    #[arg(long, default_value = "created_at")]
+    pub sort: String,
+    /// Sort direction.
+    #[arg(long, value_enum, default_value_t = Direction::Desc)]
+    pub direction: Direction,
+    /// File to write; standard output when omitted.
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+}
+
+/// Options of `run`.
+#[derive(Debug, Clone, Args)]
+pub struct RunArgs {
+    /// Script file; the extension selects the format (`.yaml`, `.toml`, `.json`).
+    pub file: PathBuf,
+    /// Set a script variable, overriding its value in the file.
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_field)]
+    pub vars: Vec<(String, String)>,
+    /// Print the requests of every step without logging in or sending them.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Whether to run the remaining steps after one fails; overrides `on_error`.
+    #[arg(long, value_enum)]
+    pub on_error: Option<OnError>,
+    #[command(flatten)]
+    pub confirm: ConfirmArgs,
+}
+
+/// Options of `serve`.
+#[cfg(feature = "serve")]
+#[derive(Debug, Clone, Args)]
+pub struct ServeArgs {
+    /// Address and port to listen on.
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8780")]
+    pub listen: SocketAddr,
+    /// Key clients must send in `X-API-Key` or `Authorization: Bearer`; repeat for
+    /// several clients. Required unless listening on a loopback address.
+    #[arg(
+        long = "api-key",
+        value_name = "KEY",
+        env = "CEKUNIT_API_KEYS",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    pub api_keys: Vec<String>,
+}
+
+/// Options of `init`.
+#[derive(Debug, Clone, Args)]
+pub struct InitArgs {
+    /// Base URL of the CekUnit installation; asked for when omitted.
+    #[arg(long, value_name = "URL")]
+    pub base_url: Option<String>,
+    /// Login email; asked for when omitted.
+    #[arg(long, value_name = "EMAIL")]
+    pub email: Option<String>,
+    /// File format; `env` when the `--out` file name contains `.env`, else `toml`.
+    #[arg(long, value_enum)]
+    pub format: Option<InitFormat>,
+    /// File to write; by default the profile's config file, `--config`, the
+    /// platform `config.toml` or `./.env`.
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+    /// Do not store the password; ask for it on the terminal at login instead.
+    #[arg(long)]
+    pub prompt_password: bool,
+    #[command(flatten)]
+    pub confirm: ConfirmArgs,
+}
+
+/// Formats written by `init`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InitFormat {
+    /// A config file as read by `--config`.
+    Toml,
+    /// A `.env` file as read by `--env-file`.
+    Env,
+}
+
+/// Confirmation of destructive commands.
+#[derive(Debug, Clone, Copy, Args)]
+pub struct ConfirmArgs {
+    /// Do not ask for confirmation.
+    #[arg(long, short)]
+    pub yes: bool,
+}
+
+/// Sort direction accepted by the listing endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+#[schemars(inline)]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+
+impl Direction {
+    /// Returns the value sent to the server.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Asc => "asc",
+            Self::Desc => "desc",
+        }
+    }
+}
+
+/// Parses a `name=value` field argument.
+fn parse_field(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected name=value, got '{}'", arg)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_field() {
+        assert_eq!(
+            parse_field("nama=Budi = Santoso").unwrap(),
+            ("nama".into(), "Budi = Santoso".into())
+        );
+        assert_eq!(
+            parse_field("status=").unwrap(),
+            ("status".into(), "".into())
+        );
+        assert!(parse_field("=x").is_err());
+        assert!(parse_field("nama").is_err());
+    }
+}
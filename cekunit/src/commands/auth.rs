@@ -0,0 +1,54 @@
+//! `login`, `logout` and `status`.
+
+use crate::output::Printer;
+use librcekunit::{ApiError, CekUnitClient};
+use serde_json::Value;
+
+pub fn login(client: &mut CekUnitClient) -> Result<(), ApiError> {
+    client.login()?;
+    eprintln!(
+        "Logged in as {} (session cached in {})",
+        client.config().user_email,
+        client.cache_file_path().display()
+    );
+    Ok(())
+}
+
+pub fn logout(client: &mut CekUnitClient) -> Result<(), ApiError> {
+    if client.check_session()?.is_none() {
+        eprintln!("Not logged in");
+        return Ok(());
+    }
+    client.logout()?;
+    eprintln!("Logged out");
+    Ok(())
+}
+
+/// Prints the cached session; fails with [`ApiError::NotAuthenticated`] without one.
+pub fn status(client: &CekUnitClient, out: &Printer, verify: bool) -> Result<(), ApiError> {
+    let session = client
+        .check_session()?
+        .filter(|s| s.logged_in)
+        .ok_or(ApiError::NotAuthenticated)?;
+    let config = client.config();
+    let mut fields = vec![
+        ("base_url", Value::from(config.base_url.as_str())),
+        ("email", Value::from(config.user_email.as_str())),
+        (
+            "cache",
+            Value::from(client.cache_file_path().display().to_string()),
+        ),
+        (
+            "session_age",
+            Value::from(chrono::Utc::now().timestamp() - session.logged_in_at),
+        ),
+        ("session_max_age", Value::from(client.session_max_age())),
+    ];
+    if verify {
+        if !client.verify_session()? {
+            return Err(ApiError::Unauthorized);
+        }
+        fields.push(("verified", Value::from(true)));
+    }
+    out.fields(&fields)
+}
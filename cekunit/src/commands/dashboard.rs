@@ -0,0 +1,74 @@
+//! `dashboard` subcommands.
+
+use super::{confirm, fetch_pages, field_map, write_export};
+use crate::cli::{DashboardCommand, PageArgs};
+use crate::output::Printer;
+use librcekunit::{ApiError, CekUnitClient, DashboardClient};
+
+pub fn run(
+    client: &mut CekUnitClient,
+    out: &Printer,
+    command: DashboardCommand,
+) -> Result<(), ApiError> {
+    client.ensure_logged_in()?;
+    let dashboard = client.dashboard()?;
+    match command {
+        DashboardCommand::List { search, page } => list(&dashboard, out, search.as_deref(), &page),
+        DashboardCommand::Search { term, page } => list(&dashboard, out, Some(&term), &page),
+        DashboardCommand::Export { export } => {
+            let bytes = dashboard.export_cekunit(
+                &export.format,
+                &export.sort,
+                export.direction.as_str(),
+            )?;
+            write_export(&bytes, export.out.as_deref())
+        }
+        DashboardCommand::Unique { column } => {
+            let values = dashboard.get_unique_values(&column)?;
+            out.values(&column, &values)
+        }
+        DashboardCommand::Update { no, fields } => {
+            dashboard.update_cekunit(&no, field_map(&fields))?;
+            eprintln!("Updated record {}", no);
+            Ok(())
+        }
+        DashboardCommand::Delete { no, confirm: args } => {
+            confirm(&format!("delete record {}", no), args)?;
+            dashboard.delete_cekunit(&no)?;
+            eprintln!("Deleted record {}", no);
+            Ok(())
+        }
+        DashboardCommand::DeleteBy {
+            column,
+            value,
+            confirm: args,
+        } => {
+            confirm(
+                &format!("delete every record with {} = '{}'", column, value),
+                args,
+            )?;
+            dashboard.delete_by_category(&column, &value)?;
+            eprintln!("Deleted records with {} = '{}'", column, value);
+            Ok(())
+        }
+        DashboardCommand::DeleteAll { confirm: args } => {
+            confirm("delete ALL records", args)?;
+            dashboard.delete_all()?;
+            eprintln!("Deleted all records");
+            Ok(())
+        }
+    }
+}
+
+fn list(
+    dashboard: &DashboardClient,
+    out: &Printer,
+    search: Option<&str>,
+    args: &PageArgs,
+) -> Result<(), ApiError> {
+    let direction = args.direction.map(|d| d.as_str());
+    let listing = fetch_pages(args, |page| {
+        dashboard.get_dashboard(Some(page), search, args.sort.as_deref(), direction)
+    })?;
+    out.listing(listing)
+}
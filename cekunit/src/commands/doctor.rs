@@ -0,0 +1,56 @@
+//! `doctor`: runs [`librcekunit::doctor::diagnose`] and prints the report.
+
+use super::{cache_manager, config_loader};
+use crate::cli::Cli;
+use crate::output::OutputMode;
+use colored::Colorize;
+use librcekunit::ApiError;
+use librcekunit::api::dashboard::ListPage;
+use librcekunit::doctor::{CheckStatus, diagnose};
+
+/// Prints one line per check, with a hint under each warning and failure.
+///
+/// # Errors
+/// Returns [`ApiError::Other`] if a check failed, so the exit status is non-zero.
+pub fn run(cli: &Cli) -> Result<(), ApiError> {
+    let diagnosis = diagnose(&config_loader(cli)?, cache_manager(cli)?);
+    if cli.output == OutputMode::Table {
+        for check in &diagnosis.checks {
+            let mark = match check.status {
+                CheckStatus::Pass => "✓".green(),
+                CheckStatus::Warn => "!".yellow(),
+                CheckStatus::Fail => "✗".red(),
+                CheckStatus::Skip => "-".dimmed(),
+            };
+            println!("{} {}: {}", mark, check.name.bold(), check.detail);
+            if let Some(hint) = &check.hint {
+                println!("  {}", hint.dimmed());
+            }
+        }
+    } else {
+        let listing = ListPage {
+            columns: ["check", "status", "detail", "hint"]
+                .map(String::from)
+                .into(),
+            rows: diagnosis
+                .checks
+                .iter()
+                .map(|c| {
+                    vec![
+                        c.name.clone(),
+                        c.status.label().to_lowercase(),
+                        c.detail.clone(),
+                        c.hint.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect(),
+            ..ListPage::default()
+        };
+        cli.printer().listing(listing)?;
+    }
+    match diagnosis.failures().count() {
+        0 => Ok(()),
+        1 => Err(ApiError::Other("1 check failed".into())),
+        n => Err(ApiError::Other(format!("{} checks failed", n))),
+    }
+}
@@ -0,0 +1,378 @@
+//! `init`: asks for the connection settings, tests them and writes a config file.
+//!
+//! The login is tested with the session cache the written configuration will use, so
+//! the next command runs without logging in again. The listing and form endpoints
+//! are probed with a `GET`; on a terminal, endpoints that are not found can be given
+//! another path. The other endpoints keep their defaults.
+
+use super::{cache_manager, confirm, io_error};
+use crate::cli::{Cli, InitArgs, InitFormat};
+use crate::profile;
+use colored::Colorize;
+use librcekunit::handler::config::{ConfigFile, DEFAULT_ENDPOINTS, ENDPOINT_KEYS, VarMap};
+use librcekunit::{ApiError, CekUnitClient, EnvConfig};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+type Probe = fn(&CekUnitClient) -> Result<String, ApiError>;
+
+/// Endpoints that can be checked without changing anything.
+const PROBES: &[(&str, Probe)] = &[
+    ("DASHBOARD_ENDPOINT", |c| {
+        c.dashboard()?.get_dashboard(Some(1), None, None, None)
+    }),
+    ("INPUT_USER_ENDPOINT", |c| {
+        c.input_user()?
+            .get_input_user(Some(1), None, None, None, None, None)
+    }),
+    ("INPUT_DATA_ENDPOINT", |c| c.input_data()?.get_form()),
+    ("PIC_ENDPOINT", |c| {
+        c.pic()?.get_pic_list(Some(1), None, None)
+    }),
+    ("USERS_ENDPOINT", |c| {
+        c.users()?.get_users_list(Some(1), None, None)
+    }),
+];
+
+pub fn run(cli: &Cli, args: &InitArgs) -> Result<(), ApiError> {
+    let (path, format) = target(cli, args)?;
+    if path.exists() {
+        confirm(&format!("overwrite {}", path.display()), args.confirm)?;
+    }
+    let interactive = io::stdin().is_terminal();
+
+    let base_url = answer(
+        args.base_url.as_deref(),
+        "Base URL (e.g. https://cekunit.example.com)",
+        interactive,
+        check_url,
+    )?;
+    let base_url = base_url.trim_end_matches('/').to_string();
+    let email = answer(args.email.as_deref(), "Email", interactive, check_email)?;
+    let password = read_password(interactive)?;
+
+    let mut vars: VarMap = DEFAULT_ENDPOINTS
+        .iter()
+        .map(|(var, path)| (var.to_string(), path.to_string()))
+        .collect();
+    vars.insert("BASE_URL".into(), base_url.clone());
+    vars.insert("USER_EMAIL".into(), email.clone());
+    vars.insert("USER_PASSWORD".into(), password.clone());
+
+    let config = EnvConfig::from_vars(&vars)?;
+    let mut client = CekUnitClient::with_config_and_cache(config, cache_manager(cli)?)?;
+    client.login()?;
+    // The server answers a wrong password with a redirect back to the login page.
+    if !client.verify_session()? {
+        client.clear_session_cache()?;
+        return Err(ApiError::LoginFailed(format!(
+            "{} was not accepted by {}",
+            email, base_url
+        )));
+    }
+    eprintln!("{} Logged in as {}", "✓".green(), email);
+
+    let mut overrides = VarMap::new();
+    let missing = probe(&client, &vars);
+    if interactive && !missing.is_empty() {
+        for var in missing {
+            let label = format!("Path for {}, or Enter to keep '{}'", var, vars[var]);
+            let path = ask(&label, interactive, |_| Ok(()))?;
+            if !path.is_empty() {
+                vars.insert(var.to_string(), path.clone());
+                overrides.insert(var.to_string(), path);
+            }
+        }
+        if !overrides.is_empty() {
+            let config = EnvConfig::from_vars(&vars)?;
+            let client = CekUnitClient::with_config_and_cache(config, cache_manager(cli)?)?;
+            probe(&client, &vars);
+        }
+    }
+
+    let password = (!args.prompt_password).then_some(password.as_str());
+    let contents = render(format, &base_url, &email, password, &overrides)?;
+    write_private(&path, &contents)?;
+    eprintln!("Wrote {}", path.display());
+    eprintln!("Try: {}", hint(cli, args, &path, format));
+    Ok(())
+}
+
+/// Returns the file to write and its format.
+fn target(cli: &Cli, args: &InitArgs) -> Result<(PathBuf, InitFormat), ApiError> {
+    let format = args.format.unwrap_or_else(|| match &args.out {
+        Some(path) if path.to_string_lossy().contains(".env") => InitFormat::Env,
+        _ => InitFormat::Toml,
+    });
+    let path = match (&args.out, format, &cli.config, &cli.profile) {
+        (Some(path), ..) => path.clone(),
+        (None, InitFormat::Env, ..) => PathBuf::from(".env"),
+        (None, InitFormat::Toml, Some(path), _) => path.clone(),
+        (None, InitFormat::Toml, None, Some(name)) => profile::config_path(name)?,
+        (None, InitFormat::Toml, None, None) => profile::default_config_path()?,
+    };
+    Ok((path, format))
+}
+
+/// GETs the [`PROBES`] and prints the results.
+///
+/// # Returns
+/// The variables of the endpoints that were not found.
+fn probe(client: &CekUnitClient, vars: &VarMap) -> Vec<&'static str> {
+    let mut missing = Vec::new();
+    for (var, probe) in PROBES {
+        let name = format!("{} ({})", vars[*var], var);
+        match probe(client) {
+            Ok(_) => eprintln!("{} {}", "✓".green(), name),
+            Err(e) if matches!(e.kind(), ApiError::ResourceNotFound) => {
+                eprintln!("{} {}: not found", "✗".red(), name);
+                missing.push(*var);
+            }
+            Err(e) => eprintln!("{} {}: {}", "!".yellow(), name, e),
+        }
+    }
+    missing
+}
+
+/// Returns the value given on the command line, or asks for it.
+fn answer(
+    given: Option<&str>,
+    label: &str,
+    interactive: bool,
+    check: fn(&str) -> Result<(), String>,
+) -> Result<String, ApiError> {
+    match given {
+        Some(value) => {
+            check(value.trim()).map_err(ApiError::Other)?;
+            Ok(value.trim().to_string())
+        }
+        None => ask(label, interactive, check),
+    }
+}
+
+/// Reads one line from standard input, asking again on a terminal if it is invalid.
+fn ask(
+    label: &str,
+    interactive: bool,
+    check: fn(&str) -> Result<(), String>,
+) -> Result<String, ApiError> {
+    loop {
+        eprint!("{}: ", label);
+        io::stderr().flush().map_err(io_error)?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line).map_err(io_error)? == 0 {
+            return Err(ApiError::Other(format!("No answer for '{}'", label)));
+        }
+        let value = line.trim().to_string();
+        match check(&value) {
+            Ok(()) => return Ok(value),
+            Err(e) if interactive => eprintln!("{}", e.red()),
+            Err(e) => return Err(ApiError::Other(e)),
+        }
+    }
+}
+
+/// Reads the password without echo on a terminal, or as one line from a pipe.
+fn read_password(interactive: bool) -> Result<String, ApiError> {
+    let password = if interactive {
+        rpassword::prompt_password("Password: ").map_err(io_error)?
+    } else {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line).map_err(io_error)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+    if password.is_empty() {
+        return Err(ApiError::Other("The password must not be empty".into()));
+    }
+    Ok(password)
+}
+
+fn check_url(url: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"));
+    match rest {
+        Some(host) if !host.trim_matches('/').is_empty() => Ok(()),
+        _ => Err("The base URL must start with http:// or https://".into()),
+    }
+}
+
+fn check_email(email: &str) -> Result<(), String> {
+    if email.contains('@') {
+        Ok(())
+    } else {
+        Err("The email must contain '@'".into())
+    }
+}
+
+/// Renders the configuration; only endpoints that differ from the defaults are written.
+fn render(
+    format: InitFormat,
+    base_url: &str,
+    email: &str,
+    password: Option<&str>,
+    overrides: &VarMap,
+) -> Result<String, ApiError> {
+    let mut out = String::from("# Written by `cekunit init`.\n");
+    match format {
+        InitFormat::Toml => {
+            let file = ConfigFile {
+                base_url: Some(base_url.to_string()),
+                user_email: Some(email.to_string()),
+                user_password: password.map(str::to_string),
+                user_password_prompt: password.is_none().then_some(true),
+                endpoints: ENDPOINT_KEYS
+                    .iter()
+                    .filter_map(|(name, var)| {
+                        Some((name.to_string(), overrides.get(*var)?.clone()))
+                    })
+                    .collect(),
+                ..ConfigFile::default()
+            };
+            let toml = toml::to_string_pretty(&file).map_err(|e| ApiError::Other(e.to_string()))?;
+            out.push_str(&toml);
+        }
+        InitFormat::Env => {
+            let mut line = |var: &str, value: &str| {
+                out.push_str(&format!("{}={}\n", var, env_value(value)));
+            };
+            line("BASE_URL", base_url);
+            line("USER_EMAIL", email);
+            match password {
+                Some(password) => line("USER_PASSWORD", password),
+                None => line("USER_PASSWORD_PROMPT", "true"),
+            }
+            for (var, path) in overrides {
+                line(var, path);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Quotes a `.env` value unless it only contains safe characters.
+fn env_value(value: &str) -> String {
+    let plain = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-./:@+,".contains(c));
+    if plain {
+        value.to_string()
+    } else if !value.contains('\'') {
+        format!("'{}'", value)
+    } else {
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "\\$");
+        format!("\"{}\"", escaped)
+    }
+}
+
+/// Writes a file only its owner can read, since it may contain the password.
+fn write_private(path: &Path, contents: &str) -> Result<(), ApiError> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(io_error)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(io_error)?;
+    // The mode only applies to a new file; narrow an existing one before writing.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .map_err(io_error)?;
+    }
+    file.set_len(0).map_err(io_error)?;
+    file.write_all(contents.as_bytes()).map_err(io_error)
+}
+
+/// Returns a command that uses the written file.
+fn hint(cli: &Cli, args: &InitArgs, path: &Path, format: InitFormat) -> String {
+    let discovered = [".env", "cekunit.toml", "cekunit.json"]
+        .iter()
+        .any(|name| path == Path::new(name))
+        || profile::default_config_path().is_ok_and(|default| path == default);
+    match (&cli.profile, format) {
+        (Some(name), InitFormat::Toml) if args.out.is_none() && cli.config.is_none() => {
+            format!("cekunit --profile {} status", name)
+        }
+        _ if discovered => "cekunit status".into(),
+        (_, InitFormat::Toml) => format!("cekunit --config {} status", path.display()),
+        (_, InitFormat::Env) => format!("cekunit --env-file {} status", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_values_round_trip() {
+        let values = ["plain@example.com", "has space", "it's $HOME \"x\" \\"];
+        let mut overrides = VarMap::new();
+        overrides.insert("PIC_ENDPOINT".into(), "admin/pic".into());
+        for value in values {
+            let text = render(
+                InitFormat::Env,
+                "https://x.test",
+                value,
+                Some(value),
+                &overrides,
+            )
+            .unwrap();
+            let vars: VarMap = dotenvy::from_read_iter(text.as_bytes())
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(vars["USER_EMAIL"], value);
+            assert_eq!(vars["USER_PASSWORD"], value);
+            assert_eq!(vars["PIC_ENDPOINT"], "admin/pic");
+        }
+    }
+
+    #[test]
+    fn test_toml_without_password() {
+        let mut overrides = VarMap::new();
+        overrides.insert("USERS_ENDPOINT".into(), "admin/users".into());
+        let text = render(
+            InitFormat::Toml,
+            "https://x.test",
+            "a@x.test",
+            None,
+            &overrides,
+        )
+        .unwrap();
+        let file: ConfigFile = toml::from_str(&text).unwrap();
+        assert_eq!(file.user_password, None);
+        assert_eq!(file.user_password_prompt, Some(true));
+        assert_eq!(file.endpoints["users"], "admin/users");
+        assert_eq!(file.endpoints.len(), 1);
+    }
+
+    #[test]
+    fn test_checks() {
+        assert!(check_url("https://cekunit.example.com").is_ok());
+        assert!(check_url("https://").is_err());
+        assert!(check_url("cekunit.example.com").is_err());
+        assert!(check_email("admin").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private_narrows_existing_file() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profiles/staging.env");
+        write_private(&path, "USER_PASSWORD=first\n").unwrap();
+        let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, "X=1\n").unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "X=1\n");
+    }
+}
@@ -0,0 +1,56 @@
+//! `input-user` and `input-data` subcommands.
+
+use super::{fetch_pages, field_map, write_export};
+use crate::cli::{InputDataCommand, InputUserCommand};
+use crate::output::Printer;
+use librcekunit::{ApiError, CekUnitClient};
+
+pub fn run_input_user(
+    client: &mut CekUnitClient,
+    out: &Printer,
+    command: InputUserCommand,
+) -> Result<(), ApiError> {
+    client.ensure_logged_in()?;
+    let input_user = client.input_user()?;
+    match command {
+        InputUserCommand::List { filter, page } => {
+            let direction = page.direction.map(|d| d.as_str());
+            let listing = fetch_pages(&page, |n| {
+                input_user.get_input_user(
+                    Some(n),
+                    filter.search.as_deref(),
+                    page.sort.as_deref(),
+                    direction,
+                    filter.from.as_deref(),
+                    filter.to.as_deref(),
+                )
+            })?;
+            out.listing(listing)
+        }
+        InputUserCommand::Export { filter, export } => {
+            let bytes = input_user.export_input_user(
+                &export.format,
+                &export.sort,
+                export.direction.as_str(),
+                filter.search.as_deref(),
+                filter.from.as_deref(),
+                filter.to.as_deref(),
+            )?;
+            write_export(&bytes, export.out.as_deref())
+        }
+    }
+}
+
+pub fn run_input_data(
+    client: &mut CekUnitClient,
+    command: InputDataCommand,
+) -> Result<(), ApiError> {
+    client.ensure_logged_in()?;
+    match command {
+        InputDataCommand::Create { fields } => {
+            client.input_data()?.insert_nasabah(field_map(&fields))?;
+            eprintln!("Created record");
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! `completions` and `man`: files for installing the binary on a machine.
+
+use super::io_error;
+use crate::cli::Cli;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use librcekunit::ApiError;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Prints the completion script for `shell` to standard output.
+pub fn completions(shell: Shell) -> Result<(), ApiError> {
+    let mut command = Cli::command();
+    clap_complete::generate(shell, &mut command, "cekunit", &mut io::stdout());
+    Ok(())
+}
+
+/// Prints the main manual page, or writes one page per subcommand into `out`.
+pub fn man(out: Option<&Path>) -> Result<(), ApiError> {
+    let command = Cli::command();
+    match out {
+        Some(dir) => {
+            fs::create_dir_all(dir).map_err(io_error)?;
+            clap_mangen::generate_to(command, dir).map_err(io_error)?;
+            eprintln!("Wrote manual pages to {}", dir.display());
+            Ok(())
+        }
+        None => clap_mangen::Man::new(command)
+            .render(&mut io::stdout())
+            .map_err(io_error),
+    }
+}
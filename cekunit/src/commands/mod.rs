@@ -0,0 +1,346 @@
+//! Implementations of the `cekunit` subcommands.
+//!
+//! Every command runs against one [`CekUnitClient`] built from the global options.
+//! Data goes to standard output; progress and confirmation messages go to standard
+//! error so the output can be piped.
+
+pub mod auth;
+pub mod dashboard;
+pub mod doctor;
+pub mod init;
+pub mod input;
+pub mod install;
+pub mod pic;
+pub mod users;
+
+use crate::cli::{Cli, Command, ConfirmArgs, PageArgs};
+use crate::output::Printer;
+#[cfg(feature = "serve")]
+use crate::serve;
+use crate::{profile, rpc, script, shell, tui};
+use librcekunit::api::dashboard::ListPage;
+use librcekunit::handler::config::ConfigLoader;
+use librcekunit::{ApiError, CacheManager, CekUnitClient};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+/// Runs the command selected on the command line.
+///
+/// # Errors
+/// Returns the first [`ApiError`] raised by the configuration, the session or the
+/// request itself.
+pub fn run(cli: Cli) -> Result<(), ApiError> {
+    // These commands work without a configuration.
+    match &cli.command {
+        Command::Init(args) => return init::run(&cli, args),
+        Command::Doctor => return doctor::run(&cli),
+        Command::Completions { shell } => return install::completions(*shell),
+        Command::Man { out } => return install::man(out.as_deref()),
+        _ => {}
+    }
+    let mut client = build_client(&cli)?;
+    match cli.command {
+        Command::Shell => return shell::run(client, &cli),
+        #[cfg(feature = "serve")]
+        Command::Serve(args) => return serve::run(client, args),
+        Command::Rpc => return rpc::run(client),
+        _ => {}
+    }
+    execute(&mut client, &cli.printer(), cli.command)
+}
+
+/// Runs one command with an existing client.
+///
+/// # Errors
+/// Returns the [`ApiError`] of the command; `shell` cannot be nested and `init`
+/// cannot run with an existing client.
+pub fn execute(
+    client: &mut CekUnitClient,
+    out: &Printer,
+    command: Command,
+) -> Result<(), ApiError> {
+    match command {
+        Command::Login => auth::login(client),
+        Command::Logout => auth::logout(client),
+        Command::Status { verify } => auth::status(client, out, verify),
+        Command::Dashboard(command) => dashboard::run(client, out, command),
+        Command::InputUser(command) => input::run_input_user(client, out, command),
+        Command::InputData(command) => input::run_input_data(client, command),
+        Command::Pic(command) => pic::run(client, out, command),
+        Command::Users(command) => users::run(client, out, command),
+        Command::Tui => tui::run(client),
+        Command::Run(args) => script::run(client, out, args),
+        Command::Completions { shell } => install::completions(shell),
+        Command::Man { out } => install::man(out.as_deref()),
+        Command::Init(_) => Err(ApiError::Other(
+            "Run `cekunit init` on its own, outside the shell".into(),
+        )),
+        Command::Doctor => Err(ApiError::Other(
+            "Run `cekunit doctor` on its own, outside the shell".into(),
+        )),
+        #[cfg(feature = "serve")]
+        Command::Serve(_) => Err(ApiError::Other(
+            "Run `cekunit serve` on its own, outside the shell".into(),
+        )),
+        Command::Rpc => Err(ApiError::Other(
+            "Run `cekunit rpc` on its own, outside the shell".into(),
+        )),
+        Command::Shell => Err(ApiError::Other("Already in the shell".into())),
+    }
+}
+
+/// Builds the client from the global options.
+fn build_client(cli: &Cli) -> Result<CekUnitClient, ApiError> {
+    let config = config_loader(cli)?.load()?;
+    CekUnitClient::with_config_and_cache(config, cache_manager(cli)?)
+}
+
+/// Returns the configuration sources selected by `--config`, `--env-file` and the
+/// profile.
+pub(crate) fn config_loader(cli: &Cli) -> Result<ConfigLoader, ApiError> {
+    let mut loader = ConfigLoader::new();
+    if let Some(path) = &cli.config {
+        loader = loader.config_file(path);
+    } else if let Some(name) = &cli.profile {
+        loader = loader.config_file(profile::config_path(name)?);
+    }
+    if let Some(path) = &cli.env_file {
+        loader = loader.env_file(path);
+    }
+    Ok(loader)
+}
+
+/// Returns the session cache selected by `--cache-dir` or the profile.
+pub(crate) fn cache_manager(cli: &Cli) -> Result<CacheManager, ApiError> {
+    let cache_dir = match (&cli.cache_dir, &cli.profile) {
+        (Some(dir), _) => Some(dir.clone()),
+        (None, Some(name)) => Some(profile::cache_dir(name)?),
+        (None, None) => None,
+    };
+    match &cache_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir)
+                .map_err(|e| ApiError::CacheError(format!("Failed to create cache dir: {}", e)))?;
+            Ok(CacheManager::with_paths(
+                dir.clone(),
+                dir.join("session.json"),
+            ))
+        }
+        None => CacheManager::new(),
+    }
+}
+
+/// Pages of a listing read at most by one `--all`, `get` or completion lookup.
+pub(crate) const MAX_PAGES: u32 = 20;
+
+/// Fetches the page selected by `args`, and the following ones with `--all`.
+///
+/// The rows of every fetched page are appended to the first one. At most
+/// [`MAX_PAGES`] pages are read; the listing's `page` is then the last one read, so
+/// [`ListPage::has_next`] tells that more remain.
+pub(crate) fn fetch_pages(
+    args: &PageArgs,
+    mut fetch: impl FnMut(u32) -> Result<String, ApiError>,
+) -> Result<ListPage, ApiError> {
+    let mut listing = ListPage::parse(&fetch(args.page)?)?;
+    let mut last = listing.clone();
+    let mut read = 1;
+    while args.all && last.has_next() {
+        if read == MAX_PAGES {
+            log::warn!(
+                "stopped after {} pages; the listing continues at page {}",
+                MAX_PAGES,
+                last.page.unwrap_or(args.page) + 1
+            );
+            break;
+        }
+        let next = last.page.unwrap_or(args.page) + 1;
+        last = ListPage::parse(&fetch(next)?)?;
+        listing.rows.extend(last.rows.iter().cloned());
+        listing.ids.extend(last.ids.iter().cloned());
+        listing.page = last.page;
+        read += 1;
+    }
+    Ok(listing)
+}
+
+/// Writes exported bytes to `out`, or to standard output.
+pub(crate) fn write_export(bytes: &[u8], out: Option<&Path>) -> Result<(), ApiError> {
+    match out {
+        Some(path) => {
+            fs::write(path, bytes).map_err(io_error)?;
+            eprintln!("Wrote {} bytes to {}", bytes.len(), path.display());
+        }
+        None => io::stdout().lock().write_all(bytes).map_err(io_error)?,
+    }
+    Ok(())
+}
+
+/// Converts `name=value` arguments into the map the sub-clients expect.
+pub(crate) fn field_map(fields: &[(String, String)]) -> HashMap<&str, &str> {
+    fields
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect()
+}
+
+/// Converts a JSON object of fields, as sent to `serve` and `rpc`, into
+/// `name=value` pairs; numbers and booleans become text and `null` an empty value.
+///
+/// # Errors
+/// Returns [`ApiError::ValidationError`] if a field is an array or an object.
+pub(crate) fn json_fields(
+    fields: &BTreeMap<String, Value>,
+) -> Result<Vec<(String, String)>, ApiError> {
+    fields
+        .iter()
+        .map(|(name, value)| {
+            let text = match value {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                Value::Null => String::new(),
+                _ => {
+                    return Err(ApiError::ValidationError(format!(
+                        "field '{}' must be a string, number or boolean",
+                        name
+                    )));
+                }
+            };
+            Ok((name.clone(), text))
+        })
+        .collect()
+}
+
+/// Runs `op`, logging in again and retrying once if the session turns out to be
+/// missing or expired.
+///
+/// A listing answered with the login page fails to parse, a JSON endpoint answered
+/// with it fails to decode and a post with a stale session fails with 419, so on those
+/// errors the server is asked whether the session is still valid before logging in.
+/// Used by the long-running `serve` and `rpc` modes, which log in lazily, and by the
+/// completion lookups of the shell.
+///
+/// # Errors
+/// Returns the [`ApiError`] of `op`, or of the login.
+pub(crate) fn with_relogin<T>(
+    client: &mut CekUnitClient,
+    op: impl Fn(&CekUnitClient) -> Result<T, ApiError>,
+) -> Result<T, ApiError> {
+    match op(client) {
+        Err(e)
+            if (e.is_auth_error()
+                || matches!(e.kind(), ApiError::HtmlParseError(_))
+                || matches!(e.kind(), ApiError::Transport { source, .. } if source.is_decode()))
+                && !client.verify_session().unwrap_or(false) =>
+        {
+            log::info!("session not valid ({}); logging in again", e.code());
+            client.login()?;
+            op(client)
+        }
+        result => result,
+    }
+}
+
+/// Asks on the terminal whether to go ahead with `action`.
+///
+/// # Errors
+/// Returns [`ApiError::Other`] if the user declines, or if standard input is not a
+/// terminal and `--yes` was not given.
+pub(crate) fn confirm(action: &str, args: ConfirmArgs) -> Result<(), ApiError> {
+    if args.yes {
+        return Ok(());
+    }
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return Err(ApiError::Other(format!(
+            "Refusing to {} without confirmation; pass --yes",
+            action
+        )));
+    }
+    eprint!("{}? [y/N] ", capitalize(action));
+    io::stderr().flush().map_err(io_error)?;
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer).map_err(io_error)?;
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        Ok(())
+    } else {
+        Err(ApiError::Other("Aborted".into()))
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub(crate) fn io_error(e: io::Error) -> ApiError {
+    ApiError::IoError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders page `n` of a listing with one row per page and `pages` pages.
+    fn page_html(n: u32, pages: u32) -> String {
+        let links: String = (1..=pages)
+            .map(|p| match p == n {
+                true => format!(r#"<li class="active" aria-current="page"><span>{p}</span></li>"#),
+                false => format!(r#"<li><a href="?page={p}">{p}</a></li>"#),
+            })
+            .collect();
+        format!(
+            r#"<table><thead><tr><th>Nama</th></tr></thead><tbody>
+            <tr><td>row {n}</td><td><a href="/pic/{n}/edit">Edit</a></td></tr></tbody></table>
+            <nav><ul class="pagination">{links}</ul></nav>"#
+        )
+    }
+
+    #[test]
+    fn test_fetch_pages_stops_at_the_limit() {
+        let args = PageArgs {
+            page: 2,
+            all: true,
+            sort: None,
+            direction: None,
+        };
+        let mut fetched = Vec::new();
+        let listing = fetch_pages(&args, |n| {
+            fetched.push(n);
+            Ok(page_html(n, 50))
+        })
+        .unwrap();
+        assert_eq!(fetched, (2..2 + MAX_PAGES).collect::<Vec<_>>());
+        assert_eq!(listing.len(), MAX_PAGES as usize);
+        assert_eq!(listing.ids.len(), listing.rows.len());
+        assert_eq!(listing.id(1), Some("3"));
+        assert_eq!(listing.page, Some(MAX_PAGES + 1));
+        assert!(listing.has_next());
+    }
+
+    #[test]
+    fn test_json_fields() {
+        let fields: BTreeMap<String, Value> =
+            serde_json::from_str(r#"{"nama": "Hana", "no_hp": 811, "aktif": true, "email": null}"#)
+                .unwrap();
+        let pairs = json_fields(&fields).unwrap();
+        let map = field_map(&pairs);
+        assert_eq!(map["no_hp"], "811");
+        assert_eq!(map["aktif"], "true");
+        assert_eq!(map["email"], "");
+
+        let nested: BTreeMap<String, Value> =
+            serde_json::from_str(r#"{"nama": ["Hana"]}"#).unwrap();
+        assert!(matches!(
+            json_fields(&nested),
+            Err(ApiError::ValidationError(_))
+        ));
+    }
+}
@@ -0,0 +1,36 @@
+//! `pic` subcommands.
+
+use super::{confirm, fetch_pages, field_map};
+use crate::cli::PicCommand;
+use crate::output::Printer;
+use librcekunit::{ApiError, CekUnitClient};
+
+pub fn run(client: &mut CekUnitClient, out: &Printer, command: PicCommand) -> Result<(), ApiError> {
+    client.ensure_logged_in()?;
+    let pic = client.pic()?;
+    match command {
+        PicCommand::List { page } => {
+            let direction = page.direction.map(|d| d.as_str());
+            let listing = fetch_pages(&page, |n| {
+                pic.get_pic_list(Some(n), page.sort.as_deref(), direction)
+            })?;
+            out.listing(listing)
+        }
+        PicCommand::Create { fields } => {
+            pic.insert_pic(field_map(&fields))?;
+            eprintln!("Created PIC");
+            Ok(())
+        }
+        PicCommand::Update { id, fields } => {
+            pic.update_pic(&id, field_map(&fields))?;
+            eprintln!("Updated PIC {}", id);
+            Ok(())
+        }
+        PicCommand::Delete { id, confirm: args } => {
+            confirm(&format!("delete PIC {}", id), args)?;
+            pic.delete_pic(&id)?;
+            eprintln!("Deleted PIC {}", id);
+            Ok(())
+        }
+    }
+}
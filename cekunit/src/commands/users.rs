@@ -0,0 +1,29 @@
+//! `users` subcommands.
+
+use super::{fetch_pages, field_map};
+use crate::cli::UsersCommand;
+use crate::output::Printer;
+use librcekunit::{ApiError, CekUnitClient};
+
+pub fn run(
+    client: &mut CekUnitClient,
+    out: &Printer,
+    command: UsersCommand,
+) -> Result<(), ApiError> {
+    client.ensure_logged_in()?;
+    let users = client.users()?;
+    match command {
+        UsersCommand::List { page } => {
+            let direction = page.direction.map(|d| d.as_str());
+            let listing = fetch_pages(&page, |n| {
+                users.get_users_list(Some(n), page.sort.as_deref(), direction)
+            })?;
+            out.listing(listing)
+        }
+        UsersCommand::Update { id, fields } => {
+            users.update_user(&id, field_map(&fields))?;
+            eprintln!("Updated user {}", id);
+            Ok(())
+        }
+    }
+}
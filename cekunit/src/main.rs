@@ -0,0 +1,37 @@
+//! `cekunit`: command-line client for the CekUnit admin panel.
+//!
+//! A thin layer over [`librcekunit::CekUnitClient`]; see `cekunit --help`.
+
+mod cli;
+mod commands;
+mod output;
+mod profile;
+mod rpc;
+mod script;
+#[cfg(feature = "serve")]
+mod serve;
+mod shell;
+mod tui;
+
+use clap::Parser;
+use cli::Cli;
+use colored::Colorize;
+use output::OutputMode;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let cli = Cli::parse();
+    let mode = cli.output;
+    match commands::run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if matches!(mode, OutputMode::Json | OutputMode::Ndjson) {
+                eprintln!("{}", output::error_json(&e));
+            } else {
+                eprintln!("{} {}", "error:".red().bold(), e);
+            }
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
@@ -0,0 +1,457 @@
+//! Rendering of command results as a table, JSON, NDJSON or CSV.
+//!
+//! Only the table mode writes anything besides data to standard output; the other
+//! modes are meant to be piped into `jq` or a spreadsheet. The table shows the headers
+//! of the panel ("Nama Nasabah"); the other modes name each column by its form field
+//! (`nama_nasabah`) where the page gives one, so the names match `update` and `sort`.
+
+use crate::commands::io_error;
+use clap::ValueEnum;
+use colored::Colorize;
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use comfy_table::{Cell, Color, ContentArrangement, Table};
+use librcekunit::ApiError;
+use librcekunit::api::dashboard::ListPage;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Output format selected with `--output`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputMode {
+    /// Aligned table for reading in a terminal.
+    #[default]
+    Table,
+    /// One JSON document.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+/// Client-side ordering given as `COLUMN` or `COLUMN:asc|desc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBy {
+    pub column: String,
+    pub descending: bool,
+}
+
+impl FromStr for OrderBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (column, direction) = match s.rsplit_once(':') {
+            Some((column, direction)) => (column, Some(direction)),
+            None => (s, None),
+        };
+        let descending = match direction.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(other) => return Err(format!("unknown direction '{}', use asc or desc", other)),
+        };
+        if column.trim().is_empty() {
+            return Err("missing column name".into());
+        }
+        Ok(Self {
+            column: column.trim().to_string(),
+            descending,
+        })
+    }
+}
+
+/// Renders results according to the global output options.
+#[derive(Debug, Clone, Default)]
+pub struct Printer {
+    pub mode: OutputMode,
+    /// Columns to keep, in this order; all columns when `None`.
+    pub columns: Option<Vec<String>>,
+    pub order_by: Option<OrderBy>,
+}
+
+impl Printer {
+    /// Prints a listing after applying `--columns` and `--order-by`.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::Other`] if a selected column does not exist.
+    pub fn listing(&self, mut listing: ListPage) -> Result<(), ApiError> {
+        if let Some(order) = &self.order_by {
+            sort(&mut listing, order)?;
+        }
+        if let Some(columns) = &self.columns {
+            let indices = columns
+                .iter()
+                .map(|c| column_index(&listing, c))
+                .collect::<Result<Vec<_>, _>>()?;
+            let pick = |row: &[String]| -> Vec<String> {
+                indices.iter().map(|&i| cell(row, i).to_string()).collect()
+            };
+            listing.rows = listing.rows.iter().map(|row| pick(row)).collect();
+            listing.columns = pick(&listing.columns);
+            listing.fields = indices
+                .iter()
+                .map(|&i| listing.fields.get(i).cloned().flatten())
+                .collect();
+        }
+
+        match self.mode {
+            OutputMode::Table => {
+                if listing.is_empty() {
+                    eprintln!("{}", "No records".dimmed());
+                } else {
+                    println!("{}", table(&listing));
+                }
+                match (listing.page, listing.pages, listing.total) {
+                    (Some(page), Some(pages), Some(total)) => {
+                        eprintln!("Page {} of {} ({} records)", page, pages, total)
+                    }
+                    (Some(page), Some(pages), None) => eprintln!("Page {} of {}", page, pages),
+                    _ => {}
+                }
+                Ok(())
+            }
+            OutputMode::Json => {
+                let keys = keys(&listing);
+                let records: Vec<Record> =
+                    listing.rows.iter().map(|row| Record(&keys, row)).collect();
+                print_json(&records)
+            }
+            OutputMode::Ndjson => {
+                let keys = keys(&listing);
+                let mut out = io::stdout().lock();
+                for row in &listing.rows {
+                    let line = serde_json::to_string(&Record(&keys, row))
+                        .map_err(|e| ApiError::JsonError(e.to_string()))?;
+                    writeln!(out, "{}", line).map_err(io_error)?;
+                }
+                Ok(())
+            }
+            OutputMode::Csv => print_csv(&keys(&listing), &listing.rows),
+        }
+    }
+
+    /// Prints a list of values of one column.
+    pub fn values(&self, column: &str, values: &[String]) -> Result<(), ApiError> {
+        match self.mode {
+            OutputMode::Table => {
+                let mut out = io::stdout().lock();
+                for value in values {
+                    writeln!(out, "{}", value).map_err(io_error)?;
+                }
+                Ok(())
+            }
+            OutputMode::Json => print_json(&values),
+            OutputMode::Ndjson => {
+                let mut out = io::stdout().lock();
+                for value in values {
+                    writeln!(out, "{}", Value::from(value.as_str())).map_err(io_error)?;
+                }
+                Ok(())
+            }
+            OutputMode::Csv => {
+                let rows: Vec<Vec<String>> = values.iter().map(|v| vec![v.clone()]).collect();
+                print_csv(&[column.to_string()], &rows)
+            }
+        }
+    }
+
+    /// Prints one record of named fields, such as the session status.
+    pub fn fields(&self, fields: &[(&str, Value)]) -> Result<(), ApiError> {
+        let object = Fields(fields);
+        match self.mode {
+            OutputMode::Table => {
+                let mut out = io::stdout().lock();
+                for (name, value) in fields {
+                    writeln!(out, "{}: {}", name.bold(), plain(value)).map_err(io_error)?;
+                }
+                Ok(())
+            }
+            OutputMode::Json => print_json(&object),
+            OutputMode::Ndjson => {
+                let line = serde_json::to_string(&object)
+                    .map_err(|e| ApiError::JsonError(e.to_string()))?;
+                writeln!(io::stdout().lock(), "{}", line).map_err(io_error)
+            }
+            OutputMode::Csv => {
+                let header: Vec<String> = fields.iter().map(|(n, _)| n.to_string()).collect();
+                let row: Vec<String> = fields.iter().map(|(_, v)| plain(v)).collect();
+                print_csv(&header, &[row])
+            }
+        }
+    }
+}
+
+/// Describes a listing as one JSON object, for `serve` and `rpc`:
+/// `{"page", "pages", "total", "next_page", "records"}` with each row keyed by column.
+/// `next_page` is `null` after the last page; with `all` it is set when the listing
+/// stopped at [`MAX_PAGES`](crate::commands::MAX_PAGES).
+pub fn listing_json(listing: &ListPage) -> Value {
+    let keys = keys(listing);
+    let records: Vec<Record> = listing.rows.iter().map(|row| Record(&keys, row)).collect();
+    serde_json::json!({
+        "page": listing.page,
+        "pages": listing.pages,
+        "total": listing.total,
+        "next_page": listing.page.filter(|_| listing.has_next()).map(|page| page + 1),
+        "records": records,
+    })
+}
+
+/// Describes one row of a listing as a JSON object keyed by column.
+pub fn record_json(listing: &ListPage, row: usize) -> Value {
+    let row = listing.rows.get(row).map_or(&[][..], Vec::as_slice);
+    serde_json::to_value(Record(&keys(listing), row)).unwrap_or_default()
+}
+
+/// Names the columns of a listing by their form field, or by their header when the
+/// page does not give one. A name used before gets a suffix: `nama`, `nama_2`, ...
+fn keys(listing: &ListPage) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::with_capacity(listing.columns.len());
+    for i in 0..listing.columns.len() {
+        let name = listing.field(i).unwrap_or(&listing.columns[i]);
+        let mut key = name.to_string();
+        let mut n = 2;
+        while keys.contains(&key) {
+            key = format!("{}_{}", name, n);
+            n += 1;
+        }
+        keys.push(key);
+    }
+    keys
+}
+
+/// Sorts the rows of a listing by a column, keeping each row's id with it.
+fn sort(listing: &mut ListPage, order: &OrderBy) -> Result<(), ApiError> {
+    let index = column_index(listing, &order.column)?;
+    let mut ids = std::mem::take(&mut listing.ids);
+    ids.resize(listing.rows.len(), None);
+    let mut pairs: Vec<_> = std::mem::take(&mut listing.rows)
+        .into_iter()
+        .zip(ids)
+        .collect();
+    pairs.sort_by(|(a, _), (b, _)| {
+        let ordering = compare(cell(a, index), cell(b, index));
+        if order.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    (listing.rows, listing.ids) = pairs.into_iter().unzip();
+    Ok(())
+}
+
+/// A row serialised as a JSON object with keys in column order.
+struct Record<'a>(&'a [String], &'a [String]);
+
+impl Serialize for Record<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (i, column) in self.0.iter().enumerate() {
+            map.serialize_entry(column, cell(self.1, i))?;
+        }
+        map.end()
+    }
+}
+
+/// Named fields serialised as a JSON object with keys in the given order.
+struct Fields<'a>(&'a [(&'a str, Value)]);
+
+impl Serialize for Fields<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Builds the terminal table, colouring status columns.
+///
+/// `comfy-table` measures ANSI escapes as text unless built with its `custom_styling`
+/// feature, so cells are coloured through its own API; `colored` decides whether
+/// colour is enabled (`NO_COLOR`, `CLICOLOR`, `CLICOLOR_FORCE`, terminal detection).
+fn table(listing: &ListPage) -> Table {
+    let colorize = colored::control::SHOULD_COLORIZE.should_colorize();
+    let status_columns: Vec<bool> = listing
+        .columns
+        .iter()
+        .map(|c| c.to_lowercase().contains("status"))
+        .collect();
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    if colorize {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+    table.set_header(
+        listing
+            .columns
+            .iter()
+            .map(|c| Cell::new(c).add_attribute(comfy_table::Attribute::Bold)),
+    );
+    for row in &listing.rows {
+        table.add_row(row.iter().enumerate().map(|(i, value)| {
+            let cell = Cell::new(value);
+            match status_color(value) {
+                Some(color) if colorize && status_columns.get(i) == Some(&true) => cell.fg(color),
+                _ => cell,
+            }
+        }));
+    }
+    table
+}
+
+/// Returns the colour of a status value: green when done or active, yellow while in
+/// progress, red when failed or inactive.
+fn status_color(value: &str) -> Option<Color> {
+    let value = value.to_lowercase();
+    let any = |words: &[&str]| words.iter().any(|w| value.contains(w));
+    if any(&[
+        "nonaktif", "inactive", "gagal", "fail", "tolak", "reject", "batal", "cancel",
+    ]) {
+        Some(Color::Red)
+    } else if any(&[
+        "aktif", "active", "selesai", "done", "success", "sukses", "approved",
+    ]) {
+        Some(Color::Green)
+    } else if any(&["pending", "proses", "process", "waiting", "menunggu"]) {
+        Some(Color::Yellow)
+    } else {
+        None
+    }
+}
+
+fn column_index(listing: &ListPage, name: &str) -> Result<usize, ApiError> {
+    listing.column_index(name).ok_or_else(|| {
+        ApiError::Other(format!(
+            "Unknown column '{}'; available: {}",
+            name,
+            keys(listing).join(", ")
+        ))
+    })
+}
+
+fn cell(row: &[String], index: usize) -> &str {
+    row.get(index).map(String::as_str).unwrap_or("")
+}
+
+/// Compares numerically when both values are numbers, else case-insensitively.
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+/// Returns a JSON value as plain text, without quotes around strings.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), ApiError> {
+    let json =
+        serde_json::to_string_pretty(value).map_err(|e| ApiError::JsonError(e.to_string()))?;
+    writeln!(io::stdout().lock(), "{}", json).map_err(io_error)
+}
+
+/// Describes a failed command as one JSON object, for `--output json` and `ndjson`.
+///
+/// `code` and `exit_code` are stable (see [`ApiError::code`] and
+/// [`ApiError::exit_code`]); `status` is `null` when no HTTP response was received.
+pub fn error_json(err: &ApiError) -> Value {
+    serde_json::json!({
+        "code": err.code(),
+        "exit_code": err.exit_code(),
+        "message": err.to_string(),
+        "status": err.status().map(|s| s.as_u16()),
+        "operation": err.context().map(|c| c.operation.as_str()),
+        "fields": err.field_errors(),
+    })
+}
+
+fn print_csv(header: &[String], rows: &[Vec<String>]) -> Result<(), ApiError> {
+    let mut writer = csv::Writer::from_writer(io::stdout().lock());
+    let csv_error = |e: csv::Error| ApiError::IoError(e.to_string());
+    writer.write_record(header).map_err(csv_error)?;
+    for row in rows {
+        writer.write_record(row).map_err(csv_error)?;
+    }
+    writer.flush().map_err(io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_by_parsing() {
+        let order: OrderBy = "nopol:DESC".parse().unwrap();
+        assert_eq!(order.column, "nopol");
+        assert!(order.descending);
+        assert!(!"no".parse::<OrderBy>().unwrap().descending);
+        assert!("no:sideways".parse::<OrderBy>().is_err());
+        assert!(":desc".parse::<OrderBy>().is_err());
+    }
+
+    #[test]
+    fn test_compare_and_status_colors() {
+        assert_eq!(compare("9", "10"), Ordering::Less);
+        assert_eq!(compare("b", "A"), Ordering::Greater);
+        assert_eq!(status_color("Aktif"), Some(Color::Green));
+        assert_eq!(status_color("nonaktif"), Some(Color::Red));
+        assert_eq!(status_color("pending"), Some(Color::Yellow));
+        assert_eq!(status_color("Honda"), None);
+    }
+
+    #[test]
+    fn test_record_keeps_column_order() {
+        let columns = vec!["no".to_string(), "merk".to_string(), "id".to_string()];
+        let row = vec!["1".to_string(), "Honda".to_string()];
+        let json = serde_json::to_string(&Record(&columns, &row)).unwrap();
+        assert_eq!(json, r#"{"no":"1","merk":"Honda","id":""}"#);
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_sort_keeps_ids_with_rows() {
+        let mut listing = ListPage {
+            columns: strings(&["Nama"]),
+            rows: vec![strings(&["Citra"]), strings(&["Andi"]), strings(&["Budi"])],
+            ids: vec![Some("3".into()), Some("1".into()), None],
+            ..Default::default()
+        };
+        sort(&mut listing, &"nama:desc".parse().unwrap()).unwrap();
+        assert_eq!(listing.rows, [["Citra"], ["Budi"], ["Andi"]]);
+        assert_eq!(listing.ids, [Some("3".into()), None, Some("1".into())]);
+    }
+
+    #[test]
+    fn test_duplicate_keys_get_suffixes() {
+        let listing = ListPage {
+            columns: strings(&["Nama", "Nama", "Email", "nama"]),
+            fields: vec![None, None, Some("nama".into()), None],
+            rows: vec![strings(&["a", "b", "c", "d"])],
+            ..Default::default()
+        };
+        assert_eq!(keys(&listing), ["Nama", "Nama_2", "nama", "nama_2"]);
+        assert_eq!(
+            record_json(&listing, 0),
+            serde_json::json!({"Nama": "a", "Nama_2": "b", "nama": "c", "nama_2": "d"})
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! Named profiles: one config file and one session cache per CekUnit installation.
+//!
+//! A profile `NAME` reads `profiles/NAME.toml` in the platform config directory
+//! (`~/.config/libcekunit/profiles/` on Linux) and caches its session in
+//! `profiles/NAME/` in the platform cache directory, so several installations can be
+//! used side by side.
+
+use directories::ProjectDirs;
+use librcekunit::ApiError;
+use std::path::PathBuf;
+
+/// Name shown when no profile is selected.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Checks that a profile name is usable as a file name.
+pub fn parse_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err("profile names may only contain letters, digits, '-' and '_'".into())
+    }
+}
+
+/// Returns the config file of profile `name`.
+pub fn config_path(name: &str) -> Result<PathBuf, ApiError> {
+    Ok(dirs()?
+        .config_dir()
+        .join("profiles")
+        .join(format!("{}.toml", name)))
+}
+
+/// Returns the platform `config.toml`, which is read when neither a profile nor
+/// `--config` is given.
+pub fn default_config_path() -> Result<PathBuf, ApiError> {
+    Ok(dirs()?.config_dir().join("config.toml"))
+}
+
+/// Returns the session cache directory of profile `name`.
+pub fn cache_dir(name: &str) -> Result<PathBuf, ApiError> {
+    Ok(dirs()?.cache_dir().join("profiles").join(name))
+}
+
+fn dirs() -> Result<ProjectDirs, ApiError> {
+    ProjectDirs::from("com", "cekunit", "libcekunit")
+        .ok_or_else(|| ApiError::CacheError("Cannot determine the home directory".into()))
+}
@@ -0,0 +1,595 @@
+//! The methods of `cekunit rpc`, each with typed parameters and their JSON Schema.
+//!
+//! Parameters are given by name, as an object; the schema of each method is
+//! generated from its parameter type, so `rpc.discover` and the parsing in
+//! [`dispatch`] cannot disagree.
+
+use super::{INVALID_PARAMS, METHOD_NOT_FOUND, RpcError};
+use crate::cli::{Direction, PageArgs};
+use crate::commands::{MAX_PAGES, fetch_pages, field_map, json_fields, with_relogin};
+use crate::output::{listing_json, record_json};
+use librcekunit::api::dashboard::ListPage;
+use librcekunit::{ApiError, CekUnitClient};
+use schemars::{JsonSchema, schema_for};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// One method as listed by `rpc.discover`.
+pub struct Method {
+    pub name: &'static str,
+    pub summary: &'static str,
+    /// Whether the method changes or deletes data on the panel.
+    pub mutates: bool,
+    /// JSON Schema of the `params` object.
+    pub params: Value,
+}
+
+/// Returns every method, in the order `rpc.discover` lists them.
+pub fn methods() -> Vec<Method> {
+    fn method<P: JsonSchema>(name: &'static str, summary: &'static str, mutates: bool) -> Method {
+        let params = serde_json::to_value(schema_for!(P)).unwrap_or_default();
+        Method {
+            name,
+            summary,
+            mutates,
+            params,
+        }
+    }
+    vec![
+        method::<NoParams>("login", "Log in and cache the session.", false),
+        method::<NoParams>("logout", "Log out and clear the cached session.", false),
+        method::<StatusParams>("status", "Describe the cached session.", false),
+        method::<ListParams>("list", "Fetch one page of a listing, or every page.", false),
+        method::<GetParams>(
+            "get",
+            "Fetch one row of a listing by its key. Records are searched by the key; other \
+             listings are read page by page until the row is found, at most 20 pages.",
+            false,
+        ),
+        method::<ValuesParams>(
+            "values",
+            "List the distinct values of a record column.",
+            false,
+        ),
+        method::<ExportParams>(
+            "export",
+            "Export records or input-user rows to a file.",
+            false,
+        ),
+        method::<CreateParams>("create", "Create a record or a PIC.", true),
+        method::<UpdateParams>("update", "Update fields of a record, PIC or user.", true),
+        method::<DeleteParams>("delete", "Delete a record or a PIC.", true),
+        method::<DeleteWhereParams>(
+            "delete_where",
+            "Delete every record with a column value.",
+            true,
+        ),
+        method::<DeleteAllParams>("delete_all", "Delete ALL records.", true),
+        method::<NoParams>(
+            "rpc.discover",
+            "List the methods and their parameter schemas.",
+            false,
+        ),
+    ]
+}
+
+/// Runs `method` with `params`.
+///
+/// # Errors
+/// Returns [`METHOD_NOT_FOUND`] or [`INVALID_PARAMS`] errors, or the [`ApiError`]
+/// of the operation.
+pub fn dispatch(
+    client: &mut CekUnitClient,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    match method {
+        "login" => {
+            parse::<NoParams>(params)?;
+            client.login()?;
+            Ok(status(client, false)?)
+        }
+        "logout" => {
+            parse::<NoParams>(params)?;
+            let logged_in = client.check_session()?.is_some();
+            if logged_in {
+                client.logout()?;
+            }
+            Ok(json!({ "logged_out": logged_in }))
+        }
+        "status" => {
+            let params: StatusParams = parse(params)?;
+            Ok(status(client, params.verify)?)
+        }
+        "list" => {
+            let params: ListParams = parse(params)?;
+            let page = with_relogin(client, |client| list(client, &params))?;
+            Ok(listing_json(&page))
+        }
+        "get" => {
+            let params: GetParams = parse(params)?;
+            Ok(with_relogin(client, |client| get(client, &params))?)
+        }
+        "values" => {
+            let params: ValuesParams = parse(params)?;
+            let values = with_relogin(client, |client| {
+                client.dashboard()?.get_unique_values(&params.column)
+            })?;
+            Ok(json!(values))
+        }
+        "export" => {
+            let params: ExportParams = parse(params)?;
+            let bytes = with_relogin(client, |client| export(client, &params))?;
+            fs::write(&params.path, &bytes).map_err(|e| {
+                ApiError::IoError(format!("Cannot write {}: {}", params.path.display(), e))
+            })?;
+            Ok(json!({ "path": params.path, "bytes": bytes.len() }))
+        }
+        "create" => {
+            let params: CreateParams = parse(params)?;
+            let fields = json_fields(&params.fields)?;
+            with_relogin(client, |client| match params.resource {
+                CreateResource::Records => client.input_data()?.insert_nasabah(field_map(&fields)),
+                CreateResource::Pics => client.pic()?.insert_pic(field_map(&fields)),
+            })?;
+            Ok(Value::Null)
+        }
+        "update" => {
+            let params: UpdateParams = parse(params)?;
+            let fields = json_fields(&params.fields)?;
+            let id = params.id.as_str();
+            with_relogin(client, |client| match params.resource {
+                UpdateResource::Records => {
+                    client.dashboard()?.update_cekunit(id, field_map(&fields))
+                }
+                UpdateResource::Pics => client.pic()?.update_pic(id, field_map(&fields)),
+                UpdateResource::Users => client.users()?.update_user(id, field_map(&fields)),
+            })?;
+            Ok(Value::Null)
+        }
+        "delete" => {
+            let params: DeleteParams = parse(params)?;
+            with_relogin(client, |client| match params.resource {
+                DeleteResource::Records => client.dashboard()?.delete_cekunit(&params.id),
+                DeleteResource::Pics => client.pic()?.delete_pic(&params.id),
+            })?;
+            Ok(Value::Null)
+        }
+        "delete_where" => {
+            let params: DeleteWhereParams = parse(params)?;
+            with_relogin(client, |client| {
+                client
+                    .dashboard()?
+                    .delete_by_category(&params.column, &params.value)
+            })?;
+            Ok(Value::Null)
+        }
+        "delete_all" => {
+            let params: DeleteAllParams = parse(params)?;
+            if !params.confirm {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    "delete_all needs \"confirm\": true",
+                ));
+            }
+            with_relogin(client, |client| client.dashboard()?.delete_all())?;
+            Ok(Value::Null)
+        }
+        "rpc.discover" => {
+            parse::<NoParams>(params)?;
+            let methods: Vec<Value> = methods()
+                .into_iter()
+                .map(|m| {
+                    json!({
+                        "name": m.name,
+                        "summary": m.summary,
+                        "mutates": m.mutates,
+                        "params": m.params,
+                    })
+                })
+                .collect();
+            Ok(json!({ "methods": methods }))
+        }
+        other => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method '{}'; see rpc.discover", other),
+        )),
+    }
+}
+
+/// Parses named parameters; a missing `params` is an empty object.
+fn parse<P: DeserializeOwned>(params: Value) -> Result<P, RpcError> {
+    let params = match params {
+        Value::Null => json!({}),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+/// No parameters.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct NoParams {}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct StatusParams {
+    /// Also ask the server whether the session is still accepted.
+    #[serde(default)]
+    verify: bool,
+}
+
+/// A listing of the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+#[schemars(inline)]
+enum Resource {
+    Records,
+    Pics,
+    Users,
+    InputUsers,
+}
+
+impl Resource {
+    /// The form field identifying a row.
+    fn key(self) -> &'static str {
+        match self {
+            Self::Records => "no",
+            Self::Pics | Self::Users | Self::InputUsers => "id",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ListParams {
+    resource: Resource,
+    /// Page to fetch (1-based).
+    #[serde(default = "first_page")]
+    #[schemars(range(min = 1))]
+    page: u32,
+    /// Fetch every page from `page` on, at most 20; `next_page` tells where the rest
+    /// starts.
+    #[serde(default)]
+    all: bool,
+    /// Column the server sorts by.
+    sort: Option<String>,
+    direction: Option<Direction>,
+    /// Only include rows matching this term (records and input-users).
+    search: Option<String>,
+    /// First date to include, e.g. `2025-01-01` (input-users).
+    from: Option<String>,
+    /// Last date to include (input-users).
+    to: Option<String>,
+}
+
+fn first_page() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GetParams {
+    resource: Resource,
+    /// Key of the row: `no` for records, `id` otherwise.
+    id: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ValuesParams {
+    /// Record column, e.g. `merk`.
+    column: String,
+}
+
+/// A listing that can be exported.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+#[schemars(inline)]
+enum ExportResource {
+    Records,
+    InputUsers,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ExportParams {
+    resource: ExportResource,
+    /// File to write, relative to the working directory of `cekunit rpc`.
+    path: PathBuf,
+    /// Export format; supported values depend on the server.
+    #[serde(default = "default_format")]
+    format: String,
+    /// Column to sort by.
+    #[serde(default = "default_sort")]
+    sort: String,
+    #[serde(default = "default_direction")]
+    direction: Direction,
+    /// Only include rows matching this term (input-users).
+    search: Option<String>,
+    /// First date to include (input-users).
+    from: Option<String>,
+    /// Last date to include (input-users).
+    to: Option<String>,
+}
+
+fn default_format() -> String {
+    "excel".into()
+}
+
+fn default_sort() -> String {
+    "created_at".into()
+}
+
+fn default_direction() -> Direction {
+    Direction::Desc
+}
+
+/// Form fields; numbers and booleans are sent as text and `null` as empty.
+type Fields = BTreeMap<String, Value>;
+
+/// A listing rows can be created in.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+#[schemars(inline)]
+enum CreateResource {
+    Records,
+    Pics,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CreateParams {
+    resource: CreateResource,
+    fields: Fields,
+}
+
+/// A listing whose rows can be updated.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+#[schemars(inline)]
+enum UpdateResource {
+    Records,
+    Pics,
+    Users,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct UpdateParams {
+    resource: UpdateResource,
+    /// Record number or id.
+    id: String,
+    fields: Fields,
+}
+
+/// A listing whose rows can be deleted.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+#[schemars(inline)]
+enum DeleteResource {
+    Records,
+    Pics,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DeleteParams {
+    resource: DeleteResource,
+    /// Record number or id.
+    id: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DeleteWhereParams {
+    /// Record column, e.g. `status`.
+    column: String,
+    /// Value of `column` to delete.
+    value: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DeleteAllParams {
+    /// Must be `true`.
+    confirm: bool,
+}
+
+/// Describes the cached session like `cekunit status`.
+fn status(client: &CekUnitClient, verify: bool) -> Result<Value, ApiError> {
+    let session = client.check_session()?.filter(|s| s.logged_in);
+    let config = client.config();
+    let mut status = json!({
+        "logged_in": session.is_some(),
+        "base_url": config.base_url,
+        "email": config.user_email,
+        "cache": client.cache_file_path(),
+        "session_age": session.map(|s| chrono::Utc::now().timestamp() - s.logged_in_at),
+        "session_max_age": client.session_max_age(),
+    });
+    if verify {
+        status["verified"] = Value::from(client.verify_session()?);
+    }
+    Ok(status)
+}
+
+fn list(client: &CekUnitClient, params: &ListParams) -> Result<ListPage, ApiError> {
+    let args = PageArgs {
+        page: params.page.max(1),
+        all: params.all,
+        sort: params.sort.clone(),
+        direction: params.direction,
+    };
+    let sort = params.sort.as_deref();
+    let direction = params.direction.map(Direction::as_str);
+    let search = params.search.as_deref();
+    match params.resource {
+        Resource::Records => {
+            let dashboard = client.dashboard()?;
+            fetch_pages(&args, |n| {
+                dashboard.get_dashboard(Some(n), search, sort, direction)
+            })
+        }
+        Resource::Pics => {
+            let pic = client.pic()?;
+            fetch_pages(&args, |n| pic.get_pic_list(Some(n), sort, direction))
+        }
+        Resource::Users => {
+            let users = client.users()?;
+            fetch_pages(&args, |n| users.get_users_list(Some(n), sort, direction))
+        }
+        Resource::InputUsers => {
+            let input_user = client.input_user()?;
+            fetch_pages(&args, |n| {
+                input_user.get_input_user(
+                    Some(n),
+                    search,
+                    sort,
+                    direction,
+                    params.from.as_deref(),
+                    params.to.as_deref(),
+                )
+            })
+        }
+    }
+}
+
+/// Finds a row by the id its edit link or delete form names, or by its key field;
+/// records are searched for the key first.
+fn get(client: &CekUnitClient, params: &GetParams) -> Result<Value, ApiError> {
+    let key = params.resource.key();
+    for page in 1..=MAX_PAGES {
+        let query = ListParams {
+            resource: params.resource,
+            page,
+            all: false,
+            sort: None,
+            direction: None,
+            search: (params.resource == Resource::Records).then(|| params.id.clone()),
+            from: None,
+            to: None,
+        };
+        let listing = list(client, &query)?;
+        // Only a column the page names as the key field; a header such as "No" is as
+        // likely a running number.
+        let column = listing
+            .fields
+            .iter()
+            .position(|f| f.as_deref() == Some(key));
+        if column.is_none() && (0..listing.len()).all(|i| listing.id(i).is_none()) {
+            if listing.is_empty() {
+                break;
+            }
+            return Err(ApiError::HtmlParseError(format!(
+                "the listing has no `{}` column and no edit or delete links naming its rows",
+                key
+            )));
+        }
+        let found = (0..listing.len()).find(|&i| {
+            listing.id(i) == Some(params.id.as_str())
+                || column.and_then(|c| listing.rows[i].get(c)) == Some(&params.id)
+        });
+        if let Some(i) = found {
+            return Ok(record_json(&listing, i));
+        }
+        if !listing.has_next() {
+            break;
+        }
+        if page == MAX_PAGES {
+            return Err(ApiError::Other(format!(
+                "no row with {} {} in the first {} pages; use `list` with `search` or `page`",
+                key, params.id, MAX_PAGES
+            )));
+        }
+    }
+    Err(ApiError::ResourceNotFound)
+}
+
+fn export(client: &CekUnitClient, params: &ExportParams) -> Result<Vec<u8>, ApiError> {
+    let direction = params.direction.as_str();
+    match params.resource {
+        ExportResource::Records => {
+            client
+                .dashboard()?
+                .export_cekunit(&params.format, &params.sort, direction)
+        }
+        ExportResource::InputUsers => client.input_user()?.export_input_user(
+            &params.format,
+            &params.sort,
+            direction,
+            params.search.as_deref(),
+            params.from.as_deref(),
+            params.to.as_deref(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use librcekunit::testing::{FakeCekUnit, Table};
+
+    #[test]
+    fn test_schemas() {
+        let methods = methods();
+        let list = methods.iter().find(|m| m.name == "list").unwrap();
+        assert_eq!(
+            list.params["properties"]["resource"]["enum"],
+            json!(["records", "pics", "users", "input-users"])
+        );
+        assert_eq!(list.params["required"], json!(["resource"]));
+        assert_eq!(list.params["additionalProperties"], false);
+
+        let delete = methods.iter().find(|m| m.name == "delete").unwrap();
+        assert!(delete.mutates);
+        assert_eq!(
+            delete.params["properties"]["resource"]["enum"],
+            json!(["records", "pics"])
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let params: ListParams = parse(json!({ "resource": "pics", "direction": "asc" })).unwrap();
+        assert_eq!(params.page, 1);
+        assert_eq!(params.direction, Some(Direction::Asc));
+        assert!(parse::<NoParams>(Value::Null).is_ok());
+
+        let err = parse::<ListParams>(json!({ "resource": "pics", "limit": 5 })).unwrap_err();
+        assert_eq!(err.code, INVALID_PARAMS);
+        assert!(err.message.contains("limit"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_get_stops_at_the_row() {
+        let fake = FakeCekUnit::builder()
+            .rows(Table::Pic, 120)
+            .per_page(5)
+            .start();
+        let mut client = fake.client().unwrap();
+        client.login().unwrap();
+        let params = |id: &str| GetParams {
+            resource: Resource::Pics,
+            id: id.into(),
+        };
+
+        let row = get(&client, &params("12")).unwrap();
+        assert_eq!(row["nama"], "Eko 12");
+        let pages = |fake: &FakeCekUnit| {
+            fake.requests()
+                .iter()
+                .filter(|r| r.starts_with("GET /pic?"))
+                .count()
+        };
+        assert_eq!(pages(&fake), 3);
+
+        let err = get(&client, &params("999")).unwrap_err();
+        assert!(err.to_string().contains("first 20 pages"), "{}", err);
+        assert_eq!(pages(&fake), 3 + 20);
+    }
+}
@@ -0,0 +1,201 @@
+//! `cekunit rpc`: JSON-RPC 2.0 over standard input and output.
+//!
+//! Each line of standard input is one request, or a batch as an array; each answer
+//! is written to standard output as one line. Notifications (requests without an
+//! `id`) run without an answer. Logging goes to standard error, so standard output
+//! only ever carries responses.
+//!
+//! One [`CekUnitClient`] serves every request. It logs in on the first request that
+//! needs a session and again whenever the session expires. The methods and their
+//! parameter schemas are listed in [`methods`], and by the `rpc.discover` method.
+
+pub mod methods;
+
+use crate::commands::io_error;
+use crate::output::error_json;
+use librcekunit::{ApiError, CekUnitClient};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::{self, BufRead, Write};
+
+/// Invalid JSON was received.
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON is not a valid request object.
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// The parameters do not match the method's schema.
+pub const INVALID_PARAMS: i64 = -32602;
+/// Errors of the panel or the client use `SERVER_ERROR - exit_code`, e.g. `-32008`
+/// for a validation failure (see [`ApiError::exit_code`]).
+pub const SERVER_ERROR: i64 = -32000;
+
+/// Answers requests until standard input is closed.
+///
+/// # Errors
+/// Returns [`ApiError::IoError`] if standard input cannot be read or standard
+/// output cannot be written. Failed requests are answered, not returned.
+pub fn run(mut client: CekUnitClient) -> Result<(), ApiError> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(io_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_line(&mut client, &line) {
+            writeln!(stdout, "{}", response).map_err(io_error)?;
+            stdout.flush().map_err(io_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// A JSON-RPC error object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    /// For [`ApiError`]s, the object of [`error_json`]: stable `code`, `exit_code`,
+    /// HTTP `status`, `operation` and field errors.
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<ApiError> for RpcError {
+    fn from(err: ApiError) -> Self {
+        Self {
+            code: SERVER_ERROR - i64::from(err.exit_code()),
+            message: err.to_string(),
+            data: Some(error_json(&err)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Answers one line: a request, a batch, or something unparseable.
+fn handle_line(client: &mut CekUnitClient, line: &str) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => {
+            return Some(failure(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ));
+        }
+    };
+    match message {
+        Value::Array(batch) if batch.is_empty() => Some(failure(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "Empty batch"),
+        )),
+        Value::Array(batch) => {
+            let responses: Vec<Value> = batch
+                .into_iter()
+                .filter_map(|message| handle(client, message))
+                .collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        message => handle(client, message),
+    }
+}
+
+/// Runs one request; returns `None` for a notification.
+fn handle(client: &mut CekUnitClient, message: Value) -> Option<Value> {
+    let id = message.get("id").cloned();
+    let request = match serde_json::from_value::<Request>(message) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        _ => {
+            return Some(failure(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "Expected a JSON-RPC 2.0 request object"),
+            ));
+        }
+    };
+    let result = methods::dispatch(client, &request.method, request.params);
+    if let Err(e) = &result {
+        log::warn!("{}: {}", request.method, e.message);
+    }
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => failure(id, e),
+    })
+}
+
+fn failure(id: Value, err: RpcError) -> Value {
+    let mut error = json!({ "code": err.code, "message": err.message });
+    if let Some(data) = err.data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_errors() {
+        // The client is never used: every line fails before a method runs.
+        let config = librcekunit::EnvConfig::from_vars(
+            &[
+                ("BASE_URL", "http://127.0.0.1:1"),
+                ("USER_EMAIL", "a@example.com"),
+                ("USER_PASSWORD", "secret123"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let cache = librcekunit::CacheManager::with_paths(
+            dir.path().to_path_buf(),
+            dir.path().join("session.json"),
+        );
+        let mut client = CekUnitClient::with_config_and_cache(config, cache).unwrap();
+
+        let parse = handle_line(&mut client, "{").unwrap();
+        assert_eq!(parse["error"]["code"], PARSE_ERROR);
+        assert_eq!(parse["id"], Value::Null);
+
+        let invalid = handle_line(&mut client, r#"{"jsonrpc":"1.0","id":1,"method":"status"}"#);
+        assert_eq!(invalid.unwrap()["error"]["code"], INVALID_REQUEST);
+
+        let batch = handle_line(
+            &mut client,
+            r#"[{"jsonrpc":"2.0","id":"a","method":"nope"},{"jsonrpc":"2.0","method":"nope"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            batch.as_array().unwrap().len(),
+            1,
+            "notifications get no answer"
+        );
+        assert_eq!(batch[0]["id"], "a");
+        assert_eq!(batch[0]["error"]["code"], METHOD_NOT_FOUND);
+
+        let params = handle_line(
+            &mut client,
+            r#"{"jsonrpc":"2.0","id":2,"method":"list","params":{"resource":"cars"}}"#,
+        )
+        .unwrap();
+        assert_eq!(params["error"]["code"], INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,407 @@
+//! `cekunit run`: applies a script of operations in one session.
+//!
+//! A script is a YAML, TOML or JSON file (chosen by its extension) with a list of
+//! `steps`, each naming an [`Op`] and its arguments, optional `vars`, and what to do
+//! when a step fails:
+//!
+//! ```yaml
+//! vars:
+//!   status: selesai
+//! on_error: continue
+//! steps:
+//!   - name: close record 42
+//!     op: dashboard.update
+//!     no: 42
+//!     fields: { status: "${status}" }
+//!   - op: dashboard.export
+//!     format: csv
+//!     out: "records-${status}.csv"
+//! ```
+//!
+//! `${name}` is replaced by a variable in every string of a step, and `$$` by `$`.
+//! Numbers and booleans are used as strings. The whole script is checked before the
+//! first request, so a typo does not leave it half applied.
+
+pub mod op;
+
+use crate::cli::RunArgs;
+use crate::commands::confirm;
+use crate::output::{OutputMode, Printer};
+use clap::ValueEnum;
+use colored::Colorize;
+use librcekunit::api::dashboard::ListPage;
+use librcekunit::{ApiError, CekUnitClient};
+use op::Op;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// What to do with the remaining steps after one fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OnError {
+    /// Skip the remaining steps.
+    #[default]
+    Stop,
+    /// Run the remaining steps anyway.
+    Continue,
+}
+
+/// A step with its variables substituted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// Label shown in the progress and summary, if given.
+    pub name: Option<String>,
+    pub op: Op,
+}
+
+impl Step {
+    fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(self.op.name())
+    }
+}
+
+/// A parsed script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script {
+    pub on_error: OnError,
+    pub steps: Vec<Step>,
+}
+
+/// The file layout, before variables are substituted.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawScript {
+    #[serde(default)]
+    vars: BTreeMap<String, Value>,
+    #[serde(default)]
+    on_error: OnError,
+    steps: Vec<Value>,
+}
+
+impl Script {
+    /// Reads a script, with `overrides` replacing variables of the same name.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::IoError`] if the file cannot be read, and
+    /// [`ApiError::Other`] if it is invalid, naming the step.
+    pub fn load(path: &Path, overrides: &[(String, String)]) -> Result<Self, ApiError> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| ApiError::IoError(format!("Cannot read {}: {}", path.display(), e)))?;
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        Self::parse(&text, extension, overrides)
+            .map_err(|e| ApiError::Other(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Parses a script in the format named by `extension` (`yaml`, `yml`, `toml`
+    /// or `json`).
+    fn parse(text: &str, extension: &str, overrides: &[(String, String)]) -> Result<Self, String> {
+        let raw: RawScript = match extension.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => serde_norway::from_str(text).map_err(|e| e.to_string())?,
+            "toml" => toml::from_str(text).map_err(|e| e.to_string())?,
+            "json" => serde_json::from_str(text).map_err(|e| e.to_string())?,
+            other => {
+                return Err(format!(
+                    "unknown script format '{}'; use .yaml, .toml or .json",
+                    other
+                ));
+            }
+        };
+        let mut vars = BTreeMap::new();
+        for (name, value) in raw.vars {
+            let value =
+                scalar(&value).ok_or_else(|| format!("variable '{}' is not a scalar", name))?;
+            vars.insert(name, value);
+        }
+        vars.extend(overrides.iter().cloned());
+
+        let steps = raw
+            .steps
+            .into_iter()
+            .enumerate()
+            .map(|(i, step)| {
+                prepare(step, &vars)
+                    .and_then(|mut step| {
+                        let name = step
+                            .as_object_mut()
+                            .and_then(|fields| fields.remove("name"))
+                            .and_then(|name| name.as_str().map(str::to_string));
+                        let op = serde_json::from_value(step).map_err(|e| e.to_string())?;
+                        Ok(Step { name, op })
+                    })
+                    .map_err(|e| format!("step {}: {}", i + 1, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            on_error: raw.on_error,
+            steps,
+        })
+    }
+}
+
+/// Returns a number, boolean or string as a string.
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Substitutes variables in every string of `value` and turns numbers and booleans
+/// into strings.
+fn prepare(value: Value, vars: &BTreeMap<String, String>) -> Result<Value, String> {
+    Ok(match value {
+        Value::String(s) => Value::String(substitute(&s, vars)?),
+        Value::Number(_) | Value::Bool(_) => Value::String(scalar(&value).unwrap_or_default()),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| prepare(item, vars))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| Ok((key, prepare(value, vars)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        Value::Null => Value::Null,
+    })
+}
+
+/// Replaces `${name}` with the variable and `$$` with `$`.
+fn substitute(text: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(after) = after.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = after.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("unclosed '${{' in '{}'", text))?;
+            let name = &after[..end];
+            let value = vars
+                .get(name)
+                .ok_or_else(|| format!("undefined variable '{}'", name))?;
+            out.push_str(value);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Runs the script selected by `args`, or prints its requests with `--dry-run`.
+///
+/// # Errors
+/// Returns [`ApiError::Other`] if the script is invalid, the deletions are not
+/// confirmed, or a step failed; the error of the login otherwise.
+pub fn run(client: &mut CekUnitClient, out: &Printer, args: RunArgs) -> Result<(), ApiError> {
+    let script = Script::load(&args.file, &args.vars)?;
+    let total = script.steps.len();
+
+    if args.dry_run {
+        let mut rows = Vec::new();
+        for (i, step) in script.steps.iter().enumerate() {
+            let request = step.op.plan(client.config())?;
+            rows.push(vec![(i + 1).to_string(), step.label().to_string(), request]);
+        }
+        return report(out, ["step", "name", "request"], rows, |row| {
+            format!("{}. {}: {}", row[0], row[1], row[2])
+        });
+    }
+
+    let deletions = script
+        .steps
+        .iter()
+        .filter(|s| s.op.is_destructive())
+        .count();
+    if deletions > 0 {
+        confirm(
+            &format!(
+                "run {} deleting step{} from {}",
+                deletions,
+                if deletions == 1 { "" } else { "s" },
+                args.file.display()
+            ),
+            args.confirm,
+        )?;
+    }
+    let on_error = args.on_error.unwrap_or(script.on_error);
+    client.ensure_logged_in()?;
+
+    let (mut done, mut failed, mut skipped) = (0, 0, 0);
+    let mut rows = Vec::new();
+    for (i, step) in script.steps.iter().enumerate() {
+        let prefix = format!("[{}/{}] {}", i + 1, total, step.label());
+        let (status, detail) = if failed > 0 && on_error == OnError::Stop {
+            skipped += 1;
+            ("skipped", String::new())
+        } else {
+            match step.op.apply(client) {
+                Ok(message) => {
+                    done += 1;
+                    eprintln!("{} {}: {}", "✓".green(), prefix, message);
+                    ("done", message)
+                }
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("{} {}: {}", "✗".red(), prefix, e);
+                    ("failed", e.to_string())
+                }
+            }
+        };
+        rows.push(vec![
+            (i + 1).to_string(),
+            step.label().to_string(),
+            status.to_string(),
+            detail,
+        ]);
+    }
+    eprintln!(
+        "{} steps: {} done, {} failed, {} skipped",
+        total, done, failed, skipped
+    );
+    if out.mode != OutputMode::Table {
+        report(out, ["step", "name", "status", "detail"], rows, |_| {
+            String::new()
+        })?;
+    }
+    if failed > 0 {
+        return Err(ApiError::Other(format!(
+            "{} of {} steps failed",
+            failed, total
+        )));
+    }
+    Ok(())
+}
+
+/// Prints rows as lines in table mode, or as records in the other modes.
+fn report<const N: usize>(
+    out: &Printer,
+    columns: [&str; N],
+    rows: Vec<Vec<String>>,
+    line: impl Fn(&[String]) -> String,
+) -> Result<(), ApiError> {
+    if out.mode == OutputMode::Table {
+        for row in &rows {
+            println!("{}", line(row));
+        }
+        return Ok(());
+    }
+    out.listing(ListPage {
+        columns: columns.map(String::from).into(),
+        rows,
+        ..ListPage::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_substitute() {
+        let vars = vars(&[("status", "selesai"), ("no", "42")]);
+        assert_eq!(
+            substitute("${no}: ${status}", &vars).unwrap(),
+            "42: selesai"
+        );
+        assert_eq!(substitute("$$5 and $x", &vars).unwrap(), "$5 and $x");
+        assert!(
+            substitute("${missing}", &vars)
+                .unwrap_err()
+                .contains("'missing'")
+        );
+        assert!(
+            substitute("${status", &vars)
+                .unwrap_err()
+                .contains("unclosed")
+        );
+    }
+
+    #[test]
+    fn test_formats_parse_alike() {
+        let yaml = r#"
+vars: { status: selesai, no: 42 }
+on_error: continue
+steps:
+  - name: close
+    op: dashboard.update
+    no: ${no}
+    fields: { status: "${status}" }
+  - op: pic.delete
+    id: 7
+"#;
+        let toml = r#"
+on_error = "continue"
+[vars]
+status = "selesai"
+no = 42
+[[steps]]
+name = "close"
+op = "dashboard.update"
+no = "${no}"
+fields = { status = "${status}" }
+[[steps]]
+op = "pic.delete"
+id = 7
+"#;
+        let json = r#"{
+  "vars": {"status": "selesai", "no": 42},
+  "on_error": "continue",
+  "steps": [
+    {"name": "close", "op": "dashboard.update", "no": "${no}", "fields": {"status": "${status}"}},
+    {"op": "pic.delete", "id": 7}
+  ]
+}"#;
+        let expected = Script::parse(yaml, "yaml", &[]).unwrap();
+        assert_eq!(expected.on_error, OnError::Continue);
+        assert_eq!(expected.steps[0].name.as_deref(), Some("close"));
+        assert_eq!(
+            expected.steps[0].op,
+            Op::DashboardUpdate {
+                no: "42".into(),
+                fields: [("status".to_string(), "selesai".to_string())].into(),
+            }
+        );
+        assert_eq!(expected.steps[1].op, Op::PicDelete { id: "7".into() });
+        assert_eq!(Script::parse(toml, "toml", &[]).unwrap(), expected);
+        assert_eq!(Script::parse(json, "json", &[]).unwrap(), expected);
+
+        let overridden = Script::parse(yaml, "yml", &[("no".into(), "43".into())]).unwrap();
+        assert!(matches!(&overridden.steps[0].op, Op::DashboardUpdate { no, .. } if no == "43"));
+    }
+
+    #[test]
+    fn test_invalid_steps_name_the_step() {
+        let error = |text: &str| Script::parse(text, "yaml", &[]).unwrap_err();
+        assert!(error("steps: [{op: pic.delete}]").contains("step 1: missing field `id`"));
+        assert!(error("steps: [{op: pic.delete, id: 1}, {op: pic.remove}]").contains("step 2"));
+        assert!(error("steps: [{op: pic.delete, id: 1, force: true}]").contains("unknown field"));
+        assert!(error("steps: [{op: pic.delete, id: '${x}'}]").contains("undefined variable"));
+        assert!(
+            Script::parse("{}", "ini", &[])
+                .unwrap_err()
+                .contains("unknown script format")
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! The operations a script step can run, one per sub-client method.
+
+use librcekunit::{ApiError, CekUnitClient, EnvConfig};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One operation, selected by the `op` key of a step.
+///
+/// The names follow the subcommands, e.g. `dashboard.delete-by` runs what
+/// `cekunit dashboard delete-by` runs.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "op", deny_unknown_fields)]
+pub enum Op {
+    #[serde(rename = "dashboard.update")]
+    DashboardUpdate {
+        no: String,
+        fields: BTreeMap<String, String>,
+    },
+    #[serde(rename = "dashboard.delete")]
+    DashboardDelete { no: String },
+    #[serde(rename = "dashboard.delete-by")]
+    DashboardDeleteBy { column: String, value: String },
+    #[serde(rename = "dashboard.delete-all")]
+    DashboardDeleteAll {},
+    #[serde(rename = "dashboard.export")]
+    DashboardExport {
+        out: PathBuf,
+        #[serde(default = "default_format")]
+        format: String,
+        #[serde(default = "default_sort")]
+        sort: String,
+        #[serde(default = "default_direction")]
+        direction: String,
+    },
+    #[serde(rename = "input-data.create")]
+    InputDataCreate { fields: BTreeMap<String, String> },
+    #[serde(rename = "input-user.export")]
+    InputUserExport {
+        search: Option<String>,
+        from: Option<String>,
+        to: Option<String>,
+        out: PathBuf,
+        #[serde(default = "default_format")]
+        format: String,
+        #[serde(default = "default_sort")]
+        sort: String,
+        #[serde(default = "default_direction")]
+        direction: String,
+    },
+    #[serde(rename = "pic.create")]
+    PicCreate { fields: BTreeMap<String, String> },
+    #[serde(rename = "pic.update")]
+    PicUpdate {
+        id: String,
+        fields: BTreeMap<String, String>,
+    },
+    #[serde(rename = "pic.delete")]
+    PicDelete { id: String },
+    #[serde(rename = "users.update")]
+    UsersUpdate {
+        id: String,
+        fields: BTreeMap<String, String>,
+    },
+}
+
+fn default_format() -> String {
+    "excel".into()
+}
+
+fn default_sort() -> String {
+    "created_at".into()
+}
+
+fn default_direction() -> String {
+    "desc".into()
+}
+
+impl Op {
+    /// Returns the `op` name, e.g. `"pic.update"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DashboardUpdate { .. } => "dashboard.update",
+            Self::DashboardDelete { .. } => "dashboard.delete",
+            Self::DashboardDeleteBy { .. } => "dashboard.delete-by",
+            Self::DashboardDeleteAll {} => "dashboard.delete-all",
+            Self::DashboardExport { .. } => "dashboard.export",
+            Self::InputDataCreate { .. } => "input-data.create",
+            Self::InputUserExport { .. } => "input-user.export",
+            Self::PicCreate { .. } => "pic.create",
+            Self::PicUpdate { .. } => "pic.update",
+            Self::PicDelete { .. } => "pic.delete",
+            Self::UsersUpdate { .. } => "users.update",
+        }
+    }
+
+    /// Returns whether the operation deletes data.
+    pub fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            Self::DashboardDelete { .. }
+                | Self::DashboardDeleteBy { .. }
+                | Self::DashboardDeleteAll {}
+                | Self::PicDelete { .. }
+        )
+    }
+
+    /// Describes the request the operation sends, for `--dry-run`.
+    ///
+    /// Updates and deletes are sent as `POST` with Laravel's `_method` field; the
+    /// method shown is the one the server sees.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::EnvError`] if the endpoint is not configured.
+    pub fn plan(&self, config: &EnvConfig) -> Result<String, ApiError> {
+        Ok(match self {
+            Self::DashboardUpdate { no, fields } => format!(
+                "PUT {} {}",
+                config.full_cekunit_item_url(no)?,
+                describe(fields)
+            ),
+            Self::DashboardDelete { no } => {
+                format!("DELETE {}", config.full_cekunit_item_url(no)?)
+            }
+            Self::DashboardDeleteBy { column, value } => format!(
+                "POST {} column={} value={}",
+                config.full_cekunit_delete_category_url()?,
+                column,
+                value
+            ),
+            Self::DashboardDeleteAll {} => format!("DELETE {}", config.full_delete_all_url()?),
+            Self::DashboardExport {
+                out,
+                format,
+                sort,
+                direction,
+            } => format!(
+                "GET {}?format={}&sort={}&direction={} > {}",
+                config.full_cekunit_export_url()?,
+                format,
+                sort,
+                direction,
+                out.display()
+            ),
+            Self::InputDataCreate { fields } => format!(
+                "POST {} {}",
+                config.full_input_data_url()?,
+                describe(fields)
+            ),
+            Self::InputUserExport {
+                search,
+                from,
+                to,
+                out,
+                format,
+                sort,
+                direction,
+            } => {
+                let mut query = format!("format={}&sort={}&direction={}", format, sort, direction);
+                for (key, value) in [("search", search), ("start_date", from), ("end_date", to)] {
+                    if let Some(value) = value {
+                        query.push_str(&format!("&{}={}", key, value));
+                    }
+                }
+                format!(
+                    "GET {}?{} > {}",
+                    config.full_input_user_export_url()?,
+                    query,
+                    out.display()
+                )
+            }
+            Self::PicCreate { fields } => {
+                format!("POST {} {}", config.full_input_pic_url()?, describe(fields))
+            }
+            Self::PicUpdate { id, fields } => {
+                format!("PUT {} {}", config.full_pic_item_url(id)?, describe(fields))
+            }
+            Self::PicDelete { id } => format!("DELETE {}", config.full_pic_item_url(id)?),
+            Self::UsersUpdate { id, fields } => format!(
+                "PUT {} {}",
+                config.full_users_item_url(id)?,
+                describe(fields)
+            ),
+        })
+    }
+
+    /// Runs the operation with a logged-in client.
+    ///
+    /// # Returns
+    /// A short description of what was done, e.g. `"wrote 1024 bytes to out.csv"`.
+    ///
+    /// # Errors
+    /// Returns the [`ApiError`] of the sub-client method, or [`ApiError::IoError`] if
+    /// an export cannot be written.
+    pub fn apply(&self, client: &CekUnitClient) -> Result<String, ApiError> {
+        match self {
+            Self::DashboardUpdate { no, fields } => {
+                client.dashboard()?.update_cekunit(no, field_map(fields))?;
+                Ok(format!("updated record {}", no))
+            }
+            Self::DashboardDelete { no } => {
+                client.dashboard()?.delete_cekunit(no)?;
+                Ok(format!("deleted record {}", no))
+            }
+            Self::DashboardDeleteBy { column, value } => {
+                client.dashboard()?.delete_by_category(column, value)?;
+                Ok(format!("deleted records with {} = '{}'", column, value))
+            }
+            Self::DashboardDeleteAll {} => {
+                client.dashboard()?.delete_all()?;
+                Ok("deleted all records".into())
+            }
+            Self::DashboardExport {
+                out,
+                format,
+                sort,
+                direction,
+            } => {
+                let bytes = client
+                    .dashboard()?
+                    .export_cekunit(format, sort, direction)?;
+                write(out, &bytes)
+            }
+            Self::InputDataCreate { fields } => {
+                client.input_data()?.insert_nasabah(field_map(fields))?;
+                Ok("created customer record".into())
+            }
+            Self::InputUserExport {
+                search,
+                from,
+                to,
+                out,
+                format,
+                sort,
+                direction,
+            } => {
+                let bytes = client.input_user()?.export_input_user(
+                    format,
+                    sort,
+                    direction,
+                    search.as_deref(),
+                    from.as_deref(),
+                    to.as_deref(),
+                )?;
+                write(out, &bytes)
+            }
+            Self::PicCreate { fields } => {
+                client.pic()?.insert_pic(field_map(fields))?;
+                Ok("created PIC".into())
+            }
+            Self::PicUpdate { id, fields } => {
+                client.pic()?.update_pic(id, field_map(fields))?;
+                Ok(format!("updated PIC {}", id))
+            }
+            Self::PicDelete { id } => {
+                client.pic()?.delete_pic(id)?;
+                Ok(format!("deleted PIC {}", id))
+            }
+            Self::UsersUpdate { id, fields } => {
+                client.users()?.update_user(id, field_map(fields))?;
+                Ok(format!("updated user {}", id))
+            }
+        }
+    }
+}
+
+fn field_map(fields: &BTreeMap<String, String>) -> HashMap<&str, &str> {
+    fields
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect()
+}
+
+fn describe(fields: &BTreeMap<String, String>) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Writes an export; relative paths are resolved against the working directory.
+fn write(out: &Path, bytes: &[u8]) -> Result<String, ApiError> {
+    fs::write(out, bytes)
+        .map_err(|e| ApiError::IoError(format!("Cannot write {}: {}", out.display(), e)))?;
+    Ok(format!("wrote {} bytes to {}", bytes.len(), out.display()))
+}
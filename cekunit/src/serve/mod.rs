@@ -0,0 +1,633 @@
+//! `cekunit serve`: a local JSON API over one managed session.
+//!
+//! Tools that are not written in Rust get records, PICs, users and exports as JSON
+//! instead of scraping the panel themselves. Every request shares one
+//! [`CekUnitClient`]: requests run one at a time on a blocking thread, and a request
+//! that finds the session expired logs in again and is retried once. The routes are
+//! described by the OpenAPI document at `/openapi.json`, built in [`openapi`].
+//!
+//! Without an API key the gateway only listens on a loopback address, and only
+//! answers requests whose `Host` names that address, so a web page cannot reach it by
+//! pointing its own domain at `127.0.0.1` (DNS rebinding).
+
+pub mod openapi;
+
+use crate::cli::{Direction, PageArgs, ServeArgs};
+use crate::commands::{fetch_pages, field_map, io_error, json_fields, with_relogin};
+use crate::output::{error_json, listing_json};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, HOST};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use librcekunit::handler::error::exit_code;
+use librcekunit::{ApiError, CekUnitClient};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex, PoisonError};
+use subtle::ConstantTimeEq;
+
+/// Fields of a create or update request, as a JSON object of scalars.
+type Fields = BTreeMap<String, Value>;
+
+/// Serves the API until interrupted with Ctrl-C.
+///
+/// # Errors
+/// Returns [`ApiError::Other`] if no API key is set for a non-loopback address,
+/// the [`ApiError`] of the initial login, or [`ApiError::IoError`] if the address
+/// cannot be bound.
+pub fn run(mut client: CekUnitClient, args: ServeArgs) -> Result<(), ApiError> {
+    let keys: Vec<String> = args
+        .api_keys
+        .iter()
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .collect();
+    if keys.is_empty() && !args.listen.ip().is_loopback() {
+        return Err(ApiError::Other(format!(
+            "Refusing to serve on {} without an API key; pass --api-key or listen on 127.0.0.1",
+            args.listen
+        )));
+    }
+    client.ensure_logged_in()?;
+
+    // Bound before the gateway is built, so it knows the port chosen for port 0.
+    let listener = TcpListener::bind(args.listen).map_err(io_error)?;
+    listener.set_nonblocking(true).map_err(io_error)?;
+    let addr = listener.local_addr().map_err(io_error)?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(io_error)?;
+    // `gateway` outlives the runtime, so the client is dropped on this thread:
+    // reqwest's blocking client must not be dropped on an async thread.
+    let gateway = Arc::new(Gateway {
+        client: Mutex::new(client),
+        keys,
+        addr,
+    });
+    let app = router(Arc::clone(&gateway));
+    runtime
+        .block_on(async move {
+            let listener = tokio::net::TcpListener::from_std(listener)?;
+            eprintln!(
+                "Listening on http://{} (OpenAPI document at /openapi.json)",
+                addr
+            );
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                    eprintln!("Shutting down");
+                })
+                .await
+        })
+        .map_err(io_error)
+}
+
+/// The shared session, the accepted API keys and the listening address.
+struct Gateway {
+    client: Mutex<CekUnitClient>,
+    keys: Vec<String>,
+    addr: SocketAddr,
+}
+
+impl Gateway {
+    /// Runs `op` with the client on a blocking thread.
+    ///
+    /// An expired session is renewed and `op` retried once; see [`with_relogin`].
+    async fn call<T, F>(self: &Arc<Self>, op: F) -> Result<T, ErrorResponse>
+    where
+        T: Send + 'static,
+        F: Fn(&CekUnitClient) -> Result<T, ApiError> + Send + 'static,
+    {
+        let gateway = Arc::clone(self);
+        tokio::task::spawn_blocking(move || {
+            let mut client = gateway
+                .client
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            with_relogin(&mut client, op)
+        })
+        .await
+        .map_err(|e| ApiError::Other(format!("Request handler failed: {}", e)))?
+        .map_err(ErrorResponse)
+    }
+
+    /// Returns whether the request carries one of the keys; any request is accepted
+    /// when no key is configured.
+    ///
+    /// Every key is compared in constant time, so the time taken does not tell how
+    /// much of a guess was right.
+    fn accepts(&self, headers: &HeaderMap) -> bool {
+        if self.keys.is_empty() {
+            return true;
+        }
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+        let key = header("x-api-key")
+            .or_else(|| header(AUTHORIZATION.as_str())?.strip_prefix("Bearer "))
+            .map(str::trim);
+        key.is_some_and(|key| {
+            self.keys.iter().fold(false, |found, k| {
+                found | bool::from(k.as_bytes().ct_eq(key.as_bytes()))
+            })
+        })
+    }
+
+    /// Returns whether `Host` names the listening address; see [`names_address`].
+    fn serves_host(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get(HOST)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|host| names_address(host, self.addr))
+    }
+}
+
+/// Returns whether a `Host` header names `addr`, or `localhost` on its port.
+fn names_address(host: &str, addr: SocketAddr) -> bool {
+    let ip = match addr.ip() {
+        IpAddr::V6(ip) => format!("[{}]", ip),
+        ip => ip.to_string(),
+    };
+    let port = addr.port();
+    [ip.as_str(), "localhost"].iter().any(|name| {
+        host.eq_ignore_ascii_case(&format!("{}:{}", name, port))
+            || (port == 80 && host.eq_ignore_ascii_case(name))
+    })
+}
+
+fn router(gateway: Arc<Gateway>) -> Router {
+    let api = Router::new()
+        .route(
+            "/records",
+            get(list_records).post(create_record).delete(delete_records),
+        )
+        .route("/records/export", get(export_records))
+        .route("/records/values/{column}", get(record_values))
+        .route("/records/{no}", put(update_record).delete(delete_record))
+        .route("/pics", get(list_pics).post(create_pic))
+        .route("/pics/{id}", put(update_pic).delete(delete_pic))
+        .route("/users", get(list_users))
+        .route("/users/{id}", put(update_user))
+        .route("/input-users", get(list_input_users))
+        .route("/input-users/export", get(export_input_users))
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&gateway),
+            require_key,
+        ));
+    Router::new()
+        .route("/health", get(|| async { Json(json!({ "status": "ok" })) }))
+        .route("/openapi.json", get(|| async { Json(openapi::document()) }))
+        .merge(api)
+        .with_state(gateway)
+}
+
+async fn require_key(
+    State(gateway): State<Arc<Gateway>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // A key cannot be read by another site's page, so only keyless access is
+    // limited to the listening address.
+    if gateway.keys.is_empty() && !gateway.serves_host(request.headers()) {
+        let body = json!({
+            "code": "invalid_host",
+            "message": format!("Send requests to http://{}", gateway.addr),
+        });
+        return (StatusCode::FORBIDDEN, Json(body)).into_response();
+    }
+    if gateway.accepts(request.headers()) {
+        return next.run(request).await;
+    }
+    let body = json!({
+        "code": "invalid_api_key",
+        "message": "Missing or unknown API key; send it in X-API-Key or Authorization: Bearer",
+    });
+    (StatusCode::UNAUTHORIZED, Json(body)).into_response()
+}
+
+/// An [`ApiError`] answered with the JSON of [`error_json`].
+struct ErrorResponse(ApiError);
+
+impl From<ApiError> for ErrorResponse {
+    fn from(err: ApiError) -> Self {
+        Self(err)
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        log::warn!("{}", self.0);
+        (http_status(&self.0), Json(error_json(&self.0))).into_response()
+    }
+}
+
+/// Maps an error to the status the gateway answers with.
+///
+/// Rejections the client can act on keep their status; other failures of the panel
+/// are reported as a bad gateway, and local failures as an internal error.
+fn http_status(err: &ApiError) -> StatusCode {
+    match err.exit_code() {
+        exit_code::VALIDATION => StatusCode::UNPROCESSABLE_ENTITY,
+        exit_code::NOT_FOUND => StatusCode::NOT_FOUND,
+        exit_code::FORBIDDEN => StatusCode::FORBIDDEN,
+        exit_code::RATE_LIMITED => StatusCode::TOO_MANY_REQUESTS,
+        exit_code::NETWORK if err.is_timeout() => StatusCode::GATEWAY_TIMEOUT,
+        exit_code::CONFIG | exit_code::LOCAL_IO | exit_code::GENERAL => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
+
+type ApiResult<T> = Result<T, ErrorResponse>;
+
+/// Query of the listing routes; `search`, `from` and `to` only apply where the
+/// panel supports them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ListQuery {
+    page: Option<u32>,
+    all: bool,
+    sort: Option<String>,
+    direction: Option<Direction>,
+    search: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+impl ListQuery {
+    fn page_args(&self) -> PageArgs {
+        PageArgs {
+            page: self.page.unwrap_or(1).max(1),
+            all: self.all,
+            sort: self.sort.clone(),
+            direction: self.direction,
+        }
+    }
+
+    fn direction(&self) -> Option<&'static str> {
+        self.direction.map(Direction::as_str)
+    }
+}
+
+/// Query of the export routes, with the defaults of `cekunit ... export`; `search`,
+/// `from` and `to` only apply to input-user exports.
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default = "default_sort")]
+    sort: String,
+    #[serde(default = "default_direction")]
+    direction: Direction,
+    search: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+fn default_format() -> String {
+    "excel".into()
+}
+
+fn default_sort() -> String {
+    "created_at".into()
+}
+
+fn default_direction() -> Direction {
+    Direction::Desc
+}
+
+/// Query of `DELETE /records`: either `column` and `value`, or `all=true` with
+/// `confirm=true`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DeleteQuery {
+    column: Option<String>,
+    value: Option<String>,
+    all: bool,
+    confirm: bool,
+}
+
+/// Answers an export with the content type of its format.
+fn export(format: &str, bytes: Vec<u8>) -> Response {
+    let content_type = match format {
+        "csv" => "text/csv; charset=utf-8",
+        "excel" | "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    };
+    ([(CONTENT_TYPE, content_type)], bytes).into_response()
+}
+
+async fn list_records(
+    State(gateway): State<Arc<Gateway>>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Json<Value>> {
+    let page = gateway
+        .call(move |client| {
+            let dashboard = client.dashboard()?;
+            fetch_pages(&query.page_args(), |n| {
+                dashboard.get_dashboard(
+                    Some(n),
+                    query.search.as_deref(),
+                    query.sort.as_deref(),
+                    query.direction(),
+                )
+            })
+        })
+        .await?;
+    Ok(Json(listing_json(&page)))
+}
+
+async fn record_values(
+    State(gateway): State<Arc<Gateway>>,
+    Path(column): Path<String>,
+) -> ApiResult<Json<Vec<String>>> {
+    let values = gateway
+        .call(move |client| client.dashboard()?.get_unique_values(&column))
+        .await?;
+    Ok(Json(values))
+}
+
+async fn export_records(
+    State(gateway): State<Arc<Gateway>>,
+    Query(query): Query<ExportQuery>,
+) -> ApiResult<Response> {
+    if query.search.is_some() || query.from.is_some() || query.to.is_some() {
+        return Err(ErrorResponse(ApiError::ValidationError(
+            "the records export cannot be filtered; search, from and to only apply to /input-users/export".into(),
+        )));
+    }
+    let format = query.format.clone();
+    let bytes = gateway
+        .call(move |client| {
+            client
+                .dashboard()?
+                .export_cekunit(&query.format, &query.sort, query.direction.as_str())
+        })
+        .await?;
+    Ok(export(&format, bytes))
+}
+
+async fn create_record(
+    State(gateway): State<Arc<Gateway>>,
+    Json(fields): Json<Fields>,
+) -> ApiResult<StatusCode> {
+    gateway
+        .call(move |client| {
+            let fields = json_fields(&fields)?;
+            client.input_data()?.insert_nasabah(field_map(&fields))
+        })
+        .await?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn update_record(
+    State(gateway): State<Arc<Gateway>>,
+    Path(no): Path<String>,
+    Json(fields): Json<Fields>,
+) -> ApiResult<StatusCode> {
+    gateway
+        .call(move |client| {
+            let fields = json_fields(&fields)?;
+            client.dashboard()?.update_cekunit(&no, field_map(&fields))
+        })
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_record(
+    State(gateway): State<Arc<Gateway>>,
+    Path(no): Path<String>,
+) -> ApiResult<StatusCode> {
+    gateway
+        .call(move |client| client.dashboard()?.delete_cekunit(&no))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_records(
+    State(gateway): State<Arc<Gateway>>,
+    Query(query): Query<DeleteQuery>,
+) -> ApiResult<StatusCode> {
+    gateway
+        .call(move |client| match &query {
+            DeleteQuery {
+                column: Some(column),
+                value: Some(value),
+                all: false,
+                ..
+            } => client.dashboard()?.delete_by_category(column, value),
+            DeleteQuery {
+                column: None,
+                value: None,
+                all: true,
+                confirm: true,
+            } => client.dashboard()?.delete_all(),
+            DeleteQuery {
+                column: None,
+                value: None,
+                all: true,
+                confirm: false,
+            } => Err(ApiError::ValidationError(
+                "deleting every record also needs confirm=true".into(),
+            )),
+            _ => Err(ApiError::ValidationError(
+                "pass either column and value, or all=true and confirm=true".into(),
+            )),
+        })
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_pics(
+    State(gateway): State<Arc<Gateway>>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Json<Value>> {
+    let page = gateway
+        .call(move |client| {
+            let pic = client.pic()?;
+            fetch_pages(&query.page_args(), |n| {
+                pic.get_pic_list(Some(n), query.sort.as_deref(), query.direction())
+            })
+        })
+        .await?;
+    Ok(Json(listing_json(&page)))
+}
+
+async fn create_pic(
+    State(gateway): State<Arc<Gateway>>,
+    Json(fields): Json<Fields>,
+) -> ApiResult<StatusCode> {
+    gateway
+        .call(move |client| {
+            let fields = json_fields(&fields)?;
+            client.pic()?.insert_pic(field_map(&fields))
+        })
+        .await?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn update_pic(
+    State(gateway): State<Arc<Gateway>>,
+    Path(id): Path<String>,
+    Json(fields): Json<Fields>,
+) -> ApiResult<StatusCode> {
+    gateway
+        .call(move |client| {
+            let fields = json_fields(&fields)?;
+            client.pic()?.update_pic(&id, field_map(&fields))
+        })
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_pic(
+    State(gateway): State<Arc<Gateway>>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    gateway
+        .call(move |client| client.pic()?.delete_pic(&id))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_users(
+    State(gateway): State<Arc<Gateway>>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Json<Value>> {
+    let page = gateway
+        .call(move |client| {
+            let users = client.users()?;
+            fetch_pages(&query.page_args(), |n| {
+                users.get_users_list(Some(n), query.sort.as_deref(), query.direction())
+            })
+        })
+        .await?;
+    Ok(Json(listing_json(&page)))
+}
+
+async fn update_user(
+    State(gateway): State<Arc<Gateway>>,
+    Path(id): Path<String>,
+    Json(fields): Json<Fields>,
+) -> ApiResult<StatusCode> {
+    gateway
+        .call(move |client| {
+            let fields = json_fields(&fields)?;
+            client.users()?.update_user(&id, field_map(&fields))
+        })
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_input_users(
+    State(gateway): State<Arc<Gateway>>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Json<Value>> {
+    let page = gateway
+        .call(move |client| {
+            let input_user = client.input_user()?;
+            fetch_pages(&query.page_args(), |n| {
+                input_user.get_input_user(
+                    Some(n),
+                    query.search.as_deref(),
+                    query.sort.as_deref(),
+                    query.direction(),
+                    query.from.as_deref(),
+                    query.to.as_deref(),
+                )
+            })
+        })
+        .await?;
+    Ok(Json(listing_json(&page)))
+}
+
+async fn export_input_users(
+    State(gateway): State<Arc<Gateway>>,
+    Query(query): Query<ExportQuery>,
+) -> ApiResult<Response> {
+    let format = query.format.clone();
+    let bytes = gateway
+        .call(move |client| {
+            client.input_user()?.export_input_user(
+                &query.format,
+                &query.sort,
+                query.direction.as_str(),
+                query.search.as_deref(),
+                query.from.as_deref(),
+                query.to.as_deref(),
+            )
+        })
+        .await?;
+    Ok(export(&format, bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_mapping() {
+        assert_eq!(
+            http_status(&ApiError::ValidationError("x".into())),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            http_status(&ApiError::ServerError(500)),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            http_status(&ApiError::NotAuthenticated),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            http_status(&ApiError::RequestTimeout),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(
+            http_status(&ApiError::IoError("x".into())),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_accepts_configured_keys() {
+        let fake = librcekunit::testing::FakeCekUnit::start();
+        let gateway = Gateway {
+            client: Mutex::new(fake.client().unwrap()),
+            keys: vec!["first-key".into(), "second-key".into()],
+            addr: "127.0.0.1:8780".parse().unwrap(),
+        };
+        let headers = |name: &str, value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                axum::http::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                value.parse().unwrap(),
+            );
+            headers
+        };
+        assert!(gateway.accepts(&headers("x-api-key", "second-key")));
+        assert!(gateway.accepts(&headers("authorization", "Bearer first-key")));
+        assert!(!gateway.accepts(&headers("x-api-key", "first-ke")));
+        assert!(!gateway.accepts(&headers("x-api-key", "first-key2")));
+        assert!(!gateway.accepts(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_names_address() {
+        let addr: SocketAddr = "127.0.0.1:8780".parse().unwrap();
+        assert!(names_address("127.0.0.1:8780", addr));
+        assert!(names_address("LOCALHOST:8780", addr));
+        assert!(!names_address("localhost:8781", addr));
+        assert!(!names_address("rebind.example.com:8780", addr));
+        let addr: SocketAddr = "[::1]:80".parse().unwrap();
+        assert!(names_address("[::1]", addr));
+        assert!(names_address("localhost", addr));
+        assert!(!names_address("::1", addr));
+    }
+}
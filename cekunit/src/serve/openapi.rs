@@ -0,0 +1,349 @@
+//! The OpenAPI 3.1 document of `cekunit serve`, served at `/openapi.json`.
+
+use serde_json::{Map, Value, json};
+
+/// Returns the OpenAPI document describing every route of the gateway.
+pub fn document() -> Value {
+    let paths = json!({
+        "/health": {
+            "get": {
+                "operationId": "health",
+                "summary": "Check that the gateway is running.",
+                "tags": ["gateway"],
+                "security": [],
+                "responses": { "200": json_response("The gateway is running.", json!({ "type": "object" })) },
+            },
+        },
+        "/openapi.json": {
+            "get": {
+                "operationId": "openapi",
+                "summary": "This document.",
+                "tags": ["gateway"],
+                "security": [],
+                "responses": { "200": json_response("The OpenAPI document.", json!({ "type": "object" })) },
+            },
+        },
+        "/records": {
+            "get": list("listRecords", "records", "List CekUnit records on the dashboard.", &["search"]),
+            "post": create("createRecord", "records", "Submit a new customer record."),
+            "delete": {
+                "operationId": "deleteRecords",
+                "summary": "Delete every record with a column value, or all records with `all=true` and `confirm=true`.",
+                "tags": ["records"],
+                "parameters": [
+                    query("column", "string", "Column to match, e.g. `status`."),
+                    query("value", "string", "Value of `column` to delete."),
+                    query("all", "boolean", "Delete ALL records; not combined with `column`."),
+                    query("confirm", "boolean", "Must be `true` with `all=true`."),
+                ],
+                "responses": done("204", "Deleted."),
+            },
+        },
+        "/records/export": {
+            "get": export("exportRecords", "records", "Export the records; they cannot be filtered, so `search`, `from` and `to` are rejected.", &[]),
+        },
+        "/records/values/{column}": {
+            "get": {
+                "operationId": "recordValues",
+                "summary": "List the distinct values of a column.",
+                "tags": ["records"],
+                "parameters": [path("column", "Column name, e.g. `merk`.")],
+                "responses": with_errors(json!({
+                    "200": json_response("The values.", json!({ "type": "array", "items": { "type": "string" } })),
+                })),
+            },
+        },
+        "/records/{no}": {
+            "parameters": [path("no", "Record number.")],
+            "put": update("updateRecord", "records", "Update fields of a record."),
+            "delete": delete("deleteRecord", "records", "Delete a record."),
+        },
+        "/pics": {
+            "get": list("listPics", "pics", "List PICs.", &[]),
+            "post": create("createPic", "pics", "Add a PIC."),
+        },
+        "/pics/{id}": {
+            "parameters": [path("id", "PIC id.")],
+            "put": update("updatePic", "pics", "Update fields of a PIC."),
+            "delete": delete("deletePic", "pics", "Delete a PIC."),
+        },
+        "/users": {
+            "get": list("listUsers", "users", "List users.", &[]),
+        },
+        "/users/{id}": {
+            "parameters": [path("id", "User id.")],
+            "put": update("updateUser", "users", "Update fields of a user."),
+        },
+        "/input-users": {
+            "get": list("listInputUsers", "input-users", "List input-user rows.", &["search", "from", "to"]),
+        },
+        "/input-users/export": {
+            "get": export("exportInputUsers", "input-users", "Export input-user rows.", &["search", "from", "to"]),
+        },
+    });
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "CekUnit gateway",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "JSON access to a CekUnit admin panel through one managed session, served by `cekunit serve`.",
+        },
+        "servers": [{ "url": "/" }],
+        "security": [{ "apiKey": [] }, { "bearer": [] }],
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "apiKey": { "type": "apiKey", "in": "header", "name": "X-API-Key" },
+                "bearer": { "type": "http", "scheme": "bearer" },
+            },
+            "schemas": {
+                "Listing": {
+                    "type": "object",
+                    "properties": {
+                        "page": { "type": ["integer", "null"] },
+                        "pages": { "type": ["integer", "null"] },
+                        "total": { "type": ["integer", "null"] },
+                        "next_page": {
+                            "type": ["integer", "null"],
+                            "description": "Page to read next, or `null` after the last one. With `all=true`, set when the listing stopped after 20 pages.",
+                        },
+                        "records": {
+                            "type": "array",
+                            "items": { "type": "object", "additionalProperties": { "type": "string" } },
+                            "description": "Rows keyed by the column headers of the panel.",
+                        },
+                    },
+                    "required": ["page", "pages", "total", "next_page", "records"],
+                },
+                "Fields": {
+                    "type": "object",
+                    "additionalProperties": { "type": ["string", "number", "boolean", "null"] },
+                    "description": "Form fields to send; numbers and booleans are sent as text.",
+                },
+                "Error": {
+                    "type": "object",
+                    "properties": {
+                        "code": { "type": "string", "description": "Stable error code, e.g. `validation_failed`." },
+                        "exit_code": { "type": "integer", "description": "Exit code `cekunit` uses for this error." },
+                        "message": { "type": "string" },
+                        "status": { "type": ["integer", "null"], "description": "HTTP status of the panel, if it answered." },
+                        "operation": { "type": ["string", "null"] },
+                        "fields": {
+                            "type": "object",
+                            "additionalProperties": { "type": "array", "items": { "type": "string" } },
+                            "description": "Validation messages per form field.",
+                        },
+                    },
+                    "required": ["code", "message"],
+                },
+            },
+        },
+    })
+}
+
+fn list(id: &str, tag: &str, summary: &str, filters: &[&str]) -> Value {
+    let mut parameters = vec![
+        query("page", "integer", "Page to fetch (1-based)."),
+        query(
+            "all",
+            "boolean",
+            "Fetch every page from `page` on, at most 20.",
+        ),
+        query("sort", "string", "Column the panel sorts by."),
+        direction(),
+    ];
+    parameters.extend(filters.iter().map(|name| filter(name)));
+    json!({
+        "operationId": id,
+        "summary": summary,
+        "tags": [tag],
+        "parameters": parameters,
+        "responses": with_errors(json!({
+            "200": json_response("One page, or every page with `all=true`.", reference("Listing")),
+        })),
+    })
+}
+
+fn export(id: &str, tag: &str, summary: &str, filters: &[&str]) -> Value {
+    let mut parameters = vec![
+        json!({
+            "name": "format",
+            "in": "query",
+            "description": "Export format; supported values depend on the panel.",
+            "schema": { "type": "string", "default": "excel" },
+        }),
+        json!({
+            "name": "sort",
+            "in": "query",
+            "description": "Column to sort by.",
+            "schema": { "type": "string", "default": "created_at" },
+        }),
+        direction(),
+    ];
+    parameters.extend(filters.iter().map(|name| filter(name)));
+    json!({
+        "operationId": id,
+        "summary": summary,
+        "tags": [tag],
+        "parameters": parameters,
+        "responses": with_errors(json!({
+            "200": {
+                "description": "The exported file, with the content type of its format.",
+                "content": { "application/octet-stream": { "schema": { "type": "string", "format": "binary" } } },
+            },
+        })),
+    })
+}
+
+fn create(id: &str, tag: &str, summary: &str) -> Value {
+    json!({
+        "operationId": id,
+        "summary": summary,
+        "tags": [tag],
+        "requestBody": fields_body(),
+        "responses": done("201", "Created."),
+    })
+}
+
+fn update(id: &str, tag: &str, summary: &str) -> Value {
+    json!({
+        "operationId": id,
+        "summary": summary,
+        "tags": [tag],
+        "requestBody": fields_body(),
+        "responses": done("204", "Updated."),
+    })
+}
+
+fn delete(id: &str, tag: &str, summary: &str) -> Value {
+    json!({
+        "operationId": id,
+        "summary": summary,
+        "tags": [tag],
+        "responses": done("204", "Deleted."),
+    })
+}
+
+fn fields_body() -> Value {
+    json!({
+        "required": true,
+        "content": { "application/json": { "schema": reference("Fields") } },
+    })
+}
+
+fn done(status: &str, description: &str) -> Value {
+    let mut responses = Map::new();
+    responses.insert(status.into(), json!({ "description": description }));
+    with_errors(Value::Object(responses))
+}
+
+/// Adds the error responses every authenticated route can give.
+fn with_errors(mut responses: Value) -> Value {
+    let errors = [
+        ("401", "Missing or unknown API key."),
+        ("404", "The panel has no such record or page."),
+        ("422", "The panel rejected the fields or the query."),
+        ("429", "The panel is rate-limiting the gateway."),
+        (
+            "502",
+            "The panel failed, or the session could not be renewed.",
+        ),
+        ("504", "The panel did not answer in time."),
+    ];
+    if let Value::Object(map) = &mut responses {
+        for (status, description) in errors {
+            map.insert(
+                status.into(),
+                json_response(description, reference("Error")),
+            );
+        }
+    }
+    responses
+}
+
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+fn reference(schema: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", schema) })
+}
+
+fn path(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+fn query(name: &str, kind: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "description": description,
+        "schema": { "type": kind },
+    })
+}
+
+fn direction() -> Value {
+    json!({
+        "name": "direction",
+        "in": "query",
+        "description": "Sort direction.",
+        "schema": { "type": "string", "enum": ["asc", "desc"] },
+    })
+}
+
+fn filter(name: &str) -> Value {
+    let description = match name {
+        "search" => "Only include rows matching this term.",
+        "from" => "First date to include, e.g. `2025-01-01`.",
+        "to" => "Last date to include.",
+        _ => "",
+    };
+    query(name, "string", description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operations_are_unique_and_resolve() {
+        let doc = document();
+        let mut ids = Vec::new();
+        for (path, item) in doc["paths"].as_object().unwrap() {
+            for (method, operation) in item.as_object().unwrap() {
+                if method == "parameters" {
+                    continue;
+                }
+                ids.push(operation["operationId"].as_str().unwrap().to_string());
+                for parameter in operation["parameters"]
+                    .as_array()
+                    .into_iter()
+                    .chain(item["parameters"].as_array())
+                    .flatten()
+                    .filter(|p| p["in"] == "path")
+                {
+                    let name = parameter["name"].as_str().unwrap();
+                    assert!(path.contains(&format!("{{{}}}", name)), "{path}: {name}");
+                }
+            }
+        }
+        let count = ids.len();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), count, "operationIds are unique");
+
+        let text = doc.to_string();
+        for schema in doc["components"]["schemas"].as_object().unwrap().keys() {
+            assert!(text.contains(&format!("#/components/schemas/{}", schema)));
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Tab completion for the interactive shell.
+//!
+//! Subcommands and options come from the clap definition. Field names, column values
+//! and PIC/user identifiers come from a [`Source`], which the shell backs with the
+//! logged-in client and the tests with fixed data.
+
+use clap::{Arg, ArgAction};
+use rustyline::completion::Pair;
+
+/// Words handled by the shell itself rather than by clap.
+pub const BUILTINS: &[&str] = &["exit", "quit"];
+
+/// The listings whose columns and rows can be completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Listing {
+    Dashboard,
+    InputUser,
+    Pic,
+    Users,
+}
+
+impl Listing {
+    /// Returns the listing behind a top-level subcommand.
+    pub fn of_command(name: &str) -> Option<Self> {
+        match name {
+            "dashboard" => Some(Self::Dashboard),
+            "input-user" => Some(Self::InputUser),
+            "pic" => Some(Self::Pic),
+            "users" => Some(Self::Users),
+            _ => None,
+        }
+    }
+}
+
+/// Server data used for completion. Failures are reported as no candidates.
+pub trait Source {
+    /// Returns the form field names of a listing's columns, e.g. `nama_nasabah`.
+    fn fields(&self, listing: Listing) -> Vec<String>;
+    /// Returns the distinct values of a dashboard column.
+    fn unique_values(&self, column: &str) -> Vec<String>;
+    /// Returns `(id, name)` pairs of the rows of a listing.
+    fn names(&self, listing: Listing) -> Vec<(String, String)>;
+}
+
+/// Completes the word before `pos` in `line`.
+///
+/// `command` must have been built (see [`clap::Command::build`]) so that global
+/// options are known to every subcommand.
+///
+/// # Returns
+/// The byte offset where the completed word starts, and the candidates.
+pub fn complete(
+    command: &clap::Command,
+    source: &dyn Source,
+    line: &str,
+    pos: usize,
+) -> (usize, Vec<Pair>) {
+    let head = &line[..pos];
+    let start = head.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+    let word = &head[start..];
+    let before = shlex::split(&head[..start])
+        .unwrap_or_else(|| head[..start].split_whitespace().map(String::from).collect());
+
+    let mut current = command;
+    let mut path: Vec<&str> = Vec::new();
+    let mut positionals: Vec<&str> = Vec::new();
+    let mut pending: Option<&Arg> = None;
+    for token in &before {
+        if pending.take().is_some() {
+            continue;
+        }
+        if let Some(flag) = token.strip_prefix('-') {
+            if !token.contains('=') {
+                pending = find_arg(current, flag).filter(|arg| takes_value(arg));
+            }
+        } else if let Some(sub) = current.find_subcommand(token) {
+            current = sub;
+            path.push(sub.get_name());
+            positionals.clear();
+        } else {
+            positionals.push(token);
+        }
+    }
+    let listing = path.first().and_then(|name| Listing::of_command(name));
+
+    if let Some(arg) = pending {
+        return option_values(arg, listing, source, start, word);
+    }
+    if word.starts_with('-') {
+        let flags = current
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set())
+            .filter_map(|arg| arg.get_long())
+            .map(|long| format!("--{}", long));
+        return (start, plain(flags, word));
+    }
+    if current.has_subcommands() {
+        let mut names: Vec<String> = current
+            .get_subcommands()
+            .map(|sub| sub.get_name().to_string())
+            .collect();
+        if path.is_empty() {
+            names.extend(BUILTINS.iter().map(|b| b.to_string()));
+        }
+        return (start, plain(names, word));
+    }
+
+    let index = positionals.len();
+    let candidates = match (path.as_slice(), index) {
+        (["dashboard", "unique" | "delete-by"], 0) => {
+            plain(source.fields(Listing::Dashboard), word)
+        }
+        (["dashboard", "delete-by"], 1) => {
+            let word = word.trim_start_matches(['\'', '"']);
+            plain(source.unique_values(positionals[0]), word)
+                .into_iter()
+                .map(|pair| Pair {
+                    replacement: quote(&pair.replacement),
+                    ..pair
+                })
+                .collect()
+        }
+        ([_, "update" | "delete"], 0) => listing
+            .map(|l| identifiers(source.names(l), word))
+            .unwrap_or_default(),
+        ([_, "update"], _) | ([_, "create"], _) => listing
+            .map(|l| {
+                let fields = source.fields(l).into_iter().map(|c| format!("{}=", c));
+                plain(fields, word)
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    (start, candidates)
+}
+
+/// Completes the value of an option.
+fn option_values(
+    arg: &Arg,
+    listing: Option<Listing>,
+    source: &dyn Source,
+    start: usize,
+    word: &str,
+) -> (usize, Vec<Pair>) {
+    let fields = || listing.map(|l| source.fields(l)).unwrap_or_default();
+    match arg.get_id().as_str() {
+        "columns" => {
+            // Complete the last entry of a comma-separated list.
+            let offset = word.rfind(',').map(|i| i + 1).unwrap_or(0);
+            (start + offset, plain(fields(), &word[offset..]))
+        }
+        "order_by" | "sort" => (start, plain(fields(), word)),
+        _ => {
+            let values = arg
+                .get_possible_values()
+                .into_iter()
+                .filter(|v| !v.is_hide_set())
+                .map(|v| v.get_name().to_string());
+            (start, plain(values, word))
+        }
+    }
+}
+
+/// Finds an option by the text after its first dash (`-output` or `o`).
+fn find_arg<'a>(command: &'a clap::Command, flag: &str) -> Option<&'a Arg> {
+    match flag.strip_prefix('-') {
+        Some(long) => command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long)),
+        None => {
+            let short = flag.chars().last()?;
+            command
+                .get_arguments()
+                .find(|arg| arg.get_short() == Some(short))
+        }
+    }
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::Set | ArgAction::Append)
+}
+
+/// Candidates that start with `word`, sorted and deduplicated.
+fn plain(values: impl IntoIterator<Item = String>, word: &str) -> Vec<Pair> {
+    let mut values: Vec<String> = values
+        .into_iter()
+        .filter(|v| v.to_lowercase().starts_with(&word.to_lowercase()))
+        .collect();
+    values.sort();
+    values.dedup();
+    values
+        .into_iter()
+        .map(|v| Pair {
+            display: v.clone(),
+            replacement: v,
+        })
+        .collect()
+}
+
+/// Row identifiers shown with their names, matching either.
+fn identifiers(names: Vec<(String, String)>, word: &str) -> Vec<Pair> {
+    let word = word.to_lowercase();
+    names
+        .into_iter()
+        .filter(|(id, name)| {
+            id.to_lowercase().starts_with(&word) || name.to_lowercase().contains(&word)
+        })
+        .map(|(id, name)| Pair {
+            display: format!("{} ({})", id, name),
+            replacement: id,
+        })
+        .collect()
+}
+
+/// Quotes a value for the shell line if needed.
+fn quote(value: &str) -> String {
+    shlex::try_quote(value)
+        .map(|q| q.into_owned())
+        .unwrap_or_else(|_| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::CommandFactory;
+
+    struct Fixed;
+
+    impl Source for Fixed {
+        fn fields(&self, listing: Listing) -> Vec<String> {
+            match listing {
+                Listing::Pic => vec!["id".into(), "nama".into(), "no_hp".into()],
+                _ => vec!["no".into(), "nopol".into(), "merk".into()],
+            }
+        }
+
+        fn unique_values(&self, column: &str) -> Vec<String> {
+            match column {
+                "nopol" => vec!["B 1001 ABC".into(), "B 1002 ABC".into()],
+                _ => Vec::new(),
+            }
+        }
+
+        fn names(&self, _listing: Listing) -> Vec<(String, String)> {
+            vec![("1".into(), "Andi".into()), ("12".into(), "Budi".into())]
+        }
+    }
+
+    fn run(line: &str) -> (usize, Vec<String>) {
+        let mut command = Cli::command();
+        command.build();
+        let (start, pairs) = complete(&command, &Fixed, line, line.len());
+        (start, pairs.into_iter().map(|p| p.replacement).collect())
+    }
+
+    #[test]
+    fn test_complete_commands_and_flags() {
+        assert_eq!(run("da"), (0, vec!["dashboard".to_string()]));
+        assert!(run("").1.contains(&"exit".to_string()));
+        assert_eq!(
+            run("dashboard del"),
+            (
+                10,
+                vec!["delete".into(), "delete-all".into(), "delete-by".into()]
+            )
+        );
+        assert_eq!(run("pic list --al").1, ["--all"]);
+        assert_eq!(run("pic list -o n").1, ["ndjson"]);
+        assert_eq!(run("pic list --output=json --al").1, ["--all"]);
+    }
+
+    #[test]
+    fn test_complete_columns_values_and_names() {
+        assert_eq!(
+            run("pic list --columns id,na"),
+            (22, vec!["nama".to_string()])
+        );
+        assert_eq!(run("dashboard list --order-by no").1, ["no", "nopol"]);
+        assert_eq!(run("dashboard unique m").1, ["merk"]);
+        assert_eq!(
+            run("dashboard delete-by nopol B").1,
+            ["'B 1001 ABC'", "'B 1002 ABC'"]
+        );
+        assert_eq!(run("pic delete bu").1, ["12"]);
+        assert_eq!(run("pic delete 1").1, ["1", "12"]);
+        assert_eq!(run("pic update 12 na").1, ["nama="]);
+        assert!(run("users list ").1.is_empty());
+    }
+}
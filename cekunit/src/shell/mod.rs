@@ -0,0 +1,330 @@
+//! `cekunit shell`: an interactive prompt that keeps one session open.
+//!
+//! Each line is parsed like the command line (without the `cekunit` prefix) and run
+//! against the same [`CekUnitClient`], so the login happens once. Like on the command
+//! line, every command checks the session first and logs in again if the server
+//! dropped it; completion lookups do the same. The prompt shows the profile and the
+//! age of the session; history is kept next to the session cache.
+
+pub mod completion;
+
+use crate::cli::{Cli, PageArgs};
+use crate::commands::{execute, fetch_pages, with_relogin};
+use crate::output::Printer;
+use crate::profile::DEFAULT_PROFILE;
+use clap::{CommandFactory, Parser};
+use colored::Colorize;
+use completion::{BUILTINS, Listing, Source};
+use librcekunit::api::dashboard::ListPage;
+use librcekunit::{ApiError, CekUnitClient};
+use rustyline::completion::{Completer, Pair};
+use rustyline::config::Configurer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Context, Editor, Helper};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Options that select the client; they only take effect when the shell starts.
+const STARTUP_OPTIONS: &[&str] = &["--profile", "-P", "--config", "--env-file", "--cache-dir"];
+
+/// Runs the shell until `exit`, `quit` or end of input.
+///
+/// # Errors
+/// Returns [`ApiError`] if the initial login fails or the line editor cannot be set up.
+/// Errors of individual commands are printed and the shell continues.
+pub fn run(client: CekUnitClient, cli: &Cli) -> Result<(), ApiError> {
+    let profile = cli
+        .profile
+        .as_deref()
+        .unwrap_or(DEFAULT_PROFILE)
+        .to_string();
+    let mut client = client;
+    client.ensure_logged_in()?;
+    let client = Rc::new(RefCell::new(client));
+
+    let mut command = Cli::command();
+    command.build();
+    let helper = ShellHelper {
+        command,
+        source: ClientSource::new(client.clone()),
+    };
+    let mut editor: Editor<ShellHelper, DefaultHistory> =
+        Editor::new().map_err(|e| ApiError::IoError(e.to_string()))?;
+    editor.set_completion_type(CompletionType::List);
+    editor.set_helper(Some(helper));
+    let history = client
+        .borrow()
+        .cache_file_path()
+        .with_file_name("history.txt");
+    let _ = editor.load_history(&history);
+
+    loop {
+        let prompt = prompt(&profile, &client.borrow());
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(ApiError::IoError(e.to_string())),
+        };
+        let Some(words) = shlex::split(&line) else {
+            eprintln!("{} unbalanced quotes", "error:".red().bold());
+            continue;
+        };
+        match words.first().map(String::as_str) {
+            None => continue,
+            Some(word) if BUILTINS.contains(&word) => break,
+            Some(_) => {}
+        }
+        let _ = editor.add_history_entry(line.as_str());
+        if words.iter().any(|w| {
+            STARTUP_OPTIONS
+                .iter()
+                .any(|o| w == o || w.starts_with(&format!("{}=", o)))
+        }) {
+            eprintln!(
+                "{} profile and config options only apply when the shell starts",
+                "warning:".yellow().bold()
+            );
+        }
+        let parsed = match Cli::try_parse_from(std::iter::once("cekunit".to_string()).chain(words))
+        {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let _ = e.print();
+                continue;
+            }
+        };
+        let out: Printer = parsed.printer();
+        let result = execute(&mut client.borrow_mut(), &out, parsed.command);
+        if let Some(helper) = editor.helper_mut() {
+            helper.source.invalidate();
+        }
+        if let Err(e) = result {
+            eprintln!("{} {}", "error:".red().bold(), e);
+        }
+    }
+    let _ = editor.save_history(&history);
+    Ok(())
+}
+
+/// Builds the prompt, e.g. `cekunit (staging, 12m)> `.
+fn prompt(profile: &str, client: &CekUnitClient) -> String {
+    let age = match client.check_session() {
+        Ok(Some(session)) if session.logged_in => {
+            format_age(chrono::Utc::now().timestamp() - session.logged_in_at)
+        }
+        _ => "logged out".to_string(),
+    };
+    format!("cekunit ({}, {})> ", profile, age)
+}
+
+/// Formats seconds as `45s`, `12m` or `2h05m`.
+fn format_age(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// Line-editor helper providing completion.
+struct ShellHelper {
+    command: clap::Command,
+    source: ClientSource,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(completion::complete(&self.command, &self.source, line, pos))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Completion data fetched with the shell's client and kept until the next command.
+struct ClientSource {
+    client: Rc<RefCell<CekUnitClient>>,
+    listings: RefCell<HashMap<Listing, ListPage>>,
+    values: RefCell<HashMap<String, Vec<String>>>,
+}
+
+impl ClientSource {
+    fn new(client: Rc<RefCell<CekUnitClient>>) -> Self {
+        Self {
+            client,
+            listings: RefCell::default(),
+            values: RefCell::default(),
+        }
+    }
+
+    /// Forgets fetched data, since a command may have changed it.
+    fn invalidate(&self) {
+        self.listings.borrow_mut().clear();
+        self.values.borrow_mut().clear();
+    }
+
+    /// Returns the rows of a listing, fetching them on first use.
+    ///
+    /// At most [`MAX_PAGES`](crate::commands::MAX_PAGES) pages are read, so a long listing offers the candidates
+    /// of its first pages only.
+    fn listing(&self, listing: Listing) -> Option<ListPage> {
+        if let Some(page) = self.listings.borrow().get(&listing) {
+            return Some(page.clone());
+        }
+        let args = PageArgs {
+            page: 1,
+            all: true,
+            sort: None,
+            direction: None,
+        };
+        let all = with_relogin(&mut self.client.borrow_mut(), |client| match listing {
+            Listing::Dashboard => {
+                let dashboard = client.dashboard()?;
+                fetch_pages(&args, |n| {
+                    dashboard.get_dashboard(Some(n), None, None, None)
+                })
+            }
+            Listing::InputUser => {
+                let input_user = client.input_user()?;
+                fetch_pages(&args, |n| {
+                    input_user.get_input_user(Some(n), None, None, None, None, None)
+                })
+            }
+            Listing::Pic => {
+                let pic = client.pic()?;
+                fetch_pages(&args, |n| pic.get_pic_list(Some(n), None, None))
+            }
+            Listing::Users => {
+                let users = client.users()?;
+                fetch_pages(&args, |n| users.get_users_list(Some(n), None, None))
+            }
+        })
+        .ok()?;
+        self.listings.borrow_mut().insert(listing, all.clone());
+        Some(all)
+    }
+}
+
+impl Source for ClientSource {
+    fn fields(&self, listing: Listing) -> Vec<String> {
+        self.listing(listing)
+            .map(|page| page.fields.into_iter().flatten().collect())
+            .unwrap_or_default()
+    }
+
+    fn unique_values(&self, column: &str) -> Vec<String> {
+        if let Some(values) = self.values.borrow().get(column) {
+            return values.clone();
+        }
+        let values = with_relogin(&mut self.client.borrow_mut(), |client| {
+            client.dashboard()?.get_unique_values(column)
+        });
+        match values {
+            Ok(values) => {
+                self.values
+                    .borrow_mut()
+                    .insert(column.to_string(), values.clone());
+                values
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn names(&self, listing: Listing) -> Vec<(String, String)> {
+        let Some(page) = self.listing(listing) else {
+            return Vec::new();
+        };
+        // The id comes from the row's links; the name is the first column whose field
+        // or header looks like a name.
+        let is_name = |c: &str| {
+            let c = c.to_lowercase();
+            c.contains("nama") || c.contains("name") || c.contains("nopol")
+        };
+        let Some(name) = (0..page.columns.len())
+            .find(|&i| page.field(i).is_some_and(is_name) || is_name(&page.columns[i]))
+        else {
+            return Vec::new();
+        };
+        (0..page.len())
+            .filter_map(|i| Some((page.id(i)?.to_string(), page.rows[i].get(name)?.clone())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use librcekunit::testing::{FakeCekUnit, Table};
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(-3), "0s");
+        assert_eq!(format_age(59), "59s");
+        assert_eq!(format_age(754), "12m");
+        assert_eq!(format_age(7500), "2h05m");
+    }
+
+    #[test]
+    fn test_completion_logs_in_again() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        client.login().unwrap();
+        let source = ClientSource::new(Rc::new(RefCell::new(client)));
+
+        fake.expire_sessions();
+        assert_eq!(
+            source.fields(Listing::Pic),
+            ["id", "nama", "email", "no_hp"]
+        );
+        assert!(
+            source
+                .names(Listing::Pic)
+                .contains(&("3".into(), "Citra 3".into()))
+        );
+        fake.expire_sessions();
+        assert!(!source.unique_values("merk").is_empty());
+        assert_eq!(fake.logged_in_sessions(), 1);
+    }
+
+    #[test]
+    fn test_completion_reads_a_limited_number_of_pages() {
+        let fake = FakeCekUnit::builder()
+            .rows(Table::Pic, 120)
+            .per_page(5)
+            .start();
+        let mut client = fake.client().unwrap();
+        client.login().unwrap();
+        let source = ClientSource::new(Rc::new(RefCell::new(client)));
+
+        let names = source.names(Listing::Pic);
+        assert_eq!(names.len(), 100);
+        assert!(names.iter().any(|(id, _)| id == "100"));
+        let pages = fake
+            .requests()
+            .iter()
+            .filter(|r| r.starts_with("GET /pic?"))
+            .count();
+        assert_eq!(pages, crate::commands::MAX_PAGES as usize);
+    }
+}
@@ -0,0 +1,574 @@
+//! State of the terminal UI and its key bindings.
+//!
+//! [`App::handle`] only changes the state; requests are left to the caller, which
+//! reloads views marked stale and performs the returned [`Action`]s.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use librcekunit::ApiError;
+use librcekunit::api::dashboard::ListPage;
+
+/// The listings shown as tabs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    Dashboard,
+    Pic,
+    Users,
+    InputUser,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 4] = [Tab::Dashboard, Tab::Pic, Tab::Users, Tab::InputUser];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Dashboard => "Dashboard",
+            Self::Pic => "PIC",
+            Self::Users => "Users",
+            Self::InputUser => "Input user",
+        }
+    }
+
+    /// Returns the name of one row, used in messages.
+    pub fn noun(self) -> &'static str {
+        match self {
+            Self::Dashboard => "record",
+            Self::Pic => "PIC",
+            Self::Users => "user",
+            Self::InputUser => "entry",
+        }
+    }
+
+    /// Whether rows can be edited.
+    pub fn editable(self) -> bool {
+        !matches!(self, Self::InputUser)
+    }
+
+    /// Whether rows can be deleted.
+    pub fn deletable(self) -> bool {
+        matches!(self, Self::Dashboard | Self::Pic)
+    }
+
+    /// Whether the server filters this listing; other listings are filtered locally.
+    pub fn server_search(self) -> bool {
+        matches!(self, Self::Dashboard | Self::InputUser)
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|&t| t == self).unwrap_or(0)
+    }
+}
+
+/// Parameters of a list request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub page: u32,
+    /// Only set for listings searched by the server.
+    pub search: Option<String>,
+    pub sort: Option<String>,
+    pub direction: &'static str,
+}
+
+/// One tab: the loaded page and the query that produced it.
+#[derive(Debug, Clone)]
+pub struct View {
+    pub listing: ListPage,
+    pub page: u32,
+    pub search: Option<String>,
+    /// Index of the sort column; only columns with a field can be sorted.
+    pub sort: Option<usize>,
+    pub descending: bool,
+    /// Index into [`View::rows`].
+    pub selected: usize,
+    /// Whether the page must be (re)loaded before drawing.
+    pub stale: bool,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            listing: ListPage::default(),
+            page: 1,
+            search: None,
+            sort: None,
+            descending: false,
+            selected: 0,
+            stale: true,
+        }
+    }
+}
+
+impl View {
+    /// Returns the indices of the rows shown, after local filtering.
+    pub fn rows(&self, tab: Tab) -> Vec<usize> {
+        let needle = match &self.search {
+            Some(search) if !tab.server_search() => search.to_lowercase(),
+            _ => return (0..self.listing.rows.len()).collect(),
+        };
+        self.listing
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.iter().any(|v| v.to_lowercase().contains(&needle)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// What the keyboard currently drives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Browse,
+    /// Typing a search term.
+    Search(String),
+    /// Choosing the field of the selected row to edit.
+    Field(usize),
+    /// Typing the new value of a field.
+    Edit {
+        field: usize,
+        value: String,
+    },
+    /// Waiting for `y` to delete the selected row.
+    Confirm,
+}
+
+/// A change requested by the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Update {
+        id: String,
+        field: String,
+        value: String,
+    },
+    Delete {
+        id: String,
+    },
+}
+
+/// Message shown in the footer until the next key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Info(String),
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct App {
+    pub tab: Tab,
+    views: [View; 4],
+    pub mode: Mode,
+    pub status: Option<Status>,
+    pub quit: bool,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            tab: Tab::Dashboard,
+            views: Default::default(),
+            mode: Mode::Browse,
+            status: None,
+            quit: false,
+        }
+    }
+
+    pub fn view(&self) -> &View {
+        &self.views[self.tab.index()]
+    }
+
+    fn view_mut(&mut self) -> &mut View {
+        &mut self.views[self.tab.index()]
+    }
+
+    /// Returns the request that loads the current tab.
+    pub fn query(&self) -> Query {
+        let view = self.view();
+        Query {
+            page: view.page,
+            search: view.search.clone().filter(|_| self.tab.server_search()),
+            sort: view
+                .sort
+                .and_then(|i| view.listing.field(i))
+                .map(str::to_string),
+            direction: if view.descending { "desc" } else { "asc" },
+        }
+    }
+
+    /// Returns the index of the selected row in the listing.
+    fn selected_index(&self) -> Option<usize> {
+        let view = self.view();
+        view.rows(self.tab).get(view.selected).copied()
+    }
+
+    /// Returns the selected row.
+    pub fn selected_row(&self) -> Option<&[String]> {
+        let index = self.selected_index()?;
+        self.view().listing.rows.get(index).map(Vec::as_slice)
+    }
+
+    /// Returns the record id of the selected row, taken from its edit link or delete
+    /// form; the columns shown do not necessarily include it.
+    pub fn selected_id(&self) -> Option<String> {
+        let index = self.selected_index()?;
+        self.view().listing.id(index).map(str::to_string)
+    }
+
+    /// Returns the columns of the selected row that can be edited: those the page names
+    /// a form field for, except the one showing the id.
+    fn editable_fields(&self) -> Vec<usize> {
+        let listing = &self.view().listing;
+        let id = self.selected_id();
+        let row = self.selected_row().unwrap_or_default();
+        (0..listing.columns.len())
+            .filter(|&i| listing.field(i).is_some())
+            .filter(|&i| id.is_none() || row.get(i) != id.as_ref())
+            .collect()
+    }
+
+    /// Stores the result of loading the current tab.
+    pub fn loaded(&mut self, result: Result<ListPage, ApiError>) {
+        let tab = self.tab;
+        let view = self.view_mut();
+        view.stale = false;
+        match result {
+            Ok(listing) => {
+                view.listing = listing;
+                let count = view.rows(tab).len();
+                view.selected = view.selected.min(count.saturating_sub(1));
+            }
+            Err(e) => self.status = Some(Status::Error(e.to_string())),
+        }
+    }
+
+    /// Reports the outcome of an [`Action`] and reloads the current tab.
+    pub fn finished(&mut self, action: &Action, result: Result<(), ApiError>) {
+        let noun = self.tab.noun();
+        self.status = Some(match (action, result) {
+            (Action::Update { id, field, .. }, Ok(())) => {
+                Status::Info(format!("Updated {} of {} {}", field, noun, id))
+            }
+            (Action::Delete { id }, Ok(())) => Status::Info(format!("Deleted {} {}", noun, id)),
+            (_, Err(e)) => Status::Error(e.to_string()),
+        });
+        self.view_mut().stale = true;
+    }
+
+    /// Applies a key press.
+    ///
+    /// # Returns
+    /// The change to perform, if the key completed one.
+    pub fn handle(&mut self, key: KeyEvent) -> Option<Action> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return None;
+        }
+        match std::mem::replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => {
+                self.status = None;
+                self.browse(key.code);
+                None
+            }
+            Mode::Search(mut term) => {
+                match key.code {
+                    KeyCode::Enter => {
+                        let tab = self.tab;
+                        let view = self.view_mut();
+                        view.search = Some(term.trim().to_string()).filter(|s| !s.is_empty());
+                        view.page = 1;
+                        view.selected = 0;
+                        view.stale = tab.server_search();
+                    }
+                    KeyCode::Esc => {}
+                    code => {
+                        edit_text(&mut term, code);
+                        self.mode = Mode::Search(term);
+                    }
+                }
+                None
+            }
+            Mode::Field(field) => {
+                let fields = self.editable_fields();
+                let position = fields.iter().position(|&f| f == field).unwrap_or(0);
+                self.mode = match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        Mode::Field(fields[position.saturating_sub(1)])
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        Mode::Field(fields[(position + 1).min(fields.len() - 1)])
+                    }
+                    KeyCode::Enter => {
+                        let value = self
+                            .selected_row()
+                            .and_then(|row| row.get(field))
+                            .cloned()
+                            .unwrap_or_default();
+                        Mode::Edit { field, value }
+                    }
+                    KeyCode::Esc => Mode::Browse,
+                    _ => Mode::Field(field),
+                };
+                None
+            }
+            Mode::Edit { field, mut value } => match key.code {
+                KeyCode::Enter => Some(Action::Update {
+                    id: self.selected_id()?,
+                    field: self.view().listing.field(field)?.to_string(),
+                    value,
+                }),
+                KeyCode::Esc => {
+                    self.mode = Mode::Field(field);
+                    None
+                }
+                code => {
+                    edit_text(&mut value, code);
+                    self.mode = Mode::Edit { field, value };
+                    None
+                }
+            },
+            Mode::Confirm => match key.code {
+                KeyCode::Char('y' | 'Y') => Some(Action::Delete {
+                    id: self.selected_id()?,
+                }),
+                _ => {
+                    self.status = Some(Status::Info("Cancelled".into()));
+                    None
+                }
+            },
+        }
+    }
+
+    fn browse(&mut self, code: KeyCode) {
+        let tab = self.tab;
+        let next = |step: usize| Tab::ALL[(tab.index() + step) % Tab::ALL.len()];
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab => self.tab = next(1),
+            KeyCode::BackTab => self.tab = next(Tab::ALL.len() - 1),
+            KeyCode::Char(c @ '1'..='4') => self.tab = Tab::ALL[c as usize - '1' as usize],
+            code => self.status = self.browse_view(code).map(Status::Info),
+        }
+    }
+
+    /// Applies a key acting on the current view; returns a message for the footer.
+    fn browse_view(&mut self, code: KeyCode) -> Option<String> {
+        let tab = self.tab;
+        let count = self.view().rows(tab).len();
+        let view = self.view_mut();
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                view.selected = (view.selected + 1).min(count.saturating_sub(1))
+            }
+            KeyCode::Home | KeyCode::Char('g') => view.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => view.selected = count.saturating_sub(1),
+            KeyCode::Right | KeyCode::PageDown | KeyCode::Char('n') => {
+                if !view.listing.has_next() {
+                    return Some("Last page".into());
+                }
+                view.page = view.listing.page.unwrap_or(view.page) + 1;
+                view.selected = 0;
+                view.stale = true;
+            }
+            KeyCode::Left | KeyCode::PageUp | KeyCode::Char('p') => {
+                if view.page <= 1 {
+                    return Some("First page".into());
+                }
+                view.page -= 1;
+                view.selected = 0;
+                view.stale = true;
+            }
+            KeyCode::Char('/') => {
+                let term = view.search.clone().unwrap_or_default();
+                self.mode = Mode::Search(term);
+            }
+            KeyCode::Char('s') => {
+                let listing = &view.listing;
+                let after = view.sort.map_or(0, |i| i + 1);
+                view.sort = (after..listing.columns.len()).find(|&i| listing.field(i).is_some());
+                view.page = 1;
+                view.stale = true;
+            }
+            KeyCode::Char('S') => {
+                view.descending = !view.descending;
+                view.page = 1;
+                view.stale = true;
+            }
+            KeyCode::Char('r') => view.stale = true,
+            KeyCode::Char('e') | KeyCode::Enter if count > 0 => {
+                if !tab.editable() {
+                    return Some(format!("{} is read-only", tab.title()));
+                }
+                if self.selected_id().is_none() {
+                    return Some(format!("No edit link gives the id of this {}", tab.noun()));
+                }
+                match self.editable_fields().first() {
+                    Some(&field) => self.mode = Mode::Field(field),
+                    None => return Some("The page names no editable fields".into()),
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete if count > 0 => {
+                if !tab.deletable() {
+                    return Some(format!("A {} cannot be deleted", tab.noun()));
+                }
+                if self.selected_id().is_none() {
+                    return Some(format!("No edit link gives the id of this {}", tab.noun()));
+                }
+                self.mode = Mode::Confirm;
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Applies a key to a line being typed.
+fn edit_text(text: &mut String, code: KeyCode) {
+    match code {
+        KeyCode::Char(c) => text.push(c),
+        KeyCode::Backspace => {
+            text.pop();
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(app: &mut App, keys: &str) -> Option<Action> {
+        let mut action = None;
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\x1b' => KeyCode::Esc,
+                '\x08' => KeyCode::Backspace,
+                c => KeyCode::Char(c),
+            };
+            action = app.handle(KeyEvent::from(code)).or(action);
+        }
+        action
+    }
+
+    /// A page like the panel's: a running number, labels, and ids only in the links.
+    fn pic_page() -> ListPage {
+        ListPage {
+            columns: vec![
+                "No".into(),
+                "Nama PIC".into(),
+                "No. HP".into(),
+                "Aksi".into(),
+            ],
+            fields: vec![None, Some("nama".into()), Some("no_hp".into()), None],
+            rows: vec![
+                vec![
+                    "1".into(),
+                    "Andi".into(),
+                    "0811".into(),
+                    "Edit Hapus".into(),
+                ],
+                vec![
+                    "2".into(),
+                    "Budi".into(),
+                    "0812".into(),
+                    "Edit Hapus".into(),
+                ],
+            ],
+            ids: vec![Some("11".into()), Some("12".into())],
+            page: Some(1),
+            pages: Some(2),
+            total: Some(3),
+        }
+    }
+
+    #[test]
+    fn test_paging_sorting_and_search() {
+        let mut app = App::new();
+        app.loaded(Ok(pic_page()));
+        press(&mut app, "nss");
+        assert_eq!(
+            app.query(),
+            Query {
+                page: 1,
+                search: None,
+                sort: Some("no_hp".into()),
+                direction: "asc"
+            }
+        );
+        press(&mut app, "s");
+        assert_eq!(app.query().sort, None);
+        press(&mut app, "S/honda\n");
+        let query = app.query();
+        assert_eq!(
+            (query.direction, query.search.as_deref()),
+            ("desc", Some("honda"))
+        );
+        assert!(app.view().stale);
+
+        // PIC search filters the loaded page without a request.
+        press(&mut app, "2");
+        app.loaded(Ok(pic_page()));
+        press(&mut app, "/bud\n");
+        assert!(!app.view().stale);
+        assert_eq!(app.query().search, None);
+        assert_eq!(app.selected_row().unwrap()[1], "Budi");
+    }
+
+    #[test]
+    fn test_edit_and_delete() {
+        let mut app = App::new();
+        press(&mut app, "2");
+        app.loaded(Ok(pic_page()));
+        let action = press(&mut app, "je\x1bej\n\x08\x08\x08\x089999\n");
+        assert_eq!(
+            action,
+            Some(Action::Update {
+                id: "12".into(),
+                field: "no_hp".into(),
+                value: "9999".into()
+            })
+        );
+        app.finished(&action.unwrap(), Ok(()));
+        assert_eq!(
+            app.status,
+            Some(Status::Info("Updated no_hp of PIC 12".into()))
+        );
+
+        assert_eq!(press(&mut app, "dn"), None);
+        assert_eq!(app.status, Some(Status::Info("Cancelled".into())));
+        assert_eq!(
+            press(&mut app, "dy"),
+            Some(Action::Delete { id: "12".into() })
+        );
+
+        press(&mut app, "3");
+        app.loaded(Ok(ListPage {
+            ids: vec![None, None],
+            ..pic_page()
+        }));
+        press(&mut app, "e");
+        assert_eq!(app.mode, Mode::Browse);
+        assert_eq!(
+            app.status,
+            Some(Status::Info(
+                "No edit link gives the id of this user".into()
+            ))
+        );
+
+        press(&mut app, "4");
+        app.loaded(Ok(pic_page()));
+        press(&mut app, "e");
+        assert_eq!(app.mode, Mode::Browse);
+        assert_eq!(
+            app.status,
+            Some(Status::Info("Input user is read-only".into()))
+        );
+        press(&mut app, "q");
+        assert!(app.quit);
+    }
+}
@@ -0,0 +1,143 @@
+//! `cekunit tui`: a full-screen browser for the dashboard, PIC, user and input-user
+//! listings.
+//!
+//! Pages are loaded with the regular sub-clients when a tab is first shown and after
+//! every change. The state and key bindings live in [`app`], the layout in [`view`];
+//! this module only talks to the terminal and the server.
+
+pub mod app;
+pub mod view;
+
+use app::{Action, App, Query, Tab};
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::style::{Print, PrintStyledContent, Stylize};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use librcekunit::api::dashboard::ListPage;
+use librcekunit::{ApiError, CekUnitClient};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use view::{Style, render};
+
+/// Runs the terminal UI until the user quits.
+///
+/// # Errors
+/// Returns [`ApiError::Other`] if standard output is not a terminal, and the error of
+/// the initial login or of the terminal. Failed requests are shown in the footer.
+pub fn run(client: &mut CekUnitClient) -> Result<(), ApiError> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(ApiError::Other("The TUI needs a terminal".into()));
+    }
+    client.ensure_logged_in()?;
+    // Log lines on standard error would be drawn over the screen.
+    log::set_max_level(log::LevelFilter::Off);
+
+    let _screen = Screen::enter().map_err(tui_error)?;
+    let mut out = io::stdout();
+    let mut app = App::new();
+    while !app.quit {
+        if app.view().stale {
+            draw(&mut out, &app).map_err(tui_error)?;
+            let result = fetch(client, app.tab, &app.query());
+            app.loaded(result);
+        }
+        draw(&mut out, &app).map_err(tui_error)?;
+        match event::read().map_err(tui_error)? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                if let Some(action) = app.handle(key) {
+                    let result = perform(client, app.tab, &action);
+                    app.finished(&action, result);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Raw mode on the alternate screen, restored when dropped (also on panic).
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn draw(out: &mut impl Write, app: &App) -> io::Result<()> {
+    let (width, height) = terminal::size()?;
+    // Leave the last column free: writing there scrolls some terminals.
+    let lines = render(
+        app,
+        usize::from(width.saturating_sub(1)),
+        usize::from(height),
+    );
+    for (y, line) in lines.iter().enumerate() {
+        queue!(out, cursor::MoveTo(0, y as u16))?;
+        let text = line.text.as_str();
+        match line.style {
+            Style::Normal => queue!(out, Print(text))?,
+            Style::Title => queue!(out, PrintStyledContent(text.bold()))?,
+            Style::Header => queue!(out, PrintStyledContent(text.bold().underlined()))?,
+            Style::Selected => queue!(out, PrintStyledContent(text.reverse()))?,
+            Style::Dim => queue!(out, PrintStyledContent(text.dim()))?,
+            Style::Error => queue!(out, PrintStyledContent(text.red()))?,
+        }
+    }
+    out.flush()
+}
+
+/// Loads one page of a tab.
+fn fetch(client: &CekUnitClient, tab: Tab, query: &Query) -> Result<ListPage, ApiError> {
+    let page = Some(query.page);
+    let search = query.search.as_deref();
+    let sort = query.sort.as_deref();
+    let direction = Some(query.direction);
+    let html = match tab {
+        Tab::Dashboard => client
+            .dashboard()?
+            .get_dashboard(page, search, sort, direction)?,
+        Tab::Pic => client.pic()?.get_pic_list(page, sort, direction)?,
+        Tab::Users => client.users()?.get_users_list(page, sort, direction)?,
+        Tab::InputUser => client
+            .input_user()?
+            .get_input_user(page, search, sort, direction, None, None)?,
+    };
+    ListPage::parse(&html)
+}
+
+/// Sends a change of the current tab to the server.
+fn perform(client: &CekUnitClient, tab: Tab, action: &Action) -> Result<(), ApiError> {
+    match action {
+        Action::Update { id, field, value } => {
+            let data = HashMap::from([(field.as_str(), value.as_str())]);
+            match tab {
+                Tab::Dashboard => client.dashboard()?.update_cekunit(id, data),
+                Tab::Pic => client.pic()?.update_pic(id, data),
+                Tab::Users => client.users()?.update_user(id, data),
+                Tab::InputUser => Err(ApiError::Other("Input user entries are read-only".into())),
+            }
+        }
+        Action::Delete { id } => match tab {
+            Tab::Dashboard => client.dashboard()?.delete_cekunit(id),
+            Tab::Pic => client.pic()?.delete_pic(id),
+            Tab::Users | Tab::InputUser => Err(ApiError::Other(format!(
+                "{} cannot be deleted",
+                tab.title()
+            ))),
+        },
+    }
+}
+
+fn tui_error(e: io::Error) -> ApiError {
+    ApiError::IoError(format!("Terminal error: {}", e))
+}
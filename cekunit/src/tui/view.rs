@@ -0,0 +1,279 @@
+//! Layout of the terminal UI as styled lines.
+//!
+//! The screen is, from top to bottom: the tabs, a line about the page and query, the
+//! table, the detail pane of the selected row and a footer with help, messages or
+//! the text being typed. Every line is padded to the width of the terminal so that
+//! drawing a frame overwrites the previous one.
+
+use super::app::{App, Mode, Status, Tab};
+
+/// How a line is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Normal,
+    Title,
+    Header,
+    Selected,
+    Dim,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub text: String,
+    pub style: Style,
+}
+
+/// Separator between table columns.
+const SEPARATOR: &str = " │ ";
+/// Columns wider than this are cut before fitting the table to the screen.
+const MAX_COLUMN_WIDTH: usize = 40;
+
+const HELP: &str = "q quit · tab/1-4 switch · ↑↓ select · ←→ page · / search · s sort · \
+                    S reverse · e edit · d delete · r reload";
+
+/// Lays out `app` on a `width` × `height` screen.
+///
+/// # Returns
+/// Exactly `height` lines of `width` characters.
+pub fn render(app: &App, width: usize, height: usize) -> Vec<Line> {
+    let view = app.view();
+    let listing = &view.listing;
+    let rows = view.rows(app.tab);
+    let mut lines = vec![tabs(app.tab), info(app)];
+
+    let selected = app.selected_row();
+    let detail_height = match selected {
+        Some(row) => row.len().min((height / 3).max(1)),
+        None => 0,
+    };
+    let body_height = height.saturating_sub(lines.len() + 3 + detail_height);
+
+    let widths = column_widths(app, width);
+    lines.push(line(table_row(&listing.columns, &widths), Style::Header));
+    if rows.is_empty() && !view.stale {
+        lines.push(line("No records".into(), Style::Dim));
+    }
+    let offset = view.selected.saturating_sub(body_height.saturating_sub(1));
+    for (position, &index) in rows.iter().enumerate().skip(offset).take(body_height) {
+        let style = if position == view.selected {
+            Style::Selected
+        } else {
+            Style::Normal
+        };
+        lines.push(line(table_row(&listing.rows[index], &widths), style));
+    }
+    while lines.len() < 3 + body_height {
+        lines.push(line(String::new(), Style::Normal));
+    }
+
+    lines.push(line("─".repeat(width), Style::Dim));
+    if let Some(row) = selected {
+        lines.extend(detail(app, row, detail_height));
+    }
+    lines.push(footer(app));
+
+    lines.truncate(height);
+    while lines.len() < height {
+        lines.push(line(String::new(), Style::Normal));
+    }
+    for line in &mut lines {
+        line.text = fit(&line.text, width);
+    }
+    lines
+}
+
+fn line(text: String, style: Style) -> Line {
+    Line { text, style }
+}
+
+fn tabs(current: Tab) -> Line {
+    let titles: Vec<String> = Tab::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, &tab)| {
+            if tab == current {
+                format!("[{} {}]", i + 1, tab.title())
+            } else {
+                format!(" {} {} ", i + 1, tab.title())
+            }
+        })
+        .collect();
+    line(titles.join(" "), Style::Title)
+}
+
+fn info(app: &App) -> Line {
+    let view = app.view();
+    let listing = &view.listing;
+    let mut parts = vec![match listing.pages {
+        Some(pages) => format!("Page {} of {}", view.page, pages),
+        None => format!("Page {}", view.page),
+    }];
+    if let Some(total) = listing.total {
+        parts.push(format!("{} records", total));
+    }
+    if let Some(column) = view.sort.and_then(|i| listing.columns.get(i)) {
+        let arrow = if view.descending { "↓" } else { "↑" };
+        parts.push(format!("sort {} {}", column, arrow));
+    }
+    if let Some(search) = &view.search {
+        parts.push(format!("search \"{}\"", search));
+    }
+    if view.stale {
+        parts.push("loading…".into());
+    }
+    line(parts.join(" · "), Style::Dim)
+}
+
+/// Natural column widths, narrowed until the table fits `width`.
+fn column_widths(app: &App, width: usize) -> Vec<usize> {
+    let listing = &app.view().listing;
+    let mut widths: Vec<usize> = listing
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            listing
+                .rows
+                .iter()
+                .filter_map(|row| row.get(i))
+                .chain([column])
+                .map(|text| text.chars().count())
+                .max()
+                .unwrap_or(0)
+                .min(MAX_COLUMN_WIDTH)
+        })
+        .collect();
+    let separators = widths.len().saturating_sub(1) * SEPARATOR.chars().count();
+    let available = width.saturating_sub(separators);
+    while widths.iter().sum::<usize>() > available {
+        match widths.iter_mut().max() {
+            Some(widest) if *widest > 3 => *widest -= 1,
+            _ => break,
+        }
+    }
+    widths
+}
+
+fn table_row(cells: &[String], widths: &[usize]) -> String {
+    widths
+        .iter()
+        .enumerate()
+        .map(|(i, &width)| fit(cells.get(i).map_or("", String::as_str), width))
+        .collect::<Vec<_>>()
+        .join(SEPARATOR)
+}
+
+/// The fields of the selected row, scrolled to the field being edited.
+fn detail(app: &App, row: &[String], height: usize) -> Vec<Line> {
+    let columns = &app.view().listing.columns;
+    let label_width = columns.iter().map(|c| c.chars().count()).max().unwrap_or(0);
+    let (marked, editing) = match &app.mode {
+        Mode::Field(field) => (Some(*field), None),
+        Mode::Edit { field, value } => (Some(*field), Some(value)),
+        _ => (None, None),
+    };
+    let offset = marked.map_or(0, |field| field.saturating_sub(height.saturating_sub(1)));
+    columns
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(i, column)| {
+            let value = match editing {
+                Some(value) if marked == Some(i) => format!("{}█", value),
+                _ => row.get(i).cloned().unwrap_or_default(),
+            };
+            let (marker, style) = if marked == Some(i) {
+                ("› ", Style::Selected)
+            } else {
+                ("  ", Style::Normal)
+            };
+            let label = format!("{:>width$}", column, width = label_width);
+            line(format!("{}{}: {}", marker, label, value), style)
+        })
+        .collect()
+}
+
+fn footer(app: &App) -> Line {
+    match &app.mode {
+        Mode::Search(term) => line(format!("/{}█", term), Style::Normal),
+        Mode::Field(_) => line(
+            "↑↓ choose a field · enter edit · esc back".into(),
+            Style::Dim,
+        ),
+        Mode::Edit { .. } => line("enter save · esc cancel".into(), Style::Dim),
+        Mode::Confirm => {
+            let id = app.selected_id().unwrap_or_default();
+            let text = format!("Delete {} {}? [y/N]", app.tab.noun(), id);
+            line(text, Style::Error)
+        }
+        Mode::Browse => match &app.status {
+            Some(Status::Info(message)) => line(message.clone(), Style::Normal),
+            Some(Status::Error(message)) => line(message.clone(), Style::Error),
+            None => line(HELP.into(), Style::Dim),
+        },
+    }
+}
+
+/// Cuts or pads `text` to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let count = text.chars().count();
+    if count > width {
+        let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+        if width > 0 {
+            cut.push('…');
+        }
+        cut
+    } else {
+        format!("{}{}", text, " ".repeat(width - count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent};
+    use librcekunit::api::dashboard::ListPage;
+
+    #[test]
+    fn test_render_layout() {
+        let mut app = App::new();
+        app.loaded(Ok(ListPage {
+            columns: vec!["No".into(), "Nopol".into(), "Nama Nasabah".into()],
+            fields: vec![None, Some("nopol".into()), Some("nama_nasabah".into())],
+            rows: vec![
+                vec!["1".into(), "B 1001 ABC".into(), "Andi Wijaya Kusuma".into()],
+                vec!["2".into(), "B 1002 ABC".into(), "Budi".into()],
+            ],
+            ids: vec![Some("41".into()), Some("42".into())],
+            page: Some(1),
+            pages: Some(3),
+            total: Some(25),
+        }));
+        app.handle(KeyEvent::from(KeyCode::Down));
+        app.handle(KeyEvent::from(KeyCode::Char('e')));
+
+        let lines = render(&app, 30, 12);
+        let text: Vec<&str> = lines.iter().map(|l| l.text.trim_end()).collect();
+        assert_eq!(lines.len(), 12);
+        assert!(lines.iter().all(|l| l.text.chars().count() == 30));
+        assert!(text[0].starts_with("[1 Dashboard]  2 PIC"));
+        assert_eq!(text[1], "Page 1 of 3 · 25 records");
+        assert_eq!(text[2], "No │ Nopol      │ Nama Nasabah");
+        assert_eq!(text[3], "1  │ B 1001 ABC │ Andi Wijaya…");
+        assert_eq!(text[4], "2  │ B 1002 ABC │ Budi");
+        assert_eq!(lines[4].style, Style::Selected);
+        assert_eq!(text[8], format!("{}No: 2", " ".repeat(12)));
+        assert_eq!(text[9], format!("›{}Nopol: B 1002 ABC", " ".repeat(8)));
+        assert_eq!(lines[9].style, Style::Selected);
+        assert!(text[11].starts_with("↑↓ choose a field"));
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit("Honda", 3), "Ho…");
+        assert_eq!(fit("ok", 4), "ok  ");
+        assert_eq!(fit("x", 0), "");
+    }
+}
@@ -0,0 +1,723 @@
+//! End-to-end tests of the `cekunit` binary against the fake server.
+
+use assert_cmd::Command;
+use librcekunit::testing::{FakeCekUnit, Table};
+use predicates::prelude::*;
+use std::path::Path;
+
+/// The binary with a clean environment pointing at `server`, caching in `dir`.
+fn cekunit(server: &FakeCekUnit, dir: &Path) -> Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("cekunit");
+    cmd.env_clear()
+        .envs(server.vars())
+        .env("CEKUNIT_CACHE_DIR", dir)
+        .current_dir(dir);
+    cmd
+}
+
+#[test]
+fn session_commands() {
+    let server = FakeCekUnit::start();
+    let dir = tempfile::tempdir().unwrap();
+
+    cekunit(&server, dir.path())
+        .arg("status")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Not authenticated"));
+    cekunit(&server, dir.path())
+        .arg("login")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Logged in as admin@example.com"));
+    cekunit(&server, dir.path())
+        .args(["status", "--verify"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("email: admin@example.com"))
+        .stdout(predicate::str::contains("verified: true"));
+    cekunit(&server, dir.path())
+        .arg("logout")
+        .assert()
+        .success();
+    assert_eq!(server.logged_in_sessions(), 0);
+    cekunit(&server, dir.path())
+        .arg("status")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn listing_and_export() {
+    let server = FakeCekUnit::builder().per_page(10).start();
+    let dir = tempfile::tempdir().unwrap();
+
+    // Logs in on demand.
+    cekunit(&server, dir.path())
+        .args(["dashboard", "list", "--page", "3"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nama Nasabah"))
+        .stderr(predicate::str::contains("Page 3 of 3 (25 records)"));
+
+    let output = cekunit(&server, dir.path())
+        .args([
+            "users",
+            "list",
+            "--all",
+            "--sort",
+            "name",
+            "--direction",
+            "desc",
+        ])
+        .args(["--output", "csv"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(String::from_utf8(output).unwrap().lines().count(), 1 + 5);
+
+    cekunit(&server, dir.path())
+        .args(["dashboard", "search", "B 1007"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("B 1007 ABC"))
+        .stdout(predicate::str::contains("B 1008 ABC").not());
+
+    cekunit(&server, dir.path())
+        .args(["dashboard", "unique", "merk"])
+        .assert()
+        .success()
+        .stdout("Honda\nKawasaki\nSuzuki\nYamaha\n");
+
+    let export = dir.path().join("records.csv");
+    cekunit(&server, dir.path())
+        .args(["dashboard", "export", "--format", "csv", "--out"])
+        .arg(&export)
+        .assert()
+        .success();
+    let csv = std::fs::read_to_string(&export).unwrap();
+    assert_eq!(csv.lines().count(), 1 + 25);
+
+    cekunit(&server, dir.path())
+        .args(["input-user", "export", "--format", "pdf"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unsupported export format"));
+}
+
+#[test]
+fn pic_mutations_and_confirmation() {
+    let server = FakeCekUnit::builder().rows(Table::Pic, 2).start();
+    let dir = tempfile::tempdir().unwrap();
+
+    cekunit(&server, dir.path())
+        .args(["pic", "create", "nama=Hana", "no_hp=0811"])
+        .assert()
+        .success();
+    cekunit(&server, dir.path())
+        .args(["pic", "update", "3", "nama=Hana Putri"])
+        .assert()
+        .success();
+    cekunit(&server, dir.path())
+        .args(["pic", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hana Putri"));
+
+    cekunit(&server, dir.path())
+        .args(["pic", "update", "3", "nama"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected name=value"));
+
+    // Without a terminal, destructive commands need --yes.
+    cekunit(&server, dir.path())
+        .args(["pic", "delete", "3"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("pass --yes"));
+    assert_eq!(server.rows(Table::Pic).len(), 3);
+    cekunit(&server, dir.path())
+        .args(["pic", "delete", "3", "--yes"])
+        .assert()
+        .success();
+    assert_eq!(server.rows(Table::Pic).len(), 2);
+}
+
+#[test]
+fn output_modes() {
+    let server = FakeCekUnit::builder().rows(Table::Pic, 3).start();
+    let dir = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| -> String {
+        let output = cekunit(&server, dir.path()).args(args).assert().success();
+        String::from_utf8(output.get_output().stdout.clone()).unwrap()
+    };
+
+    let text = run(&[
+        "pic",
+        "list",
+        "-o",
+        "json",
+        "--columns",
+        "nama,id",
+        "--order-by",
+        "id:desc",
+    ]);
+    // Keys follow the --columns order.
+    assert!(text.find("\"nama\"").unwrap() < text.find("\"id\"").unwrap());
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([
+            {"nama": "Citra 3", "id": "3"},
+            {"nama": "Budi 2", "id": "2"},
+            {"nama": "Andi 1", "id": "1"},
+        ])
+    );
+    let ndjson = run(&["pic", "list", "-o", "ndjson", "--columns", "id"]);
+    assert_eq!(ndjson, "{\"id\":\"1\"}\n{\"id\":\"2\"}\n{\"id\":\"3\"}\n");
+    let csv = run(&["-o", "csv", "pic", "list", "--columns", "ID,nama"]);
+    assert_eq!(csv, "id,nama\n1,Andi 1\n2,Budi 2\n3,Citra 3\n");
+    let table = run(&["pic", "list", "-o", "table", "--order-by", "nama:desc"]);
+    assert!(table.find("Citra").unwrap() < table.find("Andi").unwrap());
+    assert!(!table.contains('\u{1b}'), "no colour without a terminal");
+
+    let status: serde_json::Value = serde_json::from_str(&run(&["status", "-o", "json"])).unwrap();
+    assert_eq!(status["email"], "admin@example.com");
+    assert!(status["session_age"].is_i64());
+    assert_eq!(
+        run(&["dashboard", "unique", "status", "-o", "json"])
+            .lines()
+            .count(),
+        5
+    );
+
+    // Status cells are coloured when colour is forced.
+    cekunit(&server, dir.path())
+        .env("CLICOLOR_FORCE", "1")
+        .args(["dashboard", "list", "--columns", "no,status"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\u{1b}[38;5;11m pending"));
+
+    cekunit(&server, dir.path())
+        .args(["pic", "list", "--columns", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unknown column 'nope'; available: No, id, nama, email",
+        ));
+}
+
+#[test]
+fn shell_keeps_one_session() {
+    let server = FakeCekUnit::builder().rows(Table::Pic, 2).start();
+    let dir = tempfile::tempdir().unwrap();
+
+    cekunit(&server, dir.path())
+        .arg("shell")
+        .write_stdin("pic list -o csv --columns id\n\"unbalanced\npic delete 1\nbogus\nusers list -o csv --columns id\nexit\n")
+        .assert()
+        .success()
+        .stdout("id\n1\n2\nid\n1\n2\n3\n4\n5\n")
+        .stderr(predicate::str::contains("unbalanced quotes"))
+        .stderr(predicate::str::contains("pass --yes"))
+        .stderr(predicate::str::contains("unrecognized subcommand 'bogus'"));
+
+    assert_eq!(server.rows(Table::Pic).len(), 2);
+    let logins = server
+        .requests()
+        .iter()
+        .filter(|r| r.starts_with("POST /login"))
+        .count();
+    assert_eq!(logins, 1);
+    assert!(dir.path().join("history.txt").exists());
+}
+
+#[test]
+fn tui_needs_a_terminal() {
+    let server = FakeCekUnit::start();
+    let dir = tempfile::tempdir().unwrap();
+
+    cekunit(&server, dir.path())
+        .arg("tui")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("The TUI needs a terminal"));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn init_writes_a_working_config() {
+    let server = FakeCekUnit::start();
+    let dir = tempfile::tempdir().unwrap();
+    let bare = || {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("cekunit");
+        cmd.env_clear()
+            .env("CEKUNIT_CACHE_DIR", dir.path())
+            .current_dir(dir.path());
+        cmd
+    };
+
+    bare()
+        .args(["init", "--out", "cekunit.toml"])
+        .write_stdin(format!(
+            "{}\nadmin@example.com\nwrong-password\n",
+            server.url()
+        ))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Login failed"));
+    assert!(!dir.path().join("cekunit.toml").exists());
+
+    bare()
+        .args(["init", "--out", "cekunit.toml"])
+        .write_stdin(format!(
+            "{}/\nadmin@example.com\npassword123\n",
+            server.url()
+        ))
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains("✓ Logged in as admin@example.com"))
+        .stderr(predicate::str::contains("✓ pic (PIC_ENDPOINT)"))
+        .stderr(predicate::str::contains("Try: cekunit status"));
+    let written = std::fs::read_to_string(dir.path().join("cekunit.toml")).unwrap();
+    assert!(written.contains(&format!("base_url = \"{}\"", server.url())));
+    assert!(written.contains("user_password = \"password123\""));
+
+    // The config is found in the working directory and the session is reused.
+    bare()
+        .args(["pic", "list", "-o", "csv", "--columns", "id"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("id\n1\n"));
+    let logins = server
+        .requests()
+        .iter()
+        .filter(|r| r.starts_with("POST /login"))
+        .count();
+    assert_eq!(logins, 2);
+
+    bare()
+        .args(["init", "--out", "cekunit.toml"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("overwrite cekunit.toml"));
+    bare()
+        .args([
+            "init",
+            "--out",
+            ".env.staging",
+            "--prompt-password",
+            "--email",
+        ])
+        .arg("admin@example.com")
+        .args(["--base-url", &server.url()])
+        .write_stdin("password123\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Try: cekunit --env-file .env.staging status",
+        ));
+    let env = std::fs::read_to_string(dir.path().join(".env.staging")).unwrap();
+    assert!(env.contains("USER_PASSWORD_PROMPT=true"));
+    assert!(!env.contains("password123"));
+}
+
+#[test]
+fn completions_and_man_pages() {
+    let dir = tempfile::tempdir().unwrap();
+    let bare = || {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("cekunit");
+        cmd.env_clear().current_dir(dir.path());
+        cmd
+    };
+
+    for (shell, marker) in [
+        ("bash", "_cekunit()"),
+        ("zsh", "#compdef cekunit"),
+        ("fish", "complete -c cekunit"),
+    ] {
+        bare()
+            .args(["completions", shell])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(marker))
+            .stdout(predicate::str::contains("delete-by"));
+    }
+    bare()
+        .arg("man")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(".TH cekunit 1"));
+    bare().args(["man", "--out", "man"]).assert().success();
+    let page =
+        std::fs::read_to_string(dir.path().join("man/cekunit-dashboard-delete-by.1")).unwrap();
+    assert!(page.contains("\\-\\-yes"));
+}
+
+#[test]
+fn doctor_reports_each_check() {
+    let server = FakeCekUnit::start();
+    let dir = tempfile::tempdir().unwrap();
+
+    cekunit(&server, dir.path())
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("✓ csrf"))
+        .stdout(predicate::str::contains(
+            "✓ login: logged in as admin@example.com",
+        ))
+        .stdout(predicate::str::contains(
+            "✓ PIC_ENDPOINT: GET /pic -> HTTP 200",
+        ));
+    // Only pages are fetched; nothing is changed.
+    let requests = server.requests();
+    assert!(requests.iter().any(|r| r == "GET /users -> 200"));
+    assert!(!requests.iter().any(|r| r.contains("delete-all")));
+    assert_eq!(server.rows(Table::Cekunit).len(), 25);
+
+    let status = |checks: &[serde_json::Value], name: &str| {
+        checks.iter().find(|c| c["check"] == name).unwrap()["status"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let output = cekunit(&server, dir.path())
+        .args(["doctor", "-o", "json"])
+        .env("PIC_ENDPOINT", "people")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("1 check failed"))
+        .get_output()
+        .stdout
+        .clone();
+    let checks: Vec<serde_json::Value> = serde_json::from_slice(&output).unwrap();
+    assert_eq!(status(&checks, "cache"), "pass");
+    assert_eq!(status(&checks, "login"), "pass");
+    assert_eq!(status(&checks, "PIC_ENDPOINT"), "fail");
+    assert_eq!(status(&checks, "USERS_ENDPOINT"), "pass");
+    assert_eq!(status(&checks, "PIC_ITEM_ENDPOINT"), "skip");
+    let pic = checks
+        .iter()
+        .find(|c| c["check"] == "PIC_ENDPOINT")
+        .unwrap();
+    assert!(pic["hint"].as_str().unwrap().contains("set PIC_ENDPOINT"));
+
+    let output = cekunit(&server, dir.path())
+        .args(["doctor", "-o", "json"])
+        .env("USER_PASSWORD", "wrong-password")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("1 check failed"))
+        .get_output()
+        .stdout
+        .clone();
+    let checks: Vec<serde_json::Value> = serde_json::from_slice(&output).unwrap();
+    assert_eq!(status(&checks, "login"), "fail");
+    assert_eq!(status(&checks, "USERS_ENDPOINT"), "skip");
+}
+
+#[test]
+fn run_applies_a_script_in_one_session() {
+    let server = FakeCekUnit::start();
+    let dir = tempfile::tempdir().unwrap();
+    let pics = server.rows(Table::Pic).len();
+    std::fs::write(
+        dir.path().join("changes.yaml"),
+        r#"
+vars:
+  status: selesai
+steps:
+  - name: close record 1
+    op: dashboard.update
+    no: 1
+    fields: { status: "${status}" }
+  - op: pic.update
+    id: 999
+    fields: { nama: Nobody }
+  - op: pic.delete
+    id: 2
+  - op: dashboard.export
+    format: csv
+    out: "records-${status}.csv"
+"#,
+    )
+    .unwrap();
+
+    cekunit(&server, dir.path())
+        .args(["run", "changes.yaml", "--dry-run", "--var", "status=batal"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "1. close record 1: PUT {}/cekunit/1 status=batal",
+            server.url()
+        )))
+        .stdout(predicate::str::contains("4. dashboard.export: GET "))
+        .stdout(predicate::str::contains("> records-batal.csv"));
+    assert!(server.requests().is_empty());
+
+    cekunit(&server, dir.path())
+        .args(["run", "changes.yaml"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Refusing to run 1 deleting step from changes.yaml without confirmation",
+        ));
+
+    // Stops at the failing step by default.
+    cekunit(&server, dir.path())
+        .args(["run", "changes.yaml", "--yes", "-o", "csv"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::starts_with(
+            "step,name,status,detail\n1,close record 1,done,updated record 1\n",
+        ))
+        .stdout(predicate::str::contains(
+            "2,pic.update,failed,\"pic.update (POST",
+        ))
+        .stdout(predicate::str::ends_with(
+            "3,pic.delete,skipped,\n4,dashboard.export,skipped,\n",
+        ))
+        .stderr(predicate::str::contains(
+            "4 steps: 1 done, 1 failed, 2 skipped",
+        ))
+        .stderr(predicate::str::contains("1 of 4 steps failed"));
+    assert_eq!(server.rows(Table::Pic).len(), pics);
+
+    cekunit(&server, dir.path())
+        .args(["run", "changes.yaml", "--yes", "--on-error", "continue"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "4 steps: 3 done, 1 failed, 0 skipped",
+        ));
+    assert_eq!(server.rows(Table::Pic).len(), pics - 1);
+    assert_eq!(server.rows(Table::Cekunit)[0]["status"], "selesai");
+    assert!(dir.path().join("records-selesai.csv").exists());
+    let logins = server
+        .requests()
+        .iter()
+        .filter(|r| r.starts_with("POST /login"))
+        .count();
+    assert_eq!(logins, 1);
+}
+
+#[test]
+fn exit_codes_and_json_errors() {
+    let server = FakeCekUnit::start();
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = cekunit(&server, dir.path())
+        .args(["pic", "create", "nama=", "-o", "json"])
+        .assert()
+        .code(8)
+        .stdout("");
+    // Log warnings may come first; the error is the last line.
+    let stderr = String::from_utf8(output.get_output().stderr.clone()).unwrap();
+    let error: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(error["code"], "validation_failed");
+    assert_eq!(error["exit_code"], 8);
+    assert_eq!(error["status"], 422);
+    assert_eq!(error["operation"], "pic.create");
+    assert_eq!(error["fields"]["nama"][0], "The nama field is required.");
+
+    cekunit(&server, dir.path())
+        .args(["pic", "update", "999", "nama=Hana"])
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains("Resource not found"));
+
+    cekunit(&server, dir.path())
+        .env_remove("USER_EMAIL")
+        .args(["status", "-o", "ndjson"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(r#""code":"config_missing""#));
+}
+
+#[test]
+fn serve_exposes_the_panel_as_json() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let server = FakeCekUnit::builder().rows(Table::Users, 250).start();
+    let dir = tempfile::tempdir().unwrap();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("cekunit"))
+        .args(["serve", "--listen", "127.0.0.1:0", "--api-key", "secret"])
+        .env_clear()
+        .envs(server.vars())
+        .env("CEKUNIT_CACHE_DIR", dir.path())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let stderr = child.stderr.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if let Some(rest) = line.strip_prefix("Listening on ") {
+                let _ = tx.send(rest.split(' ').next().unwrap_or_default().to_string());
+            }
+        }
+    });
+    let base = rx
+        .recv_timeout(std::time::Duration::from_secs(30))
+        .expect("server started");
+    let http = reqwest::blocking::Client::new();
+    let get = |path: &str| {
+        http.get(format!("{base}{path}"))
+            .header("X-API-Key", "secret")
+    };
+    let json =
+        |response: reqwest::blocking::Response| -> serde_json::Value { response.json().unwrap() };
+
+    let health = http.get(format!("{base}/health")).send().unwrap();
+    assert_eq!(health.status(), 200);
+    let denied = http.get(format!("{base}/records")).send().unwrap();
+    assert_eq!(denied.status(), 401);
+    assert_eq!(json(denied)["code"], "invalid_api_key");
+
+    let records = json(get("/records?all=true").send().unwrap());
+    assert_eq!(records["records"].as_array().unwrap().len(), 25);
+    assert!(records["records"][0]["nopol"].is_string());
+    assert!(records["next_page"].is_null());
+    // `all` stops after 20 pages and says where the rest starts.
+    let users = json(get("/users?all=true").send().unwrap());
+    assert_eq!(users["records"].as_array().unwrap().len(), 200);
+    assert_eq!(users["next_page"], 21);
+
+    // An expired session is renewed once, transparently.
+    server.expire_sessions();
+    let pics = http
+        .get(format!("{base}/pics"))
+        .bearer_auth("secret")
+        .send()
+        .unwrap();
+    assert_eq!(pics.status(), 200);
+    assert_eq!(json(pics)["records"].as_array().unwrap().len(), 10);
+    let logins = server
+        .requests()
+        .iter()
+        .filter(|r| r.starts_with("POST /login"))
+        .count();
+    assert_eq!(logins, 2);
+
+    let updated = http
+        .put(format!("{base}/records/1"))
+        .header("X-API-Key", "secret")
+        .json(&serde_json::json!({ "status": "selesai" }))
+        .send()
+        .unwrap();
+    assert_eq!(updated.status(), 204);
+    assert_eq!(server.rows(Table::Cekunit)[0]["status"], "selesai");
+
+    let invalid = http
+        .post(format!("{base}/pics"))
+        .header("X-API-Key", "secret")
+        .json(&serde_json::json!({ "nama": "" }))
+        .send()
+        .unwrap();
+    assert_eq!(invalid.status(), 422);
+    let error = json(invalid);
+    assert_eq!(error["code"], "validation_failed");
+    assert_eq!(error["fields"]["nama"][0], "The nama field is required.");
+    let missing = http
+        .delete(format!("{base}/pics/999"))
+        .header("X-API-Key", "secret")
+        .send()
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+    let unscoped = http
+        .delete(format!("{base}/records"))
+        .header("X-API-Key", "secret")
+        .send()
+        .unwrap();
+    assert_eq!(unscoped.status(), 422);
+    let unconfirmed = http
+        .delete(format!("{base}/records?all=true"))
+        .header("X-API-Key", "secret")
+        .send()
+        .unwrap();
+    assert_eq!(unconfirmed.status(), 422);
+    assert_eq!(server.rows(Table::Cekunit).len(), 25);
+
+    let filtered = get("/records/export?format=csv&search=Andi")
+        .send()
+        .unwrap();
+    assert_eq!(filtered.status(), 422);
+    let export = get("/records/export?format=csv").send().unwrap();
+    assert_eq!(export.headers()["content-type"], "text/csv; charset=utf-8");
+    assert!(export.text().unwrap().starts_with("no,nopol"));
+
+    let doc = json(http.get(format!("{base}/openapi.json")).send().unwrap());
+    assert_eq!(doc["openapi"], "3.1.0");
+    assert!(doc["paths"]["/records/{no}"]["put"].is_object());
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn rpc_answers_json_rpc_on_stdio() {
+    let server = FakeCekUnit::start();
+    let dir = tempfile::tempdir().unwrap();
+    let requests = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"list","params":{"resource":"pics","all":true}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"get","params":{"resource":"records","id":"12"}}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"update","params":{"resource":"records","id":"12","fields":{"status":"selesai"}}}"#,
+        r#"{"jsonrpc":"2.0","id":4,"method":"create","params":{"resource":"pics","fields":{"nama":""}}}"#,
+        r#"{"jsonrpc":"2.0","id":5,"method":"export","params":{"resource":"records","path":"out.csv","format":"csv"}}"#,
+        r#"{"jsonrpc":"2.0","method":"delete","params":{"resource":"pics","id":"1"}}"#,
+        r#"{"jsonrpc":"2.0","id":6,"method":"delete_all","params":{"confirm":false}}"#,
+        r#"{"jsonrpc":"2.0","id":7,"method":"rpc.discover"}"#,
+    ];
+    let output = cekunit(&server, dir.path())
+        .arg("rpc")
+        .write_stdin(requests.join("\n") + "\n")
+        .assert()
+        .success();
+    let responses: Vec<serde_json::Value> = String::from_utf8(output.get_output().stdout.clone())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(responses.len(), 7, "the notification is not answered");
+
+    assert_eq!(responses[0]["id"], 1);
+    assert_eq!(
+        responses[0]["result"]["records"].as_array().unwrap().len(),
+        12
+    );
+    assert_eq!(responses[1]["result"]["no"], "12");
+    assert_eq!(responses[2]["result"], serde_json::Value::Null);
+    assert_eq!(server.rows(Table::Cekunit)[11]["status"], "selesai");
+
+    let error = &responses[3]["error"];
+    assert_eq!(error["code"], -32008);
+    assert_eq!(error["data"]["code"], "validation_failed");
+    assert_eq!(error["data"]["status"], 422);
+    assert_eq!(
+        error["data"]["fields"]["nama"][0],
+        "The nama field is required."
+    );
+
+    assert_eq!(responses[4]["result"]["path"], "out.csv");
+    assert!(dir.path().join("out.csv").exists());
+    assert_eq!(server.rows(Table::Pic).len(), 11);
+    assert_eq!(responses[5]["error"]["code"], -32602);
+    assert_eq!(server.rows(Table::Cekunit).len(), 25);
+
+    let methods = responses[6]["result"]["methods"].as_array().unwrap();
+    let list = methods.iter().find(|m| m["name"] == "list").unwrap();
+    assert_eq!(list["params"]["required"], serde_json::json!(["resource"]));
+    let logins = server
+        .requests()
+        .iter()
+        .filter(|r| r.starts_with("POST /login"))
+        .count();
+    assert_eq!(logins, 1);
+}
@@ -2,7 +2,8 @@
 //!
 //! This module provides the [`LoginClient`] struct, which handles the entire login process:
 //! - Fetching a CSRF token from the login page.
-//! - Submitting credentials (email/password) along with the token.
+//! - Submitting credentials (email/password) along with the token. The password is
+//!   requested from a [`CredentialProvider`] at login time and not stored.
 //! - Extracting session cookies from the response.
 //! - Persisting the session (cookies and token) in a cache file.
 //!
@@ -14,13 +15,16 @@ use crate::api::auth::utils::{
     cookies::{add_cookies_to_headers, extract_cookies},
     token::extract_csrf_token,
 };
+use crate::handler::credentials::CredentialProvider;
 use crate::handler::env::EnvConfig;
-use crate::handler::error::ApiError;
-use reqwest::StatusCode;
+use crate::handler::error::{ApiError, ErrorContext};
+use crate::handler::telemetry::operation_span;
+use crate::middleware::{ClientEvent, MiddlewareStack};
 use reqwest::blocking::Client;
 use reqwest::header::{CONTENT_TYPE, HeaderMap, USER_AGENT};
 use std::collections::HashMap;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// User-Agent string used for all requests.
 ///
@@ -44,12 +48,12 @@ const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
 ///
 /// # Example
 /// ```no_run
-/// use cekunit_client::api::auth::LoginClient;
+/// use librcekunit::api::auth::LoginClient;
 ///
 /// let mut client = LoginClient::new()?;
 /// let session = client.login()?;
 /// println!("Logged in, cookies: {}", session.cookies.len());
-/// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+/// # Ok::<(), librcekunit::handler::error::ApiError>(())
 /// ```
 pub struct LoginClient {
     /// The underlying reqwest blocking client.
@@ -58,6 +62,10 @@ pub struct LoginClient {
     pub config: EnvConfig,
     /// Manager for reading/writing the session cache.
     pub cache_manager: CacheManager,
+    /// Source of the password, asked once per login.
+    credentials: Arc<dyn CredentialProvider>,
+    /// Hooks run around every request.
+    middleware: MiddlewareStack,
 }
 
 impl LoginClient {
@@ -89,15 +97,51 @@ impl LoginClient {
     /// - The cache directory cannot be created.
     /// - The HTTP client cannot be built.
     pub fn with_config(config: EnvConfig) -> Result<Self, ApiError> {
-        let cache_manager = CacheManager::new()?;
+        Self::with_config_and_cache(config, CacheManager::new()?)
+    }
+
+    /// Creates a new `LoginClient` with a given configuration and an existing cache manager.
+    ///
+    /// This is useful when sharing the same cache (and thus the same session) across
+    /// multiple clients, or to keep the session out of the user's cache directory.
+    ///
+    /// # Arguments
+    /// * `config` - The environment configuration.
+    /// * `cache_manager` - An existing cache manager (typically from the main client).
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] if the HTTP client cannot be built.
+    pub fn with_config_and_cache(
+        config: EnvConfig,
+        cache_manager: CacheManager,
+    ) -> Result<Self, ApiError> {
         let client = Self::build_client()?;
+        let credentials = Arc::new(config.password.clone());
         Ok(Self {
             client,
             config,
             cache_manager,
+            credentials,
+            middleware: MiddlewareStack::new(),
         })
     }
 
+    /// Replaces the password source configured in [`EnvConfig::password`].
+    ///
+    /// # Arguments
+    /// * `provider` - Provider asked for the password on every [`login`](Self::login).
+    pub fn set_credential_provider(&mut self, provider: Arc<dyn CredentialProvider>) {
+        self.credentials = provider;
+    }
+
+    /// Sets the middlewares every request of this client is sent through.
+    ///
+    /// [`CekUnitClient::add_middleware`](crate::CekUnitClient::add_middleware) does this
+    /// for the clients it manages.
+    pub fn set_middleware(&mut self, middleware: MiddlewareStack) {
+        self.middleware = middleware;
+    }
+
     /// Builds and configures the HTTP client.
     ///
     /// The client is configured with:
@@ -122,7 +166,7 @@ impl LoginClient {
             .pool_max_idle_per_host(10)
             .build()
             .map_err(|e| {
-                log::error!("Failed to build HTTP client: {}", e);
+                tracing::error!(error = %e, "failed to build HTTP client");
                 ApiError::from(e)
             })
     }
@@ -130,72 +174,78 @@ impl LoginClient {
     /// Performs the full login flow and returns the cached session data.
     ///
     /// The steps are:
-    /// 1. Validate that credentials are present (non‑empty).
+    /// 1. Validate that the email is present (non‑empty).
     /// 2. Fetch a CSRF token from the login page (with retries).
-    /// 3. Build a login form with the token, email, and password.
-    /// 4. Attach any previously cached cookies (if any).
-    /// 5. Send a POST request to the login endpoint (with retries).
-    /// 6. Validate the response status.
-    /// 7. Extract cookies from the response headers.
-    /// 8. Build a `CacheData` object containing cookies, CSRF token, and timestamp.
-    /// 9. Save the cache data to the cache file.
+    /// 3. Ask the credential provider for the password and build a login form with
+    ///    the token, email, and password.
+    /// 4. Send a POST request to the login endpoint (with retries), with the cookies
+    ///    of the login page.
+    /// 5. Check the response status (redirects count as success).
+    /// 6. Extract cookies from the response headers.
+    /// 7. Build a `CacheData` object containing cookies, CSRF token, and timestamp.
+    /// 8. Save the cache data to the cache file.
     ///
     /// # Returns
     /// The newly created [`CacheData`] representing the authenticated session.
     ///
     /// # Errors
     /// Returns [`ApiError`] if any step fails:
-    /// - Credentials are empty.
+    /// - The email is empty or the password cannot be obtained.
     /// - CSRF token cannot be fetched (after retries).
     /// - Login request fails (after retries).
     /// - Response status indicates failure (4xx or 5xx).
     /// - Response body cannot be read.
     /// - Cache cannot be saved.
     pub fn login(&mut self) -> Result<CacheData, ApiError> {
-        log::info!(
-            " Starting login process to: {}",
-            self.config.full_login_url()
-        );
+        let _span = operation_span!("auth.login").entered();
+        let result = self.perform_login();
+        self.middleware.emit(ClientEvent::Login {
+            success: result.is_ok(),
+        });
+        result
+    }
+
+    /// Runs the login steps listed in [`login`](Self::login).
+    fn perform_login(&mut self) -> Result<CacheData, ApiError> {
+        tracing::info!("starting login");
         self.validate_credentials()?;
 
         let csrf_token = self.fetch_csrf_token_with_retry()?;
-        log::debug!(
-            " CSRF token fetched: {}…",
-            &csrf_token[..10.min(csrf_token.len())]
-        );
+        tracing::debug!("CSRF token fetched");
+
+        tracing::debug!(source = %self.credentials.describe(), "requesting password");
+        let password = self.credentials.password(&self.config.user_email)?;
 
         let mut login_form = HashMap::new();
         login_form.insert("_token", csrf_token.as_str());
         login_form.insert("email", self.config.user_email.as_str());
-        login_form.insert("password", self.config.user_password.as_str());
+        login_form.insert("password", password.expose_str());
 
-        let mut headers = self.build_base_headers()?;
-        self.attach_cached_cookies(&mut headers)?;
+        // No cached cookies: the cookie jar already holds the session the token on the
+        // login page belongs to, and an explicit `Cookie` header would replace it.
+        let headers = self.build_base_headers()?;
 
-        log::info!(" Sending login request...");
-        let response = self.execute_login_request(&headers, &login_form)?;
+        tracing::debug!("sending login request");
+        let (response, ctx) = self.execute_login_request(&headers, &login_form)?;
+        drop(login_form);
+        drop(password);
+        let response = ctx.check_allow_redirect(response)?;
 
-        let status = response.status();
         let headers_clone = response.headers().clone();
-        let body = response.text().map_err(|e| {
-            log::error!("Failed to read response body: {}", e);
-            ApiError::from(e)
-        })?;
-
-        self.validate_login_response(status, &body)?;
+        response.text().map_err(|e| ctx.transport(e))?;
 
         let cookies = extract_cookies(&headers_clone);
-        log::debug!(" Received {} cookies", cookies.len());
+        tracing::debug!(cookies = cookies.len(), "login response received");
         if cookies.is_empty() {
-            log::warn!("️ No cookies received from login response!");
+            tracing::warn!("login response set no cookies");
         }
 
         let cache_data = self.build_cache_data(cookies, csrf_token)?;
         self.cache_manager.save(&cache_data)?;
 
-        log::info!(
-            " Login successful. Cache saved at {:?}",
-            self.cache_manager.cache_file_path()
+        tracing::info!(
+            cache_file = %self.cache_manager.cache_file_path().display(),
+            "login successful"
         );
 
         Ok(cache_data)
@@ -217,44 +267,79 @@ impl LoginClient {
     /// - The response body cannot be read.
     /// - No CSRF token is found in the HTML.
     pub fn fetch_csrf_token(&self) -> Result<String, ApiError> {
-        log::debug!(" Fetching CSRF token from login page");
-        let response = self
-            .client
-            .get(self.config.full_login_url())
-            .send()
-            .map_err(|e| {
-                log::error!("Network error while fetching CSRF token: {}", e);
-                ApiError::from(e)
-            })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body_snippet = response
-                .text()
-                .unwrap_or_default()
-                .chars()
-                .take(200)
-                .collect::<String>();
-            log::error!(
-                "Failed to fetch login page: HTTP {} - {}",
-                status,
-                body_snippet
-            );
-            return Err(ApiError::LoginFailed(format!(
-                "Failed to fetch login page (HTTP {}): {}",
-                status, body_snippet
-            )));
-        }
-
-        let html = response.text().map_err(|e| {
-            log::error!("Failed to read response body: {}", e);
-            ApiError::from(e)
+        let _span = operation_span!("auth.csrf").entered();
+        tracing::debug!("fetching CSRF token from login page");
+        let url = self.config.full_login_url()?;
+        let ctx = ErrorContext::new("auth.csrf", "GET", &url);
+        let response = self.middleware.send(self.client.get(&url), &ctx)?;
+        let response = ctx.check(response)?;
+        let html = response.text().map_err(|e| ctx.transport(e))?;
+
+        let token = extract_csrf_token(&html).inspect_err(|_| {
+            tracing::error!("CSRF token not found in login page");
         })?;
+        self.middleware.emit(ClientEvent::CsrfRefresh);
+        Ok(token)
+    }
 
-        extract_csrf_token(&html).map_err(|e| {
-            log::error!("CSRF token not found in login page HTML");
-            e
-        })
+    /// Asks the server whether the cached session is still accepted.
+    ///
+    /// Sends one authenticated GET request to the dashboard with the cached cookies.
+    /// The session is considered alive if the server answers with a success status
+    /// and does not redirect to the login page. On success the cache timestamp is
+    /// refreshed, and the CSRF token is updated if the page exposes a new one.
+    ///
+    /// # Returns
+    /// - `Ok(true)` if the server accepted the session.
+    /// - `Ok(false)` if there is no logged-in session in the cache, or the server
+    ///   rejected it (HTTP 401/403/419 or a redirect to the login page).
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] if the request fails at the transport level, the server
+    /// returns any other error status, or the cache cannot be read or written.
+    pub fn verify_session(&self) -> Result<bool, ApiError> {
+        let _span = operation_span!("auth.verify_session").entered();
+        let cache = match self.cache_manager.load()? {
+            Some(cache) if cache.logged_in => cache,
+            _ => return Ok(false),
+        };
+
+        let cookie_map: HashMap<String, String> = cache
+            .cookies
+            .iter()
+            .map(|c| (c.name.clone(), c.value.expose().clone()))
+            .collect();
+        let mut headers = HeaderMap::new();
+        add_cookies_to_headers(&mut headers, &cookie_map)?;
+
+        let url = self.config.full_dashboard_url()?;
+        let ctx = ErrorContext::new("auth.verify_session", "GET", &url);
+        let request = self.client.get(&url).headers(headers);
+        let response = self.middleware.send(request, &ctx)?;
+
+        let status = response.status();
+        if matches!(status.as_u16(), 401 | 403 | 419) {
+            tracing::info!(status = status.as_u16(), "session rejected by server");
+            return Ok(false);
+        }
+        let response = ctx.check(response)?;
+        let landed = response.url().as_str().trim_end_matches('/').to_string();
+        if landed == self.config.full_login_url()?.trim_end_matches('/') {
+            tracing::info!("session expired: redirected to the login page");
+            return Ok(false);
+        }
+
+        let html = response.text().map_err(|e| ctx.transport(e))?;
+        let refreshed = match extract_csrf_token(&html) {
+            Ok(token) => {
+                self.middleware.emit(ClientEvent::CsrfRefresh);
+                cache.with_csrf_token(token)
+            }
+            Err(_) => cache.touch(),
+        };
+        self.cache_manager.save(&refreshed)?;
+        tracing::debug!("session verified by server");
+        Ok(true)
     }
 
     /// Returns the currently cached session, if any.
@@ -279,66 +364,24 @@ impl LoginClient {
         &self.cache_manager
     }
 
-    /// Validates that the credentials in the configuration are not empty.
+    /// Validates that the email in the configuration is not empty.
     ///
+    /// The password is checked by its [`CredentialProvider`] when requested.
     /// Logs a warning if the email does not contain '@' (but does not fail).
     ///
     /// # Errors
-    /// Returns [`ApiError::LoginFailed`] if `USER_EMAIL` or `USER_PASSWORD` is empty.
+    /// Returns [`ApiError::LoginFailed`] if `USER_EMAIL` is empty.
     fn validate_credentials(&self) -> Result<(), ApiError> {
         if self.config.user_email.is_empty() {
-            log::error!(" USER_EMAIL is empty");
+            tracing::error!("USER_EMAIL is empty");
             return Err(ApiError::LoginFailed("USER_EMAIL cannot be empty".into()));
         }
-        if self.config.user_password.is_empty() {
-            log::error!(" USER_PASSWORD is empty");
-            return Err(ApiError::LoginFailed(
-                "USER_PASSWORD cannot be empty".into(),
-            ));
-        }
         if !self.config.user_email.contains('@') {
-            log::warn!("️ USER_EMAIL does not contain '@', mungkin bukan format email");
+            tracing::warn!("USER_EMAIL does not contain '@'; it may not be an email address");
         }
         Ok(())
     }
 
-    /// Validates the login response status and body.
-    ///
-    /// If the status is successful (2xx), returns `Ok(())`.
-    /// Otherwise, maps the status code to an appropriate [`ApiError`] variant.
-    ///
-    /// # Arguments
-    /// * `status` - HTTP status code.
-    /// * `body` - Response body (used for preview in error messages).
-    fn validate_login_response(&self, status: StatusCode, body: &str) -> Result<(), ApiError> {
-        if status.is_success() {
-            return Ok(());
-        }
-
-        let clean_body = body.split('<').next().unwrap_or("Unknown error").trim();
-        log::error!(" Login failed: HTTP {} - {}", status, clean_body);
-
-        match status.as_u16() {
-            419 => Err(ApiError::LoginFailed(
-                "CSRF token expired or invalid".into(),
-            )),
-            422 => Err(ApiError::LoginFailed(
-                "Validation error: email/password incorrect".into(),
-            )),
-            429 => Err(ApiError::LoginFailed(
-                "Too many requests, please try later".into(),
-            )),
-            500..=599 => Err(ApiError::LoginFailed(format!(
-                "Server error (HTTP {})",
-                status
-            ))),
-            _ => Err(ApiError::LoginFailed(format!(
-                "HTTP {}: {}",
-                status, clean_body
-            ))),
-        }
-    }
-
     /// Fetches a CSRF token with retry logic.
     ///
     /// Retries up to [`MAX_RETRIES`] times with exponential backoff.
@@ -355,7 +398,7 @@ impl LoginClient {
             match self.fetch_csrf_token() {
                 Ok(token) => return Ok(token),
                 Err(e) => {
-                    log::warn!("️ CSRF fetch attempt {} failed: {}", attempt + 1, e);
+                    tracing::warn!(attempt = attempt + 1, error = %e, "CSRF token fetch failed");
                     last_error = Some(e);
                     if attempt < MAX_RETRIES - 1 {
                         let delay = INITIAL_RETRY_DELAY * 2_u32.pow(attempt);
@@ -380,52 +423,43 @@ impl LoginClient {
     /// * `form` - Form data (including `_token`, `email`, `password`).
     ///
     /// # Returns
-    /// The response on success (any 2xx or 3xx status, or any status <500 after retry?).
-    /// The implementation returns the response if status is success or <500, meaning
-    /// it will also return on 3xx redirects, which is acceptable.
+    /// The first response with a status below 500, together with the
+    /// [`ErrorContext`] (including the retry count) used to check it.
     ///
     /// # Errors
-    /// Returns the last error encountered, or a generic error if all retries fail.
+    /// Returns the error of the last attempt, with its context attached.
     fn execute_login_request(
         &self,
         headers: &HeaderMap,
         form: &HashMap<&str, &str>,
-    ) -> Result<reqwest::blocking::Response, ApiError> {
-        let mut last_error = None;
-        for attempt in 0..MAX_RETRIES {
-            match self
-                .client
-                .post(self.config.full_login_url())
-                .headers(headers.clone())
-                .form(form)
-                .send()
-            {
+    ) -> Result<(reqwest::blocking::Response, ErrorContext), ApiError> {
+        let url = self.config.full_login_url()?;
+        let mut attempt = 0;
+        loop {
+            let ctx = ErrorContext::new("auth.login", "POST", &url).with_retries(attempt);
+            let request = self.client.post(&url).headers(headers.clone()).form(form);
+            let error = match self.middleware.send(request, &ctx) {
+                Ok(response) if response.status().as_u16() < 500 => return Ok((response, ctx)),
                 Ok(response) => {
-                    if response.status().is_success() || response.status().as_u16() < 500 {
-                        return Ok(response);
-                    }
-                    log::warn!(
-                        "️ Server error (HTTP {}), attempt {} will retry",
-                        response.status(),
-                        attempt + 1
+                    let status = response.status();
+                    tracing::warn!(
+                        status = status.as_u16(),
+                        attempt = attempt + 1,
+                        "server error on login request"
                     );
-                    last_error = Some(ApiError::RequestFailed(format!(
-                        "HTTP {}",
-                        response.status()
-                    )));
+                    ctx.status_error(status, &response.text().unwrap_or_default())
                 }
                 Err(e) => {
-                    log::warn!("️ Network error on attempt {}: {}", attempt + 1, e);
-                    last_error = Some(ApiError::from(e));
+                    tracing::warn!(attempt = attempt + 1, error = %e, "login request failed");
+                    e
                 }
+            };
+            attempt += 1;
+            if attempt >= MAX_RETRIES {
+                return Err(error);
             }
-            if attempt < MAX_RETRIES - 1 {
-                let delay = INITIAL_RETRY_DELAY * 2_u32.pow(attempt);
-                std::thread::sleep(delay);
-            }
+            std::thread::sleep(INITIAL_RETRY_DELAY * 2_u32.pow(attempt - 1));
         }
-        Err(last_error
-            .unwrap_or_else(|| ApiError::LoginFailed("Login request failed after retries".into())))
     }
 
     /// Builds the base headers for the login request.
@@ -450,58 +484,91 @@ impl LoginClient {
         Ok(headers)
     }
 
-    /// Attaches any cached cookies to the provided headers.
-    ///
-    /// If a cached session exists, its cookies are loaded and added to the headers
-    /// using [`add_cookies_to_headers`]. This is useful for maintaining session
-    /// across requests (though for login we usually don't need previous cookies,
-    /// but it's harmless).
-    fn attach_cached_cookies(&self, headers: &mut HeaderMap) -> Result<(), ApiError> {
-        if let Some(cache) = self.cache_manager.load()? {
-            let cookie_map: HashMap<String, String> = cache
-                .cookies
-                .iter()
-                .map(|c| (c.name.clone(), c.value.clone()))
-                .collect();
-            if !cookie_map.is_empty() {
-                add_cookies_to_headers(headers, &cookie_map)?;
-                log::debug!(" Loaded {} cached cookies", cookie_map.len());
-            }
-        }
-        Ok(())
-    }
-
     /// Builds a `CacheData` object from the received cookies and CSRF token.
     ///
     /// Converts the cookie map into a vector of [`Cookie`] structs, using the base URL
     /// as the domain and default path `/`. Also sets `http_only` to `true` and `secure`
     /// to `false` (these may be inaccurate but are not critical for reuse).
     ///
-    /// The timestamp is set to the current time.
+    /// The timestamp is set to the current time and the schema version to
+    /// [`CACHE_SCHEMA_VERSION`](crate::api::auth::utils::cache::CACHE_SCHEMA_VERSION).
     fn build_cache_data(
         &self,
         cookies: HashMap<String, String>,
         csrf_token: String,
     ) -> Result<CacheData, ApiError> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs() as i64;
-        Ok(CacheData {
-            cookies: cookies
-                .into_iter()
-                .map(|(name, value)| Cookie {
-                    name,
-                    value,
-                    domain: self.config.base_url.clone(),
-                    path: "/".to_string(),
-                    http_only: true,
-                    secure: false,
-                })
-                .collect(),
-            csrf_token,
-            logged_in: true,
-            timestamp: now,
-        })
+        let cookies = cookies
+            .into_iter()
+            .map(|(name, value)| Cookie {
+                name,
+                value: value.into(),
+                domain: self.config.base_url.clone(),
+                path: "/".to_string(),
+                http_only: true,
+                secure: false,
+                extra: Default::default(),
+            })
+            .collect();
+        Ok(CacheData::new(cookies, csrf_token, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::redact::Secret;
+    use mockito::Matcher;
+
+    /// Tests that re-login sends the session cookie of the login page, not the
+    /// cookies of an expired cached session, which made the panel answer 419.
+    #[test]
+    fn test_relogin_ignores_cached_cookies() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("GET", "/login")
+            .with_header("set-cookie", "laravel_session=fresh; path=/")
+            .with_body(r#"<input type="hidden" name="_token" value="tok">"#)
+            .create();
+        let login = server
+            .mock("POST", "/login")
+            .match_header("cookie", Matcher::Regex("laravel_session=fresh".into()))
+            .with_status(302)
+            .with_header("location", "/dashboard")
+            .create();
+        server
+            .mock("GET", "/dashboard")
+            .with_header("set-cookie", "laravel_session=new; path=/")
+            .create();
+
+        let vars = [
+            ("BASE_URL", server.url()),
+            ("USER_EMAIL", "a@example.com".to_string()),
+            ("USER_PASSWORD", "secret123".to_string()),
+            ("LOGIN_ENDPOINT", "login".to_string()),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        let config = EnvConfig::from_vars(&vars).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let cache =
+            CacheManager::with_paths(dir.path().to_path_buf(), dir.path().join("session.json"));
+        let expired = Cookie {
+            name: "laravel_session".to_string(),
+            value: Secret::new("stale".to_string()),
+            domain: server.url(),
+            path: "/".to_string(),
+            http_only: true,
+            secure: false,
+            extra: Default::default(),
+        };
+        cache
+            .save(&CacheData::new(vec![expired], "old".to_string(), true))
+            .unwrap();
+
+        let mut client = LoginClient::with_config_and_cache(config, cache).unwrap();
+        let session = client.login().unwrap();
+        login.assert();
+        assert_eq!(session.cookies[0].value.expose(), "new");
     }
 }
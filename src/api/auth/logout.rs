@@ -13,8 +13,9 @@ use crate::api::auth::utils::{
     cookies::add_cookies_to_headers,
 };
 use crate::handler::env::EnvConfig;
-use crate::handler::error::ApiError;
-use reqwest::StatusCode;
+use crate::handler::error::{ApiError, ErrorContext};
+use crate::handler::telemetry::operation_span;
+use crate::middleware::{ClientEvent, MiddlewareStack};
 use reqwest::blocking::Client;
 use reqwest::header::{CONTENT_TYPE, HeaderMap, USER_AGENT};
 use std::collections::HashMap;
@@ -43,11 +44,11 @@ const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
 ///
 /// # Example
 /// ```no_run
-/// use cekunit_client::api::auth::LogoutClient;
+/// use librcekunit::api::auth::LogoutClient;
 ///
 /// let mut client = LogoutClient::new()?;
 /// client.logout()?; // uses cached token
-/// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+/// # Ok::<(), librcekunit::handler::error::ApiError>(())
 /// ```
 pub struct LogoutClient {
     /// The underlying reqwest blocking client.
@@ -56,6 +57,8 @@ pub struct LogoutClient {
     pub config: EnvConfig,
     /// Manager for reading/writing the session cache.
     pub cache_manager: CacheManager,
+    /// Hooks run around every request.
+    middleware: MiddlewareStack,
 }
 
 impl LogoutClient {
@@ -87,15 +90,41 @@ impl LogoutClient {
     /// - The cache directory cannot be created.
     /// - The HTTP client cannot be built.
     pub fn with_config(config: EnvConfig) -> Result<Self, ApiError> {
-        let cache_manager = CacheManager::new()?;
+        Self::with_config_and_cache(config, CacheManager::new()?)
+    }
+
+    /// Creates a new `LogoutClient` with a given configuration and an existing cache manager.
+    ///
+    /// This is useful when sharing the same cache (and thus the same session) across
+    /// multiple clients, or to keep the session out of the user's cache directory.
+    ///
+    /// # Arguments
+    /// * `config` - The environment configuration.
+    /// * `cache_manager` - An existing cache manager (typically from the main client).
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] if the HTTP client cannot be built.
+    pub fn with_config_and_cache(
+        config: EnvConfig,
+        cache_manager: CacheManager,
+    ) -> Result<Self, ApiError> {
         let client = Self::build_client()?;
         Ok(Self {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
+    /// Sets the middlewares every request of this client is sent through.
+    ///
+    /// [`CekUnitClient::add_middleware`](crate::CekUnitClient::add_middleware) does this
+    /// for the clients it manages.
+    pub fn set_middleware(&mut self, middleware: MiddlewareStack) {
+        self.middleware = middleware;
+    }
+
     /// Builds and configures the HTTP client.
     ///
     /// The client is configured identically to the login client to ensure
@@ -121,7 +150,7 @@ impl LogoutClient {
             .pool_max_idle_per_host(10)
             .build()
             .map_err(|e| {
-                log::error!(" Failed to build HTTP client: {}", e);
+                tracing::error!(error = %e, "failed to build HTTP client");
                 ApiError::from(e)
             })
     }
@@ -140,11 +169,12 @@ impl LogoutClient {
     /// - The logout request fails after retries.
     /// - The server returns a client error (4xx) that is not retried.
     pub fn logout(&mut self) -> Result<(), ApiError> {
-        log::info!(" Starting logout process (using cached token)");
+        let _span = operation_span!("auth.logout").entered();
+        tracing::info!("starting logout with cached token");
         let cache_data = self.load_valid_session()?;
         let headers = self.build_headers(&cache_data)?;
         let mut form = HashMap::new();
-        form.insert("_token", cache_data.csrf_token.as_str());
+        form.insert("_token", cache_data.csrf_token.expose_str());
         self.execute_logout_request(headers, form)
     }
 
@@ -159,7 +189,8 @@ impl LogoutClient {
     /// # Errors
     /// Same as [`logout`](Self::logout).
     pub fn logout_with_token(&mut self, csrf_token: &str) -> Result<(), ApiError> {
-        log::info!(" Starting logout process (using provided token)");
+        let _span = operation_span!("auth.logout").entered();
+        tracing::info!("starting logout with provided token");
         let cache_data = self.load_valid_session()?;
         let headers = self.build_headers(&cache_data)?;
         let mut form = HashMap::new();
@@ -175,7 +206,8 @@ impl LogoutClient {
     /// # Errors
     /// Returns [`ApiError`] if the cache file cannot be removed.
     pub fn clear_cache(&mut self) -> Result<(), ApiError> {
-        log::info!(" Clearing cache manually");
+        let _span = operation_span!("auth.clear_cache").entered();
+        tracing::info!("clearing session cache");
         self.cache_manager.clear()
     }
 
@@ -211,16 +243,16 @@ impl LogoutClient {
     fn load_valid_session(&self) -> Result<CacheData, ApiError> {
         match self.cache_manager.load()? {
             Some(data) if data.logged_in => {
-                log::debug!(" Valid session loaded ({} cookies)", data.cookies.len());
+                tracing::debug!(cookies = data.cookies.len(), "session loaded");
                 Ok(data)
             }
             Some(_) => {
-                log::warn!("️ Session exists but not logged in – clearing cache");
+                tracing::warn!("cached session is not logged in; clearing cache");
                 self.cache_manager.clear()?;
                 Err(ApiError::NotAuthenticated)
             }
             None => {
-                log::warn!("️ No active session found");
+                tracing::warn!("no active session found");
                 Err(ApiError::NotAuthenticated)
             }
         }
@@ -253,12 +285,12 @@ impl LogoutClient {
         let cookie_map: HashMap<String, String> = cache_data
             .cookies
             .iter()
-            .map(|c| (c.name.clone(), c.value.clone()))
+            .map(|c| (c.name.clone(), c.value.expose().clone()))
             .collect();
 
         if !cookie_map.is_empty() {
             add_cookies_to_headers(&mut headers, &cookie_map)?;
-            log::debug!(" Attached {} cookies to logout request", cookie_map.len());
+            tracing::debug!(cookies = cookie_map.len(), "attached cached cookies");
         }
 
         Ok(headers)
@@ -277,94 +309,58 @@ impl LogoutClient {
     /// * `form` - Form data containing the `_token`.
     ///
     /// # Errors
-    /// Returns the last error encountered, or a mapped error from the response status.
+    /// Returns the error of the last attempt, with an [`ErrorContext`] attached.
     fn execute_logout_request(
         &mut self,
         headers: HeaderMap,
         form: HashMap<&str, &str>,
     ) -> Result<(), ApiError> {
-        let url = self.config.full_logout_url();
-        log::info!(" Sending logout request to: {}", url);
+        let url = self.config.full_logout_url()?;
+        tracing::debug!("sending logout request");
 
-        let mut last_error = None;
-        for attempt in 0..MAX_RETRIES {
-            match self
-                .client
-                .post(&url)
-                .headers(headers.clone())
-                .form(&form)
-                .send()
-            {
+        let mut attempt = 0;
+        loop {
+            let ctx = ErrorContext::new("auth.logout", "POST", &url).with_retries(attempt);
+            let request = self.client.post(&url).headers(headers.clone()).form(&form);
+            let error = match self.middleware.send(request, &ctx) {
+                Ok(response) if response.status().as_u16() < 500 => {
+                    ctx.check_allow_redirect(response).inspect_err(|e| {
+                        tracing::error!(error = %e, "logout rejected");
+                        self.middleware.emit(ClientEvent::Logout { success: false });
+                    })?;
+                    if let Err(e) = self.cache_manager.clear() {
+                        tracing::error!(error = %e, "failed to clear cache after logout");
+                    } else {
+                        tracing::debug!("session cache cleared");
+                    }
+                    tracing::info!("logout successful");
+                    self.middleware.emit(ClientEvent::Logout { success: true });
+                    return Ok(());
+                }
                 Ok(response) => {
                     let status = response.status();
-                    if status.is_success() || status.as_u16() == 302 || status.as_u16() == 303 {
-                        log::debug!(" Logout response status: {}", status);
-                        if let Err(e) = self.cache_manager.clear() {
-                            log::error!(" Failed to clear cache after logout: {}", e);
-                        } else {
-                            log::info!(" Cache cleared successfully");
-                        }
-                        log::info!(" Logout successful!");
-                        return Ok(());
-                    }
-
-                    let body = response.text().unwrap_or_default();
-                    let clean_body = body.split('<').next().unwrap_or("Unknown error").trim();
-
-                    if status.as_u16() < 500 {
-                        log::error!(
-                            " Logout failed (client error): HTTP {} - {}",
-                            status,
-                            clean_body
-                        );
-                        return Err(self.map_logout_error(status, clean_body));
-                    }
-
-                    log::warn!(
-                        "️ Logout server error (HTTP {}), attempt {} will retry",
-                        status,
-                        attempt + 1
+                    tracing::warn!(
+                        status = status.as_u16(),
+                        attempt = attempt + 1,
+                        "server error on logout request"
                     );
-                    last_error = Some(ApiError::LogoutFailed(format!(
-                        "HTTP {} - {}",
-                        status, clean_body
-                    )));
+                    ctx.status_error(status, &response.text().unwrap_or_default())
                 }
                 Err(e) => {
-                    log::warn!("️ Logout network error on attempt {}: {}", attempt + 1, e);
-                    last_error = Some(ApiError::from(e));
+                    tracing::warn!(attempt = attempt + 1, error = %e, "logout request failed");
+                    e
                 }
-            }
+            };
 
-            if attempt < MAX_RETRIES - 1 {
-                let delay = INITIAL_RETRY_DELAY * 2_u32.pow(attempt);
-                log::debug!(" Waiting {:?} before retry...", delay);
-                std::thread::sleep(delay);
+            attempt += 1;
+            if attempt >= MAX_RETRIES {
+                tracing::error!(error = %error, "logout failed after retries");
+                self.middleware.emit(ClientEvent::Logout { success: false });
+                return Err(error);
             }
-        }
-
-        let err = last_error.unwrap_or_else(|| {
-            ApiError::LogoutFailed("Logout request failed after maximum retries".into())
-        });
-        log::error!(" All logout retry attempts failed: {}", err);
-        Err(err)
-    }
-
-    /// Maps an HTTP status code to a specific [`ApiError::LogoutFailed`] variant.
-    ///
-    /// Provides human‑readable messages for common status codes:
-    /// - 419 → CSRF token expired
-    /// - 422 → Validation error (missing token)
-    /// - 429 → Too many requests
-    /// - 5xx → Server error
-    /// - Others → Generic message with status and body preview
-    fn map_logout_error(&self, status: StatusCode, body: &str) -> ApiError {
-        match status.as_u16() {
-            419 => ApiError::LogoutFailed("CSRF token expired or invalid".into()),
-            422 => ApiError::LogoutFailed("Validation error (maybe missing _token)".into()),
-            429 => ApiError::LogoutFailed("Too many requests, please try later".into()),
-            500..=599 => ApiError::LogoutFailed(format!("Server error (HTTP {})", status)),
-            _ => ApiError::LogoutFailed(format!("HTTP {}: {}", status, body)),
+            let delay = INITIAL_RETRY_DELAY * 2_u32.pow(attempt - 1);
+            tracing::debug!(delay_ms = delay.as_millis() as u64, "waiting before retry");
+            std::thread::sleep(delay);
         }
     }
 }
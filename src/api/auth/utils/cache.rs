@@ -4,14 +4,30 @@
 //! using the system's cache directory. It allows the application to maintain
 //! login state across runs and provides utilities for loading, saving, and
 //! validating cached sessions.
+//!
+//! The on-disk format is versioned via [`CACHE_SCHEMA_VERSION`]. Files written by
+//! older releases are migrated in memory when loaded, and fields written by newer
+//! releases are preserved verbatim so that a downgrade does not destroy them.
 
 use crate::handler::error::ApiError;
+use crate::handler::redact::Secret;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Current version of the session cache file format.
+///
+/// Version history:
+/// - `0`: the original unversioned layout (no `version` field).
+/// - `1`: adds the `version` field; unknown fields are preserved.
+/// - `2`: adds `logged_in_at`; older files take it from `timestamp`.
+pub const CACHE_SCHEMA_VERSION: u32 = 2;
+
 /// Represents a cached session, including cookies and CSRF token.
 ///
 /// This structure is serialized to JSON and stored in the cache directory.
@@ -19,23 +35,58 @@ use std::time::{SystemTime, UNIX_EPOCH};
 /// re‑logging in.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CacheData {
+    /// Schema version of the cache file (see [`CACHE_SCHEMA_VERSION`]).
+    #[serde(default)]
+    pub version: u32,
     /// List of cookies associated with the session.
     pub cookies: Vec<Cookie>,
     /// The CSRF token extracted from the login page or a subsequent page.
-    pub csrf_token: String,
+    pub csrf_token: Secret<String>,
     /// Flag indicating whether the session is considered logged in.
     ///
     /// This is set to `true` after a successful login.
     pub logged_in: bool,
     /// Unix timestamp (seconds) when this cache entry was last updated.
     pub timestamp: i64,
+    /// Unix timestamp (seconds) of the login that started this session.
+    ///
+    /// Unlike `timestamp`, it is not moved by CSRF token refreshes or session checks,
+    /// so [`is_fresh`](Self::is_fresh) measures the age of the session itself.
+    #[serde(default)]
+    pub logged_in_at: i64,
+    /// Fields not known to this version of the library.
+    ///
+    /// They are kept as-is and written back on save, so a cache file produced by
+    /// a newer release survives being touched by an older one.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
 impl CacheData {
+    /// Creates a new `CacheData` at the current schema version, logged in and
+    /// timestamped now.
+    ///
+    /// # Arguments
+    /// * `cookies` - Cookies associated with the session.
+    /// * `csrf_token` - The current CSRF token.
+    /// * `logged_in` - Whether the session is authenticated.
+    pub fn new(cookies: Vec<Cookie>, csrf_token: String, logged_in: bool) -> Self {
+        Self {
+            version: CACHE_SCHEMA_VERSION,
+            cookies,
+            csrf_token: csrf_token.into(),
+            logged_in,
+            timestamp: now(),
+            logged_in_at: now(),
+            extra: BTreeMap::new(),
+        }
+    }
+
     /// Creates a new `CacheData` instance with an updated CSRF token.
     ///
     /// This method updates the CSRF token and resets the timestamp to the current time.
     /// It is typically used when a fresh token is needed, e.g., before a POST request.
+    /// The login time, [`logged_in_at`](Self::logged_in_at), is left unchanged.
     ///
     /// # Arguments
     /// * `new_token` - The new CSRF token to store.
@@ -43,7 +94,16 @@ impl CacheData {
     /// # Returns
     /// A new `CacheData` instance with the updated token and current timestamp.
     pub fn with_csrf_token(mut self, new_token: String) -> Self {
-        self.csrf_token = new_token;
+        self.csrf_token = new_token.into();
+        self.timestamp = now();
+        self
+    }
+
+    /// Returns this `CacheData` with its timestamp reset to the current time.
+    ///
+    /// Used after the server has confirmed that the session is still valid. The login
+    /// time, and with it [`is_fresh`](Self::is_fresh), is left unchanged.
+    pub fn touch(mut self) -> Self {
         self.timestamp = now();
         self
     }
@@ -51,12 +111,12 @@ impl CacheData {
     /// Checks whether the cached session is still fresh (not expired).
     ///
     /// # Arguments
-    /// * `max_age_seconds` - Maximum allowed age of the cache in seconds.
+    /// * `max_age_seconds` - Maximum allowed age of the session in seconds.
     ///
     /// # Returns
-    /// `true` if the cache was created or updated less than `max_age_seconds` ago.
+    /// `true` if the session was logged in less than `max_age_seconds` ago.
     pub fn is_fresh(&self, max_age_seconds: i64) -> bool {
-        now() - self.timestamp < max_age_seconds
+        now() - self.logged_in_at < max_age_seconds
     }
 }
 
@@ -69,15 +129,29 @@ pub struct Cookie {
     /// Name of the cookie.
     pub name: String,
     /// Value of the cookie.
-    pub value: String,
+    pub value: Secret<String>,
     /// Domain for which the cookie is valid.
+    #[serde(default)]
     pub domain: String,
     /// Path within the domain for which the cookie is valid.
+    #[serde(default = "default_cookie_path")]
     pub path: String,
     /// Whether the cookie is marked as `HttpOnly` (not accessible to JavaScript).
+    #[serde(default)]
     pub http_only: bool,
     /// Whether the cookie is marked as `Secure` (only sent over HTTPS).
+    #[serde(default)]
     pub secure: bool,
+    /// Cookie attributes not known to this version of the library, such as `expires`.
+    ///
+    /// Kept as-is and written back on save, like [`CacheData::extra`].
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// Default cookie path used when a cached cookie has none.
+fn default_cookie_path() -> String {
+    "/".to_string()
 }
 
 /// Manages reading and writing the session cache to the filesystem.
@@ -135,7 +209,8 @@ impl CacheManager {
 
     /// Saves the given cache data to the cache file.
     ///
-    /// The data is serialized to JSON with pretty formatting.
+    /// The data is serialized to JSON with pretty formatting. On Unix, a new file is
+    /// created readable and writable by its owner only, since it holds session cookies.
     ///
     /// # Arguments
     /// * `data` - The cache data to save.
@@ -144,25 +219,39 @@ impl CacheManager {
     /// Returns [`ApiError`] if serialization or file writing fails.
     pub fn save(&self, data: &CacheData) -> Result<(), ApiError> {
         let json = serde_json::to_string_pretty(data)?;
-        fs::write(&self.cache_file, json)
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(&self.cache_file)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
             .map_err(|e| ApiError::CacheError(format!("Failed to write cache: {}", e)))
     }
 
     /// Loads the cache data from the cache file.
     ///
-    /// If the file does not exist, returns `Ok(None)`. If the file exists but cannot
-    /// be read or parsed, an error is returned.
+    /// If the file does not exist, returns `Ok(None)`. Files written with an older
+    /// schema version are migrated to [`CACHE_SCHEMA_VERSION`] (see [`migrate_cache`]);
+    /// files written by a newer release are loaded on a best-effort basis, keeping any
+    /// unknown fields in [`CacheData::extra`].
     ///
     /// # Errors
-    /// Returns [`ApiError`] if reading the file or parsing JSON fails.
+    /// Returns [`ApiError::CacheError`] if the file cannot be read, is not valid JSON,
+    /// or does not describe a session in any known layout.
     pub fn load(&self) -> Result<Option<CacheData>, ApiError> {
         if !self.cache_file.exists() {
             return Ok(None);
         }
         let content = fs::read_to_string(&self.cache_file)
             .map_err(|e| ApiError::CacheError(format!("Failed to read cache: {}", e)))?;
-        let data: CacheData = serde_json::from_str(&content)?;
-        Ok(Some(data))
+        let raw: Value = serde_json::from_str(&content).map_err(|e| {
+            ApiError::CacheError(format!(
+                "Cache file {:?} is not valid JSON: {}",
+                self.cache_file, e
+            ))
+        })?;
+        migrate_cache(raw).map(Some)
     }
 
     /// Deletes the cache file if it exists.
@@ -180,7 +269,8 @@ impl CacheManager {
     /// Updates the CSRF token in the cache.
     ///
     /// If a cache entry exists, it is loaded, its CSRF token is replaced,
-    /// and the timestamp is updated. If no cache exists, nothing is done.
+    /// and the timestamp is updated; the login time is kept. If no cache exists,
+    /// nothing is done.
     ///
     /// # Arguments
     /// * `new_token` - The new CSRF token.
@@ -198,7 +288,8 @@ impl CacheManager {
     /// Loads the cache only if it is fresh (not expired).
     ///
     /// # Arguments
-    /// * `max_age_seconds` - Maximum allowed age of the cache in seconds.
+    /// * `max_age_seconds` - Maximum allowed age of the session in seconds, counted
+    ///   from its login (see [`CacheData::is_fresh`]).
     ///
     /// # Returns
     /// - `Ok(Some(data))` if the cache exists and is fresh.
@@ -240,6 +331,76 @@ impl Default for CacheManager {
     }
 }
 
+/// Upgrades a raw cache document to the current schema and deserializes it.
+///
+/// The document's `version` field selects the starting point (a missing field means
+/// the unversioned layout, version `0`). Each step upgrades one version; documents
+/// newer than [`CACHE_SCHEMA_VERSION`] are passed through untouched.
+///
+/// # Errors
+/// Returns [`ApiError::CacheError`] if the document is not a JSON object, has a
+/// malformed `version`, or lacks fields required by the current layout.
+pub fn migrate_cache(raw: Value) -> Result<CacheData, ApiError> {
+    let Value::Object(mut doc) = raw else {
+        return Err(ApiError::CacheError(
+            "Cache file does not contain a JSON object".into(),
+        ));
+    };
+    let mut version = match doc.get("version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| ApiError::CacheError(format!("Invalid cache version: {}", v)))?,
+    };
+
+    if version > CACHE_SCHEMA_VERSION {
+        tracing::warn!(
+            version,
+            supported = CACHE_SCHEMA_VERSION,
+            "session cache schema is newer than supported; loading best-effort"
+        );
+    }
+    while version < CACHE_SCHEMA_VERSION {
+        doc = migrate_step(doc, version)?;
+        version += 1;
+        doc.insert("version".into(), Value::from(version));
+        tracing::debug!(version, "migrated session cache schema");
+    }
+
+    serde_json::from_value(Value::Object(doc)).map_err(|e| {
+        ApiError::CacheError(format!(
+            "Cache file (schema version {}) has an unexpected layout: {}",
+            version, e
+        ))
+    })
+}
+
+/// Upgrades a cache document from schema version `from` to `from + 1`.
+fn migrate_step(mut doc: Map<String, Value>, from: u32) -> Result<Map<String, Value>, ApiError> {
+    match from {
+        // v0 -> v1: only the version marker is new; backfill fields some early
+        // builds did not write.
+        0 => {
+            doc.entry("logged_in").or_insert(Value::Bool(false));
+            doc.entry("timestamp").or_insert(Value::from(0));
+            doc.entry("cookies").or_insert(Value::Array(Vec::new()));
+            Ok(doc)
+        }
+        // v1 -> v2: the login time was not recorded; the last update is the best
+        // (latest) guess.
+        1 => {
+            let timestamp = doc.get("timestamp").cloned().unwrap_or(Value::from(0));
+            doc.entry("logged_in_at").or_insert(timestamp);
+            Ok(doc)
+        }
+        _ => Err(ApiError::CacheError(format!(
+            "No migration path from cache schema version {}",
+            from
+        ))),
+    }
+}
+
 /// Returns the current Unix timestamp in seconds.
 fn now() -> i64 {
     SystemTime::now()
@@ -247,3 +408,110 @@ fn now() -> i64 {
         .unwrap_or_default()
         .as_secs() as i64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    /// Creates a cache manager rooted in a fresh temporary directory.
+    fn temp_manager() -> (TempDir, CacheManager) {
+        let dir = TempDir::new().unwrap();
+        let manager =
+            CacheManager::with_paths(dir.path().to_path_buf(), dir.path().join("session.json"));
+        (dir, manager)
+    }
+
+    /// Tests that the legacy unversioned layout is migrated to the current version.
+    #[test]
+    fn test_migrate_unversioned_layout() {
+        let legacy = json!({
+            "cookies": [{
+                "name": "laravel_session",
+                "value": "abc",
+                "domain": "https://example.com",
+                "path": "/",
+                "http_only": true,
+                "secure": false
+            }],
+            "csrf_token": "tok",
+            "logged_in": true,
+            "timestamp": 1_700_000_000
+        });
+        let data = migrate_cache(legacy).unwrap();
+        assert_eq!(data.version, CACHE_SCHEMA_VERSION);
+        assert_eq!(data.cookies.len(), 1);
+        assert_eq!(data.csrf_token.expose(), "tok");
+        assert!(data.logged_in);
+        assert_eq!(data.logged_in_at, 1_700_000_000);
+        assert!(data.extra.is_empty());
+    }
+
+    /// Tests that unknown fields from a newer release are kept and written back.
+    #[test]
+    fn test_unknown_fields_round_trip() {
+        let (_dir, manager) = temp_manager();
+        let newer = json!({
+            "version": CACHE_SCHEMA_VERSION + 1,
+            "cookies": [{ "name": "s", "value": "v", "expires": 123 }],
+            "csrf_token": "tok",
+            "logged_in": true,
+            "timestamp": 1,
+            "profile": "staging"
+        });
+        fs::write(manager.cache_file_path(), newer.to_string()).unwrap();
+
+        let data = manager.load().unwrap().unwrap();
+        assert_eq!(data.version, CACHE_SCHEMA_VERSION + 1);
+        assert_eq!(data.extra.get("profile"), Some(&json!("staging")));
+        assert_eq!(data.cookies[0].path, "/");
+        assert_eq!(data.cookies[0].extra.get("expires"), Some(&json!(123)));
+
+        manager.save(&data).unwrap();
+        let saved: Value =
+            serde_json::from_str(&fs::read_to_string(manager.cache_file_path()).unwrap()).unwrap();
+        assert_eq!(saved["profile"], json!("staging"));
+        assert_eq!(saved["cookies"][0]["expires"], json!(123));
+        assert_eq!(saved["version"], json!(CACHE_SCHEMA_VERSION + 1));
+    }
+
+    /// Tests that unparseable cache files yield a descriptive cache error.
+    #[test]
+    fn test_corrupt_cache_is_cache_error() {
+        let (_dir, manager) = temp_manager();
+        fs::write(manager.cache_file_path(), "not json").unwrap();
+        assert!(matches!(manager.load(), Err(ApiError::CacheError(_))));
+
+        fs::write(manager.cache_file_path(), r#"{"version": "one"}"#).unwrap();
+        assert!(matches!(manager.load(), Err(ApiError::CacheError(_))));
+    }
+
+    /// Tests that freshly built data is saved with the current version.
+    #[test]
+    fn test_new_data_is_current_version() {
+        let (_dir, manager) = temp_manager();
+        manager
+            .save(&CacheData::new(Vec::new(), "tok".into(), true))
+            .unwrap();
+        let data = manager.load().unwrap().unwrap();
+        assert_eq!(data.version, CACHE_SCHEMA_VERSION);
+        assert!(data.is_fresh(60));
+    }
+
+    /// Tests that the cache file is only accessible to its owner.
+    #[cfg(unix)]
+    #[test]
+    fn test_save_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let (_dir, manager) = temp_manager();
+        manager
+            .save(&CacheData::new(Vec::new(), "tok".into(), true))
+            .unwrap();
+        let mode = fs::metadata(manager.cache_file_path())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -26,7 +26,7 @@ use std::collections::HashMap;
 /// # Example
 /// ```
 /// use reqwest::header::{HeaderMap, HeaderValue, SET_COOKIE};
-/// use cekunit_client::api::auth::utils::cookies::extract_cookies;
+/// use librcekunit::api::auth::utils::cookies::extract_cookies;
 ///
 /// let mut headers = HeaderMap::new();
 /// headers.insert(SET_COOKIE, HeaderValue::from_static("session=abc123; Path=/"));
@@ -39,10 +39,10 @@ use std::collections::HashMap;
 pub fn extract_cookies(headers: &HeaderMap) -> HashMap<String, String> {
     let mut cookies = HashMap::new();
     for value in headers.get_all(SET_COOKIE) {
-        if let Ok(cookie_str) = value.to_str() {
-            if let Some((name, value)) = parse_set_cookie(cookie_str) {
-                cookies.insert(name, value);
-            }
+        if let Ok(cookie_str) = value.to_str()
+            && let Some((name, value)) = parse_set_cookie(cookie_str)
+        {
+            cookies.insert(name, value);
         }
     }
     cookies
@@ -62,7 +62,7 @@ pub fn extract_cookies(headers: &HeaderMap) -> HashMap<String, String> {
 /// # Example
 /// ```
 /// use std::collections::HashMap;
-/// use cekunit_client::api::auth::utils::cookies::build_cookie_header;
+/// use librcekunit::api::auth::utils::cookies::build_cookie_header;
 ///
 /// let mut cookies = HashMap::new();
 /// cookies.insert("session".to_string(), "xyz".to_string());
@@ -99,7 +99,7 @@ pub fn build_cookie_header(cookies: &HashMap<String, String>) -> String {
 /// ```
 /// use std::collections::HashMap;
 /// use reqwest::header::{HeaderMap, COOKIE};
-/// use cekunit_client::api::auth::utils::cookies::add_cookies_to_headers;
+/// use librcekunit::api::auth::utils::cookies::add_cookies_to_headers;
 ///
 /// let mut headers = HeaderMap::new();
 /// let mut cookies = HashMap::new();
@@ -135,9 +135,7 @@ pub fn add_cookies_to_headers(
 /// * `None` if the string is malformed (e.g., no `=`, empty name).
 ///
 /// # Examples
-/// ```
-/// use cekunit_client::api::auth::utils::cookies::parse_set_cookie;
-///
+/// ```ignore
 /// let (name, value) = parse_set_cookie("session=abc123; Path=/; HttpOnly").unwrap();
 /// assert_eq!(name, "session");
 /// assert_eq!(value, "abc123");
@@ -42,7 +42,7 @@ const CSRF_META_SELECTOR: Attr<&str, &str> = Attr("name", "csrf-token");
 ///
 /// # Examples
 /// ```
-/// use cekunit_client::api::auth::utils::token::extract_csrf_token;
+/// use librcekunit::api::auth::utils::token::extract_csrf_token;
 ///
 /// let html = r#"<input type="hidden" name="_token" value="abc123">"#;
 /// assert_eq!(extract_csrf_token(html).unwrap(), "abc123");
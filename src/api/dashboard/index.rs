@@ -13,9 +13,11 @@
 
 use crate::api::auth::utils::cache::{CacheData, CacheManager};
 use crate::handler::env::EnvConfig;
-use crate::handler::error::ApiError;
+use crate::handler::error::{ApiError, ErrorContext};
+use crate::handler::telemetry::operation_span;
+use crate::middleware::{ClientEvent, MiddlewareStack};
 use reqwest::blocking::Client;
-use reqwest::header::{CONTENT_TYPE, HeaderMap, USER_AGENT};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, HeaderMap, USER_AGENT};
 use std::collections::HashMap;
 
 /// Client for dashboard and CekUnit operations.
@@ -27,12 +29,12 @@ use std::collections::HashMap;
 ///
 /// # Example
 /// ```no_run
-/// use cekunit_client::api::dashboard::DashboardClient;
+/// use librcekunit::api::dashboard::DashboardClient;
 ///
 /// let client = DashboardClient::new()?;
 /// let html = client.get_dashboard(Some(1), None, Some("created_at"), Some("desc"))?;
 /// println!("Dashboard page 1: {}", html);
-/// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+/// # Ok::<(), librcekunit::handler::error::ApiError>(())
 /// ```
 pub struct DashboardClient {
     /// The underlying reqwest blocking client.
@@ -41,6 +43,8 @@ pub struct DashboardClient {
     config: EnvConfig,
     /// Cache manager for loading the session (cookies + CSRF token).
     cache_manager: CacheManager,
+    /// Hooks run around every request.
+    middleware: MiddlewareStack,
 }
 
 impl DashboardClient {
@@ -61,6 +65,7 @@ impl DashboardClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
@@ -83,6 +88,7 @@ impl DashboardClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
@@ -105,9 +111,18 @@ impl DashboardClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
+    /// Sets the middlewares every request of this client is sent through.
+    ///
+    /// [`CekUnitClient::add_middleware`](crate::CekUnitClient::add_middleware) does this
+    /// for the clients it manages.
+    pub fn set_middleware(&mut self, middleware: MiddlewareStack) {
+        self.middleware = middleware;
+    }
+
     /// Builds and configures the HTTP client.
     ///
     /// The client is configured with:
@@ -122,7 +137,7 @@ impl DashboardClient {
             .user_agent("Mozilla/5.0 (X11; Linux x86_64; rv:148.0) Gecko/20100101 Firefox/148.0")
             .cookie_store(true)
             .build()
-            .map_err(|e| ApiError::from(e))
+            .map_err(ApiError::from)
     }
 
     /// Ensures that a valid authenticated session exists in the cache.
@@ -161,7 +176,7 @@ impl DashboardClient {
         let cookie_map: HashMap<String, String> = cache
             .cookies
             .iter()
-            .map(|c| (c.name.clone(), c.value.clone()))
+            .map(|c| (c.name.clone(), c.value.expose().clone()))
             .collect();
 
         crate::api::auth::utils::cookies::add_cookies_to_headers(&mut headers, &cookie_map)?;
@@ -194,10 +209,11 @@ impl DashboardClient {
         sort: Option<&str>,
         direction: Option<&str>,
     ) -> Result<String, ApiError> {
+        let _span = operation_span!("dashboard.list", page, sort, direction).entered();
         let cache = self.ensure_authenticated()?;
         let headers = self.build_headers_with_cookies(&cache)?;
 
-        let mut url = self.config.full_dashboard_url();
+        let mut url = self.config.full_dashboard_url()?;
         let mut params = Vec::new();
 
         if let Some(p) = page {
@@ -214,27 +230,16 @@ impl DashboardClient {
         }
 
         if !params.is_empty() {
-            url.push_str("?");
+            url.push('?');
             url.push_str(&params.join("&"));
         }
 
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() {
-            Ok(response.text().map_err(|e| ApiError::from(e))?)
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("dashboard.list", "GET", &url);
+        let request = self.client.get(&url).headers(headers);
+        let response = self.middleware.send(request, &ctx)?;
+
+        let response = ctx.check(response)?;
+        response.text().map_err(|e| ctx.transport(e))
     }
 
     /// Exports CekUnit data in the specified format.
@@ -259,34 +264,26 @@ impl DashboardClient {
         sort: &str,
         direction: &str,
     ) -> Result<Vec<u8>, ApiError> {
+        let _span = operation_span!("dashboard.export", format, sort, direction).entered();
         let cache = self.ensure_authenticated()?;
-        let headers = self.build_headers_with_cookies(&cache)?;
+        let mut headers = self.build_headers_with_cookies(&cache)?;
+        // Any file type is accepted, but errors come back as JSON.
+        headers.insert(ACCEPT, "application/json, */*".parse().unwrap());
 
         let url = format!(
             "{}?format={}&sort={}&direction={}",
-            self.config.full_cekunit_export_url(),
+            self.config.full_cekunit_export_url()?,
             format,
             sort,
             direction
         );
 
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() {
-            Ok(response.bytes().map_err(|e| ApiError::from(e))?.to_vec())
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("dashboard.export", "GET", &url);
+        let request = self.client.get(&url).headers(headers);
+        let response = self.middleware.send(request, &ctx)?;
+
+        let response = ctx.check(response)?;
+        Ok(response.bytes().map_err(|e| ctx.transport(e))?.to_vec())
     }
 
     /// Fetches unique values for a given column from the CekUnit data.
@@ -306,33 +303,24 @@ impl DashboardClient {
     /// - The server returns a non‑success status.
     /// - The response is not valid JSON.
     pub fn get_unique_values(&self, column: &str) -> Result<Vec<String>, ApiError> {
+        let _span = operation_span!("dashboard.unique_values", column).entered();
         let cache = self.ensure_authenticated()?;
-        let headers = self.build_headers_with_cookies(&cache)?;
+        let mut headers = self.build_headers_with_cookies(&cache)?;
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
         let url = format!(
             "{}?column={}",
-            self.config.full_cekunit_unique_url(),
+            self.config.full_cekunit_unique_url()?,
             column
         );
 
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() {
-            let values: Vec<String> = response.json().map_err(|e| ApiError::from(e))?;
-            Ok(values)
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("dashboard.unique_values", "GET", &url);
+        let request = self.client.get(&url).headers(headers);
+        let response = self.middleware.send(request, &ctx)?;
+
+        let response = ctx.check(response)?;
+        let values: Vec<String> = response.json().map_err(|e| ctx.transport(e))?;
+        Ok(values)
     }
 
     /// Deletes all CekUnit records that match a given category (column = value).
@@ -350,37 +338,27 @@ impl DashboardClient {
     /// # Note
     /// This operation is irreversible. Use with caution.
     pub fn delete_by_category(&self, column: &str, value: &str) -> Result<(), ApiError> {
+        let _span = operation_span!("dashboard.delete_by_category", column).entered();
         let cache = self.ensure_authenticated()?;
         let mut headers = self.build_headers_with_cookies(&cache)?;
         headers.insert(
             CONTENT_TYPE,
             "application/x-www-form-urlencoded".parse().unwrap(),
         );
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
-        let url = self.config.full_cekunit_delete_category_url();
+        let url = self.config.full_cekunit_delete_category_url()?;
         let mut form = HashMap::new();
-        form.insert("_token", cache.csrf_token.as_str());
+        form.insert("_token", cache.csrf_token.expose_str());
         form.insert("column", column);
         form.insert("value", value);
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() {
-            Ok(())
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("dashboard.delete_by_category", "POST", &url);
+        let request = self.client.post(&url).headers(headers).form(&form);
+        let response = self.middleware.send(request, &ctx)?;
+
+        ctx.check(response)?;
+        Ok(())
     }
 
     /// Deletes **all** CekUnit records.
@@ -396,36 +374,26 @@ impl DashboardClient {
     /// # Warning
     /// This operation is extremely destructive and irreversible.
     pub fn delete_all(&self) -> Result<(), ApiError> {
+        let _span = operation_span!("dashboard.delete_all").entered();
         let cache = self.ensure_authenticated()?;
         let mut headers = self.build_headers_with_cookies(&cache)?;
         headers.insert(
             CONTENT_TYPE,
             "application/x-www-form-urlencoded".parse().unwrap(),
         );
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
-        let url = self.config.full_delete_all_url();
+        let url = self.config.full_delete_all_url()?;
         let mut form = HashMap::new();
-        form.insert("_token", cache.csrf_token.as_str());
+        form.insert("_token", cache.csrf_token.expose_str());
         form.insert("_method", "DELETE");
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() || status.as_u16() == 302 {
-            Ok(())
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("dashboard.delete_all", "POST", &url);
+        let request = self.client.post(&url).headers(headers).form(&form);
+        let response = self.middleware.send(request, &ctx)?;
+
+        ctx.check_allow_redirect(response)?;
+        Ok(())
     }
 
     /// Deletes a single CekUnit record identified by its primary key `no`.
@@ -439,36 +407,26 @@ impl DashboardClient {
     /// - The HTTP request fails.
     /// - The server returns a non‑success status (2xx or 302 is considered success).
     pub fn delete_cekunit(&self, no: &str) -> Result<(), ApiError> {
+        let _span = operation_span!("dashboard.delete", no).entered();
         let cache = self.ensure_authenticated()?;
         let mut headers = self.build_headers_with_cookies(&cache)?;
         headers.insert(
             CONTENT_TYPE,
             "application/x-www-form-urlencoded".parse().unwrap(),
         );
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
-        let url = self.config.full_cekunit_item_url(no);
+        let url = self.config.full_cekunit_item_url(no)?;
         let mut form = HashMap::new();
-        form.insert("_token", cache.csrf_token.as_str());
+        form.insert("_token", cache.csrf_token.expose_str());
         form.insert("_method", "DELETE");
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() || status.as_u16() == 302 {
-            Ok(())
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("dashboard.delete", "POST", &url);
+        let request = self.client.post(&url).headers(headers).form(&form);
+        let response = self.middleware.send(request, &ctx)?;
+
+        ctx.check_allow_redirect(response)?;
+        Ok(())
     }
 
     /// Updates an existing CekUnit record.
@@ -485,50 +443,40 @@ impl DashboardClient {
     /// - The server returns a non‑success status (2xx or 302 is considered success).
     ///
     /// # Example
-    /// ```
+    /// ```no_run
     /// # use std::collections::HashMap;
-    /// # use cekunit_client::api::dashboard::DashboardClient;
+    /// # use librcekunit::api::dashboard::DashboardClient;
     /// # let client = DashboardClient::new().unwrap();
     /// let mut updates = HashMap::new();
     /// updates.insert("status", "approved");
     /// updates.insert("notes", "Updated via API");
     /// client.update_cekunit("123", updates)?;
-    /// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+    /// # Ok::<(), librcekunit::handler::error::ApiError>(())
     /// ```
     pub fn update_cekunit(&self, no: &str, data: HashMap<&str, &str>) -> Result<(), ApiError> {
+        let _span = operation_span!("dashboard.update", no).entered();
         let cache = self.ensure_authenticated()?;
         let mut headers = self.build_headers_with_cookies(&cache)?;
         headers.insert(
             CONTENT_TYPE,
             "application/x-www-form-urlencoded".parse().unwrap(),
         );
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
-        let url = self.config.full_cekunit_item_url(no);
+        let url = self.config.full_cekunit_item_url(no)?;
         let mut form: HashMap<&str, &str> = HashMap::new();
-        form.insert("_token", cache.csrf_token.as_str());
+        form.insert("_token", cache.csrf_token.expose_str());
         form.insert("_method", "PUT");
         for (key, value) in data {
             form.insert(key, value);
         }
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() || status.as_u16() == 302 {
-            Ok(())
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("dashboard.update", "POST", &url);
+        let request = self.client.post(&url).headers(headers).form(&form);
+        let response = self.middleware.send(request, &ctx)?;
+
+        ctx.check_allow_redirect(response)?;
+        Ok(())
     }
 
     /// Fetches a fresh CSRF token from the dashboard page.
@@ -545,8 +493,11 @@ impl DashboardClient {
     /// - The dashboard page cannot be fetched.
     /// - No CSRF token is found in the HTML.
     pub fn get_csrf_token(&self) -> Result<String, ApiError> {
+        let _span = operation_span!("dashboard.csrf_token").entered();
         let html = self.get_dashboard(Some(1), None, None, None)?;
-        crate::api::auth::utils::token::extract_csrf_token(&html)
+        let token = crate::api::auth::utils::token::extract_csrf_token(&html)?;
+        self.middleware.emit(ClientEvent::CsrfRefresh);
+        Ok(token)
     }
 
     /// Returns a reference to the environment configuration.
@@ -9,9 +9,11 @@
 
 use crate::api::auth::utils::cache::{CacheData, CacheManager};
 use crate::handler::env::EnvConfig;
-use crate::handler::error::ApiError;
+use crate::handler::error::{ApiError, ErrorContext};
+use crate::handler::telemetry::operation_span;
+use crate::middleware::{ClientEvent, MiddlewareStack};
 use reqwest::blocking::Client;
-use reqwest::header::{CONTENT_TYPE, HeaderMap, USER_AGENT};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, HeaderMap, USER_AGENT};
 use std::collections::HashMap;
 use std::time::Duration;
 
@@ -23,7 +25,7 @@ use std::time::Duration;
 ///
 /// # Example
 /// ```no_run
-/// use cekunit_client::api::dashboard::InputDataClient;
+/// use librcekunit::api::dashboard::InputDataClient;
 /// use std::collections::HashMap;
 ///
 /// let client = InputDataClient::new()?;
@@ -39,7 +41,7 @@ use std::time::Duration;
 ///
 /// // Submit the form
 /// client.insert_nasabah(data)?;
-/// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+/// # Ok::<(), librcekunit::handler::error::ApiError>(())
 /// ```
 pub struct InputDataClient {
     /// The underlying reqwest blocking client.
@@ -48,6 +50,8 @@ pub struct InputDataClient {
     config: EnvConfig,
     /// Cache manager for loading the session (cookies + CSRF token).
     cache_manager: CacheManager,
+    /// Hooks run around every request.
+    middleware: MiddlewareStack,
 }
 
 impl InputDataClient {
@@ -68,6 +72,7 @@ impl InputDataClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
@@ -90,6 +95,7 @@ impl InputDataClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
@@ -112,9 +118,18 @@ impl InputDataClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
+    /// Sets the middlewares every request of this client is sent through.
+    ///
+    /// [`CekUnitClient::add_middleware`](crate::CekUnitClient::add_middleware) does this
+    /// for the clients it manages.
+    pub fn set_middleware(&mut self, middleware: MiddlewareStack) {
+        self.middleware = middleware;
+    }
+
     /// Builds and configures the HTTP client.
     ///
     /// The client is configured with:
@@ -134,7 +149,7 @@ impl InputDataClient {
             .brotli(true)
             .deflate(true)
             .build()
-            .map_err(|e| ApiError::from(e))
+            .map_err(ApiError::from)
     }
 
     /// Ensures that a valid authenticated session exists in the cache.
@@ -173,7 +188,7 @@ impl InputDataClient {
         let cookie_map: HashMap<String, String> = cache
             .cookies
             .iter()
-            .map(|c| (c.name.clone(), c.value.clone()))
+            .map(|c| (c.name.clone(), c.value.expose().clone()))
             .collect();
 
         crate::api::auth::utils::cookies::add_cookies_to_headers(&mut headers, &cookie_map)?;
@@ -196,27 +211,17 @@ impl InputDataClient {
     /// - The server returns a non‑success status (4xx or 5xx).
     /// - The response body cannot be read.
     pub fn get_form(&self) -> Result<String, ApiError> {
+        let _span = operation_span!("input_data.form").entered();
         let cache = self.ensure_authenticated()?;
         let headers = self.build_headers_with_cookies(&cache)?;
-        let url = self.config.full_input_data_url();
+        let url = self.config.full_input_data_url()?;
 
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
+        let ctx = ErrorContext::new("input_data.form", "GET", &url);
+        let request = self.client.get(&url).headers(headers);
+        let response = self.middleware.send(request, &ctx)?;
 
-        let status = response.status();
-        if status.is_success() {
-            Ok(response.text().map_err(|e| ApiError::from(e))?)
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let response = ctx.check(response)?;
+        response.text().map_err(|e| ctx.transport(e))
     }
 
     /// Submits a new nasabah (customer) record via the input data form.
@@ -227,7 +232,7 @@ impl InputDataClient {
     ///
     /// # Arguments
     /// * `data` - A map of field names to values. The map **must not** include the `_token` field,
-    ///            as it is added automatically.
+    ///   as it is added automatically.
     ///
     /// # Errors
     /// Returns [`ApiError`] if:
@@ -236,9 +241,9 @@ impl InputDataClient {
     /// - The server returns a non‑success status (2xx or 302 is considered success).
     ///
     /// # Example
-    /// ```
+    /// ```no_run
     /// # use std::collections::HashMap;
-    /// # use cekunit_client::api::dashboard::InputDataClient;
+    /// # use librcekunit::api::dashboard::InputDataClient;
     /// # let client = InputDataClient::new().unwrap();
     /// let mut nasabah_data = HashMap::new();
     /// nasabah_data.insert("nama", "Jane Doe");
@@ -247,41 +252,31 @@ impl InputDataClient {
     /// nasabah_data.insert("tanggal_lahir", "1990-01-01");
     ///
     /// client.insert_nasabah(nasabah_data)?;
-    /// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+    /// # Ok::<(), librcekunit::handler::error::ApiError>(())
     /// ```
     pub fn insert_nasabah(&self, data: HashMap<&str, &str>) -> Result<(), ApiError> {
+        let _span = operation_span!("input_data.create").entered();
         let cache = self.ensure_authenticated()?;
         let mut headers = self.build_headers_with_cookies(&cache)?;
         headers.insert(
             CONTENT_TYPE,
             "application/x-www-form-urlencoded".parse().unwrap(),
         );
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
-        let url = self.config.full_input_data_url();
+        let url = self.config.full_input_data_url()?;
         let mut form = HashMap::new();
-        form.insert("_token", cache.csrf_token.as_str());
+        form.insert("_token", cache.csrf_token.expose_str());
         for (key, value) in data {
             form.insert(key, value);
         }
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
+        let ctx = ErrorContext::new("input_data.create", "POST", &url);
+        let request = self.client.post(&url).headers(headers).form(&form);
+        let response = self.middleware.send(request, &ctx)?;
 
-        let status = response.status();
-        if status.is_success() || status.as_u16() == 302 {
-            Ok(())
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        ctx.check_allow_redirect(response)?;
+        Ok(())
     }
 
     /// Fetches a fresh CSRF token from the input data form page.
@@ -299,8 +294,11 @@ impl InputDataClient {
     /// - The form page cannot be fetched.
     /// - No CSRF token is found in the HTML.
     pub fn get_csrf_token(&self) -> Result<String, ApiError> {
+        let _span = operation_span!("input_data.csrf_token").entered();
         let html = self.get_form()?;
-        crate::api::auth::utils::token::extract_csrf_token(&html)
+        let token = crate::api::auth::utils::token::extract_csrf_token(&html)?;
+        self.middleware.emit(ClientEvent::CsrfRefresh);
+        Ok(token)
     }
 
     /// Returns a reference to the environment configuration.
@@ -11,11 +11,12 @@
 
 use crate::api::auth::utils::cache::{CacheData, CacheManager};
 use crate::handler::env::EnvConfig;
-use crate::handler::error::ApiError;
+use crate::handler::error::{ApiError, ErrorContext};
+use crate::handler::telemetry::operation_span;
+use crate::middleware::{ClientEvent, MiddlewareStack};
 use reqwest::blocking::Client;
 use reqwest::header::{ACCEPT, HeaderMap, HeaderValue, REFERER, USER_AGENT};
 use std::collections::HashMap;
-use std::io::Read;
 use std::time::Duration;
 
 /// Client for input user operations.
@@ -26,7 +27,7 @@ use std::time::Duration;
 ///
 /// # Example
 /// ```no_run
-/// use cekunit_client::api::dashboard::InputUserClient;
+/// use librcekunit::api::dashboard::InputUserClient;
 ///
 /// let client = InputUserClient::new()?;
 ///
@@ -49,7 +50,7 @@ use std::time::Duration;
 ///     None,
 ///     None
 /// )?;
-/// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+/// # Ok::<(), librcekunit::handler::error::ApiError>(())
 /// ```
 pub struct InputUserClient {
     /// The underlying reqwest blocking client.
@@ -58,6 +59,8 @@ pub struct InputUserClient {
     config: EnvConfig,
     /// Cache manager for loading the session (cookies + CSRF token).
     cache_manager: CacheManager,
+    /// Hooks run around every request.
+    middleware: MiddlewareStack,
 }
 
 impl InputUserClient {
@@ -78,6 +81,7 @@ impl InputUserClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
@@ -100,6 +104,7 @@ impl InputUserClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
@@ -122,9 +127,18 @@ impl InputUserClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
+    /// Sets the middlewares every request of this client is sent through.
+    ///
+    /// [`CekUnitClient::add_middleware`](crate::CekUnitClient::add_middleware) does this
+    /// for the clients it manages.
+    pub fn set_middleware(&mut self, middleware: MiddlewareStack) {
+        self.middleware = middleware;
+    }
+
     /// Builds and configures the HTTP client.
     ///
     /// The client is configured with:
@@ -144,7 +158,7 @@ impl InputUserClient {
             .brotli(true)
             .deflate(true)
             .build()
-            .map_err(|e| ApiError::from(e))
+            .map_err(ApiError::from)
     }
 
     /// Ensures that a valid authenticated session exists in the cache.
@@ -182,7 +196,7 @@ impl InputUserClient {
         );
         headers.insert(
             REFERER,
-            HeaderValue::from_str(&self.config.full_input_user_url())
+            HeaderValue::from_str(&self.config.full_input_user_url()?)
                 .map_err(|e| ApiError::CacheError(format!("Invalid referer: {}", e)))?,
         );
         headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
@@ -190,7 +204,7 @@ impl InputUserClient {
         let cookie_map: HashMap<String, String> = cache
             .cookies
             .iter()
-            .map(|c| (c.name.clone(), c.value.clone()))
+            .map(|c| (c.name.clone(), c.value.expose().clone()))
             .collect();
 
         crate::api::auth::utils::cookies::add_cookies_to_headers(&mut headers, &cookie_map)?;
@@ -228,10 +242,11 @@ impl InputUserClient {
         start_date: Option<&str>,
         end_date: Option<&str>,
     ) -> Result<String, ApiError> {
+        let _span = operation_span!("input_user.list", page, sort, direction).entered();
         let cache = self.ensure_authenticated()?;
         let headers = self.build_headers_with_cookies(&cache)?;
 
-        let mut url = self.config.full_input_user_url();
+        let mut url = self.config.full_input_user_url()?;
         let mut params = Vec::new();
 
         if let Some(p) = page {
@@ -254,28 +269,17 @@ impl InputUserClient {
         }
 
         if !params.is_empty() {
-            url.push_str("?");
+            url.push('?');
             url.push_str(&params.join("&"));
         }
 
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
+        let ctx = ErrorContext::new("input_user.list", "GET", &url);
+        let request = self.client.get(&url).headers(headers);
+        let response = self.middleware.send(request, &ctx)?;
 
-        let status = response.status();
-        if status.is_success() {
-            let body = response.text().map_err(|e| ApiError::from(e))?;
-            Ok(body)
-        } else {
-            let error_body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, error_body
-            )))
-        }
+        let response = ctx.check(response)?;
+        let body = response.text().map_err(|e| ctx.transport(e))?;
+        Ok(body)
     }
 
     /// Exports input user data in the specified format.
@@ -302,8 +306,8 @@ impl InputUserClient {
     /// - The response body cannot be read.
     ///
     /// # Example
-    /// ```
-    /// # use cekunit_client::api::dashboard::InputUserClient;
+    /// ```no_run
+    /// # use librcekunit::api::dashboard::InputUserClient;
     /// # let client = InputUserClient::new().unwrap();
     /// let excel_bytes = client.export_input_user(
     ///     "excel",
@@ -325,10 +329,13 @@ impl InputUserClient {
         start_date: Option<&str>,
         end_date: Option<&str>,
     ) -> Result<Vec<u8>, ApiError> {
+        let _span = operation_span!("input_user.export", format, sort, direction).entered();
         let cache = self.ensure_authenticated()?;
-        let headers = self.build_headers_with_cookies(&cache)?;
+        let mut headers = self.build_headers_with_cookies(&cache)?;
+        // Any file type is accepted, but errors come back as JSON.
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json, */*"));
 
-        let mut url = self.config.full_input_user_export_url();
+        let mut url = self.config.full_input_user_export_url()?;
         let mut params = vec![
             format!("format={}", format),
             format!("sort={}", sort),
@@ -348,27 +355,11 @@ impl InputUserClient {
         url.push('?');
         url.push_str(&params.join("&"));
 
-        let mut response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_body = response.text().unwrap_or_default();
-            return Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, error_body
-            )));
-        }
-
-        let mut buf = Vec::new();
-        response
-            .read_to_end(&mut buf)
-            .map_err(|e| ApiError::from(e))?;
-        Ok(buf)
+        let ctx = ErrorContext::new("input_user.export", "GET", &url);
+        let request = self.client.get(&url).headers(headers);
+        let response = self.middleware.send(request, &ctx)?;
+        let response = ctx.check(response)?;
+        Ok(response.bytes().map_err(|e| ctx.transport(e))?.to_vec())
     }
 
     /// Fetches a fresh CSRF token from the input user list page.
@@ -386,8 +377,11 @@ impl InputUserClient {
     /// - The input user page cannot be fetched.
     /// - No CSRF token is found in the HTML.
     pub fn get_csrf_token(&self) -> Result<String, ApiError> {
+        let _span = operation_span!("input_user.csrf_token").entered();
         let html = self.get_input_user(Some(1), None, None, None, None, None)?;
-        crate::api::auth::utils::token::extract_csrf_token(&html)
+        let token = crate::api::auth::utils::token::extract_csrf_token(&html)?;
+        self.middleware.emit(ClientEvent::CsrfRefresh);
+        Ok(token)
     }
 
     /// Returns a reference to the environment configuration.
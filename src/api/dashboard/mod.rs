@@ -2,10 +2,12 @@ pub mod index;
 pub mod input_data;
 pub mod input_user;
 pub mod pic;
+pub mod table;
 pub mod users;
 
 pub use index::*;
 pub use input_data::*;
 pub use input_user::*;
 pub use pic::*;
+pub use table::*;
 pub use users::*;
@@ -13,9 +13,11 @@
 
 use crate::api::auth::utils::cache::{CacheData, CacheManager};
 use crate::handler::env::EnvConfig;
-use crate::handler::error::ApiError;
+use crate::handler::error::{ApiError, ErrorContext};
+use crate::handler::telemetry::operation_span;
+use crate::middleware::{ClientEvent, MiddlewareStack};
 use reqwest::blocking::Client;
-use reqwest::header::{CONTENT_TYPE, HeaderMap, USER_AGENT};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, HeaderMap, USER_AGENT};
 use std::collections::HashMap;
 use std::time::Duration;
 
@@ -27,7 +29,7 @@ use std::time::Duration;
 ///
 /// # Example
 /// ```no_run
-/// use cekunit_client::api::dashboard::PicClient;
+/// use librcekunit::api::dashboard::PicClient;
 /// use std::collections::HashMap;
 ///
 /// let client = PicClient::new()?;
@@ -51,7 +53,7 @@ use std::time::Duration;
 ///
 /// // Delete a PIC
 /// client.delete_pic("5")?;
-/// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+/// # Ok::<(), librcekunit::handler::error::ApiError>(())
 /// ```
 pub struct PicClient {
     /// The underlying reqwest blocking client.
@@ -60,6 +62,8 @@ pub struct PicClient {
     config: EnvConfig,
     /// Cache manager for loading the session (cookies + CSRF token).
     cache_manager: CacheManager,
+    /// Hooks run around every request.
+    middleware: MiddlewareStack,
 }
 
 impl PicClient {
@@ -80,6 +84,7 @@ impl PicClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
@@ -102,6 +107,7 @@ impl PicClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
@@ -124,9 +130,18 @@ impl PicClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
+    /// Sets the middlewares every request of this client is sent through.
+    ///
+    /// [`CekUnitClient::add_middleware`](crate::CekUnitClient::add_middleware) does this
+    /// for the clients it manages.
+    pub fn set_middleware(&mut self, middleware: MiddlewareStack) {
+        self.middleware = middleware;
+    }
+
     /// Builds and configures the HTTP client.
     ///
     /// The client is configured with:
@@ -146,7 +161,7 @@ impl PicClient {
             .brotli(true)
             .deflate(true)
             .build()
-            .map_err(|e| ApiError::from(e))
+            .map_err(ApiError::from)
     }
 
     /// Ensures that a valid authenticated session exists in the cache.
@@ -185,7 +200,7 @@ impl PicClient {
         let cookie_map: HashMap<String, String> = cache
             .cookies
             .iter()
-            .map(|c| (c.name.clone(), c.value.clone()))
+            .map(|c| (c.name.clone(), c.value.expose().clone()))
             .collect();
 
         crate::api::auth::utils::cookies::add_cookies_to_headers(&mut headers, &cookie_map)?;
@@ -217,10 +232,11 @@ impl PicClient {
         sort: Option<&str>,
         direction: Option<&str>,
     ) -> Result<String, ApiError> {
+        let _span = operation_span!("pic.list", page, sort, direction).entered();
         let cache = self.ensure_authenticated()?;
         let headers = self.build_headers_with_cookies(&cache)?;
 
-        let mut url = self.config.full_pic_url();
+        let mut url = self.config.full_pic_url()?;
         let mut params = Vec::new();
 
         if let Some(p) = page {
@@ -234,27 +250,16 @@ impl PicClient {
         }
 
         if !params.is_empty() {
-            url.push_str("?");
+            url.push('?');
             url.push_str(&params.join("&"));
         }
 
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() {
-            Ok(response.text().map_err(|e| ApiError::from(e))?)
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("pic.list", "GET", &url);
+        let request = self.client.get(&url).headers(headers);
+        let response = self.middleware.send(request, &ctx)?;
+
+        let response = ctx.check(response)?;
+        response.text().map_err(|e| ctx.transport(e))
     }
 
     /// Creates a new PIC record.
@@ -265,7 +270,7 @@ impl PicClient {
     ///
     /// # Arguments
     /// * `data` - A map of field names to values. The map **must not** include the `_token` field,
-    ///            as it is added automatically.
+    ///   as it is added automatically.
     ///
     /// # Errors
     /// Returns [`ApiError`] if:
@@ -274,50 +279,40 @@ impl PicClient {
     /// - The server returns a non‑success status (2xx or 302 is considered success).
     ///
     /// # Example
-    /// ```
+    /// ```no_run
     /// # use std::collections::HashMap;
-    /// # use cekunit_client::api::dashboard::PicClient;
+    /// # use librcekunit::api::dashboard::PicClient;
     /// # let client = PicClient::new().unwrap();
     /// let mut new_pic = HashMap::new();
     /// new_pic.insert("name", "Alice Smith");
     /// new_pic.insert("email", "alice@example.com");
     /// new_pic.insert("phone", "555-1234");
     /// client.insert_pic(new_pic)?;
-    /// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+    /// # Ok::<(), librcekunit::handler::error::ApiError>(())
     /// ```
     pub fn insert_pic(&self, data: HashMap<&str, &str>) -> Result<(), ApiError> {
+        let _span = operation_span!("pic.create").entered();
         let cache = self.ensure_authenticated()?;
         let mut headers = self.build_headers_with_cookies(&cache)?;
         headers.insert(
             CONTENT_TYPE,
             "application/x-www-form-urlencoded".parse().unwrap(),
         );
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
-        let url = self.config.full_input_pic_url();
+        let url = self.config.full_input_pic_url()?;
         let mut form = HashMap::new();
-        form.insert("_token", cache.csrf_token.as_str());
+        form.insert("_token", cache.csrf_token.expose_str());
         for (key, value) in data {
             form.insert(key, value);
         }
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() || status.as_u16() == 302 {
-            Ok(())
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("pic.create", "POST", &url);
+        let request = self.client.post(&url).headers(headers).form(&form);
+        let response = self.middleware.send(request, &ctx)?;
+
+        ctx.check_allow_redirect(response)?;
+        Ok(())
     }
 
     /// Updates an existing PIC record.
@@ -336,49 +331,39 @@ impl PicClient {
     /// - The server returns a non‑success status (2xx or 302 is considered success).
     ///
     /// # Example
-    /// ```
+    /// ```no_run
     /// # use std::collections::HashMap;
-    /// # use cekunit_client::api::dashboard::PicClient;
+    /// # use librcekunit::api::dashboard::PicClient;
     /// # let client = PicClient::new().unwrap();
     /// let mut updates = HashMap::new();
     /// updates.insert("name", "Robert Johnson");
     /// client.update_pic("10", updates)?;
-    /// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+    /// # Ok::<(), librcekunit::handler::error::ApiError>(())
     /// ```
     pub fn update_pic(&self, id: &str, data: HashMap<&str, &str>) -> Result<(), ApiError> {
+        let _span = operation_span!("pic.update", id).entered();
         let cache = self.ensure_authenticated()?;
         let mut headers = self.build_headers_with_cookies(&cache)?;
         headers.insert(
             CONTENT_TYPE,
             "application/x-www-form-urlencoded".parse().unwrap(),
         );
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
-        let url = self.config.full_pic_item_url(id);
+        let url = self.config.full_pic_item_url(id)?;
         let mut form = HashMap::new();
-        form.insert("_token", cache.csrf_token.as_str());
+        form.insert("_token", cache.csrf_token.expose_str());
         form.insert("_method", "PUT");
         for (key, value) in data {
             form.insert(key, value);
         }
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() || status.as_u16() == 302 {
-            Ok(())
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("pic.update", "POST", &url);
+        let request = self.client.post(&url).headers(headers).form(&form);
+        let response = self.middleware.send(request, &ctx)?;
+
+        ctx.check_allow_redirect(response)?;
+        Ok(())
     }
 
     /// Deletes a PIC record.
@@ -397,36 +382,26 @@ impl PicClient {
     /// # Warning
     /// This operation is irreversible. Use with caution.
     pub fn delete_pic(&self, id: &str) -> Result<(), ApiError> {
+        let _span = operation_span!("pic.delete", id).entered();
         let cache = self.ensure_authenticated()?;
         let mut headers = self.build_headers_with_cookies(&cache)?;
         headers.insert(
             CONTENT_TYPE,
             "application/x-www-form-urlencoded".parse().unwrap(),
         );
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
-        let url = self.config.full_pic_item_url(id);
+        let url = self.config.full_pic_item_url(id)?;
         let mut form = HashMap::new();
-        form.insert("_token", cache.csrf_token.as_str());
+        form.insert("_token", cache.csrf_token.expose_str());
         form.insert("_method", "DELETE");
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .map_err(|e| ApiError::from(e))?;
-
-        let status = response.status();
-        if status.is_success() || status.as_u16() == 302 {
-            Ok(())
-        } else {
-            let body = response.text().unwrap_or_default();
-            Err(ApiError::RequestFailed(format!(
-                "HTTP {} - {}",
-                status, body
-            )))
-        }
+        let ctx = ErrorContext::new("pic.delete", "POST", &url);
+        let request = self.client.post(&url).headers(headers).form(&form);
+        let response = self.middleware.send(request, &ctx)?;
+
+        ctx.check_allow_redirect(response)?;
+        Ok(())
     }
 
     /// Fetches a fresh CSRF token from the PIC list page.
@@ -444,8 +419,11 @@ impl PicClient {
     /// - The PIC list page cannot be fetched.
     /// - No CSRF token is found in the HTML.
     pub fn get_csrf_token(&self) -> Result<String, ApiError> {
+        let _span = operation_span!("pic.csrf_token").entered();
         let html = self.get_pic_list(Some(1), None, None)?;
-        crate::api::auth::utils::token::extract_csrf_token(&html)
+        let token = crate::api::auth::utils::token::extract_csrf_token(&html)?;
+        self.middleware.emit(ClientEvent::CsrfRefresh);
+        Ok(token)
     }
 
     /// Returns a reference to the environment configuration.
@@ -0,0 +1,385 @@
+//! Parsing of the HTML list pages into rows.
+//!
+//! The listing methods ([`get_dashboard`](super::DashboardClient::get_dashboard),
+//! [`get_pic_list`](super::PicClient::get_pic_list), [`get_users_list`](super::UsersClient::get_users_list)
+//! and [`get_input_user`](super::InputUserClient::get_input_user)) return the raw HTML of
+//! the panel. [`ListPage::parse`] extracts the first `<table>` of such a page, together with
+//! the pagination state, so callers can work with plain strings.
+//!
+//! The headers are labels for people ("Nama Nasabah"), not the names the panel's forms
+//! use, and the record id is not necessarily shown in a column. Both are taken from the
+//! markup instead: field names from the header sort links and form controls, ids from
+//! the edit links and delete forms of each row.
+
+use crate::handler::error::ApiError;
+use select::document::Document;
+use select::node::Node;
+use select::predicate::{Attr, Class, Name, Predicate};
+use serde::Serialize;
+
+/// One page of a listing, as shown in the panel.
+///
+/// # Example
+/// ```
+/// use librcekunit::api::dashboard::ListPage;
+///
+/// let html = r#"<table>
+///   <thead><tr>
+///     <th>No</th>
+///     <th><a href="/dashboard?sort=nama_nasabah&amp;direction=asc">Nama Nasabah</a></th>
+///     <th>Aksi</th>
+///   </tr></thead>
+///   <tbody><tr>
+///     <td>1</td><td>Andi</td><td><a href="/cekunit/17/edit">Edit</a></td>
+///   </tr></tbody>
+/// </table>"#;
+/// let page = ListPage::parse(html)?;
+/// assert_eq!(page.columns, ["No", "Nama Nasabah", "Aksi"]);
+/// assert_eq!(page.field(1), Some("nama_nasabah"));
+/// assert_eq!(page.get(0, "nama_nasabah"), Some("Andi"));
+/// assert_eq!(page.id(0), Some("17"));
+/// # Ok::<(), librcekunit::ApiError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ListPage {
+    /// Column headers, in display order.
+    pub columns: Vec<String>,
+    /// Form field behind each column, if the page names it; see [`field`](Self::field).
+    pub fields: Vec<Option<String>>,
+    /// Cell texts of each row, in the order of [`columns`](Self::columns).
+    pub rows: Vec<Vec<String>>,
+    /// Record id of each row, if its links name one; see [`id`](Self::id).
+    pub ids: Vec<Option<String>>,
+    /// Current page number (1-based), if the page has pagination.
+    pub page: Option<u32>,
+    /// Number of pages, if known.
+    pub pages: Option<u32>,
+    /// Total number of records across all pages, if the page reports it.
+    pub total: Option<u64>,
+}
+
+impl ListPage {
+    /// Parses the first table and the pagination of a list page.
+    ///
+    /// Cell text is trimmed and inner whitespace collapsed. A row with a single cell in
+    /// a multi-column table (the "no data" placeholder) is skipped.
+    ///
+    /// The field of a column is the `sort` parameter of the link in its header, or else
+    /// the `name` of a form control (`input`, `select`, `textarea`) in its cells. The id
+    /// of a row is the last path segment of the first target in the row, trailing
+    /// `/edit` removed, e.g. `17` for `action="/cekunit/17"` or `href="/pic/17/edit"`.
+    /// Forms that spoof a method (`_method`) are looked at before links.
+    ///
+    /// Pagination is read from the links Laravel's paginator views render inside
+    /// `<nav>` or `.pagination`: the current page is the `aria-current="page"` (or
+    /// `.active`) item, the number of pages the highest `page=` link, and the total the
+    /// last number of the "Showing 1 to 10 of 25 results" line. Laravel leaves the
+    /// links out when everything fits on one page; `page` and `pages` are then `None`.
+    ///
+    /// # Arguments
+    /// * `html` - The HTML returned by one of the listing methods.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::HtmlParseError`] if the page contains no `<table>`.
+    pub fn parse(html: &str) -> Result<Self, ApiError> {
+        let doc = Document::from(html);
+        let table = doc
+            .find(Name("table"))
+            .next()
+            .ok_or_else(|| ApiError::HtmlParseError("no table found in list page".into()))?;
+
+        let header_row = table
+            .find(Name("thead"))
+            .next()
+            .and_then(|head| head.find(Name("tr")).next())
+            .or_else(|| table.find(Name("tr")).next());
+        let headers: Vec<Node> = header_row
+            .map(|tr| tr.find(Name("th")).collect())
+            .unwrap_or_default();
+        let columns: Vec<String> = headers.iter().map(text).collect();
+
+        let rows: Vec<Node> = table
+            .find(Name("tr"))
+            .filter(|tr| {
+                let cells = tr.find(Name("td")).count();
+                cells > 1 || (cells == 1 && columns.len() <= 1)
+            })
+            .collect();
+        let cells: Vec<Vec<Node>> = rows
+            .iter()
+            .map(|tr| tr.find(Name("td")).collect())
+            .collect();
+        let fields = headers
+            .iter()
+            .enumerate()
+            .map(|(i, th)| {
+                th.find(Name("a"))
+                    .find_map(|a| a.attr("href").and_then(|href| query_param(href, "sort")))
+                    .or_else(|| {
+                        cells
+                            .iter()
+                            .filter_map(|row| row.get(i))
+                            .find_map(control_name)
+                    })
+            })
+            .collect();
+
+        let mut page = ListPage {
+            columns,
+            fields,
+            rows: cells
+                .iter()
+                .map(|row| row.iter().map(text).collect())
+                .collect(),
+            ids: rows.iter().map(record_id).collect(),
+            ..Default::default()
+        };
+        let navs: Vec<Node> = doc.find(Name("nav").or(Class("pagination"))).collect();
+        page.page = navs.iter().find_map(|nav| {
+            nav.find(Attr("aria-current", "page").or(Class("active")))
+                .find_map(|current| text(&current).parse().ok())
+        });
+        page.pages = navs
+            .iter()
+            .flat_map(|nav| nav.find(Name("a")))
+            .filter_map(|a| {
+                a.attr("href")
+                    .and_then(|href| query_param(href, "page"))
+                    .and_then(|page| page.parse().ok())
+            })
+            .chain(page.page)
+            .max();
+        page.total = navs
+            .iter()
+            .flat_map(|nav| nav.find(Name("p")))
+            .find_map(|p| results_total(&text(&p)));
+        Ok(page)
+    }
+
+    /// Returns the number of rows on this page.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns `true` if this page has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the index of a column, by its field name or, case-insensitively, by its
+    /// header.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        let name = name.trim();
+        self.fields
+            .iter()
+            .position(|f| f.as_deref() == Some(name))
+            .or_else(|| {
+                self.columns
+                    .iter()
+                    .position(|c| c.eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// Returns the form field behind `column`, e.g. `"nama_nasabah"` for the column
+    /// headed "Nama Nasabah", or `None` if the page does not name it.
+    pub fn field(&self, column: usize) -> Option<&str> {
+        self.fields.get(column)?.as_deref()
+    }
+
+    /// Returns the record id of `row`, taken from its edit link or delete form, or
+    /// `None` if the row has no such target.
+    pub fn id(&self, row: usize) -> Option<&str> {
+        self.ids.get(row)?.as_deref()
+    }
+
+    /// Returns the cell of `row` in the column called `column`.
+    pub fn get(&self, row: usize, column: &str) -> Option<&str> {
+        let index = self.column_index(column)?;
+        self.rows.get(row)?.get(index).map(String::as_str)
+    }
+
+    /// Returns `true` if the pagination reports a page after this one.
+    pub fn has_next(&self) -> bool {
+        matches!((self.page, self.pages), (Some(page), Some(pages)) if page < pages)
+    }
+}
+
+/// Returns the text of a node with whitespace collapsed.
+fn text(node: &Node) -> String {
+    node.text().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns a query parameter of a link; values are used as-is, without decoding.
+fn query_param(href: &str, name: &str) -> Option<String> {
+    let (_, query) = href.split_once('?')?;
+    let query = query.split('#').next().unwrap_or(query);
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Returns the name of the first form control in a cell, ignoring Laravel's hidden
+/// `_token` and `_method` fields.
+fn control_name(cell: &Node) -> Option<String> {
+    cell.find(Name("input").or(Name("select")).or(Name("textarea")))
+        .filter_map(|control| control.attr("name"))
+        .find(|name| !name.starts_with('_'))
+        .map(str::to_string)
+}
+
+/// Returns the record id named by the forms and links of a row.
+fn record_id(row: &Node) -> Option<String> {
+    let mut forms: Vec<Node> = row.find(Name("form")).collect();
+    forms.sort_by_key(|form| form.find(Attr("name", "_method")).next().is_none());
+    forms
+        .iter()
+        .filter_map(|form| form.attr("action"))
+        .chain(row.find(Name("a")).filter_map(|a| a.attr("href")))
+        .find_map(target_id)
+}
+
+/// Returns the last path segment of a link target, trailing `/edit` removed; `None`
+/// for targets that are not paths of the site (e.g. `mailto:`) or have one segment.
+fn target_id(target: &str) -> Option<String> {
+    let path = match target.split_once("://") {
+        Some((_, rest)) => &rest[rest.find('/')?..],
+        None => target,
+    };
+    if !path.starts_with('/') {
+        return None;
+    }
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let mut segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.last() == Some(&"edit") {
+        segments.pop();
+    }
+    match segments[..] {
+        [_, .., id] => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the total of a "Showing 1 to 10 of 25 results" line, the last of its
+/// three numbers, whatever the language.
+fn results_total(line: &str) -> Option<u64> {
+    let numbers: Vec<u64> = line
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse().ok())
+        .collect();
+    match numbers[..] {
+        [_, _, total] => Some(total),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests headers, rows, placeholder rows and Laravel's bootstrap-5 paginator.
+    #[test]
+    fn test_parse_table_with_laravel_paginator() {
+        let html = r#"<html><body>
+            <table class="table">
+              <thead><tr><th> No </th><th>Nama
+                 Nasabah</th></tr></thead>
+              <tbody>
+                <tr><td>1</td><td>  Andi   Wijaya </td></tr>
+                <tr><td>2</td><td>Budi</td></tr>
+                <tr><td colspan="2">Tidak ada data</td></tr>
+              </tbody>
+            </table>
+            <nav class="d-flex justify-items-center justify-content-between">
+              <div class="d-flex justify-content-between flex-fill d-sm-none">
+                <ul class="pagination">
+                  <li class="page-item"><a class="page-link" href="https://cekunit.test/dashboard?page=1" rel="prev">&laquo; Previous</a></li>
+                  <li class="page-item"><a class="page-link" href="https://cekunit.test/dashboard?page=3" rel="next">Next &raquo;</a></li>
+                </ul>
+              </div>
+              <div class="d-none flex-sm-fill d-sm-flex align-items-sm-center justify-content-sm-between">
+                <div><p class="small text-muted">Showing <span class="fw-semibold">11</span>
+                  to <span class="fw-semibold">20</span> of <span class="fw-semibold">25</span> results</p></div>
+                <div><ul class="pagination">
+                  <li class="page-item"><a class="page-link" href="https://cekunit.test/dashboard?page=1" rel="prev" aria-label="&laquo; Previous">&lsaquo;</a></li>
+                  <li class="page-item"><a class="page-link" href="https://cekunit.test/dashboard?page=1">1</a></li>
+                  <li class="page-item active" aria-current="page"><span class="page-link">2</span></li>
+                  <li class="page-item"><a class="page-link" href="https://cekunit.test/dashboard?page=3">3</a></li>
+                  <li class="page-item"><a class="page-link" href="https://cekunit.test/dashboard?page=3" rel="next" aria-label="Next &raquo;">&rsaquo;</a></li>
+                </ul></div>
+              </div>
+            </nav>
+        </body></html>"#;
+        let page = ListPage::parse(html).unwrap();
+        assert_eq!(page.columns, ["No", "Nama Nasabah"]);
+        assert_eq!(page.rows, [["1", "Andi Wijaya"], ["2", "Budi"]]);
+        assert_eq!(
+            (page.page, page.pages, page.total),
+            (Some(2), Some(3), Some(25))
+        );
+        assert!(page.has_next());
+        assert_eq!(page.get(1, "nama nasabah"), Some("Budi"));
+        assert_eq!(page.get(1, "missing"), None);
+    }
+
+    /// Tests field names from sort links and form controls, and ids from the targets
+    /// of delete forms before links.
+    #[test]
+    fn test_fields_and_ids() {
+        let html = r#"<table>
+            <tr><th>No</th><th><a href="/pic?sort=nama&amp;direction=desc">Nama</a></th>
+                <th>Aktif</th><th>Email</th><th>Aksi</th></tr>
+            <tr><td>1</td><td>Andi</td><td><input type="checkbox" name="aktif" checked></td>
+                <td><a href="mailto:andi@example.com">andi@example.com</a></td>
+                <td><a href="https://cekunit.test/pic/31/edit?back=1">Edit</a>
+                    <form action="/pic/32" method="POST"><input name="_method" value="DELETE"></form></td></tr>
+            <tr><td>2</td><td>Budi</td><td></td><td>-</td><td><a href="/pic/33/edit">Edit</a></td></tr>
+            <tr><td>3</td><td>Citra</td><td></td><td><a href="mailto:c@example.com">c</a></td><td></td></tr>
+        </table>"#;
+        let page = ListPage::parse(html).unwrap();
+        assert_eq!(
+            page.fields,
+            [None, Some("nama".into()), Some("aktif".into()), None, None]
+        );
+        assert_eq!(page.ids, [Some("32".into()), Some("33".into()), None]);
+        assert_eq!(page.column_index("nama"), Some(1));
+        assert_eq!(page.column_index("EMAIL"), Some(3));
+        assert_eq!(page.get(1, "nama"), Some("Budi"));
+    }
+
+    /// Tests the Tailwind paginator on the last page, a single page and a page without
+    /// a table.
+    #[test]
+    fn test_parse_tailwind_paginator_and_missing_table() {
+        let html = r#"<table><tr><th>id</th></tr><tr><td>7</td></tr></table>
+            <nav role="navigation" aria-label="Pagination Navigation" class="flex items-center justify-between">
+              <div class="hidden sm:flex-1 sm:flex sm:items-center sm:justify-between">
+                <div><p class="text-sm text-gray-700 leading-5">Menampilkan 11 sampai 11 dari 11 hasil</p></div>
+                <div><span class="relative z-0 inline-flex shadow-sm rounded-md">
+                  <a href="/pic?search=a&amp;page=1" rel="prev" aria-label="&laquo; Previous">&lsaquo;</a>
+                  <a href="/pic?search=a&amp;page=1" aria-label="Go to page 1">1</a>
+                  <span aria-current="page"><span class="relative inline-flex">2</span></span>
+                </span></div>
+              </div>
+            </nav>"#;
+        let page = ListPage::parse(html).unwrap();
+        assert_eq!(page.rows, [["7"]]);
+        assert_eq!(
+            (page.page, page.pages, page.total),
+            (Some(2), Some(2), Some(11))
+        );
+        assert!(!page.has_next());
+
+        let single = ListPage::parse("<table><tr><td>1</td></tr></table>").unwrap();
+        assert_eq!(
+            (single.page, single.pages, single.total),
+            (None, None, None)
+        );
+        assert!(!single.has_next());
+
+        let err = ListPage::parse("<p>Login</p>").unwrap_err();
+        assert!(matches!(err, ApiError::HtmlParseError(_)));
+    }
+}
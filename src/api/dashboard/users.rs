@@ -11,9 +11,11 @@
 
 use crate::api::auth::utils::cache::{CacheData, CacheManager};
 use crate::handler::env::EnvConfig;
-use crate::handler::error::ApiError;
+use crate::handler::error::{ApiError, ErrorContext};
+use crate::handler::telemetry::operation_span;
+use crate::middleware::{ClientEvent, MiddlewareStack};
 use reqwest::blocking::Client;
-use reqwest::header::{CONTENT_TYPE, HeaderMap, USER_AGENT};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, HeaderMap, USER_AGENT};
 use std::collections::HashMap;
 use std::time::Duration;
 
@@ -25,7 +27,7 @@ use std::time::Duration;
 ///
 /// # Example
 /// ```no_run
-/// use cekunit_client::api::dashboard::UsersClient;
+/// use librcekunit::api::dashboard::UsersClient;
 /// use std::collections::HashMap;
 ///
 /// let client = UsersClient::new()?;
@@ -38,7 +40,7 @@ use std::time::Duration;
 /// updates.insert("name", "New Name");
 /// updates.insert("email", "new@example.com");
 /// client.update_user("42", updates)?;
-/// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+/// # Ok::<(), librcekunit::handler::error::ApiError>(())
 /// ```
 pub struct UsersClient {
     /// The underlying reqwest blocking client.
@@ -47,6 +49,8 @@ pub struct UsersClient {
     config: EnvConfig,
     /// Cache manager for loading the session (cookies + CSRF token).
     cache_manager: CacheManager,
+    /// Hooks run around every request.
+    middleware: MiddlewareStack,
 }
 
 impl UsersClient {
@@ -83,6 +87,7 @@ impl UsersClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
@@ -105,9 +110,18 @@ impl UsersClient {
             client,
             config,
             cache_manager,
+            middleware: MiddlewareStack::new(),
         })
     }
 
+    /// Sets the middlewares every request of this client is sent through.
+    ///
+    /// [`CekUnitClient::add_middleware`](crate::CekUnitClient::add_middleware) does this
+    /// for the clients it manages.
+    pub fn set_middleware(&mut self, middleware: MiddlewareStack) {
+        self.middleware = middleware;
+    }
+
     /// Builds and configures the HTTP client.
     ///
     /// The client is configured with:
@@ -132,7 +146,7 @@ impl UsersClient {
             .pool_max_idle_per_host(10)
             .build()
             .map_err(|e| {
-                log::error!(" Failed to build HTTP client: {}", e);
+                tracing::error!(error = %e, "failed to build HTTP client");
                 ApiError::RequestFailed(e.to_string())
             })
     }
@@ -150,16 +164,16 @@ impl UsersClient {
     fn ensure_authenticated(&self) -> Result<CacheData, ApiError> {
         match self.cache_manager.load()? {
             Some(cache) if cache.logged_in => {
-                log::debug!(" Valid session loaded ({} cookies)", cache.cookies.len());
+                tracing::debug!(cookies = cache.cookies.len(), "session loaded");
                 Ok(cache)
             }
             Some(_) => {
-                log::warn!("️ Session exists but not logged in – clearing cache");
+                tracing::warn!("cached session is not logged in; clearing cache");
                 self.cache_manager.clear()?;
                 Err(ApiError::NotAuthenticated)
             }
             None => {
-                log::warn!("️ No active session found");
+                tracing::warn!("no active session found");
                 Err(ApiError::NotAuthenticated)
             }
         }
@@ -186,12 +200,12 @@ impl UsersClient {
         let cookie_map: HashMap<String, String> = cache
             .cookies
             .iter()
-            .map(|c| (c.name.clone(), c.value.clone()))
+            .map(|c| (c.name.clone(), c.value.expose().clone()))
             .collect();
 
         if !cookie_map.is_empty() {
             crate::api::auth::utils::cookies::add_cookies_to_headers(&mut headers, &cookie_map)?;
-            log::debug!(" Attached {} cookies", cookie_map.len());
+            tracing::debug!(cookies = cookie_map.len(), "attached cached cookies");
         }
 
         Ok(headers)
@@ -222,10 +236,11 @@ impl UsersClient {
         sort: Option<&str>,
         direction: Option<&str>,
     ) -> Result<String, ApiError> {
+        let _span = operation_span!("users.list", page, sort, direction).entered();
         let cache = self.ensure_authenticated()?;
         let headers = self.build_headers_with_cookies(&cache)?;
 
-        let mut url = self.config.full_users_url();
+        let mut url = self.config.full_users_url()?;
         let mut params = Vec::new();
 
         if let Some(p) = page {
@@ -239,32 +254,18 @@ impl UsersClient {
         }
 
         if !params.is_empty() {
-            url.push_str("?");
+            url.push('?');
             url.push_str(&params.join("&"));
         }
 
-        log::debug!(" Requesting users list: {}", url);
-
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .map_err(|e| ApiError::from_reqwest_error(e, "GET users"))?;
+        let ctx = ErrorContext::new("users.list", "GET", &url);
+        let request = self.client.get(&url).headers(headers);
+        let response = self.middleware.send(request, &ctx)?;
 
-        let status = response.status();
-        if status.is_success() {
-            let html = response
-                .text()
-                .map_err(|e| ApiError::RequestFailed(e.to_string()))?;
-            log::debug!(" Users list fetched, {} bytes", html.len());
-            Ok(html)
-        } else {
-            let body = response.text().unwrap_or_default();
-            let err = ApiError::from_status(status, Some(&body));
-            log::error!(" Failed to fetch users list: {}", err);
-            Err(err)
-        }
+        let response = ctx.check(response)?;
+        let html = response.text().map_err(|e| ctx.transport(e))?;
+        tracing::debug!(bytes = html.len(), "users list fetched");
+        Ok(html)
     }
 
     /// Updates an existing user's details.
@@ -283,17 +284,18 @@ impl UsersClient {
     /// - The server returns a non‑success status (2xx or 302 is considered success).
     ///
     /// # Example
-    /// ```
+    /// ```no_run
     /// # use std::collections::HashMap;
-    /// # use cekunit_client::api::dashboard::UsersClient;
+    /// # use librcekunit::api::dashboard::UsersClient;
     /// # let client = UsersClient::new().unwrap();
     /// let mut updates = HashMap::new();
     /// updates.insert("name", "Jane Doe");
     /// updates.insert("email", "jane@example.com");
     /// client.update_user("5", updates)?;
-    /// # Ok::<(), cekunit_client::handler::error::ApiError>(())
+    /// # Ok::<(), librcekunit::handler::error::ApiError>(())
     /// ```
     pub fn update_user(&self, id: &str, data: HashMap<&str, &str>) -> Result<(), ApiError> {
+        let _span = operation_span!("users.update", id).entered();
         let cache = self.ensure_authenticated()?;
         let mut headers = self.build_headers_with_cookies(&cache)?;
         headers.insert(
@@ -302,35 +304,23 @@ impl UsersClient {
                 .parse()
                 .map_err(|e| ApiError::CacheError(format!("Invalid Content-Type: {}", e)))?,
         );
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
 
-        let url = self.config.full_users_item_url(id);
+        let url = self.config.full_users_item_url(id)?;
         let mut form: HashMap<&str, &str> = HashMap::new();
-        form.insert("_token", cache.csrf_token.as_str());
+        form.insert("_token", cache.csrf_token.expose_str());
         form.insert("_method", "PUT");
         for (key, value) in data {
             form.insert(key, value);
         }
 
-        log::info!(" Updating user {} at {}", id, url);
+        let ctx = ErrorContext::new("users.update", "POST", &url);
+        let request = self.client.post(&url).headers(headers).form(&form);
+        let response = self.middleware.send(request, &ctx)?;
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .map_err(|e| ApiError::from_reqwest_error(e, "PUT user"))?;
-
-        let status = response.status();
-        if status.is_success() || status.as_u16() == 302 {
-            log::info!(" User {} updated successfully", id);
-            Ok(())
-        } else {
-            let body = response.text().unwrap_or_default();
-            let err = ApiError::from_status(status, Some(&body));
-            log::error!(" Failed to update user {}: {}", id, err);
-            Err(err)
-        }
+        ctx.check_allow_redirect(response)?;
+        tracing::info!("user updated");
+        Ok(())
     }
 
     /// Fetches a fresh CSRF token from the users list page.
@@ -348,8 +338,11 @@ impl UsersClient {
     /// - The users list page cannot be fetched.
     /// - No CSRF token is found in the HTML.
     pub fn get_csrf_token(&self) -> Result<String, ApiError> {
+        let _span = operation_span!("users.csrf_token").entered();
         let html = self.get_users_list(Some(1), None, None)?;
-        crate::api::auth::utils::token::extract_csrf_token(&html)
+        let token = crate::api::auth::utils::token::extract_csrf_token(&html)?;
+        self.middleware.emit(ClientEvent::CsrfRefresh);
+        Ok(token)
     }
 
     /// Returns a reference to the environment configuration.
@@ -6,16 +6,23 @@
 //!
 //! The client is built around a shared context ([`ClientContext`]) that holds the configuration
 //! and the session cache. Sub-clients are created on demand using the [`FromContext`] trait,
-//! ensuring they all use the same configuration and session data.
+//! ensuring they all use the same configuration and session data. Middlewares registered
+//! with [`CekUnitClient::add_middleware`] live in the context too and apply to every
+//! sub-client as well as to login and logout.
 
 use crate::api::auth::utils::cache::{CacheData, CacheManager};
 use crate::api::auth::{LoginClient, LogoutClient};
 use crate::api::dashboard::{
     DashboardClient, InputDataClient, InputUserClient, PicClient, UsersClient,
 };
+use crate::handler::credentials::CredentialProvider;
 use crate::handler::env::EnvConfig;
 use crate::handler::error::ApiError;
-use std::path::PathBuf;
+use crate::handler::telemetry::operation_span;
+use crate::metrics::Metrics;
+use crate::middleware::{ClientEvent, HarRecorder, Middleware, MiddlewareStack, Transport};
+use crate::session::SessionGuard;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 /// Alias for the environment configuration type.
@@ -26,7 +33,7 @@ pub type CacheManagerType = CacheManager;
 
 /// Shared context for all clients.
 ///
-/// This struct holds the global configuration and the session cache.
+/// This struct holds the global configuration, the session cache and the middlewares.
 /// It is typically wrapped in an [`Arc`] to allow multiple sub-clients to share it safely.
 #[derive(Clone)]
 pub struct ClientContext {
@@ -34,6 +41,10 @@ pub struct ClientContext {
     pub config: ConfigType,
     /// The cache manager for session persistence.
     pub cache: CacheManagerType,
+    /// Hooks applied to every request of every sub-client.
+    pub middleware: MiddlewareStack,
+    /// Metrics registry, if enabled with [`CekUnitClient::enable_metrics`].
+    pub metrics: Option<Arc<Metrics>>,
 }
 
 /// Trait for creating a client from a shared context.
@@ -54,31 +65,44 @@ pub trait FromContext: Sized {
 
 impl FromContext for DashboardClient {
     fn from_ctx(ctx: Arc<ClientContext>) -> Result<Self, ApiError> {
-        DashboardClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())
+        let mut client =
+            DashboardClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())?;
+        client.set_middleware(ctx.middleware.clone());
+        Ok(client)
     }
 }
 
 impl FromContext for InputUserClient {
     fn from_ctx(ctx: Arc<ClientContext>) -> Result<Self, ApiError> {
-        InputUserClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())
+        let mut client =
+            InputUserClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())?;
+        client.set_middleware(ctx.middleware.clone());
+        Ok(client)
     }
 }
 
 impl FromContext for InputDataClient {
     fn from_ctx(ctx: Arc<ClientContext>) -> Result<Self, ApiError> {
-        InputDataClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())
+        let mut client =
+            InputDataClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())?;
+        client.set_middleware(ctx.middleware.clone());
+        Ok(client)
     }
 }
 
 impl FromContext for PicClient {
     fn from_ctx(ctx: Arc<ClientContext>) -> Result<Self, ApiError> {
-        PicClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())
+        let mut client = PicClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())?;
+        client.set_middleware(ctx.middleware.clone());
+        Ok(client)
     }
 }
 
 impl FromContext for UsersClient {
     fn from_ctx(ctx: Arc<ClientContext>) -> Result<Self, ApiError> {
-        UsersClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())
+        let mut client = UsersClient::with_config_and_cache(ctx.config.clone(), ctx.cache.clone())?;
+        client.set_middleware(ctx.middleware.clone());
+        Ok(client)
     }
 }
 
@@ -89,14 +113,14 @@ impl FromContext for UsersClient {
 /// parts of the application.
 ///
 /// # Example
-/// ```
-/// # use cekunit_client::CekUnitClient;
+/// ```no_run
+/// # use librcekunit::CekUnitClient;
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// let mut client = CekUnitClient::new()?;
 ///
 /// // Login and obtain a session
 /// let session = client.login()?;
-/// println!("Logged in at: {}", session.timestamp);
+/// println!("Logged in at: {}", session.logged_in_at);
 ///
 /// // Access the dashboard client
 /// let dashboard = client.dashboard()?;
@@ -110,6 +134,8 @@ impl FromContext for UsersClient {
 pub struct CekUnitClient {
     /// Shared context containing configuration and cache.
     ctx: Arc<ClientContext>,
+    /// Maximum age in seconds after which a cached session is discarded.
+    session_max_age: i64,
     /// Client for login operations.
     auth_client: LoginClient,
     /// Client for logout operations.
@@ -129,13 +155,33 @@ impl CekUnitClient {
     /// - HTTP client construction fails.
     /// - Cache directory cannot be created.
     pub fn new() -> Result<Self, ApiError> {
-        let auth = LoginClient::new()?;
-        let logout = LogoutClient::with_config(auth.config.clone())?;
+        let config = EnvConfig::load()?;
+        Self::with_config_and_cache(config, CacheManager::new()?)
+    }
+
+    /// Creates a client with a given configuration and cache manager.
+    ///
+    /// Useful when the configuration is assembled in code (see
+    /// [`ConfigLoader`](crate::handler::config::ConfigLoader)), or to keep the session
+    /// cache in a temporary directory, e.g. in tests.
+    ///
+    /// # Arguments
+    /// * `config` - The environment configuration.
+    /// * `cache` - The cache manager shared by all sub-clients.
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] if an HTTP client cannot be built.
+    pub fn with_config_and_cache(config: EnvConfig, cache: CacheManager) -> Result<Self, ApiError> {
+        let auth = LoginClient::with_config_and_cache(config.clone(), cache.clone())?;
+        let logout = LogoutClient::with_config_and_cache(config.clone(), cache.clone())?;
         let ctx = Arc::new(ClientContext {
-            config: auth.config.clone(),
-            cache: auth.cache_manager().clone(),
+            config,
+            cache,
+            middleware: MiddlewareStack::new(),
+            metrics: None,
         });
         Ok(Self {
+            session_max_age: ctx.config.session_max_age,
             ctx,
             auth_client: auth,
             logout_client: logout,
@@ -156,6 +202,168 @@ impl CekUnitClient {
         self.auth_client.login()
     }
 
+    /// Replaces the source of the login password.
+    ///
+    /// See [`LoginClient::set_credential_provider`].
+    pub fn set_credential_provider(&mut self, provider: Arc<dyn CredentialProvider>) {
+        self.auth_client.set_credential_provider(provider);
+    }
+
+    /// Registers a middleware for all requests made through this client.
+    ///
+    /// The middleware applies to login, logout and session probes, and to every
+    /// sub-client created afterwards (sub-clients obtained earlier are not affected).
+    /// Middlewares run in registration order before a request and in reverse order
+    /// after it.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use librcekunit::CekUnitClient;
+    /// use librcekunit::middleware::{HeaderMiddleware, LoggingMiddleware};
+    /// use std::sync::Arc;
+    ///
+    /// let mut client = CekUnitClient::new()?;
+    /// client.add_middleware(Arc::new(LoggingMiddleware::new(tracing::Level::INFO)));
+    /// client.add_middleware(Arc::new(HeaderMiddleware::new().header("X-Source", "cron")?));
+    /// # Ok::<(), librcekunit::ApiError>(())
+    /// ```
+    pub fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) {
+        let ctx = Arc::make_mut(&mut self.ctx);
+        ctx.middleware.push(middleware);
+        self.auth_client.set_middleware(ctx.middleware.clone());
+        self.logout_client.set_middleware(ctx.middleware.clone());
+    }
+
+    /// Sends all requests made through this client through `transport` instead of
+    /// the network.
+    ///
+    /// Like [`add_middleware`](Self::add_middleware), this applies to login, logout and
+    /// every sub-client created afterwards. Used with a [`Cassette`] to replay recorded
+    /// traffic in offline tests.
+    ///
+    /// [`Cassette`]: crate::middleware::Cassette
+    pub fn set_transport(&mut self, transport: Arc<dyn Transport>) {
+        let ctx = Arc::make_mut(&mut self.ctx);
+        ctx.middleware.set_transport(transport);
+        self.auth_client.set_middleware(ctx.middleware.clone());
+        self.logout_client.set_middleware(ctx.middleware.clone());
+    }
+
+    /// Returns the middlewares registered with [`add_middleware`](Self::add_middleware).
+    pub fn middleware(&self) -> &MiddlewareStack {
+        &self.ctx.middleware
+    }
+
+    /// Starts collecting metrics and returns the registry.
+    ///
+    /// The registry is added as a middleware (see [`add_middleware`](Self::add_middleware))
+    /// and stored in the shared context. Calling this again returns the same registry.
+    pub fn enable_metrics(&mut self) -> Arc<Metrics> {
+        if let Some(metrics) = &self.ctx.metrics {
+            return metrics.clone();
+        }
+        let metrics = Arc::new(Metrics::new());
+        self.add_middleware(metrics.clone());
+        Arc::make_mut(&mut self.ctx).metrics = Some(metrics.clone());
+        metrics
+    }
+
+    /// Records all further traffic into a HAR 1.2 file at `path`.
+    ///
+    /// Shorthand for registering a [`HarRecorder`] with the default body limit; secrets
+    /// are redacted (see the [`har`](crate::middleware::har) module).
+    ///
+    /// # Returns
+    /// The recorder, e.g. to [`flush`](HarRecorder::flush) it or to check how many
+    /// exchanges were captured. The file is also written when the client is dropped.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::IoError`] if the file cannot be written.
+    pub fn record_har(&mut self, path: impl AsRef<Path>) -> Result<Arc<HarRecorder>, ApiError> {
+        let recorder = Arc::new(HarRecorder::create(path)?);
+        self.add_middleware(recorder.clone());
+        Ok(recorder)
+    }
+
+    /// Returns the metrics registry, if [`enable_metrics`](Self::enable_metrics) was called.
+    pub fn metrics(&self) -> Option<Arc<Metrics>> {
+        self.ctx.metrics.clone()
+    }
+
+    /// Returns a logged-in session, reusing the cache when possible.
+    ///
+    /// The cached session is reused if it is marked as logged in, logged in less than
+    /// [`session_max_age`](Self::session_max_age) ago, and still accepted by the server
+    /// (see [`verify_session`](Self::verify_session)). Otherwise a full login is
+    /// performed; a session past its max age is not probed at all.
+    ///
+    /// Every reuse costs one authenticated request to the server.
+    ///
+    /// # Returns
+    /// The valid session data ([`CacheData`]).
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] if the cache cannot be read or the fallback login fails.
+    /// Errors from the liveness probe itself are logged and trigger a new login.
+    pub fn ensure_logged_in(&mut self) -> Result<CacheData, ApiError> {
+        let _span = operation_span!("client.ensure_logged_in").entered();
+        if let Some(cache) = self.ctx.cache.load_fresh(self.session_max_age)?
+            && cache.logged_in
+        {
+            match self.verify_session() {
+                Ok(true) => {
+                    tracing::debug!("reusing cached session");
+                    return Ok(self.ctx.cache.load()?.unwrap_or(cache));
+                }
+                Ok(false) => tracing::info!("cached session no longer valid; logging in again"),
+                Err(e) => tracing::warn!(error = %e, "session probe failed; logging in again"),
+            }
+            self.ctx.middleware.emit(ClientEvent::Relogin);
+        }
+        self.login()
+    }
+
+    /// Returns a guard holding a logged-in session that ends when dropped.
+    ///
+    /// The session is obtained with [`ensure_logged_in`](Self::ensure_logged_in).
+    /// By default, dropping the guard logs out via [`logout`](Self::logout); see
+    /// [`SessionGuard`] for options to keep the session or only clear the cache.
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] if no session could be established.
+    pub fn session(&mut self) -> Result<SessionGuard<'_>, ApiError> {
+        let data = self.ensure_logged_in()?;
+        Ok(SessionGuard::new(self, data))
+    }
+
+    /// Checks with the server whether the cached session is still accepted.
+    ///
+    /// Unlike [`check_session`](Self::check_session), this makes one authenticated
+    /// request. See [`LoginClient::verify_session`] for details.
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] on transport failures or unexpected HTTP statuses.
+    pub fn verify_session(&self) -> Result<bool, ApiError> {
+        self.auth_client.verify_session()
+    }
+
+    /// Returns the maximum age in seconds of a cached session, counted from its login.
+    ///
+    /// [`ensure_logged_in`](Self::ensure_logged_in) discards an older session, however
+    /// recently it was used, and logs in again without asking the server; a younger
+    /// one is still checked with the server on every call.
+    pub fn session_max_age(&self) -> i64 {
+        self.session_max_age
+    }
+
+    /// Overrides the session max-age loaded from `SESSION_MAX_AGE`.
+    ///
+    /// # Arguments
+    /// * `seconds` - The new maximum session age, in seconds.
+    pub fn set_session_max_age(&mut self, seconds: i64) {
+        self.session_max_age = seconds;
+    }
+
     /// Performs logout and clears the session cache.
     ///
     /// This method attempts to log out using the following strategy:
@@ -167,18 +375,21 @@ impl CekUnitClient {
     /// # Errors
     /// Returns [`ApiError`] if the logout request fails after retries.
     pub fn logout(&mut self) -> Result<(), ApiError> {
-        if let Ok(dashboard) = self.dashboard() {
-            if let Ok(token) = dashboard.get_csrf_token() {
-                if self.logout_client.logout_with_token(&token).is_ok() {
-                    return Ok(());
-                }
-            }
+        let _span = operation_span!("client.logout").entered();
+        if let Ok(dashboard) = self.dashboard()
+            && let Ok(token) = dashboard.get_csrf_token()
+            && self.logout_client.logout_with_token(&token).is_ok()
+        {
+            return Ok(());
         }
         self.logout_client.logout()
     }
 
     /// Checks if there is an active session in the cache.
     ///
+    /// This only reads the local cache file; use [`verify_session`](Self::verify_session)
+    /// to ask the server.
+    ///
     /// # Returns
     /// - `Ok(Some(CacheData))` if a valid session exists.
     /// - `Ok(None)` if no session is cached or the cached session is invalid.
@@ -187,11 +398,24 @@ impl CekUnitClient {
         self.auth_client.get_cached_session()
     }
 
+    /// Deletes the local session cache without contacting the server.
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] if the cache file exists but cannot be removed.
+    pub fn clear_session_cache(&self) -> Result<(), ApiError> {
+        self.ctx.cache.clear()
+    }
+
     /// Returns the path to the session cache file.
     pub fn cache_file_path(&self) -> PathBuf {
         self.auth_client.cache_file_path()
     }
 
+    /// Returns the configuration shared by all sub-clients.
+    pub fn config(&self) -> &EnvConfig {
+        &self.ctx.config
+    }
+
     /// Helper method to create a sub-client from the shared context.
     fn make<T>(&self) -> Result<T, ApiError>
     where
@@ -262,3 +486,56 @@ impl CekUnitClient {
         &self.logout_client
     }
 }
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use crate::testing::FakeCekUnit;
+
+    fn logins(fake: &FakeCekUnit) -> usize {
+        fake.requests()
+            .iter()
+            .filter(|r| r.starts_with("POST /login "))
+            .count()
+    }
+
+    #[test]
+    fn test_fresh_session_is_reused() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        let first = client.login().unwrap();
+
+        let session = client.ensure_logged_in().unwrap();
+        assert_eq!(logins(&fake), 1);
+        assert_eq!(session.logged_in_at, first.logged_in_at);
+        assert_eq!(fake.logged_in_sessions(), 1);
+    }
+
+    #[test]
+    fn test_expired_session_logs_in_again() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        let mut session = client.login().unwrap();
+        session.logged_in_at -= 3600;
+        fake.cache().save(&session).unwrap();
+        client.set_session_max_age(600);
+
+        // Using the session does not extend its max age.
+        assert!(client.verify_session().unwrap());
+        let session = client.ensure_logged_in().unwrap();
+        assert_eq!(logins(&fake), 2);
+        assert!(session.is_fresh(600));
+    }
+
+    #[test]
+    fn test_rejected_session_logs_in_again() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        client.login().unwrap();
+        fake.expire_sessions();
+
+        let session = client.ensure_logged_in().unwrap();
+        assert_eq!(logins(&fake), 2);
+        assert!(session.logged_in);
+        assert!(client.verify_session().unwrap());
+    }
+}
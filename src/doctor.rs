@@ -0,0 +1,699 @@
+//! Connectivity and configuration diagnostics.
+//!
+//! [`diagnose`] runs the checks a support request usually starts with and collects
+//! their results in a [`Diagnosis`] instead of stopping at the first failure:
+//!
+//! 1. `config`: the configuration loads and validates (see [`ConfigLoader::diagnose`]).
+//! 2. `cache`: the session cache file is readable, private and fresh.
+//! 3. `dns`: the host of `BASE_URL` resolves.
+//! 4. `connection`: the login page can be fetched, including the TLS handshake.
+//! 5. `csrf`: the login page contains a CSRF token (see [`extract_csrf_token`]).
+//! 6. `login`: the credentials are accepted and the new session works.
+//! 7. One check per page endpoint: the server knows the path (it does not answer 404).
+//!
+//! The listing pages and the data-entry form are fetched with `GET` in the session of
+//! the `login` check, without following redirects. A redirect to the login page means
+//! the session was not accepted, so the check is inconclusive and reported as a
+//! [`CheckStatus::Warn`]. Endpoints that change data, download a table or need a
+//! record id are not requested and, like checks that depend on a failed one, are
+//! reported as [`CheckStatus::Skip`].
+//!
+//! # Example
+//! ```no_run
+//! use librcekunit::CacheManager;
+//! use librcekunit::doctor::diagnose;
+//! use librcekunit::handler::config::ConfigLoader;
+//!
+//! let diagnosis = diagnose(&ConfigLoader::new(), CacheManager::new()?);
+//! print!("{}", diagnosis);
+//! if !diagnosis.is_ok() {
+//!     std::process::exit(1);
+//! }
+//! # Ok::<(), librcekunit::ApiError>(())
+//! ```
+
+use crate::api::auth::utils::cache::{CacheData, CacheManager};
+use crate::api::auth::utils::cookies::add_cookies_to_headers;
+use crate::api::auth::utils::token::extract_csrf_token;
+use crate::client::CekUnitClient;
+use crate::handler::config::ConfigLoader;
+use crate::handler::env::EnvConfig;
+use crate::handler::error::ApiError;
+use crate::handler::report::ConfigReport;
+use reqwest::Url;
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::redirect::Policy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error as _;
+use std::fmt;
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+
+/// Time allowed for each request of the network checks.
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Endpoints whose `GET` only renders a page, so they can be fetched safely.
+const PAGE_ENDPOINTS: &[&str] = &[
+    "DASHBOARD_ENDPOINT",
+    "INPUT_USER_ENDPOINT",
+    "INPUT_DATA_ENDPOINT",
+    "PIC_ENDPOINT",
+    "USERS_ENDPOINT",
+];
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    /// Nothing to do.
+    Pass,
+    /// Works, but probably not as intended.
+    Warn,
+    /// Broken; see the hint.
+    Fail,
+    /// Not run because an earlier check failed.
+    Skip,
+}
+
+impl CheckStatus {
+    /// Returns the label used by [`Diagnosis`]'s `Display` output, e.g. `"FAIL"`.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Pass => "PASS",
+            Self::Warn => "WARN",
+            Self::Fail => "FAIL",
+            Self::Skip => "SKIP",
+        }
+    }
+}
+
+/// The result of one check, with a remediation hint when something is wrong.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Check {
+    /// Short name, e.g. `"dns"` or `"PIC_ENDPOINT"`.
+    pub name: String,
+    /// Outcome of the check.
+    pub status: CheckStatus,
+    /// What was found.
+    pub detail: String,
+    /// What to do about a warning or failure.
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Pass, detail)
+    }
+
+    fn warn(name: &str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Warn, detail).with_hint(hint)
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Fail, detail).with_hint(hint)
+    }
+
+    fn skip(name: &str, reason: &str) -> Self {
+        Self::new(name, CheckStatus::Skip, reason)
+    }
+
+    fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+/// Every check run by [`diagnose`], in order.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Diagnosis {
+    /// Results in the order the checks ran.
+    pub checks: Vec<Check>,
+}
+
+impl Diagnosis {
+    /// Returns `true` if no check failed (warnings and skipped checks are allowed).
+    pub fn is_ok(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Returns the failed checks.
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail)
+    }
+
+    /// Returns the check named `name`, if it ran.
+    pub fn get(&self, name: &str) -> Option<&Check> {
+        self.checks.iter().find(|c| c.name == name)
+    }
+
+    fn push(&mut self, check: Check) -> bool {
+        let ok = check.status != CheckStatus::Fail;
+        self.checks.push(check);
+        ok
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(
+                f,
+                "[{}] {}: {}",
+                check.status.label(),
+                check.name,
+                check.detail
+            )?;
+            if let Some(hint) = &check.hint {
+                writeln!(f, "       hint: {}", hint)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs every check against the configuration of `loader` and the session in `cache`.
+///
+/// The `login` check logs in and replaces the cached session, like
+/// [`CekUnitClient::login`]. Nothing else is changed on the server.
+///
+/// # Arguments
+/// * `loader` - Where the configuration is read from.
+/// * `cache` - The session cache to inspect and to log in with.
+///
+/// # Returns
+/// The results of all checks; use [`Diagnosis::is_ok`] to see whether any failed.
+pub fn diagnose(loader: &ConfigLoader, cache: CacheManager) -> Diagnosis {
+    let mut diagnosis = Diagnosis::default();
+    let config = match loader.resolve() {
+        Ok(vars) => {
+            let (config, report) = EnvConfig::diagnose_vars(&vars);
+            diagnosis.push(check_config(config.as_ref(), &report));
+            config
+        }
+        Err(e) => {
+            diagnosis.push(Check::fail(
+                "config",
+                e.to_string(),
+                "Check the path and syntax of the config or .env file.",
+            ));
+            None
+        }
+    };
+    let Some(config) = config else {
+        for name in ["cache", "dns", "connection", "csrf", "login"] {
+            diagnosis.push(Check::skip(name, "the configuration is invalid"));
+        }
+        return diagnosis;
+    };
+
+    diagnosis.push(check_cache(&cache, config.session_max_age));
+
+    let (http, probe) = match http_client(Policy::default())
+        .and_then(|http| Ok((http, http_client(Policy::none())?)))
+    {
+        Ok(clients) => clients,
+        Err(e) => {
+            diagnosis.push(Check::fail(
+                "connection",
+                format!("cannot create an HTTP client: {}", e),
+                "The TLS backend could not be initialised; check the system certificates.",
+            ));
+            return diagnosis;
+        }
+    };
+
+    let mut reachable = diagnosis.push(check_dns(&config.base_url));
+    let mut login_page = None;
+    if reachable {
+        match check_connection(&http, &config) {
+            Ok((check, page)) => {
+                diagnosis.push(check);
+                login_page = page;
+            }
+            Err(check) => reachable = diagnosis.push(check),
+        }
+    } else {
+        diagnosis.push(Check::skip("connection", "the host does not resolve"));
+    }
+    match &login_page {
+        Some(html) => {
+            diagnosis.push(check_csrf(html));
+        }
+        None => {
+            diagnosis.push(Check::skip("csrf", "the login page was not loaded"));
+        }
+    }
+    let mut session = None;
+    if login_page.is_some() {
+        let (check, data) = check_login(config.clone(), cache);
+        diagnosis.push(check);
+        session = data;
+    } else {
+        diagnosis.push(Check::skip("login", "the login page was not loaded"));
+    }
+
+    for (key, path) in config.endpoints() {
+        let check = match (path, &session) {
+            _ if !reachable => Check::skip(key, "the server is not reachable"),
+            (None, _) => Check::skip(key, "disabled (set to an empty value)"),
+            _ if key == "LOGIN_ENDPOINT" => Check::skip(key, "fetched by the connection check"),
+            _ if !PAGE_ENDPOINTS.contains(&key) => Check::skip(
+                key,
+                "not requested, since it changes data, downloads a table or needs a record id",
+            ),
+            (Some(_), None) => Check::skip(key, "not logged in"),
+            (Some(path), Some(session)) => check_endpoint(&probe, &config, session, key, path),
+        };
+        diagnosis.push(check);
+    }
+    diagnosis
+}
+
+/// Builds the client of the network checks with the given redirect policy.
+fn http_client(redirects: Policy) -> Result<Client, reqwest::Error> {
+    Client::builder()
+        .user_agent(concat!(
+            "librcekunit/",
+            env!("CARGO_PKG_VERSION"),
+            " (doctor)"
+        ))
+        .timeout(TIMEOUT)
+        .redirect(redirects)
+        .build()
+}
+
+fn check_config(config: Option<&EnvConfig>, report: &ConfigReport) -> Check {
+    let warnings: Vec<String> = report.warnings.iter().map(|w| w.to_string()).collect();
+    match config {
+        Some(config) if warnings.is_empty() => Check::pass(
+            "config",
+            format!("{} as {}", config.base_url, config.user_email),
+        ),
+        Some(_) => Check::warn(
+            "config",
+            warnings.join("; "),
+            "The configuration loads, but these values are probably not intended.",
+        ),
+        None => Check::fail(
+            "config",
+            report
+                .errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("; "),
+            "Set the listed variables in the config file, the .env file or the environment.",
+        ),
+    }
+}
+
+fn check_cache(cache: &CacheManager, max_age: i64) -> Check {
+    let path = cache.cache_file_path();
+    if !path.exists() {
+        return Check::pass(
+            "cache",
+            format!("no session cached yet at {}", path.display()),
+        );
+    }
+    #[cfg(unix)]
+    if let Ok(meta) = path.metadata() {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = meta.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Check::warn(
+                "cache",
+                format!(
+                    "{} is readable by other users (mode {:o})",
+                    path.display(),
+                    mode
+                ),
+                format!(
+                    "The file holds session cookies; run `chmod 600 {}`.",
+                    path.display()
+                ),
+            );
+        }
+    }
+    let data = match cache.load() {
+        Ok(Some(data)) => data,
+        Ok(None) => return Check::pass("cache", "no session cached"),
+        Err(e) => {
+            return Check::fail(
+                "cache",
+                e.to_string(),
+                format!(
+                    "Delete {} to start a fresh session; it is recreated at the next login.",
+                    path.display()
+                ),
+            );
+        }
+    };
+    let age = chrono::Utc::now().timestamp() - data.logged_in_at;
+    if age < 0 {
+        Check::warn(
+            "cache",
+            format!("the session is dated {}s in the future", -age),
+            "Check the system clock; sessions are reused until SESSION_MAX_AGE has passed.",
+        )
+    } else if !data.logged_in {
+        Check::pass("cache", format!("logged out, {}", path.display()))
+    } else if data.is_fresh(max_age) {
+        Check::pass(
+            "cache",
+            format!(
+                "session {}s old (max {}s), {}",
+                age,
+                max_age,
+                path.display()
+            ),
+        )
+    } else {
+        Check::warn(
+            "cache",
+            format!(
+                "session {}s old, older than SESSION_MAX_AGE ({}s)",
+                age, max_age
+            ),
+            "The next command logs in again; raise SESSION_MAX_AGE if this happens too often.",
+        )
+    }
+}
+
+fn check_dns(base_url: &str) -> Check {
+    let url = match reqwest::Url::parse(base_url) {
+        Ok(url) => url,
+        Err(e) => return Check::fail("dns", e.to_string(), "BASE_URL is not a valid URL."),
+    };
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return Check::fail(
+            "dns",
+            format!("{} has no host", base_url),
+            "Set BASE_URL to e.g. https://cekunit.example.com.",
+        );
+    };
+    match (host, port).to_socket_addrs() {
+        Ok(mut addrs) => match addrs.next() {
+            Some(addr) => Check::pass("dns", format!("{} resolves to {}", host, addr.ip())),
+            None => Check::fail(
+                "dns",
+                format!("{} has no addresses", host),
+                "Check the host name in BASE_URL and the DNS records.",
+            ),
+        },
+        Err(e) => Check::fail(
+            "dns",
+            format!("{} does not resolve: {}", host, e),
+            "Check the host name in BASE_URL, and the network or VPN connection.",
+        ),
+    }
+}
+
+/// Fetches the login page; returns its HTML if it loaded.
+///
+/// # Errors
+/// Returns the failed check if the server did not answer at all.
+fn check_connection(http: &Client, config: &EnvConfig) -> Result<(Check, Option<String>), Check> {
+    let url = config
+        .full_login_url()
+        .map_err(|e| Check::fail("connection", e.to_string(), "Set LOGIN_ENDPOINT."))?;
+    let response = http.get(&url).send().map_err(|e| connection_error(&e))?;
+    let status = response.status();
+    let detail = format!("GET {} -> HTTP {}", url, status.as_u16());
+    if status.as_u16() == 404 {
+        let check = Check::fail(
+            "connection",
+            detail,
+            "The login page was not found; check that BASE_URL includes any path prefix, and LOGIN_ENDPOINT.",
+        );
+        return Ok((check, None));
+    }
+    if !status.is_success() {
+        let check = Check::fail(
+            "connection",
+            detail,
+            "The server answered with an error; check the server logs or try again later.",
+        );
+        return Ok((check, None));
+    }
+    let html = response.text().unwrap_or_default();
+    let check = Check::pass("connection", detail);
+    Ok((check, Some(html)))
+}
+
+fn connection_error(e: &reqwest::Error) -> Check {
+    let mut causes = Vec::new();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+    let detail = match causes.last() {
+        Some(cause) => format!("{}: {}", e, cause),
+        None => e.to_string(),
+    };
+    let lower = causes.join(" ").to_lowercase();
+    let hint = if ["certificate", "tls", "ssl", "handshake"]
+        .iter()
+        .any(|word| lower.contains(word))
+    {
+        "The TLS handshake failed; check that the certificate is valid for the host and trusted by this machine, or whether the server only speaks http://."
+    } else if e.is_timeout() {
+        "The server did not answer in time; check the network, a proxy or the server load."
+    } else if e.is_connect() {
+        "The host resolves but refuses the connection; check the port in BASE_URL, firewalls and whether the server is running."
+    } else {
+        "Check BASE_URL and the network connection."
+    };
+    Check::fail("connection", detail, hint)
+}
+
+fn check_csrf(html: &str) -> Check {
+    match extract_csrf_token(html) {
+        Ok(_) => Check::pass("csrf", "the login page contains a CSRF token"),
+        Err(_) => Check::fail(
+            "csrf",
+            "the login page contains no `_token` field or `csrf-token` meta tag",
+            "BASE_URL and LOGIN_ENDPOINT may point at another application or at a proxy page.",
+        ),
+    }
+}
+
+/// Logs in and returns the new session for the endpoint checks.
+fn check_login(config: EnvConfig, cache: CacheManager) -> (Check, Option<CacheData>) {
+    let email = config.user_email.clone();
+    let credentials = "Check USER_EMAIL and the password (USER_PASSWORD, USER_PASSWORD_FILE or USER_PASSWORD_COMMAND).";
+    let result = CekUnitClient::with_config_and_cache(config, cache).and_then(|mut client| {
+        client.login()?;
+        let verified = client.verify_session()?;
+        Ok((verified, client.check_session()?))
+    });
+    match result {
+        Ok((true, session)) => (
+            Check::pass("login", format!("logged in as {}", email)),
+            session,
+        ),
+        Ok((false, _)) => (
+            Check::fail(
+                "login",
+                format!(
+                    "{} was not accepted; the server sent the login page again",
+                    email
+                ),
+                credentials,
+            ),
+            None,
+        ),
+        Err(e) => {
+            let hint = match e.kind() {
+                ApiError::LoginFailed(_)
+                | ApiError::Unauthorized
+                | ApiError::ValidationError(_) => credentials,
+                ApiError::CsrfExpired | ApiError::CsrfInvalid(_) => {
+                    "The server rejected the CSRF token; check that cookies are not stripped by a proxy."
+                }
+                _ => "See the error above; run with RUST_LOG=debug for the individual requests.",
+            };
+            (Check::fail("login", e.to_string(), hint), None)
+        }
+    }
+}
+
+/// Fetches a page endpoint in `session`; `http` must not follow redirects.
+fn check_endpoint(
+    http: &Client,
+    config: &EnvConfig,
+    session: &CacheData,
+    key: &str,
+    path: &str,
+) -> Check {
+    let url = format!("{}/{}", config.base_url, path);
+    let cookies: HashMap<String, String> = session
+        .cookies
+        .iter()
+        .map(|c| (c.name.clone(), c.value.expose().clone()))
+        .collect();
+    let mut headers = HeaderMap::new();
+    if let Err(e) = add_cookies_to_headers(&mut headers, &cookies) {
+        return Check::fail(key, e.to_string(), "Log in again to replace the session.");
+    }
+    let response = match http.get(&url).headers(headers).send() {
+        Ok(response) => response,
+        Err(e) => return Check::fail(key, e.to_string(), "Check the network connection."),
+    };
+    let status = response.status().as_u16();
+    let detail = format!("GET /{} -> HTTP {}", path, status);
+    if response.status().is_redirection() {
+        let target = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| Url::parse(&url).ok()?.join(l).ok());
+        let login = config.full_login_url().ok();
+        let to_login = match (&target, &login) {
+            (Some(target), Some(login)) => {
+                target.as_str().trim_end_matches('/') == login.trim_end_matches('/')
+            }
+            _ => false,
+        };
+        if to_login {
+            return Check::warn(
+                key,
+                format!("{}, redirected to the login page", detail),
+                "The session was not accepted for this page, so the path could not be checked; try again or check it in a browser.",
+            );
+        }
+        if let Some(target) = target {
+            return Check::pass(key, format!("{}, redirected to {}", detail, target));
+        }
+    }
+    match status {
+        404 => Check::fail(
+            key,
+            detail,
+            format!(
+                "The server has no route at /{}; set {} to the path it uses.",
+                path, key
+            ),
+        ),
+        500.. => Check::warn(
+            key,
+            detail,
+            "The server answered with an error; check the server logs.",
+        ),
+        _ => Check::pass(key, detail),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that skipped checks and warnings do not count as failures.
+    #[test]
+    fn test_is_ok_and_display() {
+        let mut diagnosis = Diagnosis::default();
+        diagnosis.push(Check::pass("config", "ok"));
+        diagnosis.push(Check::warn("cache", "stale", "log in"));
+        diagnosis.push(Check::skip("dns", "skipped"));
+        assert!(diagnosis.is_ok());
+
+        diagnosis.push(Check::fail("login", "rejected", "check the password"));
+        assert!(!diagnosis.is_ok());
+        assert_eq!(diagnosis.failures().count(), 1);
+        assert_eq!(diagnosis.get("cache").unwrap().status, CheckStatus::Warn);
+        assert_eq!(
+            diagnosis.to_string(),
+            "[PASS] config: ok\n\
+             [WARN] cache: stale\n       hint: log in\n\
+             [SKIP] dns: skipped\n\
+             [FAIL] login: rejected\n       hint: check the password\n"
+        );
+    }
+
+    /// Tests that an invalid configuration skips the network checks.
+    #[test]
+    fn test_invalid_config_skips_network() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let loader = ConfigLoader::new()
+            .process_env(false)
+            .env_file(dir.path().join("missing.env"))
+            .set("BASE_URL", "not a url");
+        let cache = CacheManager::with_paths(dir.path().into(), dir.path().join("session.json"));
+        let diagnosis = diagnose(&loader, cache);
+        assert_eq!(diagnosis.checks[0].status, CheckStatus::Fail);
+        assert!(
+            diagnosis.checks[1..]
+                .iter()
+                .all(|c| c.status == CheckStatus::Skip)
+        );
+    }
+
+    /// Tests that an unresolvable host fails the DNS check.
+    #[test]
+    fn test_dns_failure() {
+        let check = check_dns("https://cekunit.invalid");
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.hint.unwrap().contains("BASE_URL"));
+        assert_eq!(check_dns("not a url").status, CheckStatus::Fail);
+    }
+
+    /// Tests that a redirect to the login page is inconclusive rather than a pass.
+    #[test]
+    fn test_endpoint_redirects() {
+        let mut server = mockito::Server::new();
+        let login = server
+            .mock("GET", "/pic")
+            .match_header("cookie", "laravel_session=abc")
+            .with_status(302)
+            .with_header("location", &format!("{}/login", server.url()))
+            .create();
+        server
+            .mock("GET", "/users")
+            .with_status(302)
+            .with_header("location", "/users/")
+            .create();
+        server.mock("GET", "/people").with_status(404).create();
+
+        let vars = [
+            ("BASE_URL", server.url()),
+            ("USER_EMAIL", "a@example.com".to_string()),
+            ("USER_PASSWORD", "secret123".to_string()),
+            ("LOGIN_ENDPOINT", "login".to_string()),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        let config = EnvConfig::from_vars(&vars).unwrap();
+        let cookie = crate::api::auth::utils::cache::Cookie {
+            name: "laravel_session".to_string(),
+            value: crate::handler::redact::Secret::new("abc".to_string()),
+            domain: server.url(),
+            path: "/".to_string(),
+            http_only: true,
+            secure: false,
+            extra: Default::default(),
+        };
+        let session = CacheData::new(vec![cookie], "token".into(), true);
+        let http = http_client(Policy::none()).unwrap();
+
+        let check = check_endpoint(&http, &config, &session, "PIC_ENDPOINT", "pic");
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.detail.contains("login page"));
+        login.assert();
+        let check = check_endpoint(&http, &config, &session, "USERS_ENDPOINT", "users");
+        assert_eq!(check.status, CheckStatus::Pass);
+        let check = check_endpoint(&http, &config, &session, "PIC_ENDPOINT", "people");
+        assert_eq!(check.status, CheckStatus::Fail);
+    }
+}
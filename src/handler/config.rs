@@ -0,0 +1,650 @@
+//! Layered configuration loading.
+//!
+//! This module resolves the variables that make up an [`EnvConfig`] from several
+//! sources, each overriding the previous one:
+//!
+//! 1. Built-in defaults (the endpoint paths in [`DEFAULT_ENDPOINTS`]).
+//! 2. A TOML or JSON config file (see [`ConfigFile`]).
+//! 3. A `.env` file.
+//! 4. Process environment variables.
+//! 5. Explicit overrides set on the [`ConfigLoader`].
+//!
+//! Every endpoint is optional. An endpoint that is unset in all sources falls back to
+//! its default path; setting it to an empty value disables it, so operations that
+//! need it fail with [`EnvError::EndpointNotConfigured`].
+//!
+//! All sources are normalised to environment variable names (`BASE_URL`,
+//! `LOGIN_ENDPOINT`, ...) before [`EnvConfig::from_vars`] validates them.
+//!
+//! A config file looks like this (the JSON form uses the same structure):
+//!
+//! ```toml
+//! base_url = "https://cekunit.example.com"
+//! user_email = "admin@example.com"
+//! session_max_age = 7200
+//!
+//! [endpoints]
+//! login = "login"
+//! logout = "logout"
+//! dashboard = "dashboard"
+//! ```
+
+use crate::handler::credentials::{CredentialProvider, PasswordSource};
+use crate::handler::env::{EnvConfig, EnvError};
+use crate::handler::report::ConfigReport;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Resolved configuration variables, keyed by environment variable name.
+pub type VarMap = BTreeMap<String, String>;
+
+/// Environment variable naming an explicit config file.
+pub const CONFIG_PATH_VAR: &str = "CEKUNIT_CONFIG";
+
+/// Environment variable naming an explicit `.env` file.
+pub const ENV_FILE_PATH_VAR: &str = "CEKUNIT_ENV_FILE";
+
+pub use crate::handler::redact::REDACTED;
+
+/// Endpoint names used in the `[endpoints]` table, paired with their variable names.
+pub const ENDPOINT_KEYS: &[(&str, &str)] = &[
+    ("login", "LOGIN_ENDPOINT"),
+    ("logout", "LOGOUT_ENDPOINT"),
+    ("dashboard", "DASHBOARD_ENDPOINT"),
+    ("cekunit_export", "CEKUNIT_EXPORT_ENDPOINT"),
+    ("cekunit_unique", "CEKUNIT_UNIQUE_ENDPOINT"),
+    (
+        "cekunit_delete_category",
+        "CEKUNIT_DELETE_CATEGORY_ENDPOINT",
+    ),
+    ("delete_all", "DELETE_ALL_ENDPOINT"),
+    ("cekunit_item", "CEKUNIT_ITEM_ENDPOINT"),
+    ("input_user", "INPUT_USER_ENDPOINT"),
+    ("input_user_export", "INPUT_USER_EXPORT_ENDPOINT"),
+    ("input_data", "INPUT_DATA_ENDPOINT"),
+    ("pic", "PIC_ENDPOINT"),
+    ("input_pic", "INPUT_PIC_ENDPOINT"),
+    ("pic_item", "PIC_ITEM_ENDPOINT"),
+    ("users", "USERS_ENDPOINT"),
+    ("users_item", "USERS_ITEM_ENDPOINT"),
+];
+
+/// Default endpoint paths, keyed by variable name.
+///
+/// These are guesses at the routes of a stock CekUnit installation, derived from the
+/// variable names; they have not been checked against a real panel. Run `cekunit
+/// doctor` against your server and set the endpoints that differ.
+///
+/// Applied as the lowest configuration layer unless disabled with
+/// [`ConfigLoader::default_endpoints`].
+pub const DEFAULT_ENDPOINTS: &[(&str, &str)] = &[
+    ("LOGIN_ENDPOINT", "login"),
+    ("LOGOUT_ENDPOINT", "logout"),
+    ("DASHBOARD_ENDPOINT", "dashboard"),
+    ("CEKUNIT_EXPORT_ENDPOINT", "cekunit/export"),
+    ("CEKUNIT_UNIQUE_ENDPOINT", "cekunit/unique"),
+    (
+        "CEKUNIT_DELETE_CATEGORY_ENDPOINT",
+        "cekunit/delete-category",
+    ),
+    ("DELETE_ALL_ENDPOINT", "cekunit/delete-all"),
+    ("CEKUNIT_ITEM_ENDPOINT", "cekunit"),
+    ("INPUT_USER_ENDPOINT", "input-user"),
+    ("INPUT_USER_EXPORT_ENDPOINT", "input-user/export"),
+    ("INPUT_DATA_ENDPOINT", "input-data"),
+    ("PIC_ENDPOINT", "pic"),
+    ("INPUT_PIC_ENDPOINT", "input-pic"),
+    ("PIC_ITEM_ENDPOINT", "pic"),
+    ("USERS_ENDPOINT", "users"),
+    ("USERS_ITEM_ENDPOINT", "users"),
+];
+
+/// Top-level config file keys, paired with their variable names.
+const SCALAR_KEYS: &[(&str, &str)] = &[
+    ("base_url", "BASE_URL"),
+    ("user_email", "USER_EMAIL"),
+    ("user_password", "USER_PASSWORD"),
+    ("user_password_file", "USER_PASSWORD_FILE"),
+    ("user_password_command", "USER_PASSWORD_COMMAND"),
+];
+
+/// On-disk structure of a TOML or JSON config file.
+///
+/// Every field is optional; missing values fall through to the other layers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Base URL of the CekUnit installation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Login email.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_email: Option<String>,
+    /// Login password. Prefer one of the other password sources.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_password: Option<String>,
+    /// File containing the login password.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_password_file: Option<String>,
+    /// Shell command that prints the login password.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_password_command: Option<String>,
+    /// Prompt for the password on the terminal at login.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_password_prompt: Option<bool>,
+    /// Session max-age in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_max_age: Option<i64>,
+    /// Endpoint paths keyed by the names in [`ENDPOINT_KEYS`].
+    #[serde(default)]
+    pub endpoints: BTreeMap<String, String>,
+}
+
+#[cfg(feature = "toml")]
+fn from_toml(content: &str) -> Result<ConfigFile, String> {
+    toml::from_str(content).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "toml"))]
+fn from_toml(_content: &str) -> Result<ConfigFile, String> {
+    Err("TOML config files need the `toml` feature of librcekunit".into())
+}
+
+impl ConfigFile {
+    /// Reads a config file, choosing the format from its extension.
+    ///
+    /// Files ending in `.json` are parsed as JSON; everything else as TOML, which needs
+    /// the `toml` cargo feature (on by default).
+    ///
+    /// # Errors
+    /// Returns [`EnvError::ConfigFile`] if the file cannot be read or parsed, or is a
+    /// TOML file and the `toml` feature is off.
+    pub fn from_path(path: &Path) -> Result<Self, EnvError> {
+        let display = path.display().to_string();
+        let content = fs::read_to_string(path)
+            .map_err(|e| EnvError::ConfigFile(display.clone(), e.to_string()))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            serde_json::from_str(&content).map_err(|e| EnvError::ConfigFile(display, e.to_string()))
+        } else {
+            from_toml(&content).map_err(|e| EnvError::ConfigFile(display, e))
+        }
+    }
+
+    /// Converts the file contents into variables.
+    ///
+    /// # Errors
+    /// Returns [`EnvError::Invalid`] if the `[endpoints]` table contains an unknown name.
+    pub fn to_vars(&self) -> Result<VarMap, EnvError> {
+        let mut vars = VarMap::new();
+        let scalars = [
+            &self.base_url,
+            &self.user_email,
+            &self.user_password,
+            &self.user_password_file,
+            &self.user_password_command,
+        ];
+        for ((_, var), value) in SCALAR_KEYS.iter().zip(scalars) {
+            if let Some(value) = value {
+                vars.insert(var.to_string(), value.clone());
+            }
+        }
+        if let Some(prompt) = self.user_password_prompt {
+            vars.insert("USER_PASSWORD_PROMPT".into(), prompt.to_string());
+        }
+        if let Some(secs) = self.session_max_age {
+            vars.insert("SESSION_MAX_AGE".into(), secs.to_string());
+        }
+        for (name, path) in &self.endpoints {
+            let var = endpoint_var(name).ok_or_else(|| {
+                EnvError::Invalid(
+                    format!("endpoints.{}", name),
+                    "unknown endpoint name".into(),
+                )
+            })?;
+            vars.insert(var.to_string(), path.clone());
+        }
+        Ok(vars)
+    }
+}
+
+/// Returns the variable name for an `[endpoints]` table key.
+pub fn endpoint_var(name: &str) -> Option<&'static str> {
+    ENDPOINT_KEYS
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, var)| *var)
+}
+
+/// Builder that resolves configuration from layered sources.
+///
+/// # Example
+/// ```no_run
+/// use librcekunit::handler::config::ConfigLoader;
+///
+/// let config = ConfigLoader::new()
+///     .config_file("/etc/cekunit/staging.toml")
+///     .env_file(".env.staging")
+///     .set("SESSION_MAX_AGE", "600")
+///     .load()?;
+/// println!("{}", config.redacted_dump());
+/// # Ok::<(), librcekunit::handler::env::EnvError>(())
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct ConfigLoader {
+    /// Explicit config file; required to exist when set.
+    config_file: Option<PathBuf>,
+    /// Explicit `.env` file; required to exist when set.
+    env_file: Option<PathBuf>,
+    /// Whether process environment variables are consulted.
+    use_process_env: bool,
+    /// Whether [`DEFAULT_ENDPOINTS`] fill in unset endpoints.
+    use_default_endpoints: bool,
+    /// Values that override every other source.
+    overrides: VarMap,
+}
+
+impl fmt::Debug for ConfigLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Overrides may hold the password; show only their names.
+        f.debug_struct("ConfigLoader")
+            .field("config_file", &self.config_file)
+            .field("env_file", &self.env_file)
+            .field("use_process_env", &self.use_process_env)
+            .field("use_default_endpoints", &self.use_default_endpoints)
+            .field("overrides", &self.overrides.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLoader {
+    /// Creates a loader that uses all default sources.
+    ///
+    /// Without explicit paths, the config file is taken from `CEKUNIT_CONFIG`, or
+    /// else the first existing of `./cekunit.toml`, `./cekunit.json` and
+    /// `config.toml` in the platform config directory. The `.env` file is taken from
+    /// `CEKUNIT_ENV_FILE`, or else `./.env` and its parent directories.
+    pub fn new() -> Self {
+        Self {
+            config_file: None,
+            env_file: None,
+            use_process_env: true,
+            use_default_endpoints: true,
+            overrides: VarMap::new(),
+        }
+    }
+
+    /// Uses the given config file instead of searching for one.
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
+
+    /// Uses the given `.env` file instead of searching for one.
+    pub fn env_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.env_file = Some(path.into());
+        self
+    }
+
+    /// Enables or disables reading process environment variables (enabled by default).
+    pub fn process_env(mut self, enabled: bool) -> Self {
+        self.use_process_env = enabled;
+        self
+    }
+
+    /// Enables or disables the built-in [`DEFAULT_ENDPOINTS`] (enabled by default).
+    ///
+    /// When disabled, only endpoints set explicitly in some source are available.
+    pub fn default_endpoints(mut self, enabled: bool) -> Self {
+        self.use_default_endpoints = enabled;
+        self
+    }
+
+    /// Sets a variable that overrides every other source.
+    ///
+    /// # Arguments
+    /// * `key` - Environment variable name, e.g. `"BASE_URL"`.
+    /// * `value` - The value to use.
+    pub fn set(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.overrides.insert(key.into(), value.into());
+        self
+    }
+
+    /// Resolves all layers into a single variable map without validating it.
+    ///
+    /// # Errors
+    /// Returns [`EnvError::ConfigFile`] if an explicitly requested file is missing or a
+    /// discovered file cannot be parsed.
+    pub fn resolve(&self) -> Result<VarMap, EnvError> {
+        let mut vars = if self.use_default_endpoints {
+            defaults()
+        } else {
+            VarMap::new()
+        };
+
+        if let Some(path) = self.find_config_file() {
+            tracing::debug!(path = %path.display(), "loading config file");
+            vars.extend(ConfigFile::from_path(&path)?.to_vars()?);
+        }
+
+        vars.extend(self.read_env_file()?);
+
+        if self.use_process_env {
+            let known = known_vars();
+            vars.extend(env::vars().filter(|(k, _)| known.contains(&k.as_str())));
+        }
+
+        vars.extend(self.overrides.clone());
+        Ok(vars)
+    }
+
+    /// Resolves all layers and builds a validated [`EnvConfig`].
+    ///
+    /// # Errors
+    /// Returns [`EnvError`] if a source cannot be read or the result is invalid.
+    /// Validation problems are always returned in [`EnvError::Report`], even a single
+    /// one.
+    ///
+    /// A password given in `USER_PASSWORD` is not kept: the config gets a
+    /// [`PasswordSource::Variable`] that reads it again from these sources at login.
+    /// A password [`set`](Self::set) on the loader is already in memory, so it is kept
+    /// as a [`PasswordSource::Value`] instead, and not copied into that loader.
+    pub fn load(&self) -> Result<EnvConfig, EnvError> {
+        let mut config = EnvConfig::from_vars(&self.resolve()?)?;
+        if let PasswordSource::Value(_) = config.password
+            && !self.overrides.contains_key("USER_PASSWORD")
+        {
+            config.password = PasswordSource::Variable(Box::new(self.clone()));
+        }
+        Ok(config)
+    }
+
+    /// Resolves all layers and reports every problem without failing.
+    ///
+    /// If a source cannot be read, the report contains that error alone, since the
+    /// remaining values would be incomplete.
+    pub fn diagnose(&self) -> ConfigReport {
+        match self.resolve() {
+            Ok(vars) => EnvConfig::diagnose_vars(&vars).1,
+            Err(e) => {
+                let mut report = ConfigReport::default();
+                report.error(e);
+                report
+            }
+        }
+    }
+
+    /// Returns the config file to read, if any.
+    fn find_config_file(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.config_file {
+            return Some(path.clone());
+        }
+        if let Ok(path) = env::var(CONFIG_PATH_VAR)
+            && !path.trim().is_empty()
+        {
+            return Some(PathBuf::from(path));
+        }
+        let mut candidates = vec![PathBuf::from("cekunit.toml"), PathBuf::from("cekunit.json")];
+        if let Some(dirs) = ProjectDirs::from("com", "cekunit", "libcekunit") {
+            candidates.push(dirs.config_dir().join("config.toml"));
+        }
+        candidates.into_iter().find(|p| p.is_file())
+    }
+
+    /// Reads the `.env` layer without modifying the process environment.
+    fn read_env_file(&self) -> Result<VarMap, EnvError> {
+        let explicit = self.env_file.clone().or_else(|| {
+            env::var(ENV_FILE_PATH_VAR)
+                .ok()
+                .filter(|p| !p.trim().is_empty())
+                .map(PathBuf::from)
+        });
+        let iter = match &explicit {
+            Some(path) => dotenvy::from_path_iter(path)
+                .map_err(|e| EnvError::ConfigFile(path.display().to_string(), e.to_string()))?,
+            None => match dotenvy::dotenv_iter() {
+                Ok(iter) => iter,
+                Err(_) => return Ok(VarMap::new()),
+            },
+        };
+        let mut vars = VarMap::new();
+        for item in iter {
+            let (key, value) = item.map_err(|e| {
+                let name = explicit
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| ".env".into());
+                EnvError::ConfigFile(name, e.to_string())
+            })?;
+            vars.insert(key, value);
+        }
+        Ok(vars)
+    }
+}
+
+/// Returns the built-in default variables.
+fn defaults() -> VarMap {
+    DEFAULT_ENDPOINTS
+        .iter()
+        .map(|(var, path)| (var.to_string(), path.to_string()))
+        .collect()
+}
+
+/// Returns every variable name that [`EnvConfig::from_vars`] understands.
+fn known_vars() -> Vec<&'static str> {
+    SCALAR_KEYS
+        .iter()
+        .chain(ENDPOINT_KEYS)
+        .map(|(_, var)| *var)
+        .chain(["USER_PASSWORD_PROMPT", "SESSION_MAX_AGE"])
+        .collect()
+}
+
+impl EnvConfig {
+    /// Converts the effective configuration back into the config file structure.
+    ///
+    /// The password is replaced by [`REDACTED`] unless `include_secrets` is `true`.
+    pub fn to_config_file(&self, include_secrets: bool) -> ConfigFile {
+        let mut file = ConfigFile {
+            base_url: Some(self.base_url.clone()),
+            user_email: Some(self.user_email.clone()),
+            session_max_age: Some(self.session_max_age),
+            // Disabled endpoints are written as "" so they stay disabled on reload.
+            endpoints: ENDPOINT_KEYS
+                .iter()
+                .zip(self.endpoints())
+                .map(|((name, _), (_, path))| (name.to_string(), path.unwrap_or("").to_string()))
+                .collect(),
+            ..ConfigFile::default()
+        };
+        match &self.password {
+            PasswordSource::Value(_) | PasswordSource::Variable(_) if include_secrets => {
+                file.user_password = self
+                    .password
+                    .password(&self.user_email)
+                    .ok()
+                    .map(|password| password.expose().clone())
+            }
+            PasswordSource::Value(_) | PasswordSource::Variable(_) => {
+                file.user_password = Some(REDACTED.to_string())
+            }
+            PasswordSource::File(path) => {
+                file.user_password_file = Some(path.display().to_string())
+            }
+            PasswordSource::Command(cmd) => file.user_password_command = Some(cmd.clone()),
+            PasswordSource::Prompt => file.user_password_prompt = Some(true),
+        }
+        file
+    }
+
+    /// Renders the effective configuration as TOML with secrets redacted.
+    ///
+    /// The output is a valid config file apart from the redacted password. Needs the
+    /// `toml` cargo feature (on by default).
+    #[cfg(feature = "toml")]
+    pub fn redacted_dump(&self) -> String {
+        toml::to_string_pretty(&self.to_config_file(false))
+            .unwrap_or_else(|e| format!("# failed to render configuration: {}\n", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Returns a loader isolated from the process environment and discovered files.
+    fn isolated(dir: &TempDir) -> ConfigLoader {
+        let env_file = dir.path().join("empty.env");
+        fs::write(&env_file, "").unwrap();
+        let config_file = dir.path().join("empty.json");
+        fs::write(&config_file, "{}").unwrap();
+        ConfigLoader::new()
+            .process_env(false)
+            .env_file(env_file)
+            .config_file(config_file)
+    }
+
+    /// Writes a complete TOML config file and returns its path.
+    #[cfg(feature = "toml")]
+    fn write_full_toml(dir: &TempDir) -> PathBuf {
+        let mut toml = String::from(
+            "base_url = \"https://file.example.com/\"\n\
+             user_email = \"file@example.com\"\n\
+             user_password = \"filepassword\"\n\
+             [endpoints]\n",
+        );
+        for (name, _) in ENDPOINT_KEYS {
+            toml.push_str(&format!("{} = \"/{}\"\n", name, name));
+        }
+        let path = dir.path().join("cekunit.toml");
+        fs::write(&path, toml).unwrap();
+        path
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml_file_layer() {
+        let dir = TempDir::new().unwrap();
+        let path = write_full_toml(&dir);
+        let config = isolated(&dir).config_file(path).load().unwrap();
+        assert_eq!(config.base_url, "https://file.example.com");
+        assert_eq!(config.login_endpoint.as_deref(), Some("login"));
+        assert_eq!(config.users_item_endpoint.as_deref(), Some("users_item"));
+    }
+
+    #[test]
+    fn test_json_file_layer() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cekunit.json");
+        fs::write(
+            &path,
+            r#"{"base_url": "https://json.example.com", "endpoints": {"login": "auth/login"}}"#,
+        )
+        .unwrap();
+        let vars = isolated(&dir).config_file(path).resolve().unwrap();
+        assert_eq!(vars["BASE_URL"], "https://json.example.com");
+        assert_eq!(vars["LOGIN_ENDPOINT"], "auth/login");
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_layer_precedence() {
+        let dir = TempDir::new().unwrap();
+        let path = write_full_toml(&dir);
+        let env_file = dir.path().join(".env.test");
+        fs::write(
+            &env_file,
+            "USER_EMAIL=dotenv@example.com\nLOGIN_ENDPOINT=dotenv-login\n",
+        )
+        .unwrap();
+        let config = isolated(&dir)
+            .config_file(path)
+            .env_file(env_file)
+            .set("LOGIN_ENDPOINT", "override-login")
+            .load()
+            .unwrap();
+        assert_eq!(config.user_email, "dotenv@example.com");
+        assert_eq!(config.login_endpoint.as_deref(), Some("override-login"));
+        assert_eq!(config.logout_endpoint.as_deref(), Some("logout"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_unknown_keys_rejected() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("bad.toml");
+        fs::write(&path, "[endpoints]\nlogn = \"login\"\n").unwrap();
+        let result = isolated(&dir).config_file(&path).resolve();
+        assert!(matches!(result, Err(EnvError::Invalid(_, _))));
+
+        fs::write(&path, "base_ur = \"https://x\"\n").unwrap();
+        let result = isolated(&dir).config_file(&path).resolve();
+        assert!(matches!(result, Err(EnvError::ConfigFile(_, _))));
+    }
+
+    #[cfg(not(feature = "toml"))]
+    #[test]
+    fn test_toml_needs_feature() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cekunit.toml");
+        fs::write(&path, "base_url = \"https://x\"\n").unwrap();
+        let result = isolated(&dir).config_file(&path).resolve();
+        assert!(matches!(result, Err(EnvError::ConfigFile(_, e)) if e.contains("`toml` feature")));
+    }
+
+    #[test]
+    fn test_password_override_is_not_copied() {
+        let dir = TempDir::new().unwrap();
+        let env_file = dir.path().join(".env");
+        fs::write(
+            &env_file,
+            "BASE_URL=https://x.example.com\nUSER_EMAIL=a@example.com\nUSER_PASSWORD=dotenv-pass\n",
+        )
+        .unwrap();
+        let loader = isolated(&dir).env_file(env_file);
+        let config = loader.load().unwrap();
+        assert!(matches!(&config.password, PasswordSource::Variable(l) if l.overrides.is_empty()));
+
+        let config = loader.set("USER_PASSWORD", "override-pass").load().unwrap();
+        assert!(
+            matches!(&config.password, PasswordSource::Value(v) if v.expose() == "override-pass")
+        );
+    }
+
+    #[test]
+    fn test_missing_explicit_file() {
+        let dir = TempDir::new().unwrap();
+        let result = isolated(&dir)
+            .env_file(dir.path().join("does-not-exist.env"))
+            .resolve();
+        assert!(matches!(result, Err(EnvError::ConfigFile(_, _))));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_redacted_dump() {
+        let dir = TempDir::new().unwrap();
+        let path = write_full_toml(&dir);
+        let config = isolated(&dir).config_file(path).load().unwrap();
+        let dump = config.redacted_dump();
+        assert!(!dump.contains("filepassword"));
+        assert!(dump.contains(REDACTED));
+
+        let reparsed: ConfigFile = toml::from_str(&dump).unwrap();
+        assert_eq!(reparsed.endpoints.len(), ENDPOINT_KEYS.len());
+    }
+}
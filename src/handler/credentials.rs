@@ -0,0 +1,219 @@
+//! Password sources for login.
+//!
+//! The password is not kept in [`EnvConfig`](crate::handler::env::EnvConfig). Instead
+//! the configuration records *where* to get it ([`PasswordSource`]), and
+//! [`LoginClient`](crate::api::auth::LoginClient) asks a [`CredentialProvider`] for it
+//! each time it logs in; the value is dropped once the login request is sent.
+//! Supported sources, selected by variable:
+//!
+//! | Variable                | Source                                               |
+//! | ----------------------- | ---------------------------------------------------- |
+//! | `USER_PASSWORD`         | The variable itself, read again at every login (discouraged outside development). |
+//! | `USER_PASSWORD_FILE`    | Contents of a file, e.g. a Docker/Kubernetes secret. |
+//! | `USER_PASSWORD_COMMAND` | Standard output of a shell command, e.g. `pass show cekunit`. |
+//! | `USER_PASSWORD_PROMPT`  | Interactive prompt on the terminal when set to `true`. |
+//!
+//! Exactly one of them must be set.
+
+use crate::handler::config::ConfigLoader;
+use crate::handler::env::EnvError;
+use crate::handler::redact::Secret;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Variables that select a [`PasswordSource`], in order of precedence.
+pub const PASSWORD_KEYS: &[&str] = &[
+    "USER_PASSWORD",
+    "USER_PASSWORD_FILE",
+    "USER_PASSWORD_COMMAND",
+    "USER_PASSWORD_PROMPT",
+];
+
+/// Supplies the login password on demand.
+///
+/// Implement this to plug in other secret stores (an OS keyring, a vault client, ...)
+/// and register it with [`LoginClient::set_credential_provider`].
+///
+/// [`LoginClient::set_credential_provider`]: crate::api::auth::LoginClient::set_credential_provider
+pub trait CredentialProvider: Send + Sync {
+    /// Returns the password for `email`.
+    ///
+    /// Called once per login; implementations should not cache the result.
+    ///
+    /// # Errors
+    /// Returns [`EnvError::Credential`] if the password cannot be obtained.
+    fn password(&self, email: &str) -> Result<Secret<String>, EnvError>;
+
+    /// Short description of the source for logs and error messages.
+    ///
+    /// Must not contain the password itself.
+    fn describe(&self) -> String;
+}
+
+/// Where the login password comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordSource {
+    /// The password itself, from `USER_PASSWORD` in the variables given to
+    /// [`EnvConfig::from_vars`](crate::handler::env::EnvConfig::from_vars).
+    Value(Secret<String>),
+    /// `USER_PASSWORD`, looked up again in the sources of this loader at every login.
+    ///
+    /// [`ConfigLoader::load`] uses this instead of [`Value`](Self::Value), so the
+    /// password is not held for the life of the process. The loader never carries a
+    /// `USER_PASSWORD` set with [`ConfigLoader::set`].
+    Variable(Box<ConfigLoader>),
+    /// A file whose contents (minus the trailing newline) are the password.
+    File(PathBuf),
+    /// A shell command that prints the password on standard output. Its standard input
+    /// is closed, so it cannot consume input meant for the calling process.
+    Command(String),
+    /// Ask on the controlling terminal.
+    Prompt,
+}
+
+impl PasswordSource {
+    /// Returns the variable name that selects this source.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Value(_) | Self::Variable(_) => "USER_PASSWORD",
+            Self::File(_) => "USER_PASSWORD_FILE",
+            Self::Command(_) => "USER_PASSWORD_COMMAND",
+            Self::Prompt => "USER_PASSWORD_PROMPT",
+        }
+    }
+
+    fn error(&self, reason: impl Into<String>) -> EnvError {
+        EnvError::Credential(self.key().to_string(), reason.into())
+    }
+}
+
+impl CredentialProvider for PasswordSource {
+    fn password(&self, email: &str) -> Result<Secret<String>, EnvError> {
+        let password = match self {
+            Self::Value(value) => value.expose().clone(),
+            Self::Variable(loader) => loader
+                .resolve()
+                .map_err(|e| self.error(e.to_string()))?
+                .remove("USER_PASSWORD")
+                .ok_or_else(|| self.error("no longer set"))?,
+            Self::File(path) => fs::read_to_string(path)
+                .map(strip_newline)
+                .map_err(|e| self.error(format!("cannot read {}: {}", path.display(), e)))?,
+            Self::Command(cmd) => run_command(cmd).map_err(|e| self.error(e))?,
+            Self::Prompt => rpassword::prompt_password(format!("Password for {}: ", email))
+                .map_err(|e| self.error(format!("cannot prompt on terminal: {}", e)))?,
+        };
+        if password.is_empty() {
+            return Err(self.error("password is empty"));
+        }
+        Ok(Secret::new(password))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Value(_) | Self::Variable(_) => "USER_PASSWORD".into(),
+            Self::File(path) => format!("file {}", path.display()),
+            Self::Command(cmd) => format!("command `{}`", cmd),
+            Self::Prompt => "interactive prompt".into(),
+        }
+    }
+}
+
+/// Removes one trailing `\n` or `\r\n`, as left by `echo` and most editors.
+fn strip_newline(mut s: String) -> String {
+    if s.ends_with('\n') {
+        s.pop();
+        if s.ends_with('\r') {
+            s.pop();
+        }
+    }
+    s
+}
+
+/// Runs `cmd` through the platform shell and returns its standard output.
+fn run_command(cmd: &str) -> Result<String, String> {
+    let mut command = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    let output = command
+        .arg(cmd)
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("cannot run `{}`: {}", cmd, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "`{}` exited with {}: {}",
+            cmd,
+            output.status,
+            stderr.trim()
+        ));
+    }
+    String::from_utf8(output.stdout)
+        .map(strip_newline)
+        .map_err(|_| format!("`{}` printed invalid UTF-8", cmd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("password");
+        fs::write(&path, "s3cret-pass\n").unwrap();
+        let source = PasswordSource::File(path.clone());
+        assert_eq!(source.password("a@b.c").unwrap().expose(), "s3cret-pass");
+
+        fs::write(&path, "").unwrap();
+        assert!(matches!(
+            source.password("a@b.c"),
+            Err(EnvError::Credential(k, _)) if k == "USER_PASSWORD_FILE"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_source() {
+        let source = PasswordSource::Command("printf 'from-command\\n'".into());
+        assert_eq!(source.password("a@b.c").unwrap().expose(), "from-command");
+
+        let failing = PasswordSource::Command("echo nope >&2; exit 3".into());
+        let err = failing.password("a@b.c").unwrap_err().to_string();
+        assert!(err.contains("nope"), "{err}");
+    }
+
+    #[test]
+    fn test_variable_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let env_file = dir.path().join(".env");
+        fs::write(&env_file, "USER_PASSWORD=first-pass\n").unwrap();
+        let config_file = dir.path().join("empty.json");
+        fs::write(&config_file, "{}").unwrap();
+        let loader = ConfigLoader::new()
+            .process_env(false)
+            .config_file(config_file)
+            .env_file(&env_file);
+        let source = PasswordSource::Variable(Box::new(loader));
+        assert!(!format!("{:?}", source).contains("first-pass"));
+        assert_eq!(source.password("a@b.c").unwrap().expose(), "first-pass");
+
+        fs::write(&env_file, "USER_PASSWORD=rotated-pass\n").unwrap();
+        assert_eq!(source.password("a@b.c").unwrap().expose(), "rotated-pass");
+    }
+
+    #[test]
+    fn test_debug_hides_value() {
+        let source = PasswordSource::Value(Secret::from("hunter22"));
+        assert!(!format!("{:?}", source).contains("hunter22"));
+    }
+}
@@ -7,12 +7,59 @@
 //! The error type implements [`std::error::Error`] via `thiserror` and provides
 //! convenient conversion traits and helper methods for creating errors from
 //! common sources.
+//!
+//! Every client maps HTTP failures through [`ErrorContext`]: it records the operation,
+//! method, redacted URL, status, a truncated body and the retry count, and wraps the
+//! mapped error in [`ApiError::Context`]. Transport failures keep the underlying
+//! [`reqwest::Error`] as their [`source`](std::error::Error::source).
+//!
+//! For automation, [`ApiError::code`] gives every variant a stable string code and
+//! [`ApiError::exit_code`] maps it to one of the process exit codes in [`exit_code`].
 
 use crate::handler::env::EnvError;
+use crate::handler::redact::{redact_url, scrub, snippet};
 use reqwest::StatusCode;
-use serde_json;
+use reqwest::blocking::Response;
+use serde_json::{self, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
 use thiserror::Error;
 
+/// Maximum number of characters of a response body kept in an [`ErrorContext`].
+pub const BODY_SNIPPET_LEN: usize = 200;
+
+/// Process exit codes returned by [`ApiError::exit_code`].
+///
+/// The values are stable: new variants are mapped onto an existing code rather
+/// than changing these. `2` is left to argument parsing errors.
+pub mod exit_code {
+    /// Any error without a more specific code.
+    pub const GENERAL: u8 = 1;
+    /// The configuration is missing or invalid ([`EnvError`](crate::handler::env::EnvError)).
+    pub const CONFIG: u8 = 3;
+    /// The server rejected the credentials.
+    pub const LOGIN_FAILED: u8 = 4;
+    /// The session is missing or expired, or the CSRF token was rejected.
+    pub const SESSION: u8 = 5;
+    /// The account may not perform the operation (HTTP 403).
+    pub const FORBIDDEN: u8 = 6;
+    /// The record or page does not exist (HTTP 404).
+    pub const NOT_FOUND: u8 = 7;
+    /// The server rejected the submitted data (HTTP 422).
+    pub const VALIDATION: u8 = 8;
+    /// The server is rate-limiting the client (HTTP 429).
+    pub const RATE_LIMITED: u8 = 9;
+    /// The server failed (HTTP 5xx).
+    pub const SERVER: u8 = 10;
+    /// The server could not be reached or did not answer in time.
+    pub const NETWORK: u8 = 11;
+    /// The session cache or a local file could not be read or written.
+    pub const LOCAL_IO: u8 = 12;
+    /// The server answered with something the client does not understand.
+    pub const UNEXPECTED_RESPONSE: u8 = 13;
+}
+
 /// Represents all errors that can occur in the CekUnit API client.
 ///
 /// Each variant carries additional context where appropriate.
@@ -27,10 +74,35 @@ pub enum ApiError {
 
     /// The request timed out.
     ///
-    /// This occurs when the server does not respond within the configured timeout.
+    /// Reqwest timeouts are reported as [`Transport`](Self::Transport); use
+    /// [`ApiError::is_timeout`] to detect both.
     #[error("Request timeout")]
     RequestTimeout,
 
+    /// The request could not be sent or its response could not be read.
+    ///
+    /// The underlying [`reqwest::Error`] is available via `source()`.
+    #[error("{message}: {source}")]
+    Transport {
+        /// What was being attempted.
+        message: String,
+        /// The transport error.
+        #[source]
+        source: Arc<reqwest::Error>,
+    },
+
+    /// An error annotated with details of the request that caused it.
+    ///
+    /// Use [`ApiError::kind`] to get the underlying error regardless of context.
+    #[error("{context}: {error}")]
+    Context {
+        /// The underlying error.
+        #[source]
+        error: Box<ApiError>,
+        /// Details of the failed request.
+        context: Box<ErrorContext>,
+    },
+
     /// Login failed.
     ///
     /// The string provides additional context, such as the HTTP status or error message.
@@ -144,7 +216,8 @@ impl ApiError {
     /// Creates an appropriate [`ApiError`] from an HTTP status code and optional response body.
     ///
     /// This function maps known status codes to specific error variants and provides
-    /// a preview of the response body for client errors (422, etc.).
+    /// a preview of the response body for client errors (422, etc.). The preview is
+    /// passed through [`scrub`] so cookies, tokens and NIK numbers are masked.
     ///
     /// # Arguments
     /// * `status` - The HTTP status code from the response.
@@ -153,7 +226,12 @@ impl ApiError {
     /// # Returns
     /// An `ApiError` variant corresponding to the status code.
     pub fn from_status(status: StatusCode, body: Option<&str>) -> Self {
-        let body_preview = body.unwrap_or("").split('<').next().unwrap_or("").trim();
+        let body = scrub(body.unwrap_or(""));
+        let message = json_message(&body);
+        let body_preview = match &message {
+            Some(message) => message.as_str(),
+            None => body.split('<').next().unwrap_or("").trim(),
+        };
         match status.as_u16() {
             401 => Self::Unauthorized,
             403 => Self::Forbidden,
@@ -177,19 +255,336 @@ impl ApiError {
     /// # Returns
     /// An `ApiError` variant that best represents the underlying error.
     pub fn from_reqwest_error(err: reqwest::Error, context: &str) -> Self {
-        if err.is_timeout() {
-            return Self::RequestTimeout;
-        }
-        if err.is_connect() {
-            return Self::RequestFailed(format!("Connection failed ({}): {}", context, err));
+        let message = if err.is_timeout() {
+            format!("Request timed out ({})", context)
+        } else if err.is_connect() {
+            format!("Connection failed ({})", context)
+        } else {
+            context.to_string()
+        };
+        Self::Transport {
+            message,
+            source: Arc::new(err),
         }
-        Self::RequestFailed(format!("{}: {}", context, err))
     }
 
     /// Convenience constructor for `CsrfTokenNotFound`.
     pub fn csrf_not_found() -> Self {
         Self::CsrfTokenNotFound
     }
+
+    /// Wraps this error in [`ApiError::Context`].
+    ///
+    /// If the error already has a context, the new one replaces it.
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        Self::Context {
+            error: Box::new(self.into_kind()),
+            context: Box::new(context),
+        }
+    }
+
+    /// Returns the underlying error, looking through [`ApiError::Context`].
+    pub fn kind(&self) -> &ApiError {
+        match self {
+            Self::Context { error, .. } => error.kind(),
+            other => other,
+        }
+    }
+
+    /// Consumes the error and returns the underlying error without context.
+    pub fn into_kind(self) -> ApiError {
+        match self {
+            Self::Context { error, .. } => error.into_kind(),
+            other => other,
+        }
+    }
+
+    /// Returns the request details, if recorded.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Returns the field errors of a failed form submission.
+    ///
+    /// Filled from Laravel's JSON validation response (`{"message": ..., "errors":
+    /// {"field": ["..."]}}`); empty for other errors.
+    pub fn field_errors(&self) -> &BTreeMap<String, Vec<String>> {
+        static EMPTY: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        self.context().map(|c| &c.fields).unwrap_or(&EMPTY)
+    }
+
+    /// Returns a stable, machine-readable code for the error, e.g. `"validation_failed"`.
+    ///
+    /// Context is looked through, so a wrapped error has the code of its kind.
+    /// Configuration errors use [`EnvError::code`].
+    ///
+    /// | Code | Variant |
+    /// |------|---------|
+    /// | `http_error` | [`RequestFailed`](Self::RequestFailed) |
+    /// | `timeout` | [`RequestTimeout`](Self::RequestTimeout), timed-out [`Transport`](Self::Transport) |
+    /// | `connection_failed` | [`Transport`](Self::Transport) that could not connect |
+    /// | `transport_error` | other [`Transport`](Self::Transport) |
+    /// | `login_failed` | [`LoginFailed`](Self::LoginFailed) |
+    /// | `logout_failed` | [`LogoutFailed`](Self::LogoutFailed) |
+    /// | `not_authenticated` | [`NotAuthenticated`](Self::NotAuthenticated) |
+    /// | `csrf_not_found` | [`CsrfTokenNotFound`](Self::CsrfTokenNotFound) |
+    /// | `csrf_expired` | [`CsrfExpired`](Self::CsrfExpired) |
+    /// | `csrf_invalid` | [`CsrfInvalid`](Self::CsrfInvalid) |
+    /// | `validation_failed` | [`ValidationError`](Self::ValidationError) |
+    /// | `rate_limited` | [`TooManyRequests`](Self::TooManyRequests) |
+    /// | `not_found` | [`ResourceNotFound`](Self::ResourceNotFound) |
+    /// | `unauthorized` | [`Unauthorized`](Self::Unauthorized) |
+    /// | `forbidden` | [`Forbidden`](Self::Forbidden) |
+    /// | `server_error` | [`ServerError`](Self::ServerError) |
+    /// | `cache_error` | [`CacheError`](Self::CacheError) |
+    /// | `json_error` | [`JsonError`](Self::JsonError) |
+    /// | `io_error` | [`IoError`](Self::IoError) |
+    /// | `html_parse_error` | [`HtmlParseError`](Self::HtmlParseError) |
+    /// | `other` | [`Other`](Self::Other) |
+    pub fn code(&self) -> &'static str {
+        match self.kind() {
+            Self::RequestFailed(_) => "http_error",
+            Self::RequestTimeout => "timeout",
+            Self::Transport { source, .. } if source.is_timeout() => "timeout",
+            Self::Transport { source, .. } if source.is_connect() => "connection_failed",
+            Self::Transport { .. } => "transport_error",
+            Self::LoginFailed(_) => "login_failed",
+            Self::LogoutFailed(_) => "logout_failed",
+            Self::NotAuthenticated => "not_authenticated",
+            Self::CsrfTokenNotFound => "csrf_not_found",
+            Self::CsrfExpired => "csrf_expired",
+            Self::CsrfInvalid(_) => "csrf_invalid",
+            Self::ValidationError(_) => "validation_failed",
+            Self::TooManyRequests => "rate_limited",
+            Self::ResourceNotFound => "not_found",
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden => "forbidden",
+            Self::ServerError(_) => "server_error",
+            Self::CacheError(_) => "cache_error",
+            Self::EnvError(e) => e.code(),
+            Self::JsonError(_) => "json_error",
+            Self::IoError(_) => "io_error",
+            Self::HtmlParseError(_) => "html_parse_error",
+            Self::Other(_) | Self::Context { .. } => "other",
+        }
+    }
+
+    /// Returns the process exit code for the error; see [`exit_code`].
+    pub fn exit_code(&self) -> u8 {
+        match self.kind() {
+            Self::EnvError(e) => e.exit_code(),
+            Self::LoginFailed(_) => exit_code::LOGIN_FAILED,
+            Self::NotAuthenticated
+            | Self::Unauthorized
+            | Self::CsrfExpired
+            | Self::CsrfInvalid(_) => exit_code::SESSION,
+            Self::Forbidden => exit_code::FORBIDDEN,
+            Self::ResourceNotFound => exit_code::NOT_FOUND,
+            Self::ValidationError(_) => exit_code::VALIDATION,
+            Self::TooManyRequests => exit_code::RATE_LIMITED,
+            Self::ServerError(_) => exit_code::SERVER,
+            Self::RequestTimeout | Self::Transport { .. } => exit_code::NETWORK,
+            Self::CacheError(_) | Self::IoError(_) => exit_code::LOCAL_IO,
+            Self::RequestFailed(_)
+            | Self::CsrfTokenNotFound
+            | Self::JsonError(_)
+            | Self::HtmlParseError(_) => exit_code::UNEXPECTED_RESPONSE,
+            Self::LogoutFailed(_) | Self::Other(_) | Self::Context { .. } => exit_code::GENERAL,
+        }
+    }
+
+    /// Returns the HTTP status associated with this error, if any.
+    pub fn status(&self) -> Option<StatusCode> {
+        if let Some(status) = self.context().and_then(|c| c.status) {
+            return StatusCode::from_u16(status).ok();
+        }
+        let code = match self.kind() {
+            Self::Unauthorized => 401,
+            Self::Forbidden => 403,
+            Self::ResourceNotFound => 404,
+            Self::CsrfExpired => 419,
+            Self::ValidationError(_) => 422,
+            Self::TooManyRequests => 429,
+            Self::ServerError(code) => *code,
+            Self::Transport { source, .. } => return source.status(),
+            _ => return None,
+        };
+        StatusCode::from_u16(code).ok()
+    }
+
+    /// Returns `true` if the request timed out.
+    pub fn is_timeout(&self) -> bool {
+        match self.kind() {
+            Self::RequestTimeout => true,
+            Self::Transport { source, .. } => source.is_timeout(),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if repeating the same request may succeed.
+    ///
+    /// This covers timeouts, connection failures, rate limiting (429) and server
+    /// errors (5xx).
+    pub fn is_retryable(&self) -> bool {
+        match self.kind() {
+            Self::RequestTimeout | Self::TooManyRequests | Self::ServerError(_) => true,
+            Self::Transport { source, .. } => source.is_timeout() || source.is_connect(),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the error means the session is missing, expired or
+    /// rejected, so logging in again may help.
+    pub fn is_auth_error(&self) -> bool {
+        matches!(
+            self.kind(),
+            Self::Unauthorized | Self::NotAuthenticated | Self::CsrfExpired | Self::CsrfInvalid(_)
+        )
+    }
+}
+
+/// Details of the request behind an error.
+///
+/// Build one per request with [`ErrorContext::new`], then use [`transport`] for send
+/// failures and [`check`] / [`check_allow_redirect`] on the response. All clients
+/// go through these methods, so every error carries the same information.
+///
+/// [`transport`]: ErrorContext::transport
+/// [`check`]: ErrorContext::check
+/// [`check_allow_redirect`]: ErrorContext::check_allow_redirect
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// Name of the library operation, e.g. `"dashboard.list"`.
+    pub operation: String,
+    /// HTTP method.
+    pub method: String,
+    /// Request URL with credentials and tokens masked.
+    pub url: String,
+    /// HTTP status, if a response was received.
+    pub status: Option<u16>,
+    /// Start of the scrubbed response body (at most [`BODY_SNIPPET_LEN`] characters).
+    pub body: Option<String>,
+    /// Number of retries made before giving up.
+    pub retries: u32,
+    /// Messages per form field from a JSON validation response.
+    ///
+    /// Form posts send `Accept: application/json`, so Laravel answers a failed
+    /// validation with 422 and its `errors` object instead of redirecting back.
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+impl ErrorContext {
+    /// Creates a context for a request; the URL is redacted here.
+    pub fn new(operation: &str, method: &str, url: &str) -> Self {
+        Self {
+            operation: operation.to_string(),
+            method: method.to_string(),
+            url: redact_url(url),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the number of retries made so far.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Maps a transport error, keeping it as the error source.
+    pub fn transport(&self, err: reqwest::Error) -> ApiError {
+        ApiError::from_reqwest_error(err, &self.operation).with_context(self.clone())
+    }
+
+    /// Maps an HTTP status and body with [`ApiError::from_status`] and attaches this
+    /// context, including the status and a body snippet.
+    pub fn status_error(&self, status: StatusCode, body: &str) -> ApiError {
+        let mut context = self.clone();
+        context.status = Some(status.as_u16());
+        context.body = Some(snippet(body, BODY_SNIPPET_LEN)).filter(|b| !b.is_empty());
+        context.fields = json_field_errors(&scrub(body));
+        ApiError::from_status(status, Some(body)).with_context(context)
+    }
+
+    /// Returns the response if its status is 2xx, or the mapped error otherwise.
+    ///
+    /// # Errors
+    /// See [`status_error`](Self::status_error).
+    pub fn check(&self, response: Response) -> Result<Response, ApiError> {
+        self.check_with(response, |status| status.is_success())
+    }
+
+    /// Like [`check`](Self::check), but also accepts 3xx redirects.
+    ///
+    /// Laravel answers successful form posts with a redirect.
+    ///
+    /// # Errors
+    /// See [`status_error`](Self::status_error).
+    pub fn check_allow_redirect(&self, response: Response) -> Result<Response, ApiError> {
+        self.check_with(response, |status| {
+            status.is_success() || status.is_redirection()
+        })
+    }
+
+    fn check_with(
+        &self,
+        response: Response,
+        ok: impl Fn(StatusCode) -> bool,
+    ) -> Result<Response, ApiError> {
+        let status = response.status();
+        if ok(status) {
+            return Ok(response);
+        }
+        let body = response.text().unwrap_or_default();
+        let err = self.status_error(status, &body);
+        tracing::debug!(error = %err, "request failed");
+        Err(err)
+    }
+}
+
+/// Returns the `message` of a JSON error body, as Laravel sends for JSON requests.
+fn json_message(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    value.get("message")?.as_str().map(str::to_string)
+}
+
+/// Returns the `errors` object of a Laravel JSON validation response.
+fn json_field_errors(body: &str) -> BTreeMap<String, Vec<String>> {
+    let Ok(Value::Object(mut value)) = serde_json::from_str::<Value>(body) else {
+        return BTreeMap::new();
+    };
+    let Some(Value::Object(errors)) = value.remove("errors") else {
+        return BTreeMap::new();
+    };
+    errors
+        .into_iter()
+        .map(|(field, messages)| {
+            let messages = match messages {
+                Value::Array(items) => items
+                    .into_iter()
+                    .filter_map(|m| m.as_str().map(str::to_string))
+                    .collect(),
+                Value::String(message) => vec![message],
+                _ => Vec::new(),
+            };
+            (field, messages)
+        })
+        .collect()
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} {}", self.operation, self.method, self.url)?;
+        if let Some(status) = self.status {
+            write!(f, ", HTTP {}", status)?;
+        }
+        if self.retries > 0 {
+            write!(f, ", {} retries", self.retries)?;
+        }
+        write!(f, ")")
+    }
 }
 
 impl From<reqwest::Error> for ApiError {
@@ -233,3 +628,106 @@ impl From<&str> for ApiError {
         Self::Other(msg.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_error_context() {
+        let ctx = ErrorContext::new(
+            "pic.create",
+            "POST",
+            "https://u:p@example.com/input-pic?_token=abc",
+        )
+        .with_retries(2);
+        let err = ctx.status_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "NIK 3201234567890001 sudah terdaftar",
+        );
+
+        assert!(matches!(err.kind(), ApiError::ValidationError(_)));
+        assert_eq!(err.status(), Some(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!err.is_retryable() && !err.is_auth_error());
+
+        let text = err.to_string();
+        assert!(text.starts_with(
+            "pic.create (POST https://example.com/input-pic?_token=********, HTTP 422, 2 retries)"
+        ));
+        assert!(!text.contains("3201234567890001"), "{text}");
+        assert_eq!(
+            err.context().and_then(|c| c.body.as_deref()),
+            Some("NIK **************** sudah terdaftar")
+        );
+    }
+
+    #[test]
+    fn test_classification() {
+        let ctx = ErrorContext::new("dashboard.list", "GET", "https://example.com/dashboard");
+        assert!(ctx.status_error(StatusCode::BAD_GATEWAY, "").is_retryable());
+        assert!(
+            ctx.status_error(StatusCode::TOO_MANY_REQUESTS, "")
+                .is_retryable()
+        );
+        assert!(
+            ctx.status_error(StatusCode::UNAUTHORIZED, "")
+                .is_auth_error()
+        );
+        assert!(ApiError::CsrfExpired.is_auth_error());
+        assert_eq!(ApiError::Forbidden.status(), Some(StatusCode::FORBIDDEN));
+        assert_eq!(ApiError::CacheError("x".into()).status(), None);
+    }
+
+    #[test]
+    fn test_codes_and_field_errors() {
+        let ctx = ErrorContext::new("pic.create", "POST", "https://example.com/input-pic");
+        let err = ctx.status_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            r#"{"message":"The nama field is required. (and 1 more error)","errors":{"nama":["The nama field is required."],"no_hp":["The no hp must be 10 digits."]}}"#,
+        );
+        assert_eq!(err.code(), "validation_failed");
+        assert_eq!(err.exit_code(), exit_code::VALIDATION);
+        assert!(matches!(
+            err.kind(),
+            ApiError::ValidationError(m) if m == "The nama field is required. (and 1 more error)"
+        ));
+        assert_eq!(err.field_errors().len(), 2);
+        assert_eq!(err.field_errors()["nama"], ["The nama field is required."]);
+
+        let plain = ctx.status_error(StatusCode::UNPROCESSABLE_ENTITY, "Invalid data");
+        assert!(plain.field_errors().is_empty());
+        assert_eq!(
+            ctx.status_error(StatusCode::UNAUTHORIZED, "").exit_code(),
+            exit_code::SESSION
+        );
+        assert_eq!(ApiError::CsrfExpired.code(), "csrf_expired");
+        assert_eq!(
+            ApiError::IoError("x".into()).exit_code(),
+            exit_code::LOCAL_IO
+        );
+        let env = ApiError::from(EnvError::NotFound("BASE_URL".into()));
+        assert_eq!(
+            (env.code(), env.exit_code()),
+            ("config_missing", exit_code::CONFIG)
+        );
+    }
+
+    #[test]
+    fn test_transport_keeps_source() {
+        use std::error::Error as _;
+
+        let ctx = ErrorContext::new("auth.csrf", "GET", "http://127.0.0.1:1/login");
+        let reqwest_err = reqwest::blocking::Client::new()
+            .get("http://127.0.0.1:1/login")
+            .send()
+            .unwrap_err();
+        let err = ctx.transport(reqwest_err);
+
+        assert!(err.is_retryable());
+        assert_eq!(err.code(), "connection_failed");
+        assert_eq!(err.exit_code(), exit_code::NETWORK);
+        assert_eq!(err.status(), None);
+        let inner = err.source().expect("context has a source");
+        assert!(inner.source().is_some(), "transport keeps reqwest::Error");
+    }
+}
@@ -1,5 +1,14 @@
+pub mod config;
+pub mod credentials;
 pub mod env;
 pub mod error;
+pub mod redact;
+pub mod report;
+pub mod telemetry;
 
+pub use config::*;
+pub use credentials::*;
 pub use env::*;
 pub use error::*;
+pub use redact::*;
+pub use report::*;
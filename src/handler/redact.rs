@@ -0,0 +1,283 @@
+//! Keeping secrets and personal data out of logs and errors.
+//!
+//! - [`Secret`] wraps passwords, CSRF tokens and cookie values so that `Debug` and
+//!   `Display` print [`REDACTED`] instead of the value.
+//! - [`scrub`] masks session cookies, tokens, passwords and NIK/KTP numbers in text
+//!   taken from HTTP responses before it is copied into an error or a log line.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Placeholder shown instead of secret values.
+pub const REDACTED: &str = "********";
+
+/// A value that must not appear in logs, errors or debug output.
+///
+/// `Debug` and `Display` print [`REDACTED`]; use [`expose`](Self::expose) where the
+/// real value is needed, e.g. when building a request. Serialization is transparent,
+/// so the session cache still stores the value itself.
+///
+/// # Example
+/// ```
+/// use librcekunit::handler::redact::Secret;
+///
+/// let token = Secret::new(String::from("abc123"));
+/// assert_eq!(format!("{:?}", token), "********");
+/// assert_eq!(token.expose(), "abc123");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    /// Wraps a value.
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Returns the wrapped value.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// Unwraps the value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl Secret<String> {
+    /// Returns the wrapped string as `&str`.
+    pub fn expose_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Length of an Indonesian NIK (the number on a KTP identity card).
+const NIK_LEN: usize = 16;
+
+/// Markers whose value runs until a `;`, `&`, quote or whitespace.
+///
+/// Matched case-insensitively; covers Laravel session and XSRF cookies,
+/// `remember_web_*` cookies and form-encoded tokens and passwords.
+const VALUE_MARKERS: &[&str] = &[
+    "_session=",
+    "xsrf-token=",
+    "remember_web_",
+    "_token=",
+    "password=",
+];
+
+/// Markers whose value is a quoted HTML attribute or JSON string.
+const QUOTED_MARKERS: &[&str] = &[
+    "name=\"_token\" value=\"",
+    "name=\"csrf-token\" content=\"",
+    "\"password\":\"",
+    "\"_token\":\"",
+];
+
+/// Header names whose whole line is masked.
+const HEADER_MARKERS: &[&str] = &["set-cookie:", "cookie:"];
+
+/// Masks secrets and personal data in text from an HTTP response.
+///
+/// Replaces with [`REDACTED`]:
+/// - the rest of any `Cookie:` / `Set-Cookie:` line,
+/// - session, XSRF and remember-me cookie values,
+/// - `_token` and `password` values in form, HTML and JSON notation,
+///
+/// and replaces every standalone run of 16 digits (a NIK/KTP number) with asterisks.
+///
+/// # Example
+/// ```
+/// use librcekunit::handler::redact::scrub;
+///
+/// let body = "NIK 3201234567890001 rejected; laravel_session=abc123";
+/// assert_eq!(
+///     scrub(body),
+///     "NIK **************** rejected; laravel_session=********"
+/// );
+/// ```
+pub fn scrub(text: &str) -> String {
+    let mut out = mask_nik(text);
+    for marker in HEADER_MARKERS {
+        out = mask_after(&out, marker, |c| c == '\n' || c == '\r');
+    }
+    for marker in VALUE_MARKERS {
+        out = mask_after(&out, marker, |c| {
+            matches!(c, ';' | '&' | '"' | '\'' | '<' | ',') || c.is_whitespace()
+        });
+    }
+    for marker in QUOTED_MARKERS {
+        out = mask_after(&out, marker, |c| c == '"');
+    }
+    out
+}
+
+/// Query parameters whose values are masked by [`redact_url`].
+const SENSITIVE_PARAMS: &[&str] = &["_token", "token", "password", "email"];
+
+/// Returns `url` with credentials and sensitive query values masked.
+///
+/// Removes a `user:password@` prefix from the authority, masks the values of
+/// `_token`, `token`, `password` and `email` query parameters, and applies [`scrub`]
+/// to the rest (so a NIK used as a search term is masked too).
+///
+/// # Example
+/// ```
+/// use librcekunit::handler::redact::redact_url;
+///
+/// assert_eq!(
+///     redact_url("https://admin:pw@example.com/pic?_token=abc&page=2"),
+///     "https://example.com/pic?_token=********&page=2"
+/// );
+/// ```
+pub fn redact_url(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, tail) = rest.split_at(authority_end);
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+
+    let (path, query) = tail.split_once('?').unwrap_or((tail, ""));
+    let mut out = String::with_capacity(url.len());
+    if !scheme.is_empty() {
+        out.push_str(scheme);
+        out.push_str("://");
+    }
+    out.push_str(host);
+    out.push_str(path);
+    if !query.is_empty() {
+        let params: Vec<String> = query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((key, _)) if SENSITIVE_PARAMS.contains(&key.to_ascii_lowercase().as_str()) => {
+                    format!("{}={}", key, REDACTED)
+                }
+                _ => pair.to_string(),
+            })
+            .collect();
+        out.push('?');
+        out.push_str(&params.join("&"));
+    }
+    scrub(&out)
+}
+
+/// Returns at most `max_chars` characters of scrubbed `body`, whitespace collapsed.
+///
+/// Used for the body snippet stored in error contexts.
+pub fn snippet(body: &str, max_chars: usize) -> String {
+    let scrubbed = scrub(body);
+    let collapsed = scrubbed.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(max_chars) {
+        Some((cut, _)) => format!("{}…", &collapsed[..cut]),
+        None => collapsed,
+    }
+}
+
+/// Replaces the text following each case-insensitive `marker` up to `end`.
+fn mask_after(text: &str, marker: &str, end: impl Fn(char) -> bool) -> String {
+    // ASCII lowercasing keeps byte offsets identical to `text`.
+    let lower = text.to_ascii_lowercase();
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find(marker) {
+        let value_start = pos + found + marker.len();
+        let value_end = text[value_start..]
+            .find(&end)
+            .map_or(text.len(), |i| value_start + i);
+        out.push_str(&text[pos..value_start]);
+        if value_end > value_start {
+            out.push_str(REDACTED);
+        }
+        pos = value_end;
+    }
+    out.push_str(&text[pos..]);
+    out
+}
+
+/// Replaces every run of exactly [`NIK_LEN`] ASCII digits with asterisks.
+fn mask_nik(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut digits = String::new();
+    let flush = |digits: &mut String, out: &mut String| {
+        if digits.len() == NIK_LEN {
+            out.extend(std::iter::repeat_n('*', NIK_LEN));
+        } else {
+            out.push_str(digits);
+        }
+        digits.clear();
+    };
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+        } else {
+            flush(&mut digits, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut digits, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_hides_value() {
+        let secret = Secret::<String>::from("hunter22");
+        assert_eq!(format!("{} {:?}", secret, secret), "******** ********");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"hunter22\"");
+    }
+
+    #[test]
+    fn test_scrub_cookies_and_tokens() {
+        let body = "Set-Cookie: laravel_session=abc; path=/\n\
+                    <input type=\"hidden\" name=\"_token\" value=\"tok123\">\n\
+                    email=a%40b.c&password=hunter22&_token=tok123";
+        let scrubbed = scrub(body);
+        for leaked in ["abc", "tok123", "hunter22"] {
+            assert!(!scrubbed.contains(leaked), "{leaked} leaked: {scrubbed}");
+        }
+        assert!(scrubbed.contains("email=a%40b.c"));
+    }
+
+    #[test]
+    fn test_snippet_truncates() {
+        assert_eq!(snippet("  a\n  b  ", 10), "a b");
+        assert_eq!(snippet("abcdef", 3), "abc…");
+    }
+
+    #[test]
+    fn test_scrub_nik_only_exact_length() {
+        assert_eq!(scrub("3201234567890001"), "****************");
+        assert_eq!(scrub("id 12345"), "id 12345");
+        assert_eq!(scrub("32012345678900012"), "32012345678900012");
+    }
+}
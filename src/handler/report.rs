@@ -0,0 +1,142 @@
+//! Aggregated configuration diagnostics.
+//!
+//! [`ConfigReport`] collects every problem found while building an [`EnvConfig`]
+//! instead of stopping at the first one, so a fresh `.env` can be fixed in one pass.
+//! It is returned by [`EnvConfig::diagnose`] and wrapped in [`EnvError::Report`] when
+//! [`EnvConfig::load`] finds any error.
+//!
+//! [`EnvConfig`]: crate::handler::env::EnvConfig
+//! [`EnvConfig::diagnose`]: crate::handler::env::EnvConfig::diagnose
+//! [`EnvConfig::load`]: crate::handler::env::EnvConfig::load
+
+use crate::handler::env::EnvError;
+use std::fmt;
+
+/// A configuration value that is accepted but probably not what was intended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigWarning {
+    /// Name of the variable the warning is about, e.g. `"BASE_URL"`.
+    pub key: String,
+    /// Human-readable explanation.
+    pub message: String,
+}
+
+impl ConfigWarning {
+    /// Creates a warning for `key`.
+    pub fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}': {}", self.key, self.message)
+    }
+}
+
+/// Every error and warning found in a configuration.
+///
+/// # Example
+/// ```no_run
+/// use librcekunit::EnvConfig;
+///
+/// let report = EnvConfig::diagnose();
+/// for error in &report.errors {
+///     eprintln!("error: {}", error);
+/// }
+/// for warning in &report.warnings {
+///     eprintln!("warning: {}", warning);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConfigReport {
+    /// Problems that prevent the configuration from loading.
+    pub errors: Vec<EnvError>,
+    /// Suspicious values that do not prevent loading.
+    pub warnings: Vec<ConfigWarning>,
+}
+
+impl ConfigReport {
+    /// Returns `true` if no errors were found (warnings are allowed).
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Records an error.
+    pub fn error(&mut self, error: EnvError) {
+        self.errors.push(error);
+    }
+
+    /// Records a warning.
+    pub fn warn(&mut self, key: impl Into<String>, message: impl Into<String>) {
+        self.warnings.push(ConfigWarning::new(key, message));
+    }
+
+    /// Unwraps `result`, recording its error and returning `None` on failure.
+    pub fn check<T>(&mut self, result: Result<T, EnvError>) -> Option<T> {
+        result.map_err(|e| self.error(e)).ok()
+    }
+
+    /// Converts the report into a result.
+    ///
+    /// # Errors
+    /// Returns [`EnvError::Report`] with every error and warning if at least one error
+    /// was recorded, even if it is the only one. [`EnvError::errors`] lists them.
+    pub fn into_result(self) -> Result<(), EnvError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(EnvError::Report(self))
+        }
+    }
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.errors.is_empty() && self.warnings.is_empty() {
+            return write!(f, "configuration OK");
+        }
+        write!(
+            f,
+            "{} configuration error(s), {} warning(s)",
+            self.errors.len(),
+            self.warnings.len()
+        )?;
+        for error in &self.errors {
+            write!(f, "\n  error: {}", error)?;
+        }
+        for warning in &self.warnings {
+            write!(f, "\n  warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_result() {
+        assert!(ConfigReport::default().into_result().is_ok());
+
+        let mut report = ConfigReport::default();
+        report.warn("BASE_URL", "uses plain HTTP");
+        assert!(report.clone().into_result().is_ok());
+
+        report.error(EnvError::NotFound("USER_EMAIL".into()));
+        let err = report.clone().into_result().unwrap_err();
+        assert!(matches!(err, EnvError::Report(ref r) if r.warnings.len() == 1));
+        assert!(matches!(err.errors(), [EnvError::NotFound(_)]));
+
+        report.error(EnvError::Empty("USER_PASSWORD".into()));
+        let err = report.into_result().unwrap_err();
+        let text = err.to_string();
+        assert!(matches!(err, EnvError::Report(ref r) if r.errors.len() == 2));
+        assert!(text.contains("USER_EMAIL") && text.contains("USER_PASSWORD"));
+        assert!(text.contains("plain HTTP"));
+    }
+}
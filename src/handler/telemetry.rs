@@ -0,0 +1,148 @@
+//! Tracing spans for library operations.
+//!
+//! Every public operation (login, logout, each dashboard, PIC, users and input call)
+//! runs inside an `INFO` span named after the operation, e.g. `dashboard.list` or
+//! `auth.login`. The same name is used in [`ErrorContext`] and [`RequestInfo`], so
+//! spans, errors and middleware events can be correlated. Span fields:
+//!
+//! | Field        | Recorded                                                   |
+//! | ------------ | ---------------------------------------------------------- |
+//! | `operation`  | When the span starts; same as the span name.               |
+//! | `page`, `id`, `no`, `column`, `format`, ... | When the span starts, if the operation takes them. |
+//! | `endpoint`   | After each request: URL path, without host or query.       |
+//! | `status`     | After each request that received a response.               |
+//! | `retries`    | After each request: earlier attempts of the same request.  |
+//! | `elapsed_ms` | After each request: duration of the request.               |
+//!
+//! When an operation makes several requests, the last one wins. Passwords, tokens,
+//! cookies, search terms and form data are never recorded.
+//!
+//! Events are emitted through `tracing`; without a `tracing` subscriber they are
+//! forwarded to the `log` crate, so `env_logger` keeps working.
+//!
+//! [`ErrorContext`]: crate::handler::error::ErrorContext
+//! [`RequestInfo`]: crate::middleware::RequestInfo
+
+use std::time::Duration;
+use tracing::Span;
+
+/// Creates the `INFO` span for a library operation.
+///
+/// Declares the fields recorded later by [`record_request`]; extra fields given after
+/// the name are recorded immediately.
+macro_rules! operation_span {
+    ($name:literal $(, $($fields:tt)+)?) => {
+        tracing::info_span!(
+            $name,
+            operation = $name,
+            $($($fields)+,)?
+            endpoint = tracing::field::Empty,
+            status = tracing::field::Empty,
+            retries = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+        )
+    };
+}
+
+pub(crate) use operation_span;
+
+/// Records the outcome of one request on an operation span.
+///
+/// # Arguments
+/// * `span` - The operation span, normally [`Span::current`].
+/// * `endpoint` - URL path of the request.
+/// * `retries` - Earlier attempts of the same request.
+/// * `status` - HTTP status, or `None` if no response was received.
+/// * `elapsed` - Duration of the request.
+pub(crate) fn record_request(
+    span: &Span,
+    endpoint: &str,
+    retries: u32,
+    status: Option<u16>,
+    elapsed: Duration,
+) {
+    let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+    span.record("endpoint", endpoint);
+    span.record("retries", retries);
+    span.record("elapsed_ms", elapsed_ms);
+    match status {
+        Some(status) => {
+            span.record("status", status);
+            tracing::debug!(endpoint, status, retries, elapsed_ms, "request completed");
+        }
+        None => tracing::debug!(endpoint, retries, elapsed_ms, "request failed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    type SpanFields = (&'static Metadata<'static>, HashMap<String, String>);
+
+    /// Collects the fields of every span.
+    #[derive(Clone, Default)]
+    struct Collector {
+        spans: Arc<Mutex<Vec<SpanFields>>>,
+    }
+
+    struct Fields<'a>(&'a mut HashMap<String, String>);
+
+    impl Visit for Fields<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl Subscriber for Collector {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let mut spans = self.spans.lock().unwrap();
+            let mut fields = HashMap::new();
+            attrs.record(&mut Fields(&mut fields));
+            spans.push((attrs.metadata(), fields));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            let index = span.into_u64() as usize - 1;
+            values.record(&mut Fields(&mut spans[index].1));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_span_fields() {
+        let collector = Collector::default();
+        tracing::subscriber::with_default(collector.clone(), || {
+            let page = Some(3_u32);
+            let span = operation_span!("dashboard.list", page);
+            super::record_request(&span, "/dashboard", 1, Some(200), Duration::from_millis(42));
+        });
+
+        let spans = collector.spans.lock().unwrap();
+        let (metadata, fields) = &spans[0];
+        assert_eq!(metadata.name(), "dashboard.list");
+        assert_eq!(fields["operation"], "\"dashboard.list\"");
+        assert_eq!(fields["page"], "3");
+        assert_eq!(fields["endpoint"], "\"/dashboard\"");
+        assert_eq!(fields["status"], "200");
+        assert_eq!(fields["retries"], "1");
+        assert_eq!(fields["elapsed_ms"], "42");
+    }
+}
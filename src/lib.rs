@@ -40,12 +40,14 @@
 //! - `USER_EMAIL` – login email
 //! - `USER_PASSWORD` – login password
 //! - `BASE_URL` – base URL of the CekUnit installation (e.g., `https://example.com`)
-//! - Various endpoint variables (see [`EnvConfig`] documentation for the full list)
+//!
+//! Endpoint variables are optional and default to guessed routes that have not been
+//! verified against a real installation (see [`handler::config::DEFAULT_ENDPOINTS`]).
 //!
 //! ## Example
 //!
 //! ```no_run
-//! use cekunit_client::CekUnitClient;
+//! use librcekunit::CekUnitClient;
 //!
 //! fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     // Create the main client – loads configuration from environment
@@ -53,7 +55,7 @@
 //!
 //!     // Log in (if no valid session exists, this will perform a new login)
 //!     let session = client.login()?;
-//!     println!("Logged in at: {}", session.timestamp);
+//!     println!("Logged in at: {}", session.logged_in_at);
 //!
 //!     // Access the dashboard client
 //!     let dashboard = client.dashboard()?;
@@ -72,7 +74,13 @@
 
 pub mod api;
 pub mod client;
+pub mod doctor;
 pub mod handler;
+pub mod metrics;
+pub mod middleware;
+pub mod session;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 // Re‑export public API for easy access
 pub use crate::api::auth::loging::LoginClient;
@@ -84,6 +92,8 @@ pub use crate::api::dashboard::{
 pub use crate::client::CekUnitClient;
 pub use crate::handler::env::EnvConfig;
 pub use crate::handler::error::ApiError;
+pub use crate::middleware::{Middleware, MiddlewareStack};
+pub use crate::session::{DropAction, SessionGuard};
 
 /// Utility functions and types for internal use, but exposed for advanced scenarios.
 ///
@@ -93,13 +103,14 @@ pub mod utils {
     pub use crate::api::auth::utils::cache::{CacheManager, Cookie};
     pub use crate::api::auth::utils::cookies;
     pub use crate::api::auth::utils::token;
+    pub use crate::handler::redact::{Secret, scrub};
 }
 
 /// Returns the current crate version as defined in `Cargo.toml`.
 ///
 /// # Example
 /// ```
-/// use cekunit_client::version;
+/// use librcekunit::version;
 /// println!("Client version: {}", version());
 /// ```
 pub fn version() -> &'static str {
@@ -110,7 +121,7 @@ pub fn version() -> &'static str {
 ///
 /// # Example
 /// ```
-/// use cekunit_client::name;
+/// use librcekunit::name;
 /// println!("Crate name: {}", name());
 /// ```
 pub fn name() -> &'static str {
@@ -121,7 +132,7 @@ pub fn name() -> &'static str {
 ///
 /// # Example
 /// ```
-/// use cekunit_client::build_info;
+/// use librcekunit::build_info;
 /// let info = build_info();
 /// println!("{}", info);
 /// ```
@@ -143,7 +154,7 @@ pub fn build_info() -> BuildInfo {
 pub struct BuildInfo {
     /// The crate version (e.g., `"0.1.0"`).
     pub version: &'static str,
-    /// The crate name (e.g., `"cekunit_client"`).
+    /// The crate name (e.g., `"librcekunit"`).
     pub name: &'static str,
     /// The authors string from `Cargo.toml`.
     pub authors: &'static str,
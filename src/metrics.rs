@@ -0,0 +1,386 @@
+//! Client-side metrics with Prometheus text exposition.
+//!
+//! [`Metrics`] is an opt-in registry enabled with [`CekUnitClient::enable_metrics`].
+//! It is a [`Middleware`], so it sees every request of every sub-client, and it also
+//! counts logins, logouts, re-logins and CSRF token refreshes via
+//! [`Middleware::on_event`]. Read it as a [`MetricsSnapshot`] or render it with
+//! [`Metrics::render_prometheus`] to serve on your own metrics endpoint.
+//!
+//! Exposed metrics:
+//!
+//! | Name                                 | Type      | Labels                 |
+//! | ------------------------------------ | --------- | ---------------------- |
+//! | `cekunit_requests_total`             | counter   | `operation`, `status`  |
+//! | `cekunit_request_retries_total`      | counter   | `operation`            |
+//! | `cekunit_request_timeouts_total`     | counter   | `operation`            |
+//! | `cekunit_request_duration_seconds`   | histogram | `operation`            |
+//! | `cekunit_logins_total`               | counter   | `result`               |
+//! | `cekunit_logouts_total`              | counter   | `result`               |
+//! | `cekunit_relogins_total`             | counter   |                        |
+//! | `cekunit_csrf_refreshes_total`       | counter   |                        |
+//!
+//! `status` is the status class (`2xx` ... `5xx`), or `error` when no response was
+//! received. `result` is `success` or `failure`.
+//!
+//! [`CekUnitClient::enable_metrics`]: crate::CekUnitClient::enable_metrics
+
+use crate::handler::error::ApiError;
+use crate::middleware::{ClientEvent, Middleware, RequestInfo};
+use reqwest::blocking::Response;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the latency histogram buckets.
+pub const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Latency distribution of one operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// Number of observations per bucket of [`LATENCY_BUCKETS`] (not cumulative);
+    /// the extra last entry counts observations above the largest bound.
+    pub buckets: Vec<u64>,
+    /// Sum of all observations, in seconds.
+    pub sum: f64,
+    /// Number of observations.
+    pub count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKETS.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    /// Records one observation.
+    pub fn observe(&mut self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Counters and latency of one operation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OperationMetrics {
+    /// Requests by status class (`"2xx"`, `"4xx"`, `"error"`, ...).
+    pub requests: BTreeMap<String, u64>,
+    /// Requests that were retries of an earlier attempt.
+    pub retries: u64,
+    /// Requests that timed out.
+    pub timeouts: u64,
+    /// Request durations.
+    pub latency: Histogram,
+}
+
+/// Point-in-time copy of all metrics.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Per-operation metrics, keyed by operation name (e.g. `"dashboard.list"`).
+    pub operations: BTreeMap<String, OperationMetrics>,
+    /// Successful logins.
+    pub logins: u64,
+    /// Failed logins.
+    pub login_failures: u64,
+    /// Successful logouts.
+    pub logouts: u64,
+    /// Failed logout attempts.
+    pub logout_failures: u64,
+    /// Cached sessions rejected by the server, causing a new login.
+    pub relogins: u64,
+    /// CSRF tokens read from a page.
+    pub csrf_refreshes: u64,
+}
+
+/// Metrics registry; see the [module documentation](self).
+///
+/// # Example
+/// ```no_run
+/// use librcekunit::CekUnitClient;
+///
+/// let mut client = CekUnitClient::new()?;
+/// let metrics = client.enable_metrics();
+/// client.ensure_logged_in()?;
+/// client.dashboard()?.get_dashboard(Some(1), None, None, None)?;
+///
+/// print!("{}", metrics.render_prometheus());
+/// # Ok::<(), librcekunit::ApiError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<MetricsSnapshot>,
+}
+
+impl Metrics {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the current values.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.state.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Resets all values to zero.
+    pub fn reset(&self) {
+        self.update(|s| *s = MetricsSnapshot::default());
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format (version 0.0.4).
+    pub fn render_prometheus(&self) -> String {
+        render(&self.snapshot())
+    }
+
+    fn update(&self, f: impl FnOnce(&mut MetricsSnapshot)) {
+        if let Ok(mut state) = self.state.lock() {
+            f(&mut state);
+        }
+    }
+
+    fn record_request(&self, info: &RequestInfo, class: &str, timeout: bool, elapsed: Duration) {
+        self.update(|s| {
+            let op = s.operations.entry(info.operation.clone()).or_default();
+            *op.requests.entry(class.to_string()).or_default() += 1;
+            if info.retries > 0 {
+                op.retries += 1;
+            }
+            if timeout {
+                op.timeouts += 1;
+            }
+            op.latency.observe(elapsed);
+        });
+    }
+}
+
+impl Middleware for Metrics {
+    fn after_response(&self, info: &RequestInfo, response: &Response, elapsed: Duration) {
+        let class = format!("{}xx", response.status().as_u16() / 100);
+        self.record_request(info, &class, false, elapsed);
+    }
+
+    fn on_error(&self, info: &RequestInfo, error: &ApiError, elapsed: Duration) {
+        self.record_request(info, "error", error.is_timeout(), elapsed);
+    }
+
+    fn on_event(&self, event: &ClientEvent) {
+        self.update(|s| match *event {
+            ClientEvent::Login { success: true } => s.logins += 1,
+            ClientEvent::Login { success: false } => s.login_failures += 1,
+            ClientEvent::Logout { success: true } => s.logouts += 1,
+            ClientEvent::Logout { success: false } => s.logout_failures += 1,
+            ClientEvent::Relogin => s.relogins += 1,
+            ClientEvent::CsrfRefresh => s.csrf_refreshes += 1,
+        });
+    }
+}
+
+/// Escapes a label value for the text format.
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn render(s: &MetricsSnapshot) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "cekunit_requests_total",
+        "counter",
+        "HTTP requests made, by operation and status class.",
+    );
+    for (name, op) in &s.operations {
+        for (class, count) in &op.requests {
+            let _ = writeln!(
+                out,
+                "cekunit_requests_total{{operation=\"{}\",status=\"{}\"}} {}",
+                label(name),
+                class,
+                count
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "cekunit_request_retries_total",
+        "counter",
+        "Requests that repeated an earlier failed attempt.",
+    );
+    for (name, op) in &s.operations {
+        let _ = writeln!(
+            out,
+            "cekunit_request_retries_total{{operation=\"{}\"}} {}",
+            label(name),
+            op.retries
+        );
+    }
+
+    header(
+        &mut out,
+        "cekunit_request_timeouts_total",
+        "counter",
+        "Requests that timed out.",
+    );
+    for (name, op) in &s.operations {
+        let _ = writeln!(
+            out,
+            "cekunit_request_timeouts_total{{operation=\"{}\"}} {}",
+            label(name),
+            op.timeouts
+        );
+    }
+
+    header(
+        &mut out,
+        "cekunit_request_duration_seconds",
+        "histogram",
+        "Request latency in seconds.",
+    );
+    for (name, op) in &s.operations {
+        let name = label(name);
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&op.latency.buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "cekunit_request_duration_seconds_bucket{{operation=\"{}\",le=\"{}\"}} {}",
+                name, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "cekunit_request_duration_seconds_bucket{{operation=\"{}\",le=\"+Inf\"}} {}",
+            name, op.latency.count
+        );
+        let _ = writeln!(
+            out,
+            "cekunit_request_duration_seconds_sum{{operation=\"{}\"}} {}",
+            name, op.latency.sum
+        );
+        let _ = writeln!(
+            out,
+            "cekunit_request_duration_seconds_count{{operation=\"{}\"}} {}",
+            name, op.latency.count
+        );
+    }
+
+    for (name, help, success, failure) in [
+        (
+            "cekunit_logins_total",
+            "Logins by result.",
+            s.logins,
+            s.login_failures,
+        ),
+        (
+            "cekunit_logouts_total",
+            "Logout attempts by result.",
+            s.logouts,
+            s.logout_failures,
+        ),
+    ] {
+        header(&mut out, name, "counter", help);
+        let _ = writeln!(out, "{}{{result=\"success\"}} {}", name, success);
+        let _ = writeln!(out, "{}{{result=\"failure\"}} {}", name, failure);
+    }
+
+    header(
+        &mut out,
+        "cekunit_relogins_total",
+        "counter",
+        "Cached sessions rejected by the server, causing a new login.",
+    );
+    let _ = writeln!(out, "cekunit_relogins_total {}", s.relogins);
+
+    header(
+        &mut out,
+        "cekunit_csrf_refreshes_total",
+        "counter",
+        "CSRF tokens read from a page.",
+    );
+    let _ = writeln!(out, "cekunit_csrf_refreshes_total {}", s.csrf_refreshes);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::error::ErrorContext;
+    use crate::middleware::MiddlewareStack;
+    use reqwest::blocking::Client;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut h = Histogram::default();
+        h.observe(Duration::from_millis(10));
+        h.observe(Duration::from_millis(300));
+        h.observe(Duration::from_secs(120));
+        assert_eq!(h.buckets[0], 1);
+        assert_eq!(h.buckets[3], 1);
+        assert_eq!(h.buckets[LATENCY_BUCKETS.len()], 1);
+        assert_eq!(h.count, 3);
+    }
+
+    #[test]
+    fn test_requests_and_events() {
+        let mut server = mockito::Server::new();
+        server.mock("GET", "/pic").with_status(200).create();
+        server.mock("GET", "/users").with_status(503).create();
+        let metrics = Arc::new(Metrics::new());
+        let mut stack = MiddlewareStack::new();
+        stack.push(metrics.clone());
+
+        for path in ["/pic", "/pic", "/users"] {
+            let url = format!("{}{}", server.url(), path);
+            let op = if path == "/pic" {
+                "pic.list"
+            } else {
+                "users.list"
+            };
+            let ctx = ErrorContext::new(op, "GET", &url).with_retries(u32::from(path == "/users"));
+            stack.send(Client::new().get(&url), &ctx).unwrap();
+        }
+        stack.emit(ClientEvent::Login { success: true });
+        stack.emit(ClientEvent::Relogin);
+        stack.emit(ClientEvent::CsrfRefresh);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.operations["pic.list"].requests["2xx"], 2);
+        assert_eq!(snapshot.operations["users.list"].requests["5xx"], 1);
+        assert_eq!(snapshot.operations["users.list"].retries, 1);
+        assert_eq!((snapshot.logins, snapshot.relogins), (1, 1));
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("cekunit_requests_total{operation=\"pic.list\",status=\"2xx\"} 2\n"));
+        assert!(text.contains(
+            "cekunit_request_duration_seconds_bucket{operation=\"pic.list\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(
+            text.contains("cekunit_request_duration_seconds_count{operation=\"users.list\"} 1\n")
+        );
+        assert!(text.contains("cekunit_logins_total{result=\"success\"} 1\n"));
+        assert!(text.contains("cekunit_csrf_refreshes_total 1\n"));
+        assert!(text.contains("# TYPE cekunit_request_duration_seconds histogram\n"));
+    }
+}
@@ -0,0 +1,263 @@
+//! Ready-made middlewares: request logging, header injection and timing.
+
+use crate::handler::error::ApiError;
+use crate::middleware::stack::{Middleware, RequestInfo};
+use reqwest::blocking::{Request, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::Level;
+
+/// Emits a `tracing` event at a level chosen at runtime.
+macro_rules! event_at {
+    ($level:expr, $($arg:tt)+) => {
+        match $level {
+            Level::ERROR => tracing::error!($($arg)+),
+            Level::WARN => tracing::warn!($($arg)+),
+            Level::INFO => tracing::info!($($arg)+),
+            Level::DEBUG => tracing::debug!($($arg)+),
+            _ => tracing::trace!($($arg)+),
+        }
+    };
+}
+
+/// Logs every request with its status and duration as `tracing` events.
+///
+/// URLs are redacted with [`redact_url`](crate::handler::redact::redact_url); headers
+/// and bodies are never logged.
+#[derive(Debug, Clone)]
+pub struct LoggingMiddleware {
+    level: Level,
+}
+
+impl LoggingMiddleware {
+    /// Logs at `level`; failures are always logged at `WARN` or above.
+    pub fn new(level: Level) -> Self {
+        Self { level }
+    }
+}
+
+impl Default for LoggingMiddleware {
+    /// Logs at `DEBUG` level.
+    fn default() -> Self {
+        Self::new(Level::DEBUG)
+    }
+}
+
+impl Middleware for LoggingMiddleware {
+    fn after_response(&self, info: &RequestInfo, response: &Response, elapsed: Duration) {
+        event_at!(
+            self.level,
+            operation = %info.operation,
+            method = %info.method,
+            url = %info.redacted_url(),
+            status = response.status().as_u16(),
+            elapsed_ms = elapsed.as_millis() as u64,
+            "response received"
+        );
+    }
+
+    fn on_error(&self, info: &RequestInfo, error: &ApiError, elapsed: Duration) {
+        // More verbose levels compare greater, so `min` keeps the more severe one.
+        event_at!(
+            self.level.min(Level::WARN),
+            operation = %info.operation,
+            method = %info.method,
+            url = %info.redacted_url(),
+            elapsed_ms = elapsed.as_millis() as u64,
+            error = %error,
+            "request failed"
+        );
+    }
+}
+
+/// Adds fixed headers to every request, replacing existing values.
+///
+/// # Example
+/// ```
+/// use librcekunit::middleware::HeaderMiddleware;
+///
+/// let headers = HeaderMiddleware::new()
+///     .header("X-Request-Source", "sync-daemon")?
+///     .header("Accept-Language", "id-ID")?;
+/// # Ok::<(), librcekunit::ApiError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HeaderMiddleware {
+    headers: HeaderMap,
+}
+
+impl HeaderMiddleware {
+    /// Creates a middleware that adds no headers yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a header to inject.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::Other`] if the name or value is not a valid header.
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self, ApiError> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| ApiError::Other(format!("Invalid header name '{}': {}", name, e)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| ApiError::Other(format!("Invalid value for header '{}': {}", name, e)))?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+}
+
+impl Middleware for HeaderMiddleware {
+    fn before_request(&self, _operation: &str, request: &mut Request) -> Result<(), ApiError> {
+        for (name, value) in &self.headers {
+            request.headers_mut().insert(name, value.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Request counts and durations of one operation, collected by [`TimingMiddleware`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationTiming {
+    /// Number of requests that received a response.
+    pub responses: u64,
+    /// Number of requests that failed without a response.
+    pub failures: u64,
+    /// Sum of all request durations.
+    pub total: Duration,
+    /// Longest request duration.
+    pub max: Duration,
+}
+
+impl OperationTiming {
+    /// Returns the mean duration over all requests, or zero if there were none.
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.responses + self.failures) {
+            Ok(0) | Err(_) => Duration::ZERO,
+            Ok(n) => self.total / n,
+        }
+    }
+
+    fn record(&mut self, elapsed: Duration) {
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+}
+
+/// Measures request latency per operation.
+///
+/// Register it through an [`Arc`](std::sync::Arc) and keep a clone to read the
+/// results with [`snapshot`](Self::snapshot).
+///
+/// # Example
+/// ```no_run
+/// use librcekunit::CekUnitClient;
+/// use librcekunit::middleware::TimingMiddleware;
+/// use std::sync::Arc;
+///
+/// let timing = Arc::new(TimingMiddleware::new());
+/// let mut client = CekUnitClient::new()?;
+/// client.add_middleware(timing.clone());
+/// client.ensure_logged_in()?;
+/// for (operation, t) in timing.snapshot() {
+///     println!("{}: {} requests, mean {:?}", operation, t.responses, t.mean());
+/// }
+/// # Ok::<(), librcekunit::ApiError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct TimingMiddleware {
+    timings: Mutex<BTreeMap<String, OperationTiming>>,
+}
+
+impl TimingMiddleware {
+    /// Creates a middleware with no recorded requests.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the timings recorded so far, keyed by operation name.
+    pub fn snapshot(&self) -> BTreeMap<String, OperationTiming> {
+        self.timings.lock().map(|t| t.clone()).unwrap_or_default()
+    }
+
+    /// Forgets all recorded timings.
+    pub fn reset(&self) {
+        if let Ok(mut timings) = self.timings.lock() {
+            timings.clear();
+        }
+    }
+
+    fn update(&self, operation: &str, f: impl FnOnce(&mut OperationTiming)) {
+        if let Ok(mut timings) = self.timings.lock() {
+            f(timings.entry(operation.to_string()).or_default());
+        }
+    }
+}
+
+impl Middleware for TimingMiddleware {
+    fn after_response(&self, info: &RequestInfo, _response: &Response, elapsed: Duration) {
+        self.update(&info.operation, |t| {
+            t.responses += 1;
+            t.record(elapsed);
+        });
+    }
+
+    fn on_error(&self, info: &RequestInfo, _error: &ApiError, elapsed: Duration) {
+        self.update(&info.operation, |t| {
+            t.failures += 1;
+            t.record(elapsed);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::error::ErrorContext;
+    use crate::middleware::stack::MiddlewareStack;
+    use reqwest::blocking::Client;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_headers_and_timing() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/users")
+            .match_header("x-request-source", "test")
+            .with_body("ok")
+            .expect(2)
+            .create();
+        let timing = Arc::new(TimingMiddleware::new());
+        let mut stack = MiddlewareStack::new();
+        stack.push(Arc::new(
+            HeaderMiddleware::new()
+                .header("X-Request-Source", "test")
+                .unwrap(),
+        ));
+        stack.push(Arc::new(LoggingMiddleware::default()));
+        stack.push(timing.clone());
+
+        let url = format!("{}/users", server.url());
+        let ctx = ErrorContext::new("users.list", "GET", &url);
+        for _ in 0..2 {
+            stack.send(Client::new().get(&url), &ctx).unwrap();
+        }
+
+        mock.assert();
+        let snapshot = timing.snapshot();
+        assert_eq!(snapshot["users.list"].responses, 2);
+        assert_eq!(snapshot["users.list"].failures, 0);
+        assert!(snapshot["users.list"].max >= snapshot["users.list"].mean());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert!(HeaderMiddleware::new().header("bad header", "x").is_err());
+        assert!(
+            HeaderMiddleware::new()
+                .header("x-ok", "bad\nvalue")
+                .is_err()
+        );
+    }
+}
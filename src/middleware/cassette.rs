@@ -0,0 +1,543 @@
+//! Record/replay transport for offline tests.
+//!
+//! A [`Cassette`] is a [`Transport`] that either records real exchanges into a JSON
+//! file or answers requests from such a file without touching the network. Record a
+//! flow once against a real (or test) server, commit the cassette, and replay it in CI.
+//!
+//! Requests are matched on method, path, query parameters and form fields. `_token` is
+//! ignored, since Laravel issues a new one per session; the host is ignored too, so a
+//! cassette recorded against one server replays under any `BASE_URL`. Passwords are
+//! stored as [`REDACTED`] and matched as such, and `Set-Cookie` values are masked.
+//! Response bodies are stored as received: review a cassette for personal data before
+//! committing it.
+//!
+//! When several recorded interactions match, they are replayed in recorded order;
+//! once all are used, the last one is repeated.
+//!
+//! A recording cassette keeps the exchanges in memory and writes the file when
+//! [`Cassette::save`] is called or the cassette is dropped.
+
+use crate::handler::error::{ApiError, ErrorContext};
+use crate::handler::redact::{REDACTED, redact_url};
+use crate::middleware::stack::{Transport, buffer};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use reqwest::blocking::{Client, Request, Response};
+use reqwest::header::{CONTENT_TYPE, HeaderName, HeaderValue, SET_COOKIE};
+use reqwest::{ResponseBuilderExt, Url};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Version of the cassette file format.
+const CASSETTE_VERSION: u32 = 1;
+
+/// Request fields left out of matching and recording.
+const IGNORED_FIELDS: &[&str] = &["_token"];
+
+/// Request fields whose values are stored as [`REDACTED`].
+const SECRET_FIELDS: &[&str] = &["password", "password_confirmation"];
+
+/// Whether a [`Cassette`] records or replays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Requests go to the network; exchanges are kept and written on save.
+    Record,
+    /// Requests are answered from the file; the network is never used.
+    Replay,
+}
+
+/// A request as stored in a cassette, normalised for matching.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// HTTP method, upper case.
+    pub method: String,
+    /// URL path, e.g. `/dashboard`.
+    pub path: String,
+    /// Query parameters without `_token`, sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub query: Vec<(String, String)>,
+    /// Form fields without `_token`, sorted; passwords are [`REDACTED`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub form: Vec<(String, String)>,
+}
+
+impl RecordedRequest {
+    /// Normalises a request for storage and matching.
+    pub fn from_request(request: &Request) -> Self {
+        let is_form = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/x-www-form-urlencoded"));
+        let form = match request.body().and_then(|b| b.as_bytes()) {
+            Some(body) if is_form => {
+                let mut url = Url::parse("http://form.invalid/").expect("valid base URL");
+                url.set_query(Some(&String::from_utf8_lossy(body)));
+                normalise(&url)
+            }
+            _ => Vec::new(),
+        };
+        Self {
+            method: request.method().as_str().to_ascii_uppercase(),
+            path: request.url().path().to_string(),
+            query: normalise(request.url()),
+            form,
+        }
+    }
+}
+
+/// A response as stored in a cassette.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// HTTP status code.
+    pub status: u16,
+    /// Final URL after redirects: path and query if on the same host, else absolute.
+    pub url: String,
+    /// Response headers; `Set-Cookie` values are masked.
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// Body, if it is valid UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Body in base64, if it is binary (e.g. an Excel export).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
+}
+
+impl RecordedResponse {
+    fn new(request_url: &Url, response: &Response, body: &[u8]) -> Self {
+        let final_url = response.url();
+        let url = if final_url.origin() == request_url.origin() {
+            match final_url.query() {
+                Some(query) => format!("{}?{}", final_url.path(), query),
+                None => final_url.path().to_string(),
+            }
+        } else {
+            final_url.to_string()
+        };
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = value.to_str().unwrap_or("");
+                let value = if name == SET_COOKIE {
+                    mask_cookie(value)
+                } else {
+                    value.to_string()
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        let (body, body_base64) = match std::str::from_utf8(body) {
+            Ok(text) => (Some(text.to_string()), None),
+            Err(_) => (None, Some(BASE64.encode(body))),
+        };
+        Self {
+            status: response.status().as_u16(),
+            url,
+            headers,
+            body,
+            body_base64,
+        }
+    }
+
+    fn to_response(&self, request_url: &Url) -> Result<Response, ApiError> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            ApiError::Other(format!("Invalid recorded response: {}", e))
+        };
+        let url = request_url.join(&self.url).map_err(|e| invalid(&e))?;
+        let mut builder = http::Response::builder().status(self.status).url(url);
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?;
+            let value = HeaderValue::from_str(value).map_err(|e| invalid(&e))?;
+            builder = builder.header(name, value);
+        }
+        let body = match (&self.body, &self.body_base64) {
+            (_, Some(encoded)) => BASE64.decode(encoded).map_err(|e| invalid(&e))?,
+            (Some(text), None) => text.clone().into_bytes(),
+            (None, None) => Vec::new(),
+        };
+        let response = builder.body(body).map_err(|e| invalid(&e))?;
+        Ok(Response::from(response))
+    }
+}
+
+/// One recorded request and its response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Name of the library operation that made the request, e.g. `"auth.login"`.
+    pub operation: String,
+    /// The normalised request.
+    pub request: RecordedRequest,
+    /// The response.
+    pub response: RecordedResponse,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CassetteFile {
+    version: u32,
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug)]
+struct State {
+    interactions: Vec<Interaction>,
+    used: Vec<bool>,
+    /// Interactions were recorded since the file was last written.
+    dirty: bool,
+}
+
+/// Records exchanges to, or replays them from, a cassette file.
+///
+/// # Example
+/// ```no_run
+/// use librcekunit::CekUnitClient;
+/// use librcekunit::middleware::Cassette;
+/// use std::sync::Arc;
+///
+/// // Records on the first run, replays offline afterwards.
+/// let cassette = Arc::new(Cassette::open("tests/fixtures/login.json")?);
+/// let mut client = CekUnitClient::new()?;
+/// client.set_transport(cassette.clone());
+/// client.login()?;
+/// assert!(cassette.unused().is_empty());
+/// cassette.save()?;
+/// # Ok::<(), librcekunit::ApiError>(())
+/// ```
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Mutex<State>,
+}
+
+impl Cassette {
+    /// Starts recording into `path`, replacing any existing file.
+    ///
+    /// An empty cassette is written right away so that an unwritable path fails here;
+    /// the exchanges are written by [`save`](Self::save) or when the cassette is dropped.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::IoError`] if the file cannot be written.
+    pub fn record(path: impl AsRef<Path>) -> Result<Self, ApiError> {
+        let cassette = Self::with_interactions(path, CassetteMode::Record, Vec::new());
+        cassette.write(&[])?;
+        Ok(cassette)
+    }
+
+    /// Loads `path` for replay.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::IoError`] if the file cannot be read,
+    /// [`ApiError::JsonError`] if it is not valid JSON, or [`ApiError::Other`] if its
+    /// format version is not supported.
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self, ApiError> {
+        let data = fs::read_to_string(path.as_ref())?;
+        let file: CassetteFile = serde_json::from_str(&data)?;
+        if file.version != CASSETTE_VERSION {
+            return Err(ApiError::Other(format!(
+                "Unsupported cassette version {} in '{}'",
+                file.version,
+                path.as_ref().display()
+            )));
+        }
+        Ok(Self::with_interactions(
+            path,
+            CassetteMode::Replay,
+            file.interactions,
+        ))
+    }
+
+    /// Replays `path` if it exists, otherwise records into it.
+    ///
+    /// # Errors
+    /// See [`record`](Self::record) and [`replay`](Self::replay).
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ApiError> {
+        if path.as_ref().exists() {
+            Self::replay(path)
+        } else {
+            Self::record(path)
+        }
+    }
+
+    fn with_interactions(
+        path: impl AsRef<Path>,
+        mode: CassetteMode,
+        interactions: Vec<Interaction>,
+    ) -> Self {
+        let used = vec![false; interactions.len()];
+        Self {
+            path: path.as_ref().to_path_buf(),
+            mode,
+            state: Mutex::new(State {
+                interactions,
+                used,
+                dirty: false,
+            }),
+        }
+    }
+
+    /// Returns whether this cassette records or replays.
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Returns the path of the cassette file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the recorded interactions.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.lock().interactions.clone()
+    }
+
+    /// Describes the interactions that were not replayed yet, e.g. `"POST /pic"`.
+    ///
+    /// Tests can assert this is empty to check that a flow made every recorded request.
+    pub fn unused(&self) -> Vec<String> {
+        let state = self.lock();
+        state
+            .interactions
+            .iter()
+            .zip(&state.used)
+            .filter(|(_, used)| !**used)
+            .map(|(i, _)| format!("{} {}", i.request.method, i.request.path))
+            .collect()
+    }
+
+    /// Writes the recorded interactions to the file.
+    ///
+    /// Does nothing when replaying or when nothing was recorded since the last save.
+    /// Dropping a recording cassette saves it too, but only logs a failure.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::IoError`] if the file cannot be written.
+    pub fn save(&self) -> Result<(), ApiError> {
+        let mut state = self.lock();
+        if self.mode == CassetteMode::Record && state.dirty {
+            self.write(&state.interactions)?;
+            state.dirty = false;
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self, interactions: &[Interaction]) -> Result<(), ApiError> {
+        let file = CassetteFile {
+            version: CASSETTE_VERSION,
+            interactions: interactions.to_vec(),
+        };
+        fs::write(&self.path, serde_json::to_vec_pretty(&file)?)?;
+        Ok(())
+    }
+
+    fn record_exchange(
+        &self,
+        client: &Client,
+        request: Request,
+        ctx: &ErrorContext,
+    ) -> Result<Response, ApiError> {
+        let recorded = RecordedRequest::from_request(&request);
+        let url = request.url().clone();
+        let response = client.execute(request).map_err(|e| ctx.transport(e))?;
+        let (response, body) = buffer(response).map_err(|e| ctx.transport(e))?;
+        let mut state = self.lock();
+        state.interactions.push(Interaction {
+            operation: ctx.operation.clone(),
+            request: recorded,
+            response: RecordedResponse::new(&url, &response, &body),
+        });
+        state.used.push(true);
+        state.dirty = true;
+        Ok(response)
+    }
+
+    fn replay_exchange(&self, request: &Request, ctx: &ErrorContext) -> Result<Response, ApiError> {
+        let wanted = RecordedRequest::from_request(request);
+        let mut state = self.lock();
+        let matching: Vec<usize> = state
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| i.request == wanted)
+            .map(|(index, _)| index)
+            .collect();
+        let index = matching
+            .iter()
+            .copied()
+            .find(|&i| !state.used[i])
+            .or_else(|| matching.last().copied())
+            .ok_or_else(|| {
+                ApiError::Other(format!(
+                    "No recorded response in cassette '{}' for {} {}",
+                    self.path.display(),
+                    wanted.method,
+                    redact_url(request.url().as_str())
+                ))
+                .with_context(ctx.clone())
+            })?;
+        state.used[index] = true;
+        state.interactions[index]
+            .response
+            .to_response(request.url())
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            tracing::warn!(path = %self.path.display(), error = %e, "cannot write cassette");
+        }
+    }
+}
+
+impl Transport for Cassette {
+    fn execute(
+        &self,
+        client: &Client,
+        request: Request,
+        ctx: &ErrorContext,
+    ) -> Result<Response, ApiError> {
+        match self.mode {
+            CassetteMode::Record => self.record_exchange(client, request, ctx),
+            CassetteMode::Replay => self.replay_exchange(&request, ctx),
+        }
+    }
+}
+
+/// Returns the query pairs of `url` without ignored fields, secrets masked, sorted.
+fn normalise(url: &Url) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| !IGNORED_FIELDS.contains(&name.as_ref()))
+        .map(|(name, value)| {
+            let value = if SECRET_FIELDS.contains(&name.as_ref()) {
+                REDACTED.to_string()
+            } else {
+                value.into_owned()
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+/// Masks the value of a `Set-Cookie` header, keeping the name and attributes.
+fn mask_cookie(header: &str) -> String {
+    let (pair, attributes) = header.split_once(';').unwrap_or((header, ""));
+    let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+    if attributes.is_empty() {
+        format!("{}={}", name, REDACTED)
+    } else {
+        format!("{}={};{}", name, REDACTED, attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::stack::MiddlewareStack;
+    use std::sync::Arc;
+
+    fn stack(cassette: Cassette) -> (MiddlewareStack, Arc<Cassette>) {
+        let cassette = Arc::new(cassette);
+        let mut stack = MiddlewareStack::new();
+        stack.set_transport(cassette.clone());
+        (stack, cassette)
+    }
+
+    fn post(stack: &MiddlewareStack, base: &str, token: &str) -> Result<Response, ApiError> {
+        let url = format!("{}/pic?page=2", base);
+        let ctx = ErrorContext::new("pic.create", "POST", &url);
+        let request = Client::new().post(&url).form(&[
+            ("_token", token),
+            ("nama", "Budi"),
+            ("password", "hunter22"),
+        ]);
+        stack.send(request, &ctx)
+    }
+
+    #[test]
+    fn test_record_then_replay_offline() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/pic?page=2")
+            .with_status(201)
+            .with_header("set-cookie", "laravel_session=s3cr3t; path=/; httponly")
+            .with_body("created")
+            .create();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pic.json");
+
+        let (recording, recording_cassette) = stack(Cassette::record(&path).unwrap());
+        let response = post(&recording, &server.url(), "first").unwrap();
+        assert_eq!(response.text().unwrap(), "created");
+        mock.assert();
+        assert!(!fs::read_to_string(&path).unwrap().contains("created"));
+        recording_cassette.save().unwrap();
+        let file = fs::read_to_string(&path).unwrap();
+        assert!(!file.contains("hunter22") && !file.contains("s3cr3t") && !file.contains("first"));
+
+        // Replay against an unreachable host, with a different token.
+        let (replaying, cassette) = stack(Cassette::open(&path).unwrap());
+        assert_eq!(cassette.mode(), CassetteMode::Replay);
+        let response = post(&replaying, "http://127.0.0.1:1", "second").unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        assert_eq!(response.url().as_str(), "http://127.0.0.1:1/pic?page=2");
+        assert_eq!(
+            response.headers()[SET_COOKIE],
+            "laravel_session=********; path=/; httponly"
+        );
+        assert_eq!(response.text().unwrap(), "created");
+        assert!(cassette.unused().is_empty());
+    }
+
+    #[test]
+    fn test_replay_miss_and_order() {
+        let request = RecordedRequest {
+            method: "GET".into(),
+            path: "/dashboard".into(),
+            query: Vec::new(),
+            form: Vec::new(),
+        };
+        let interaction = |body: &str| Interaction {
+            operation: "dashboard.list".into(),
+            request: request.clone(),
+            response: RecordedResponse {
+                status: 200,
+                url: "/dashboard".into(),
+                headers: Vec::new(),
+                body: Some(body.into()),
+                body_base64: None,
+            },
+        };
+        let cassette = Cassette::with_interactions(
+            "memory.json",
+            CassetteMode::Replay,
+            vec![interaction("one"), interaction("two")],
+        );
+        let (stack, cassette) = stack(cassette);
+
+        let get = |path: &str| {
+            let url = format!("http://cekunit.test{}", path);
+            let ctx = ErrorContext::new("dashboard.list", "GET", &url);
+            stack.send(Client::new().get(&url), &ctx)
+        };
+        let bodies: Vec<String> = (0..3)
+            .map(|_| get("/dashboard").unwrap().text().unwrap())
+            .collect();
+        assert_eq!(bodies, ["one", "two", "two"]);
+        assert!(cassette.unused().is_empty());
+
+        let err = get("/users").unwrap_err();
+        assert!(err.to_string().contains("GET http://cekunit.test/users"));
+        assert_eq!(err.context().unwrap().operation, "dashboard.list");
+    }
+}
@@ -0,0 +1,654 @@
+//! HAR 1.2 capture of all CekUnit traffic.
+//!
+//! [`HarRecorder`] is a [`Middleware`] that records every request made through the
+//! clients, with its response, into an [HTTP Archive] file that browsers' developer
+//! tools and HAR viewers can open. Only the last [`DEFAULT_HAR_MAX_ENTRIES`]
+//! exchanges are kept (see [`HarRecorder::with_max_entries`]). The file is written on
+//! [`HarRecorder::flush`], when the recorder is dropped, and at most once per
+//! [`DEFAULT_HAR_FLUSH_INTERVAL`] while requests keep coming, so a crash loses only
+//! the last few exchanges.
+//!
+//! Secrets are removed before anything is stored:
+//! - `Cookie`, `Set-Cookie`, `Authorization` and CSRF headers are replaced with
+//!   [`REDACTED`], cookies are listed by name only,
+//! - `_token`, `password` and `email` values in URLs and form bodies are masked,
+//! - bodies go through [`scrub`] (tokens, session cookies, NIK numbers) and are cut
+//!   at a configurable size.
+//!
+//! [HTTP Archive]: http://www.softwareishard.com/blog/har-12-spec/
+
+use crate::handler::error::ApiError;
+use crate::handler::redact::{REDACTED, redact_url, scrub};
+use crate::middleware::stack::{Middleware, RequestInfo};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{SecondsFormat, Utc};
+use reqwest::blocking::Response;
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
+use reqwest::{Url, Version};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default maximum number of body bytes stored per request or response.
+pub const DEFAULT_HAR_BODY_LIMIT: usize = 64 * 1024;
+
+/// Default number of exchanges kept; older ones are dropped.
+pub const DEFAULT_HAR_MAX_ENTRIES: usize = 1000;
+
+/// Default minimum time between two writes of the file while recording.
+pub const DEFAULT_HAR_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Header names whose values are never stored.
+const SECRET_HEADERS: &[&str] = &[
+    "cookie",
+    "set-cookie",
+    "authorization",
+    "x-csrf-token",
+    "x-xsrf-token",
+];
+
+/// Form fields whose values are never stored.
+const SECRET_FIELDS: &[&str] = &["_token", "password", "password_confirmation", "email"];
+
+/// Records all traffic into a HAR 1.2 file.
+///
+/// # Example
+/// ```no_run
+/// use librcekunit::CekUnitClient;
+///
+/// let mut client = CekUnitClient::new()?;
+/// let har = client.record_har("cekunit.har")?;
+/// client.ensure_logged_in()?;
+/// client.dashboard()?.get_dashboard(Some(1), None, None, None)?;
+/// har.flush()?;
+/// // cekunit.har now holds the login and dashboard exchanges
+/// # Ok::<(), librcekunit::ApiError>(())
+/// ```
+#[derive(Debug)]
+pub struct HarRecorder {
+    path: PathBuf,
+    body_limit: usize,
+    max_entries: usize,
+    flush_interval: Duration,
+    state: Mutex<State>,
+}
+
+/// Recorded exchanges and the bookkeeping for writing them.
+#[derive(Debug)]
+struct State {
+    entries: VecDeque<Entry>,
+    /// Exchanges dropped because of the entry cap.
+    dropped: usize,
+    /// Whether something was recorded since the last write.
+    dirty: bool,
+    last_write: Instant,
+}
+
+impl HarRecorder {
+    /// Creates a recorder writing to `path`, with [`DEFAULT_HAR_BODY_LIMIT`].
+    ///
+    /// An empty archive is written immediately, so an unwritable path fails here
+    /// rather than on the first request.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::IoError`] if the file cannot be written.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, ApiError> {
+        let state = State {
+            entries: VecDeque::new(),
+            dropped: 0,
+            dirty: false,
+            last_write: Instant::now(),
+        };
+        let recorder = Self {
+            path: path.as_ref().to_path_buf(),
+            body_limit: DEFAULT_HAR_BODY_LIMIT,
+            max_entries: DEFAULT_HAR_MAX_ENTRIES,
+            flush_interval: DEFAULT_HAR_FLUSH_INTERVAL,
+            state: Mutex::new(state),
+        };
+        recorder.write(&recorder.lock())?;
+        Ok(recorder)
+    }
+
+    /// Sets the maximum number of body bytes stored per request or response.
+    ///
+    /// Longer bodies are cut and marked with a `comment`.
+    pub fn with_body_limit(mut self, bytes: usize) -> Self {
+        self.body_limit = bytes;
+        self
+    }
+
+    /// Sets how many exchanges are kept; once full, the oldest is dropped for each
+    /// new one. The archive notes how many were dropped.
+    pub fn with_max_entries(mut self, entries: usize) -> Self {
+        self.max_entries = entries.max(1);
+        self
+    }
+
+    /// Sets the minimum time between two writes of the file while recording.
+    ///
+    /// [`Duration::ZERO`] writes after every exchange, which rewrites the whole file
+    /// each time.
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Returns the path of the archive.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of exchanges held, at most the entry cap.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns `true` if nothing has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the recorded exchanges to the file, if anything changed since the
+    /// last write. Also done when the recorder is dropped.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::IoError`] if the file cannot be written.
+    pub fn flush(&self) -> Result<(), ApiError> {
+        let mut state = self.lock();
+        if state.dirty {
+            self.write(&state)?;
+            state.dirty = false;
+            state.last_write = Instant::now();
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        // A panic while recording leaves the entries usable.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, entry: Entry) {
+        {
+            let mut state = self.lock();
+            state.entries.push_back(entry);
+            if state.entries.len() > self.max_entries {
+                state.entries.pop_front();
+                state.dropped += 1;
+            }
+            state.dirty = true;
+            if state.last_write.elapsed() < self.flush_interval {
+                return;
+            }
+        }
+        if let Err(e) = self.flush() {
+            tracing::warn!(path = %self.path.display(), error = %e, "cannot write HAR file");
+        }
+    }
+
+    fn write(&self, state: &State) -> Result<(), ApiError> {
+        let har = Har {
+            log: Log {
+                version: "1.2",
+                creator: Creator {
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                entries: &state.entries,
+                comment: (state.dropped > 0).then(|| {
+                    format!(
+                        "{} older exchanges dropped by librcekunit (limit {})",
+                        state.dropped, self.max_entries
+                    )
+                }),
+            },
+        };
+        fs::write(&self.path, serde_json::to_vec_pretty(&har)?)?;
+        Ok(())
+    }
+
+    fn request(&self, info: &RequestInfo) -> HarRequest {
+        let body = info.body.as_deref();
+        let mime_type = header_value(&info.headers, CONTENT_TYPE.as_str());
+        HarRequest {
+            method: info.method.to_string(),
+            url: info.redacted_url(),
+            http_version: "HTTP/1.1".into(),
+            cookies: cookie_names(&info.headers, "cookie", ';'),
+            headers: headers(&info.headers),
+            query_string: info
+                .url
+                .query_pairs()
+                .map(|(name, value)| NameValue::masked(&name, &value))
+                .collect(),
+            post_data: body.map(|body| {
+                if mime_type.starts_with("application/x-www-form-urlencoded") {
+                    let params = form_params(body);
+                    PostData {
+                        text: form_text(&params),
+                        params,
+                        mime_type,
+                    }
+                } else {
+                    PostData {
+                        params: Vec::new(),
+                        text: self.text(body).0,
+                        mime_type,
+                    }
+                }
+            }),
+            headers_size: -1,
+            body_size: body.map_or(0, |b| b.len() as i64),
+        }
+    }
+
+    /// Returns the stored form of `body`: scrubbed text, or base64 for binary data,
+    /// cut at the body limit, plus the encoding and a truncation comment.
+    fn text(&self, body: &[u8]) -> (String, Option<&'static str>, Option<String>) {
+        let cut = &body[..body.len().min(self.body_limit)];
+        let comment = (cut.len() < body.len()).then(|| {
+            format!(
+                "truncated to {} of {} bytes by librcekunit",
+                cut.len(),
+                body.len()
+            )
+        });
+        match std::str::from_utf8(cut) {
+            Ok(text) => (scrub(text), None, comment),
+            // A multi-byte character cut at the limit still counts as text.
+            Err(e) if e.error_len().is_none() && cut.len() < body.len() => {
+                (scrub(&String::from_utf8_lossy(cut)), None, comment)
+            }
+            Err(_) => (BASE64.encode(cut), Some("base64"), comment),
+        }
+    }
+
+    fn entry(&self, info: &RequestInfo, elapsed: Duration) -> Entry {
+        let started = Utc::now() - chrono::Duration::from_std(elapsed).unwrap_or_default();
+        let time = elapsed.as_secs_f64() * 1000.0;
+        Entry {
+            operation: info.operation.clone(),
+            started_date_time: started.to_rfc3339_opts(SecondsFormat::Millis, true),
+            time,
+            request: self.request(info),
+            response: HarResponse::default(),
+            cache: Empty {},
+            timings: Timings {
+                send: 0.0,
+                wait: time,
+                receive: 0.0,
+            },
+            error: None,
+        }
+    }
+}
+
+impl Drop for HarRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::warn!(path = %self.path.display(), error = %e, "cannot write HAR file");
+        }
+    }
+}
+
+impl Middleware for HarRecorder {
+    fn wants_body(&self) -> bool {
+        true
+    }
+
+    fn on_response_body(
+        &self,
+        info: &RequestInfo,
+        response: &Response,
+        body: &[u8],
+        elapsed: Duration,
+    ) {
+        let mut entry = self.entry(info, elapsed);
+        let (text, encoding, comment) = self.text(body);
+        let status = response.status();
+        entry.request.http_version = http_version(response.version()).into();
+        entry.response = HarResponse {
+            status: status.as_u16(),
+            status_text: status.canonical_reason().unwrap_or("").into(),
+            http_version: http_version(response.version()).into(),
+            cookies: cookie_names(response.headers(), "set-cookie", '\0'),
+            headers: headers(response.headers()),
+            content: Content {
+                size: body.len() as i64,
+                mime_type: header_value(response.headers(), CONTENT_TYPE.as_str()),
+                text: Some(text),
+                encoding,
+                comment,
+            },
+            redirect_url: redact_url(&header_value(response.headers(), "location")),
+            headers_size: -1,
+            body_size: body.len() as i64,
+        };
+        self.push(entry);
+    }
+
+    fn on_error(&self, info: &RequestInfo, error: &ApiError, elapsed: Duration) {
+        let mut entry = self.entry(info, elapsed);
+        entry.error = Some(scrub(&error.to_string()));
+        self.push(entry);
+    }
+}
+
+/// Decodes a form-encoded body into masked name/value pairs.
+fn form_params(body: &[u8]) -> Vec<NameValue> {
+    let mut url = Url::parse("http://form.invalid/").expect("valid base URL");
+    url.set_query(Some(&String::from_utf8_lossy(body)));
+    url.query_pairs()
+        .map(|(name, value)| NameValue::masked(&name, &value))
+        .collect()
+}
+
+/// Encodes masked pairs back into a form body, so the stored text leaks nothing the
+/// params do not.
+fn form_text(params: &[NameValue]) -> String {
+    let mut url = Url::parse("http://form.invalid/").expect("valid base URL");
+    url.query_pairs_mut()
+        .extend_pairs(params.iter().map(|p| (&p.name, &p.value)));
+    url.query().unwrap_or("").to_string()
+}
+
+fn http_version(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_2 => "HTTP/2",
+        Version::HTTP_3 => "HTTP/3",
+        _ => "HTTP/1.1",
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> String {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn headers(headers: &HeaderMap) -> Vec<NameValue> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else if name == "location" || name == "referer" {
+                redact_url(value.to_str().unwrap_or(""))
+            } else {
+                scrub(&String::from_utf8_lossy(value.as_bytes()))
+            };
+            NameValue {
+                name: name.to_string(),
+                value,
+            }
+        })
+        .collect()
+}
+
+/// Lists cookie names from `Cookie` (separated by `;`) or `Set-Cookie` headers
+/// (one per header, `separator` `'\0'`), with redacted values.
+fn cookie_names(headers: &HeaderMap, header: &str, separator: char) -> Vec<NameValue> {
+    headers
+        .get_all(header)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(separator))
+        .filter_map(|pair| pair.split(';').next()?.split_once('='))
+        .map(|(name, _)| NameValue {
+            name: name.trim().to_string(),
+            value: REDACTED.to_string(),
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct Har<'a> {
+    log: Log<'a>,
+}
+
+#[derive(Serialize)]
+struct Log<'a> {
+    version: &'static str,
+    creator: Creator,
+    entries: &'a VecDeque<Entry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Creator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    #[serde(rename = "_operation")]
+    operation: String,
+    started_date_time: String,
+    time: f64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: Empty,
+    timings: Timings,
+    #[serde(rename = "_error", skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: i64,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    params: Vec<NameValue>,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+impl NameValue {
+    /// Creates a pair, masking the value of secret fields.
+    fn masked(name: &str, value: &str) -> Self {
+        let value = if SECRET_FIELDS.contains(&name.to_ascii_lowercase().as_str()) {
+            REDACTED.to_string()
+        } else {
+            scrub(value)
+        };
+        Self {
+            name: name.to_string(),
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Empty {}
+
+#[derive(Debug, Serialize)]
+struct Timings {
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::error::ErrorContext;
+    use crate::middleware::stack::MiddlewareStack;
+    use reqwest::blocking::Client;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_records_redacted_exchange() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/login")
+            .with_status(302)
+            .with_header("set-cookie", "laravel_session=s3cr3t; path=/")
+            .with_header("location", "/dashboard")
+            .with_body("<input name=\"_token\" value=\"tok123\"> NIK 3201234567890001")
+            .create();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.har");
+        let recorder = Arc::new(HarRecorder::create(&path).unwrap().with_body_limit(40));
+        let mut stack = MiddlewareStack::new();
+        stack.push(recorder.clone());
+
+        let url = format!("{}/login", server.url());
+        let ctx = ErrorContext::new("auth.login", "POST", &url);
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let request = client
+            .post(&url)
+            .header("cookie", "XSRF-TOKEN=abc; laravel_session=old")
+            .form(&[
+                ("_token", "tok123"),
+                ("email", "a@b.c"),
+                ("password", "hunter22"),
+            ]);
+        let response = stack.send(request, &ctx).unwrap();
+        assert_eq!(response.status().as_u16(), 302);
+        assert!(
+            response.text().unwrap().contains("tok123"),
+            "body still readable"
+        );
+
+        recorder.flush().unwrap();
+        let har = fs::read_to_string(&path).unwrap();
+        for leaked in [
+            "tok123",
+            "hunter22",
+            "s3cr3t",
+            "3201234567890001",
+            "a%40b.c",
+        ] {
+            assert!(!har.contains(leaked), "{leaked} leaked into HAR");
+        }
+        let json: serde_json::Value = serde_json::from_str(&har).unwrap();
+        let entry = &json["log"]["entries"][0];
+        assert_eq!(json["log"]["version"], "1.2");
+        assert_eq!(entry["_operation"], "auth.login");
+        assert_eq!(entry["response"]["status"], 302);
+        assert_eq!(entry["response"]["cookies"][0]["name"], "laravel_session");
+        assert_eq!(entry["request"]["cookies"][1]["name"], "laravel_session");
+        assert_eq!(entry["request"]["postData"]["params"][2]["value"], REDACTED);
+        assert!(
+            entry["response"]["content"]["comment"]
+                .as_str()
+                .unwrap()
+                .starts_with("truncated to 40")
+        );
+        assert_eq!(recorder.len(), 1);
+    }
+
+    #[test]
+    fn test_caps_entries_and_writes_on_flush() {
+        let mut server = mockito::Server::new();
+        server.mock("GET", "/dashboard").expect(3).create();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.har");
+        let recorder = Arc::new(
+            HarRecorder::create(&path)
+                .unwrap()
+                .with_max_entries(2)
+                .with_flush_interval(Duration::from_secs(3600)),
+        );
+        let mut stack = MiddlewareStack::new();
+        stack.push(recorder.clone());
+
+        let url = format!("{}/dashboard", server.url());
+        let ctx = ErrorContext::new("dashboard.list", "GET", &url);
+        let client = Client::new();
+        for _ in 0..3 {
+            stack.send(client.get(&url), &ctx).unwrap();
+        }
+        let entries = |path: &Path| {
+            let har: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+            (har["log"]["entries"].as_array().unwrap().len(), har)
+        };
+        assert_eq!(entries(&path).0, 0, "not written before the interval");
+        assert_eq!(recorder.len(), 2);
+
+        drop(stack);
+        drop(recorder);
+        let (count, har) = entries(&path);
+        assert_eq!(count, 2, "written on drop");
+        assert!(
+            har["log"]["comment"]
+                .as_str()
+                .unwrap()
+                .starts_with("1 older")
+        );
+    }
+
+    #[test]
+    fn test_binary_body_is_base64() {
+        let recorder =
+            HarRecorder::create(tempfile::tempdir().unwrap().path().join("x.har")).unwrap();
+        let (text, encoding, comment) = recorder.text(&[0xff, 0x00, 0x10]);
+        assert_eq!(
+            (text.as_str(), encoding, comment),
+            ("/wAQ", Some("base64"), None)
+        );
+    }
+}
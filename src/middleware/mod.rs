@@ -0,0 +1,9 @@
+pub mod builtin;
+pub mod cassette;
+pub mod har;
+pub mod stack;
+
+pub use builtin::*;
+pub use cassette::*;
+pub use har::*;
+pub use stack::*;
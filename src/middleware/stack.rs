@@ -0,0 +1,413 @@
+//! Request/response hooks shared by every client.
+//!
+//! A [`Middleware`] sees each HTTP request made by [`LoginClient`], [`LogoutClient`] and
+//! the dashboard sub-clients: it can modify the request before it is sent, and is told
+//! about the response or the transport error afterwards. Middlewares are collected in a
+//! [`MiddlewareStack`], registered once with [`CekUnitClient::add_middleware`] and
+//! shared by all sub-clients created afterwards.
+//!
+//! [`LoginClient`]: crate::api::auth::LoginClient
+//! [`LogoutClient`]: crate::api::auth::LogoutClient
+//! [`CekUnitClient::add_middleware`]: crate::client::CekUnitClient::add_middleware
+
+use crate::handler::error::{ApiError, ErrorContext};
+use crate::handler::redact::redact_url;
+use crate::handler::telemetry::record_request;
+use reqwest::blocking::{Client, Request, RequestBuilder, Response};
+use reqwest::header::HeaderMap;
+use reqwest::{Method, ResponseBuilderExt, Url};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::Span;
+
+/// Hooks around every HTTP request.
+///
+/// All methods have empty default implementations, so implementors only override the
+/// hooks they need. Hooks run in registration order before the request and in reverse
+/// order after it.
+///
+/// # Example
+/// ```no_run
+/// use librcekunit::CekUnitClient;
+/// use librcekunit::middleware::{Middleware, RequestInfo};
+/// use reqwest::blocking::Response;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// struct SlowRequests;
+///
+/// impl Middleware for SlowRequests {
+///     fn after_response(&self, info: &RequestInfo, _response: &Response, elapsed: Duration) {
+///         if elapsed > Duration::from_secs(2) {
+///             eprintln!("slow: {} took {:?}", info.operation, elapsed);
+///         }
+///     }
+/// }
+///
+/// let mut client = CekUnitClient::new()?;
+/// client.add_middleware(Arc::new(SlowRequests));
+/// # Ok::<(), librcekunit::ApiError>(())
+/// ```
+pub trait Middleware: Send + Sync {
+    /// Called before the request is sent; may modify it, e.g. add headers.
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the library operation, e.g. `"dashboard.list"`.
+    /// * `request` - The fully built request.
+    ///
+    /// # Errors
+    /// Returning an error aborts the request; later middlewares are not called.
+    fn before_request(&self, operation: &str, request: &mut Request) -> Result<(), ApiError> {
+        let _ = (operation, request);
+        Ok(())
+    }
+
+    /// Called when a response was received, whatever its status.
+    ///
+    /// The body has not been read yet.
+    fn after_response(&self, info: &RequestInfo, response: &Response, elapsed: Duration) {
+        let _ = (info, response, elapsed);
+    }
+
+    /// Called when no response was received (timeout, connection failure, ...).
+    fn on_error(&self, info: &RequestInfo, error: &ApiError, elapsed: Duration) {
+        let _ = (info, error, elapsed);
+    }
+
+    /// Returns `true` if this middleware needs response bodies.
+    ///
+    /// If any registered middleware does, responses are read into memory before they
+    /// are handed back to the client and [`on_response_body`](Self::on_response_body)
+    /// is called.
+    fn wants_body(&self) -> bool {
+        false
+    }
+
+    /// Called with the complete response body, after
+    /// [`after_response`](Self::after_response), if [`wants_body`](Self::wants_body)
+    /// returns `true`.
+    fn on_response_body(
+        &self,
+        info: &RequestInfo,
+        response: &Response,
+        body: &[u8],
+        elapsed: Duration,
+    ) {
+        let _ = (info, response, body, elapsed);
+    }
+
+    /// Called for session-level events that span several requests.
+    fn on_event(&self, event: &ClientEvent) {
+        let _ = event;
+    }
+}
+
+/// Sends requests on behalf of a [`MiddlewareStack`].
+///
+/// By default the stack sends requests over the network with the client that built
+/// them. A transport replaces that last step, e.g. to answer from a recorded
+/// [`Cassette`](crate::middleware::cassette::Cassette). Middleware hooks still run
+/// around it.
+pub trait Transport: Send + Sync {
+    /// Sends `request` and returns the response, whatever its status.
+    ///
+    /// # Arguments
+    /// * `client` - The client that built the request; use it to reach the network.
+    /// * `request` - The request, after all `before_request` hooks ran.
+    /// * `ctx` - Context of the operation, for error mapping.
+    ///
+    /// # Errors
+    /// Returns an error if no response can be produced; network failures should be
+    /// mapped with [`ErrorContext::transport`].
+    fn execute(
+        &self,
+        client: &Client,
+        request: Request,
+        ctx: &ErrorContext,
+    ) -> Result<Response, ApiError>;
+}
+
+/// Session-level events reported to [`Middleware::on_event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientEvent {
+    /// A login finished, successfully or not.
+    Login {
+        /// Whether a session was established.
+        success: bool,
+    },
+    /// A logout request finished, successfully or not.
+    Logout {
+        /// Whether the server accepted the logout.
+        success: bool,
+    },
+    /// A cached session was rejected by the server (or could not be checked), so
+    /// [`CekUnitClient::ensure_logged_in`](crate::CekUnitClient::ensure_logged_in)
+    /// logs in again.
+    Relogin,
+    /// A fresh CSRF token was read from a page.
+    CsrfRefresh,
+}
+
+/// Snapshot of a request as it was sent, passed to the post-request hooks.
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    /// Name of the library operation, e.g. `"pic.create"`.
+    pub operation: String,
+    /// HTTP method.
+    pub method: Method,
+    /// Full request URL, including query parameters.
+    ///
+    /// May contain tokens; use [`redacted_url`](Self::redacted_url) for logging.
+    pub url: Url,
+    /// Request headers after all [`Middleware::before_request`] hooks ran.
+    pub headers: HeaderMap,
+    /// Request body, if it was buffered (form posts are).
+    pub body: Option<Vec<u8>>,
+    /// Number of earlier attempts of the same request.
+    pub retries: u32,
+}
+
+impl RequestInfo {
+    fn new(ctx: &ErrorContext, request: &Request) -> Self {
+        Self {
+            operation: ctx.operation.clone(),
+            method: request.method().clone(),
+            url: request.url().clone(),
+            headers: request.headers().clone(),
+            body: request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(<[u8]>::to_vec),
+            retries: ctx.retries,
+        }
+    }
+
+    /// Returns the URL with credentials and sensitive query values masked.
+    pub fn redacted_url(&self) -> String {
+        redact_url(self.url.as_str())
+    }
+}
+
+/// An ordered list of [`Middleware`]s through which requests are sent.
+///
+/// Cloning is cheap; clones share the middleware instances.
+#[derive(Clone, Default)]
+pub struct MiddlewareStack {
+    layers: Vec<Arc<dyn Middleware>>,
+    transport: Option<Arc<dyn Transport>>,
+}
+
+impl MiddlewareStack {
+    /// Creates an empty stack; requests are sent unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a middleware; it runs after the ones already registered.
+    pub fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.layers.push(middleware);
+    }
+
+    /// Sends requests through `transport` instead of the network.
+    pub fn set_transport(&mut self, transport: Arc<dyn Transport>) {
+        self.transport = Some(transport);
+    }
+
+    /// Returns the number of registered middlewares.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns `true` if no middleware is registered.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Reports a session-level event to every middleware, in registration order.
+    pub fn emit(&self, event: ClientEvent) {
+        for layer in &self.layers {
+            layer.on_event(&event);
+        }
+    }
+
+    /// Builds and sends a request, running all hooks.
+    ///
+    /// The endpoint, status, retry count and duration are recorded on the current
+    /// operation span (see [`telemetry`](crate::handler::telemetry)).
+    ///
+    /// # Arguments
+    /// * `builder` - The request to send.
+    /// * `ctx` - Context of the operation; used for hook arguments and error mapping.
+    ///
+    /// # Returns
+    /// The response, whatever its status; check it with [`ErrorContext::check`].
+    ///
+    /// # Errors
+    /// Returns the error of a failing `before_request` hook, the transport error
+    /// mapped through [`ErrorContext::transport`], or the error of the
+    /// [`Transport`] set with [`set_transport`](Self::set_transport).
+    pub fn send(&self, builder: RequestBuilder, ctx: &ErrorContext) -> Result<Response, ApiError> {
+        let (client, request) = builder.build_split();
+        let mut request = request.map_err(|e| ctx.transport(e))?;
+        for layer in &self.layers {
+            layer.before_request(&ctx.operation, &mut request)?;
+        }
+
+        // Only buffered bodies can be copied; building the info is skipped when
+        // nobody is listening.
+        let info = (!self.layers.is_empty()).then(|| RequestInfo::new(ctx, &request));
+        let endpoint = request.url().path().to_string();
+        let started = Instant::now();
+        let result = match &self.transport {
+            Some(transport) => transport.execute(&client, request, ctx),
+            None => client.execute(request).map_err(|e| ctx.transport(e)),
+        };
+        let elapsed = started.elapsed();
+
+        let status = result.as_ref().ok().map(|r| r.status().as_u16());
+        record_request(&Span::current(), &endpoint, ctx.retries, status, elapsed);
+        match result {
+            Ok(mut response) => {
+                if let Some(info) = &info {
+                    for layer in self.layers.iter().rev() {
+                        layer.after_response(info, &response, elapsed);
+                    }
+                    if self.layers.iter().any(|l| l.wants_body()) {
+                        let body;
+                        (response, body) = buffer(response).map_err(|e| ctx.transport(e))?;
+                        for layer in self.layers.iter().rev().filter(|l| l.wants_body()) {
+                            layer.on_response_body(info, &response, &body, elapsed);
+                        }
+                    }
+                }
+                Ok(response)
+            }
+            Err(error) => {
+                if let Some(info) = &info {
+                    for layer in self.layers.iter().rev() {
+                        layer.on_error(info, &error, elapsed);
+                    }
+                }
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Reads the body of `response` and returns an equivalent response plus the body.
+///
+/// Status, version, headers and final URL are kept, so callers cannot tell the
+/// difference.
+pub(crate) fn buffer(response: Response) -> Result<(Response, Vec<u8>), reqwest::Error> {
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version())
+        .url(response.url().clone());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = response.headers().clone();
+    }
+    let body = response.bytes()?;
+    let copy = body.to_vec();
+    let rebuilt = builder
+        .body(body)
+        .expect("parts taken from a valid response");
+    Ok((Response::from(rebuilt), copy))
+}
+
+impl fmt::Debug for MiddlewareStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MiddlewareStack")
+            .field("layers", &self.layers.len())
+            .field("transport", &self.transport.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::blocking::Client;
+    use reqwest::header::HeaderValue;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl Middleware for Recorder {
+        fn before_request(&self, operation: &str, request: &mut Request) -> Result<(), ApiError> {
+            request
+                .headers_mut()
+                .insert("x-operation", HeaderValue::from_str(operation).unwrap());
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("before {}", operation));
+            Ok(())
+        }
+
+        fn after_response(&self, info: &RequestInfo, response: &Response, _: Duration) {
+            self.events.lock().unwrap().push(format!(
+                "after {} {} {:?}",
+                info.method,
+                response.status().as_u16(),
+                info.body.as_deref().map(String::from_utf8_lossy)
+            ));
+        }
+
+        fn on_error(&self, info: &RequestInfo, _: &ApiError, _: Duration) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("error {}", info.operation));
+        }
+    }
+
+    #[test]
+    fn test_hooks_run_around_request() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/pic")
+            .match_header("x-operation", "pic.create")
+            .with_status(302)
+            .create();
+        let recorder = Arc::new(Recorder::default());
+        let mut stack = MiddlewareStack::new();
+        stack.push(recorder.clone());
+
+        let url = format!("{}/pic", server.url());
+        let ctx = ErrorContext::new("pic.create", "POST", &url);
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let response = stack.send(client.post(&url).form(&[("nama", "Budi")]), &ctx);
+
+        assert_eq!(response.unwrap().status().as_u16(), 302);
+        mock.assert();
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            ["before pic.create", "after POST 302 Some(\"nama=Budi\")"]
+        );
+    }
+
+    #[test]
+    fn test_error_hook() {
+        let recorder = Arc::new(Recorder::default());
+        let mut stack = MiddlewareStack::new();
+        stack.push(recorder.clone());
+
+        let ctx = ErrorContext::new("auth.csrf", "GET", "http://127.0.0.1:1/login");
+        let err = stack
+            .send(Client::new().get("http://127.0.0.1:1/login"), &ctx)
+            .unwrap_err();
+
+        assert!(err.is_retryable());
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            ["before auth.csrf", "error auth.csrf"]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Scoped sessions that clean up after themselves.
+//!
+//! This module provides [`SessionGuard`], returned by [`CekUnitClient::session`].
+//! The guard holds a logged-in client and, when it goes out of scope, terminates the
+//! session according to its [`DropAction`]. This makes early returns via `?` safe:
+//! the server session is closed and the local cache cleared even on error paths.
+
+use crate::api::auth::utils::cache::CacheData;
+use crate::client::CekUnitClient;
+use crate::handler::error::ApiError;
+use std::ops::{Deref, DerefMut};
+
+/// What a [`SessionGuard`] does when it is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropAction {
+    /// Log out on the server and clear the local cache (the default).
+    ///
+    /// Uses the same strategy as [`CekUnitClient::logout`]: a fresh CSRF token is
+    /// fetched first, falling back to the cached one.
+    #[default]
+    Logout,
+    /// Only delete the local session cache; the server session is left to expire.
+    ClearCache,
+    /// Do nothing; the session stays usable for later runs.
+    KeepAlive,
+}
+
+/// A logged-in [`CekUnitClient`] that ends its session when dropped.
+///
+/// The guard dereferences to the client, so all sub-clients are available directly.
+/// Failures during the drop-time cleanup are logged and never panic; call
+/// [`finish`](Self::finish) instead to observe them. A guard dropped while the thread
+/// is panicking makes no requests: [`DropAction::Logout`] only clears the cache then.
+///
+/// # Example
+/// ```no_run
+/// use librcekunit::CekUnitClient;
+///
+/// let mut client = CekUnitClient::new()?;
+/// {
+///     let session = client.session()?;
+///     let html = session.dashboard()?.get_dashboard(Some(1), None, None, None)?;
+///     println!("{} bytes", html.len());
+/// } // logged out here, even if an error returned early
+/// # Ok::<(), librcekunit::ApiError>(())
+/// ```
+pub struct SessionGuard<'a> {
+    /// The client holding the session.
+    client: &'a mut CekUnitClient,
+    /// The session data at the time the guard was created.
+    data: CacheData,
+    /// Cleanup performed on drop.
+    on_drop: DropAction,
+}
+
+impl<'a> SessionGuard<'a> {
+    /// Wraps an already logged-in client.
+    pub(crate) fn new(client: &'a mut CekUnitClient, data: CacheData) -> Self {
+        Self {
+            client,
+            data,
+            on_drop: DropAction::default(),
+        }
+    }
+
+    /// Returns the session data the guard was created with.
+    pub fn data(&self) -> &CacheData {
+        &self.data
+    }
+
+    /// Returns the action that will run when the guard is dropped.
+    pub fn drop_action(&self) -> DropAction {
+        self.on_drop
+    }
+
+    /// Sets the action that will run when the guard is dropped.
+    pub fn set_drop_action(&mut self, action: DropAction) {
+        self.on_drop = action;
+    }
+
+    /// Keeps the session alive after the guard is dropped.
+    ///
+    /// Shorthand for `set_drop_action(DropAction::KeepAlive)`.
+    pub fn keep_alive(&mut self) {
+        self.on_drop = DropAction::KeepAlive;
+    }
+
+    /// Only clears the local cache when the guard is dropped.
+    ///
+    /// Shorthand for `set_drop_action(DropAction::ClearCache)`.
+    pub fn clear_cache_only(&mut self) {
+        self.on_drop = DropAction::ClearCache;
+    }
+
+    /// Runs the configured drop action now and returns its result.
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] if logging out or clearing the cache fails.
+    pub fn finish(mut self) -> Result<(), ApiError> {
+        let action = std::mem::replace(&mut self.on_drop, DropAction::KeepAlive);
+        run_action(self.client, action)
+    }
+}
+
+impl Deref for SessionGuard<'_> {
+    type Target = CekUnitClient;
+
+    fn deref(&self) -> &Self::Target {
+        self.client
+    }
+}
+
+impl DerefMut for SessionGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.client
+    }
+}
+
+impl Drop for SessionGuard<'_> {
+    fn drop(&mut self) {
+        let mut action = self.on_drop;
+        if action == DropAction::Logout && std::thread::panicking() {
+            tracing::warn!("dropping session while panicking; clearing the cache only");
+            action = DropAction::ClearCache;
+        }
+        if let Err(e) = run_action(self.client, action) {
+            tracing::warn!(?action, error = %e, "session cleanup failed");
+        }
+    }
+}
+
+/// Performs a [`DropAction`] on the given client.
+fn run_action(client: &mut CekUnitClient, action: DropAction) -> Result<(), ApiError> {
+    match action {
+        DropAction::Logout => client.logout(),
+        DropAction::ClearCache => client.clear_session_cache(),
+        DropAction::KeepAlive => Ok(()),
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::testing::FakeCekUnit;
+
+    const LOGOUT: &str = "POST /logout -> 302";
+
+    fn logouts(fake: &FakeCekUnit) -> usize {
+        fake.requests().iter().filter(|r| *r == LOGOUT).count()
+    }
+
+    #[test]
+    fn test_drop_logs_out() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        drop(client.session().unwrap());
+        assert_eq!(logouts(&fake), 1);
+        assert_eq!(fake.logged_in_sessions(), 0);
+        assert!(fake.cache().load().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_clear_cache_makes_no_requests() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        let mut session = client.session().unwrap();
+        session.set_drop_action(DropAction::ClearCache);
+        let requests = fake.requests().len();
+        drop(session);
+        assert_eq!(fake.requests().len(), requests);
+        assert!(!fake.cache().cache_file_path().exists());
+        assert_eq!(fake.logged_in_sessions(), 1);
+    }
+
+    #[test]
+    fn test_keep_alive_leaves_the_cache() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        let mut session = client.session().unwrap();
+        session.keep_alive();
+        let data = session.data().clone();
+        drop(session);
+        assert_eq!(logouts(&fake), 0);
+        let cached = fake.cache().load().unwrap().unwrap();
+        assert_eq!(cached.csrf_token.expose(), data.csrf_token.expose());
+        assert!(client.verify_session().unwrap());
+    }
+
+    #[test]
+    fn test_finish_disarms_the_guard() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        client.session().unwrap().finish().unwrap();
+        assert_eq!(logouts(&fake), 1);
+        assert!(fake.cache().load().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_drop_while_panicking_makes_no_requests() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        client.login().unwrap();
+        let requests = fake.requests().len();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _session = client.session().unwrap();
+            panic!("boom");
+        }));
+        assert!(result.is_err());
+        // Only the session check of `session()` reached the server.
+        assert_eq!(fake.requests().len(), requests + 1);
+        assert!(!fake.cache().cache_file_path().exists());
+    }
+}
@@ -0,0 +1,1315 @@
+//! A fake CekUnit server for integration tests.
+//!
+//! Available with the `testing` cargo feature:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! librcekunit = { version = "2", features = ["testing"] }
+//! ```
+//!
+//! [`FakeCekUnit`] starts a local HTTP server that behaves like the CekUnit Laravel
+//! application, on the default endpoint paths:
+//! - every response sets a `laravel_session` and an `XSRF-TOKEN` cookie; pages carry the
+//!   session's CSRF token in `<meta name="csrf-token">` and `<input name="_token">`,
+//! - `POST` requests need the session's `_token` (otherwise `419 Page Expired`), and
+//!   update/delete routes need the matching `_method` (otherwise `405`),
+//! - pages other than `/login` redirect guests to `/login`, or answer `401` to requests
+//!   that accept JSON; logging in keeps the CSRF token and regenerates the session id,
+//!   logging out invalidates both,
+//! - the dashboard, PIC, users and input-user lists are paginated with the markup of
+//!   Laravel's `bootstrap-5` paginator and support `search`, `sort` and `direction`;
+//!   they show a running number, labelled headers ("Nama Nasabah") linking to the
+//!   sort, and edit links and delete forms naming each row's id; exports return CSV,
+//! - invalid input (e.g. a create or update with a missing required field) fails with
+//!   `422` and Laravel's JSON `errors` when the request accepts JSON, and otherwise
+//!   redirects back to the `Referer` (or the dashboard) like a browser form post,
+//! - [`fail_next`](FakeCekUnit::fail_next) injects `419`, `422`, `429`, `500` or an
+//!   expired session into the next request.
+//!
+//! # Example
+//! ```
+//! use librcekunit::testing::{FakeCekUnit, Table};
+//! use std::collections::HashMap;
+//!
+//! let fake = FakeCekUnit::start();
+//! let mut client = fake.client()?;
+//! client.login()?;
+//! client
+//!     .pic()?
+//!     .insert_pic(HashMap::from([("nama", "Budi"), ("email", "budi@example.com")]))?;
+//! assert!(fake.rows(Table::Pic).iter().any(|row| row["nama"] == "Budi"));
+//! # Ok::<(), librcekunit::ApiError>(())
+//! ```
+
+use crate::api::auth::utils::cache::CacheManager;
+use crate::client::CekUnitClient;
+use crate::handler::config::{DEFAULT_ENDPOINTS, VarMap};
+use crate::handler::env::EnvConfig;
+use crate::handler::error::ApiError;
+use mockito::{Matcher, Mock, Request, ServerGuard};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tempfile::TempDir;
+
+/// Email accepted by a fake server unless changed with
+/// [`FakeCekUnitBuilder::credentials`].
+pub const FAKE_EMAIL: &str = "admin@example.com";
+
+/// Password accepted by a fake server unless changed with
+/// [`FakeCekUnitBuilder::credentials`].
+pub const FAKE_PASSWORD: &str = "password123";
+
+/// Name of the session cookie.
+const SESSION_COOKIE: &str = "laravel_session";
+
+/// Every status the fake server can answer with; one mock is registered per status.
+const STATUSES: &[u16] = &[200, 302, 401, 404, 405, 419, 422, 429, 500];
+
+/// A record in one of the fake server's tables, keyed by column name.
+pub type Row = BTreeMap<String, String>;
+
+/// The data tables served by a [`FakeCekUnit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Table {
+    /// CekUnit records, listed on `/dashboard` and keyed by `no`.
+    Cekunit,
+    /// Persons in charge, listed on `/pic`.
+    Pic,
+    /// Application users, listed on `/users`.
+    Users,
+    /// Data entered by users, listed on `/input-user` and created via `/input-data`.
+    InputUser,
+}
+
+impl Table {
+    /// Returns the column that identifies a row.
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Cekunit => "no",
+            _ => "id",
+        }
+    }
+
+    /// Returns the columns of the table, key first.
+    pub fn columns(self) -> &'static [&'static str] {
+        match self {
+            Self::Cekunit => &["no", "nopol", "merk", "nama_nasabah", "status"],
+            Self::Pic => &["id", "nama", "email", "no_hp"],
+            Self::Users => &["id", "name", "email", "role"],
+            Self::InputUser => &["id", "nama", "nopol", "tanggal", "user"],
+        }
+    }
+
+    /// Returns the header the panel shows for `column`.
+    fn label(self, column: &str) -> &'static str {
+        match (self, column) {
+            (Self::Cekunit, "no") => "No. Unit",
+            (_, "id") => "ID",
+            (_, "nopol") => "Nopol",
+            (_, "merk") => "Merk",
+            (Self::Cekunit, "nama_nasabah") | (Self::InputUser, "nama") => "Nama Nasabah",
+            (_, "status") => "Status",
+            (Self::Pic, "nama") => "Nama PIC",
+            (_, "name") => "Nama",
+            (_, "email") => "Email",
+            (_, "no_hp") => "No. HP",
+            (_, "role") => "Peran",
+            (_, "tanggal") => "Tanggal",
+            (_, "user") => "Diinput Oleh",
+            _ => "",
+        }
+    }
+
+    /// Returns the path of a row's update and delete routes, if it has them.
+    fn item_path(self) -> Option<&'static str> {
+        match self {
+            Self::Cekunit => Some("/cekunit"),
+            Self::Pic => Some("/pic"),
+            Self::Users => Some("/users"),
+            Self::InputUser => None,
+        }
+    }
+
+    /// Returns the columns that must be non-empty when a row is created.
+    fn required(self) -> &'static [&'static str] {
+        match self {
+            Self::Cekunit => &["nopol"],
+            Self::Pic => &["nama"],
+            Self::Users => &["name", "email"],
+            Self::InputUser => &["nama", "nopol"],
+        }
+    }
+
+    /// Returns the list page that writes redirect to.
+    fn list_path(self) -> &'static str {
+        match self {
+            Self::Cekunit => "/dashboard",
+            Self::Pic => "/pic",
+            Self::Users => "/users",
+            Self::InputUser => "/input-user",
+        }
+    }
+
+    fn seed(self, count: usize) -> Vec<Row> {
+        const NAMES: &[&str] = &["Andi", "Budi", "Citra", "Dewi", "Eko", "Fitri", "Gilang"];
+        const BRANDS: &[&str] = &["Honda", "Yamaha", "Suzuki", "Kawasaki"];
+        const STATUSES: &[&str] = &["aktif", "pending", "selesai"];
+        (1..=count)
+            .map(|i| {
+                let name = NAMES[(i - 1) % NAMES.len()];
+                let values: Vec<String> = match self {
+                    Self::Cekunit => vec![
+                        i.to_string(),
+                        format!("B {} ABC", 1000 + i),
+                        BRANDS[i % BRANDS.len()].to_string(),
+                        name.to_string(),
+                        STATUSES[i % STATUSES.len()].to_string(),
+                    ],
+                    Self::Pic => vec![
+                        i.to_string(),
+                        format!("{} {}", name, i),
+                        format!("pic{}@example.com", i),
+                        format!("0812{:08}", i),
+                    ],
+                    Self::Users => vec![
+                        i.to_string(),
+                        format!("{} {}", name, i),
+                        format!("user{}@example.com", i),
+                        if i == 1 { "admin" } else { "operator" }.to_string(),
+                    ],
+                    Self::InputUser => vec![
+                        i.to_string(),
+                        name.to_string(),
+                        format!("D {} XY", 2000 + i),
+                        format!("2026-01-{:02}", (i - 1) % 28 + 1),
+                        format!("user{}@example.com", i % 3 + 1),
+                    ],
+                };
+                self.columns()
+                    .iter()
+                    .map(|c| c.to_string())
+                    .zip(values)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// A failure injected with [`FakeCekUnit::fail_next`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// Answer `419 Page Expired`, as Laravel does for a stale CSRF token.
+    CsrfExpired,
+    /// Answer `422 Unprocessable Content` with the given message and no field errors.
+    Validation(String),
+    /// Answer `429 Too Many Requests` with a `Retry-After` header.
+    TooManyRequests {
+        /// Seconds sent in `Retry-After`.
+        retry_after: u32,
+    },
+    /// Answer `500 Server Error`.
+    ServerError,
+    /// Drop the caller's session first: pages redirect to `/login` and posts fail
+    /// with `419`.
+    SessionExpired,
+}
+
+/// Options for a [`FakeCekUnit`].
+#[derive(Debug, Clone)]
+pub struct FakeCekUnitBuilder {
+    email: String,
+    password: String,
+    per_page: usize,
+    rows: BTreeMap<Table, usize>,
+}
+
+impl Default for FakeCekUnitBuilder {
+    fn default() -> Self {
+        Self {
+            email: FAKE_EMAIL.to_string(),
+            password: FAKE_PASSWORD.to_string(),
+            per_page: 10,
+            rows: BTreeMap::from([
+                (Table::Cekunit, 25),
+                (Table::Pic, 12),
+                (Table::Users, 5),
+                (Table::InputUser, 30),
+            ]),
+        }
+    }
+}
+
+impl FakeCekUnitBuilder {
+    /// Sets the only email and password accepted by `POST /login`.
+    pub fn credentials(mut self, email: &str, password: &str) -> Self {
+        self.email = email.to_string();
+        self.password = password.to_string();
+        self
+    }
+
+    /// Sets the number of rows per list page (default 10).
+    pub fn per_page(mut self, per_page: usize) -> Self {
+        self.per_page = per_page.max(1);
+        self
+    }
+
+    /// Sets the number of generated rows in `table`.
+    pub fn rows(mut self, table: Table, count: usize) -> Self {
+        self.rows.insert(table, count);
+        self
+    }
+
+    /// Starts the server.
+    ///
+    /// # Panics
+    /// Panics if the local server cannot be started.
+    pub fn start(self) -> FakeCekUnit {
+        let tables = self
+            .rows
+            .iter()
+            .map(|(&table, &count)| (table, table.seed(count)))
+            .collect();
+        let state = Arc::new(Mutex::new(State {
+            email: self.email,
+            password: self.password,
+            per_page: self.per_page,
+            tables,
+            sessions: HashMap::new(),
+            counter: 0,
+            failures: VecDeque::new(),
+            log: Vec::new(),
+            pending: None,
+        }));
+        let mut server = mockito::Server::new();
+        let mut mocks = Vec::new();
+        for method in ["GET", "POST"] {
+            for &status in STATUSES {
+                mocks.push(register(&mut server, &state, method, status));
+            }
+        }
+        FakeCekUnit {
+            server,
+            state,
+            cache_dir: tempfile::tempdir().expect("temporary cache directory"),
+            _mocks: mocks,
+        }
+    }
+}
+
+/// Registers the mock that answers `method` requests whose reply has `status`.
+///
+/// Mockito evaluates matchers under its server lock, one request at a time: the
+/// first matcher to see a request computes the reply, the response callbacks read it
+/// and the body callback clears it.
+fn register(
+    server: &mut ServerGuard,
+    state: &Arc<Mutex<State>>,
+    method: &str,
+    status: u16,
+) -> Mock {
+    let with = |f: fn(&Reply) -> String| {
+        let state = state.clone();
+        move |_: &Request| lock(&state).pending.as_ref().map(f).unwrap_or_default()
+    };
+    let matcher = state.clone();
+    let mut mock = server
+        .mock(method, Matcher::Any)
+        .match_request(move |request| lock(&matcher).prepare(request) == status)
+        .with_status(usize::from(status))
+        .with_header_from_request("content-type", with(|r| r.content_type.to_string()))
+        .with_header_from_request(
+            "set-cookie",
+            with(|r| {
+                format!(
+                    "{}={}; path=/; httponly; samesite=lax",
+                    SESSION_COOKIE, r.session
+                )
+            }),
+        )
+        .with_header_from_request(
+            "set-cookie",
+            with(|r| format!("XSRF-TOKEN={}; path=/; samesite=lax", r.token)),
+        );
+    if status == 302 {
+        mock = mock.with_header_from_request("location", with(|r| r.location.clone()));
+    }
+    if status == 429 {
+        mock = mock.with_header_from_request("retry-after", with(|r| r.retry_after.to_string()));
+    }
+    let body = state.clone();
+    mock.with_body_from_request(move |_| {
+        lock(&body)
+            .pending
+            .take()
+            .map(|r| r.body)
+            .unwrap_or_default()
+    })
+    .create()
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A running fake CekUnit server; stops when dropped.
+pub struct FakeCekUnit {
+    server: ServerGuard,
+    state: Arc<Mutex<State>>,
+    cache_dir: TempDir,
+    _mocks: Vec<Mock>,
+}
+
+impl FakeCekUnit {
+    /// Starts a server with the default options.
+    ///
+    /// # Panics
+    /// Panics if the local server cannot be started.
+    pub fn start() -> Self {
+        Self::builder().start()
+    }
+
+    /// Returns options for a server, to change before [`start`](FakeCekUnitBuilder::start).
+    pub fn builder() -> FakeCekUnitBuilder {
+        FakeCekUnitBuilder::default()
+    }
+
+    /// Returns the base URL of the server, e.g. `http://127.0.0.1:40123`.
+    pub fn url(&self) -> String {
+        self.server.url()
+    }
+
+    /// Returns the configuration variables pointing at this server.
+    ///
+    /// Useful to start from when testing configuration handling; most tests use
+    /// [`config`](Self::config) or [`client`](Self::client).
+    pub fn vars(&self) -> VarMap {
+        let state = lock(&self.state);
+        let mut vars: VarMap = DEFAULT_ENDPOINTS
+            .iter()
+            .map(|(key, path)| (key.to_string(), path.to_string()))
+            .collect();
+        vars.insert("BASE_URL".into(), self.url());
+        vars.insert("USER_EMAIL".into(), state.email.clone());
+        vars.insert("USER_PASSWORD".into(), state.password.clone());
+        vars
+    }
+
+    /// Returns a configuration pointing at this server, with valid credentials.
+    ///
+    /// # Errors
+    /// Returns [`ApiError::EnvError`] if the credentials set on the builder are invalid.
+    pub fn config(&self) -> Result<EnvConfig, ApiError> {
+        Ok(EnvConfig::from_vars(&self.vars())?)
+    }
+
+    /// Returns a cache manager in a temporary directory owned by this server.
+    ///
+    /// All clients created by [`client`](Self::client) share it.
+    pub fn cache(&self) -> CacheManager {
+        let dir = self.cache_dir.path().to_path_buf();
+        CacheManager::with_paths(dir.clone(), dir.join("session.json"))
+    }
+
+    /// Returns a client for this server that is not logged in yet.
+    ///
+    /// # Errors
+    /// Returns [`ApiError`] if the configuration is invalid or a client cannot be built.
+    pub fn client(&self) -> Result<CekUnitClient, ApiError> {
+        CekUnitClient::with_config_and_cache(self.config()?, self.cache())
+    }
+
+    /// Makes the next request fail with `failure`; calls queue up.
+    pub fn fail_next(&self, failure: Failure) {
+        lock(&self.state).failures.push_back(failure);
+    }
+
+    /// Ends every session, as after a server restart or session timeout.
+    pub fn expire_sessions(&self) {
+        lock(&self.state).sessions.clear();
+    }
+
+    /// Gives every session a new CSRF token; requests with the old one get `419`.
+    pub fn rotate_csrf_tokens(&self) {
+        let mut state = lock(&self.state);
+        let ids: Vec<String> = state.sessions.keys().cloned().collect();
+        for id in ids {
+            let token = state.next_token();
+            if let Some(session) = state.sessions.get_mut(&id) {
+                session.token = token;
+            }
+        }
+    }
+
+    /// Returns the current rows of `table`.
+    pub fn rows(&self, table: Table) -> Vec<Row> {
+        lock(&self.state)
+            .tables
+            .get(&table)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the requests handled so far, e.g. `"POST /login -> 302"`.
+    pub fn requests(&self) -> Vec<String> {
+        lock(&self.state).log.clone()
+    }
+
+    /// Returns the number of sessions that are logged in.
+    pub fn logged_in_sessions(&self) -> usize {
+        lock(&self.state)
+            .sessions
+            .values()
+            .filter(|s| s.user.is_some())
+            .count()
+    }
+}
+
+impl std::fmt::Debug for FakeCekUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FakeCekUnit")
+            .field("url", &self.url())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+struct Session {
+    token: String,
+    user: Option<String>,
+}
+
+/// A computed response, waiting to be written by the mock callbacks.
+#[derive(Debug)]
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    location: String,
+    retry_after: u32,
+    body: Vec<u8>,
+    session: String,
+    token: String,
+}
+
+impl Reply {
+    fn html(status: u16, body: String) -> Self {
+        Self {
+            status,
+            content_type: "text/html; charset=UTF-8",
+            location: String::new(),
+            retry_after: 0,
+            body: body.into_bytes(),
+            session: String::new(),
+            token: String::new(),
+        }
+    }
+
+    fn redirect(location: &str) -> Self {
+        let mut reply = Self::html(302, format!("Redirecting to {}", location));
+        reply.location = location.to_string();
+        reply
+    }
+
+    fn with_type(mut self, content_type: &'static str) -> Self {
+        self.content_type = content_type;
+        self
+    }
+}
+
+/// The parts of a request the fake server looks at.
+struct Incoming {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    form: Vec<(String, String)>,
+    session: Option<String>,
+    accept: String,
+    referer: Option<String>,
+}
+
+impl Incoming {
+    fn new(request: &Request) -> Self {
+        let (path, query) = request
+            .path_and_query()
+            .split_once('?')
+            .unwrap_or((request.path_and_query(), ""));
+        let form = request
+            .utf8_lossy_body()
+            .map(|body| parse_pairs(&body))
+            .unwrap_or_default();
+        // The last session cookie wins, so a cookie jar's fresh value beats a stale
+        // cached one sent in the same request.
+        let session = request
+            .header("cookie")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .filter(|(name, _)| *name == SESSION_COOKIE)
+            .map(|(_, value)| value.to_string())
+            .next_back();
+        let header = |name: &str| {
+            request
+                .header(name)
+                .first()
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            method: request.method().to_string(),
+            path: path.to_string(),
+            query: parse_pairs(query),
+            form,
+            session,
+            accept: header("accept").unwrap_or_default(),
+            referer: header("referer"),
+        }
+    }
+
+    /// Whether Laravel would answer errors with JSON: the first accepted type is JSON.
+    fn wants_json(&self) -> bool {
+        let first = self.accept.split(',').next().unwrap_or("");
+        first.contains("/json") || first.contains("+json")
+    }
+
+    fn query(&self, name: &str) -> Option<&str> {
+        find(&self.query, name)
+    }
+
+    fn field(&self, name: &str) -> Option<&str> {
+        find(&self.form, name)
+    }
+
+    /// Returns the form fields other than `_token` and `_method`.
+    fn data(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.form
+            .iter()
+            .filter(|(k, _)| k != "_token" && k != "_method")
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+fn find<'a>(pairs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.as_str())
+}
+
+fn parse_pairs(encoded: &str) -> Vec<(String, String)> {
+    let decode = |s: &str| {
+        let s = s.replace('+', " ");
+        urlencoding::decode(&s).map(|d| d.into_owned()).unwrap_or(s)
+    };
+    encoded
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(name), decode(value))
+        })
+        .collect()
+}
+
+/// Routes of the fake application, on the default endpoint paths.
+#[derive(Debug)]
+enum Route {
+    Login,
+    Logout,
+    List(Table),
+    Export(Table),
+    Unique,
+    DeleteCategory,
+    DeleteAll,
+    Create(Table),
+    Item(Table, String),
+    InputData,
+}
+
+impl Route {
+    fn parse(path: &str) -> Option<Self> {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        Some(match segments.as_slice() {
+            ["login"] => Self::Login,
+            ["logout"] => Self::Logout,
+            ["dashboard"] => Self::List(Table::Cekunit),
+            ["cekunit", "export"] => Self::Export(Table::Cekunit),
+            ["cekunit", "unique"] => Self::Unique,
+            ["cekunit", "delete-category"] => Self::DeleteCategory,
+            ["cekunit", "delete-all"] => Self::DeleteAll,
+            ["cekunit", no] => Self::Item(Table::Cekunit, no.to_string()),
+            ["input-user"] => Self::List(Table::InputUser),
+            ["input-user", "export"] => Self::Export(Table::InputUser),
+            ["input-data"] => Self::InputData,
+            ["pic"] => Self::List(Table::Pic),
+            ["input-pic"] => Self::Create(Table::Pic),
+            ["pic", id] => Self::Item(Table::Pic, id.to_string()),
+            ["users"] => Self::List(Table::Users),
+            ["users", id] => Self::Item(Table::Users, id.to_string()),
+            _ => return None,
+        })
+    }
+
+    /// Returns whether the route accepts the (possibly spoofed) method.
+    fn allows(&self, method: &str) -> bool {
+        match self {
+            Self::Login | Self::InputData => matches!(method, "GET" | "POST"),
+            Self::List(_) | Self::Export(_) | Self::Unique => method == "GET",
+            Self::Logout | Self::DeleteCategory | Self::Create(_) => method == "POST",
+            Self::DeleteAll => method == "DELETE",
+            Self::Item(Table::Users, _) => matches!(method, "PUT" | "PATCH"),
+            Self::Item(..) => matches!(method, "PUT" | "PATCH" | "DELETE"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    email: String,
+    password: String,
+    per_page: usize,
+    tables: BTreeMap<Table, Vec<Row>>,
+    sessions: HashMap<String, Session>,
+    counter: u64,
+    failures: VecDeque<Failure>,
+    log: Vec<String>,
+    pending: Option<Reply>,
+}
+
+impl State {
+    /// Computes the reply to `request` unless it is already computed; returns its status.
+    fn prepare(&mut self, request: &Request) -> u16 {
+        if self.pending.is_none() {
+            let incoming = Incoming::new(request);
+            let reply = self.handle(&incoming);
+            self.log.push(format!(
+                "{} {} -> {}",
+                request.method(),
+                request.path_and_query(),
+                reply.status
+            ));
+            self.pending = Some(reply);
+        }
+        self.pending.as_ref().map_or(500, |r| r.status)
+    }
+
+    fn next_token(&mut self) -> String {
+        self.counter += 1;
+        format!("fake-token-{:04}", self.counter)
+    }
+
+    fn new_session(&mut self) -> String {
+        self.counter += 1;
+        let id = format!("fake-session-{:04}", self.counter);
+        let token = self.next_token();
+        self.sessions
+            .insert(id.clone(), Session { token, user: None });
+        id
+    }
+
+    fn handle(&mut self, request: &Incoming) -> Reply {
+        let mut session = match &request.session {
+            Some(id) if self.sessions.contains_key(id) => id.clone(),
+            _ => self.new_session(),
+        };
+        let mut reply = match self.failures.pop_front() {
+            Some(Failure::SessionExpired) => {
+                self.sessions.remove(&session);
+                session = self.new_session();
+                self.route(request, &mut session)
+            }
+            Some(failure) => Self::fail(failure),
+            None => self.route(request, &mut session),
+        };
+        reply.token = self.sessions[&session].token.clone();
+        reply.session = session;
+        reply
+    }
+
+    fn fail(failure: Failure) -> Reply {
+        match failure {
+            Failure::CsrfExpired => page_expired(),
+            Failure::Validation(message) => json_validation(&message, &[]),
+            Failure::TooManyRequests { retry_after } => {
+                let mut reply = Reply::html(429, "Too Many Requests".into());
+                reply.retry_after = retry_after;
+                reply
+            }
+            Failure::ServerError | Failure::SessionExpired => {
+                Reply::html(500, "Server Error".into())
+            }
+        }
+    }
+
+    fn route(&mut self, request: &Incoming, session: &mut String) -> Reply {
+        let Some(route) = Route::parse(&request.path) else {
+            return Reply::html(404, "Not Found".into());
+        };
+        let mut method = request.method.clone();
+        if method == "POST" {
+            if request.field("_token") != Some(self.sessions[session.as_str()].token.as_str()) {
+                return page_expired();
+            }
+            if let Some(spoofed) = request.field("_method") {
+                method = spoofed.to_ascii_uppercase();
+            }
+        }
+        if !route.allows(&method) {
+            return Reply::html(405, "Method Not Allowed".into());
+        }
+
+        let logged_in = self.sessions[session.as_str()].user.is_some();
+        match route {
+            Route::Login if logged_in => Reply::redirect("/dashboard"),
+            Route::Login if method == "GET" => Reply::html(200, self.login_page(session)),
+            Route::Login => self.login(request, session),
+            _ if !logged_in && request.wants_json() => {
+                Reply::html(401, r#"{"message":"Unauthenticated."}"#.into())
+                    .with_type("application/json")
+            }
+            _ if !logged_in => Reply::redirect("/login"),
+            Route::Logout => {
+                self.sessions.remove(session.as_str());
+                *session = self.new_session();
+                Reply::redirect("/login")
+            }
+            Route::List(table) => Reply::html(200, self.list_page(table, request, session)),
+            Route::Export(table) => self.export(table, request),
+            Route::Unique => self.unique(request),
+            Route::InputData if method == "GET" => {
+                Reply::html(200, self.form_page(Table::InputUser, session))
+            }
+            Route::InputData => self.create(Table::InputUser, request),
+            Route::Create(table) => self.create(table, request),
+            Route::DeleteCategory => self.delete_category(request),
+            Route::DeleteAll => {
+                self.table(Table::Cekunit).clear();
+                Reply::redirect("/dashboard")
+            }
+            Route::Item(table, id) if method == "DELETE" => {
+                let key = table.key();
+                let rows = self.table(table);
+                let before = rows.len();
+                rows.retain(|row| row[key] != id);
+                if rows.len() == before {
+                    return Reply::html(404, "Not Found".into());
+                }
+                Reply::redirect(table.list_path())
+            }
+            Route::Item(table, id) => self.update(table, &id, request),
+        }
+    }
+
+    fn table(&mut self, table: Table) -> &mut Vec<Row> {
+        self.tables.entry(table).or_default()
+    }
+
+    fn login(&mut self, request: &Incoming, session: &mut String) -> Reply {
+        let email = request.field("email").unwrap_or("");
+        let password = request.field("password").unwrap_or("");
+        if email.is_empty() || password.is_empty() {
+            return validation(
+                request,
+                "The email and password fields are required.",
+                &["email", "password"],
+            );
+        }
+        if email != self.email || password != self.password {
+            return Reply::redirect("/login");
+        }
+        // Laravel regenerates the session id on login but keeps the CSRF token.
+        let old = self.sessions.remove(session.as_str());
+        self.counter += 1;
+        *session = format!("fake-session-{:04}", self.counter);
+        let token = old.map_or_else(|| self.next_token(), |s| s.token);
+        self.sessions.insert(
+            session.clone(),
+            Session {
+                token,
+                user: Some(email.to_string()),
+            },
+        );
+        Reply::redirect("/dashboard")
+    }
+
+    fn filtered(&self, table: Table, request: &Incoming) -> Vec<Row> {
+        let mut rows = self.tables.get(&table).cloned().unwrap_or_default();
+        if let Some(search) = request.query("search").filter(|s| !s.is_empty()) {
+            let search = search.to_lowercase();
+            rows.retain(|row| row.values().any(|v| v.to_lowercase().contains(&search)));
+        }
+        if let Some(start) = request.query("start_date").filter(|s| !s.is_empty()) {
+            rows.retain(|row| row.get("tanggal").is_none_or(|d| d.as_str() >= start));
+        }
+        if let Some(end) = request.query("end_date").filter(|s| !s.is_empty()) {
+            rows.retain(|row| row.get("tanggal").is_none_or(|d| d.as_str() <= end));
+        }
+        if let Some(sort) = request
+            .query("sort")
+            .filter(|s| table.columns().contains(s))
+        {
+            rows.sort_by(|a, b| compare(&a[sort], &b[sort]));
+        }
+        if request.query("direction") == Some("desc") {
+            rows.reverse();
+        }
+        rows
+    }
+
+    fn list_page(&self, table: Table, request: &Incoming, session: &str) -> String {
+        let rows = self.filtered(table, request);
+        let pages = rows.len().div_ceil(self.per_page).max(1);
+        let page = request
+            .query("page")
+            .and_then(|p| p.parse::<usize>().ok())
+            .unwrap_or(1)
+            .clamp(1, pages);
+        let columns = table.columns();
+
+        let token = &self.sessions[session].token;
+        let first = (page - 1) * self.per_page;
+
+        // Like a Blade view: a running number, sortable labelled columns and actions.
+        let mut body = String::from("<table class=\"table\"><thead><tr><th>No</th>");
+        for column in columns {
+            let _ = write!(
+                body,
+                "<th><a href=\"{}?sort={}&amp;direction=asc\">{}</a></th>",
+                table.list_path(),
+                column,
+                table.label(column)
+            );
+        }
+        if table.item_path().is_some() {
+            body.push_str("<th>Aksi</th>");
+        }
+        body.push_str("</tr></thead><tbody>");
+        for (n, row) in rows.iter().skip(first).take(self.per_page).enumerate() {
+            let _ = write!(body, "<tr><td>{}</td>", first + n + 1);
+            for column in columns {
+                let _ = write!(body, "<td>{}</td>", escape(&row[*column]));
+            }
+            if let Some(path) = table.item_path() {
+                let id = escape(&row[table.key()]);
+                let _ = write!(
+                    body,
+                    "<td><a class=\"btn btn-sm btn-warning\" href=\"{path}/{id}/edit\">Edit</a>"
+                );
+                if table != Table::Users {
+                    let _ = write!(
+                        body,
+                        "<form method=\"POST\" action=\"{path}/{id}\"><input type=\"hidden\" name=\"_token\" value=\"{token}\"><input type=\"hidden\" name=\"_method\" value=\"DELETE\"><button class=\"btn btn-sm btn-danger\">Hapus</button></form>"
+                    );
+                }
+                body.push_str("</td>");
+            }
+            body.push_str("</tr>");
+        }
+        body.push_str("</tbody></table>");
+        if pages > 1 {
+            body.push_str(&paginator(
+                table.list_path(),
+                page,
+                pages,
+                self.per_page,
+                rows.len(),
+            ));
+        }
+        self.layout(session, &body)
+    }
+
+    fn form_page(&self, table: Table, session: &str) -> String {
+        let mut body = format!("<form method=\"POST\" action=\"{}\">", "/input-data");
+        let _ = write!(
+            body,
+            "<input type=\"hidden\" name=\"_token\" value=\"{}\">",
+            self.sessions[session].token
+        );
+        for column in &table.columns()[1..] {
+            let _ = write!(body, "<input name=\"{}\">", column);
+        }
+        body.push_str("<button type=\"submit\">Simpan</button></form>");
+        self.layout(session, &body)
+    }
+
+    fn login_page(&self, session: &str) -> String {
+        let token = &self.sessions[session].token;
+        self.layout(
+            session,
+            &format!(
+                "<form method=\"POST\" action=\"/login\"><input type=\"hidden\" name=\"_token\" value=\"{}\"><input name=\"email\"><input name=\"password\" type=\"password\"></form>",
+                token
+            ),
+        )
+    }
+
+    fn layout(&self, session: &str, body: &str) -> String {
+        format!(
+            "<!DOCTYPE html><html><head><meta name=\"csrf-token\" content=\"{}\"><title>CekUnit</title></head><body>{}</body></html>",
+            self.sessions[session].token, body
+        )
+    }
+
+    fn export(&self, table: Table, request: &Incoming) -> Reply {
+        match request.query("format") {
+            Some("csv") | None => {}
+            Some(other) => {
+                return validation(
+                    request,
+                    &format!("Unsupported export format '{}'.", other),
+                    &["format"],
+                );
+            }
+        }
+        let columns = table.columns();
+        let mut csv = columns.join(",");
+        csv.push('\n');
+        for row in self.filtered(table, request) {
+            let line: Vec<String> = columns.iter().map(|c| csv_field(&row[*c])).collect();
+            csv.push_str(&line.join(","));
+            csv.push('\n');
+        }
+        Reply::html(200, csv).with_type("text/csv; charset=UTF-8")
+    }
+
+    fn unique(&self, request: &Incoming) -> Reply {
+        let table = Table::Cekunit;
+        let Some(column) = request
+            .query("column")
+            .filter(|c| table.columns().contains(c))
+        else {
+            return validation(request, "The selected column is invalid.", &["column"]);
+        };
+        let mut values: Vec<&str> = self.tables[&table]
+            .iter()
+            .map(|row| row[column].as_str())
+            .collect();
+        values.sort_unstable();
+        values.dedup();
+        let json = serde_json::to_string(&values).unwrap_or_default();
+        Reply::html(200, json).with_type("application/json")
+    }
+
+    fn create(&mut self, table: Table, request: &Incoming) -> Reply {
+        if let Some(missing) = table
+            .required()
+            .iter()
+            .find(|c| request.field(c).is_none_or(str::is_empty))
+        {
+            return validation(
+                request,
+                &format!("The {} field is required.", missing),
+                &[missing],
+            );
+        }
+        let key = table.key();
+        let rows = self.table(table);
+        let next = rows
+            .iter()
+            .filter_map(|row| row[key].parse::<u64>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let mut row: Row = table
+            .columns()
+            .iter()
+            .map(|c| (c.to_string(), String::new()))
+            .collect();
+        for (name, value) in request.data() {
+            if table.columns().contains(&name) && name != key {
+                row.insert(name.to_string(), value.to_string());
+            }
+        }
+        row.insert(key.to_string(), next.to_string());
+        rows.push(row);
+        Reply::redirect(table.list_path())
+    }
+
+    fn update(&mut self, table: Table, id: &str, request: &Incoming) -> Reply {
+        if let Some((name, _)) = request
+            .data()
+            .find(|(name, value)| table.required().contains(name) && value.is_empty())
+        {
+            return validation(
+                request,
+                &format!("The {} field is required.", name),
+                &[name],
+            );
+        }
+        let key = table.key();
+        let Some(row) = self.table(table).iter_mut().find(|row| row[key] == id) else {
+            return Reply::html(404, "Not Found".into());
+        };
+        for (name, value) in request.data() {
+            if table.columns().contains(&name) && name != key {
+                row.insert(name.to_string(), value.to_string());
+            }
+        }
+        Reply::redirect(table.list_path())
+    }
+
+    fn delete_category(&mut self, request: &Incoming) -> Reply {
+        let table = Table::Cekunit;
+        let column = request.field("column").unwrap_or("");
+        let Some(value) = request
+            .field("value")
+            .filter(|_| table.columns().contains(&column))
+        else {
+            return validation(
+                request,
+                "The column and value fields are required.",
+                &["column", "value"],
+            );
+        };
+        let value = value.to_string();
+        let column = column.to_string();
+        self.table(table).retain(|row| row[&column] != value);
+        Reply::redirect(table.list_path())
+    }
+}
+
+/// Renders the links of a list page like Laravel's `pagination::bootstrap-5` view:
+/// previous/next for small screens, then a "Showing x to y of z results" summary and
+/// the numbered links, with gaps shown as `...`.
+fn paginator(path: &str, page: usize, pages: usize, per_page: usize, total: usize) -> String {
+    let link = |p: usize, label: &str, rel: &str| {
+        format!(
+            "<li class=\"page-item\"><a class=\"page-link\" href=\"{}?page={}\"{}>{}</a></li>",
+            path, p, rel, label
+        )
+    };
+    let disabled = |label: &str| {
+        format!(
+            "<li class=\"page-item disabled\" aria-disabled=\"true\"><span class=\"page-link\">{}</span></li>",
+            label
+        )
+    };
+    let previous = |label: &str| match page {
+        1 => disabled(label),
+        _ => link(page - 1, label, " rel=\"prev\""),
+    };
+    let next = |label: &str| {
+        if page < pages {
+            link(page + 1, label, " rel=\"next\"")
+        } else {
+            disabled(label)
+        }
+    };
+
+    let mut html = String::from(
+        "<nav class=\"d-flex justify-items-center justify-content-between\"><div class=\"d-flex justify-content-between flex-fill d-sm-none\"><ul class=\"pagination\">",
+    );
+    html.push_str(&previous("&laquo; Previous"));
+    html.push_str(&next("Next &raquo;"));
+    let _ = write!(
+        html,
+        "</ul></div><div class=\"d-none flex-sm-fill d-sm-flex align-items-sm-center justify-content-sm-between\"><div><p class=\"small text-muted\">Showing <span class=\"fw-semibold\">{}</span> to <span class=\"fw-semibold\">{}</span> of <span class=\"fw-semibold\">{}</span> results</p></div><div><ul class=\"pagination\">",
+        (page - 1) * per_page + 1,
+        (page * per_page).min(total),
+        total
+    );
+    html.push_str(&previous("&lsaquo;"));
+    let mut last_shown = 0;
+    for p in 1..=pages {
+        if p > 2 && p + 1 < pages && p.abs_diff(page) > 3 {
+            continue;
+        }
+        if p > last_shown + 1 {
+            html.push_str(&disabled("..."));
+        }
+        last_shown = p;
+        if p == page {
+            let _ = write!(
+                html,
+                "<li class=\"page-item active\" aria-current=\"page\"><span class=\"page-link\">{}</span></li>",
+                p
+            );
+        } else {
+            html.push_str(&link(p, &p.to_string(), ""));
+        }
+    }
+    html.push_str(&next("&rsaquo;"));
+    html.push_str("</ul></div></div></nav>");
+    html
+}
+
+fn page_expired() -> Reply {
+    Reply::html(419, "Page Expired".into())
+}
+
+/// Answers like Laravel's validator does for a JSON request: the first message,
+/// plus the same message under each offending field.
+/// Answers invalid input like Laravel: `422` with the errors for a request that accepts
+/// JSON, otherwise a redirect back (the errors would be flashed to the session).
+fn validation(request: &Incoming, message: &str, fields: &[&str]) -> Reply {
+    if request.wants_json() {
+        return json_validation(message, fields);
+    }
+    let back = request
+        .referer
+        .as_deref()
+        .and_then(|r| r.find("://").map(|i| &r[i + 3..]))
+        .and_then(|r| r.find('/').map(|i| &r[i..]))
+        .unwrap_or("/dashboard");
+    Reply::redirect(back)
+}
+
+fn json_validation(message: &str, fields: &[&str]) -> Reply {
+    let errors: serde_json::Map<String, serde_json::Value> = fields
+        .iter()
+        .map(|field| (field.to_string(), serde_json::json!([message])))
+        .collect();
+    let body = serde_json::json!({ "message": message, "errors": errors });
+    Reply::html(422, body.to_string()).with_type("application/json")
+}
+
+/// Compares numerically when both values are numbers, otherwise as text.
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::dashboard::ListPage;
+    use crate::handler::error::ApiError;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_login_list_and_logout() {
+        let fake = FakeCekUnit::builder().per_page(5).start();
+        let mut client = fake.client().unwrap();
+
+        let session = client.login().unwrap();
+        assert!(session.cookies.iter().any(|c| c.name == SESSION_COOKIE));
+        assert_eq!(fake.logged_in_sessions(), 1);
+
+        let dashboard = client.dashboard().unwrap();
+        let html = dashboard
+            .get_dashboard(Some(2), None, Some("no"), Some("desc"))
+            .unwrap();
+        let page = ListPage::parse(&html).unwrap();
+        assert_eq!(
+            (page.page, page.pages, page.total),
+            (Some(2), Some(5), Some(25))
+        );
+        assert!(html.contains("<td>20</td>"));
+        let statuses = dashboard.get_unique_values("status").unwrap();
+        assert_eq!(statuses, ["aktif", "pending", "selesai"]);
+        let csv = dashboard.export_cekunit("csv", "no", "asc").unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 26);
+
+        client.logout().unwrap();
+        assert_eq!(fake.logged_in_sessions(), 0);
+        assert!(fake.requests().contains(&"POST /logout -> 302".to_string()));
+    }
+
+    #[test]
+    fn test_writes_enforce_token_and_method() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        client.login().unwrap();
+        let pic = client.pic().unwrap();
+
+        pic.update_pic("3", HashMap::from([("nama", "Ganti")]))
+            .unwrap();
+        assert_eq!(fake.rows(Table::Pic)[2]["nama"], "Ganti");
+        pic.delete_pic("3").unwrap();
+        assert_eq!(fake.rows(Table::Pic).len(), 11);
+        let err = pic
+            .insert_pic(HashMap::from([("email", "x@y.z")]))
+            .unwrap_err();
+        assert!(matches!(err.kind(), ApiError::ValidationError(m) if m.contains("nama")));
+        assert_eq!(
+            err.context().unwrap().fields["nama"],
+            ["The nama field is required."]
+        );
+        let users = client.users().unwrap();
+        assert!(
+            users
+                .update_user("2", HashMap::from([("role", "admin")]))
+                .is_ok()
+        );
+
+        fake.rotate_csrf_tokens();
+        let err = pic.delete_pic("4").unwrap_err();
+        assert!(matches!(err.kind(), ApiError::CsrfExpired));
+    }
+
+    #[test]
+    fn test_errors_follow_accept_header() {
+        let fake = FakeCekUnit::start();
+        let http = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let url = format!("{}/cekunit/unique?column=merk", fake.url());
+        assert_eq!(http.get(&url).send().unwrap().status(), 302);
+        let response = http
+            .get(&url)
+            .header("accept", "application/json")
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), 401);
+
+        // A browser form post is sent back with the errors in the session.
+        let mut client = fake.client().unwrap();
+        let session = client.login().unwrap();
+        let cookies: Vec<String> = session
+            .cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value.expose()))
+            .collect();
+        let response = http
+            .post(format!("{}/input-pic", fake.url()))
+            .header("cookie", cookies.join("; "))
+            .header("referer", format!("{}/pic?page=2", fake.url()))
+            .form(&[
+                ("_token", session.csrf_token.expose_str()),
+                ("email", "x@y.z"),
+            ])
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), 302);
+        assert_eq!(response.headers()["location"], "/pic?page=2");
+        assert_eq!(fake.rows(Table::Pic).len(), 12);
+    }
+
+    #[test]
+    fn test_injected_failures() {
+        let fake = FakeCekUnit::start();
+        let mut client = fake.client().unwrap();
+        client.login().unwrap();
+        let users = client.users().unwrap();
+
+        fake.fail_next(Failure::TooManyRequests { retry_after: 7 });
+        let err = users.get_users_list(None, None, None).unwrap_err();
+        assert!(matches!(err.kind(), ApiError::TooManyRequests));
+
+        fake.fail_next(Failure::SessionExpired);
+        assert!(!client.verify_session().unwrap());
+        let session = client.ensure_logged_in().unwrap();
+        assert!(session.logged_in);
+        assert!(client.verify_session().unwrap());
+    }
+}
@@ -0,0 +1,130 @@
+//! Offline tests of the HTTP flows, replayed from recorded cassettes.
+
+use librcekunit::handler::config::{DEFAULT_ENDPOINTS, VarMap};
+use librcekunit::handler::env::EnvConfig;
+use librcekunit::middleware::{Cassette, CassetteMode};
+use librcekunit::{ApiError, CacheManager, CekUnitClient};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+fn cassette(name: &str) -> Arc<Cassette> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/cassettes")
+        .join(name);
+    let cassette = Cassette::replay(path).expect("cassette fixture");
+    assert_eq!(cassette.mode(), CassetteMode::Replay);
+    Arc::new(cassette)
+}
+
+/// A client for a host that does not exist, with its session cache in `dir`.
+fn client(dir: &Path, cassette: Arc<Cassette>) -> CekUnitClient {
+    let mut vars: VarMap = DEFAULT_ENDPOINTS
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    vars.insert("BASE_URL".into(), "http://cekunit.invalid".into());
+    vars.insert("USER_EMAIL".into(), "admin@example.com".into());
+    vars.insert("USER_PASSWORD".into(), "correct-horse".into());
+    let config = EnvConfig::from_vars(&vars).unwrap();
+    let cache = CacheManager::with_paths(dir.to_path_buf(), dir.join("session.json"));
+    let mut client = CekUnitClient::with_config_and_cache(config, cache).unwrap();
+    client.set_transport(cassette);
+    client
+}
+
+#[test]
+fn session_flow_replays_offline() -> Result<(), ApiError> {
+    let dir = tempfile::tempdir().unwrap();
+    let cassette = cassette("session_flow.json");
+    let mut client = client(dir.path(), cassette.clone());
+
+    let session = client.login()?;
+    assert!(session.logged_in);
+    assert_eq!(session.csrf_token.expose(), "tok-login");
+    assert!(session.cookies.iter().any(|c| c.name == "laravel_session"));
+
+    let html = client
+        .dashboard()?
+        .get_dashboard(Some(1), None, None, None)?;
+    assert!(html.contains("B 1234 XYZ"));
+
+    let pic = client.pic()?;
+    pic.insert_pic(HashMap::from([("nama", "Budi"), ("no_hp", "081234567890")]))?;
+    let err = pic
+        .update_pic("7", HashMap::from([("nama", "Budi Santoso")]))
+        .unwrap_err();
+    assert!(matches!(err.kind(), ApiError::CsrfExpired));
+    assert_eq!(err.context().unwrap().status, Some(419));
+
+    client.logout()?;
+    assert!(client.check_session()?.is_none());
+    assert!(cassette.unused().is_empty(), "{:?}", cassette.unused());
+    Ok(())
+}
+
+#[test]
+fn sub_client_calls_replay_offline() -> Result<(), ApiError> {
+    let dir = tempfile::tempdir().unwrap();
+    let cassette = cassette("data_flow.json");
+    let mut client = client(dir.path(), cassette.clone());
+    client.login()?;
+
+    let dashboard = client.dashboard()?;
+    let csv = dashboard.export_cekunit("csv", "no", "asc")?;
+    assert!(String::from_utf8(csv).unwrap().starts_with("no,nopol,merk"));
+    let xlsx = dashboard.export_cekunit("excel", "created_at", "desc")?;
+    assert!(xlsx.starts_with(b"PK\x03\x04"));
+    assert_eq!(
+        dashboard.get_unique_values("merk")?,
+        ["Kawasaki", "Suzuki", "Yamaha"]
+    );
+    dashboard.update_cekunit("1", HashMap::from([("status", "Selesai")]))?;
+    dashboard.delete_cekunit("2")?;
+    dashboard.delete_by_category("merk", "Yamaha")?;
+    dashboard.delete_all()?;
+
+    let users = client.users()?;
+    assert!(users.get_users_list(None, None, None)?.contains("<table"));
+    users.update_user("1", HashMap::from([("role", "admin")]))?;
+
+    let input_user = client.input_user()?;
+    assert!(
+        input_user
+            .get_input_user(None, None, None, None, None, None)?
+            .contains("<table")
+    );
+    let csv = input_user.export_input_user("csv", "id", "desc", Some("a"), None, None)?;
+    assert!(String::from_utf8(csv).unwrap().starts_with("id,nama,nopol"));
+
+    let input_data = client.input_data()?;
+    assert!(input_data.get_form()?.contains("name=\"nopol\""));
+    input_data.insert_nasabah(HashMap::from([
+        ("nama", "Siti Aminah"),
+        ("nopol", "B 4321 CBA"),
+    ]))?;
+
+    let pic = client.pic()?;
+    assert!(pic.get_pic_list(None, None, None)?.contains("<table"));
+    pic.delete_pic("1")?;
+
+    client.logout()?;
+    assert!(cassette.unused().is_empty(), "{:?}", cassette.unused());
+    Ok(())
+}
+
+#[test]
+fn unrecorded_request_fails_without_network() -> Result<(), ApiError> {
+    let dir = tempfile::tempdir().unwrap();
+    let mut client = client(dir.path(), cassette("session_flow.json"));
+    client.login()?;
+
+    let err = client
+        .users()?
+        .get_users_list(None, None, None)
+        .unwrap_err();
+    assert!(err.to_string().contains("No recorded response"));
+    assert_eq!(err.context().unwrap().operation, "users.list");
+    assert!(!err.is_retryable());
+    Ok(())
+}